@@ -22,6 +22,7 @@ pub mod action_jump_system;
 pub mod action_move_to_system;
 pub mod action_progress_system;
 pub mod action_request_handler_system;
+pub mod action_salvage_system;
 pub mod action_undock_system;
 pub mod actions_system;
 
@@ -55,6 +56,10 @@ pub enum Action {
     Orbit {
         target_id: ObjId,
     },
+    // collect wares from a wreck
+    Salvage {
+        target_id: ObjId,
+    },
 }
 
 impl Action {
@@ -71,6 +76,19 @@ impl Action {
             _ => false,
         }
     }
+
+    /// Object used by the action, if any
+    pub fn get_target_id(&self) -> Option<ObjId> {
+        match self {
+            Action::Jump { jump_id } => Some(*jump_id),
+            Action::Dock { target_id }
+            | Action::MoveToTargetPos { target_id, .. }
+            | Action::Extract { target_id, .. }
+            | Action::Orbit { target_id }
+            | Action::Salvage { target_id } => Some(*target_id),
+            Action::Undock | Action::MoveTo { .. } | Action::Deorbit => None,
+        }
+    }
 }
 
 impl LoadingMapEntity for Action {
@@ -92,6 +110,9 @@ impl LoadingMapEntity for Action {
             Action::Orbit { target_id } => {
                 *target_id = entity_map[target_id];
             }
+            Action::Salvage { target_id } => {
+                *target_id = entity_map[target_id];
+            }
             _ => {}
         }
     }
@@ -159,6 +180,12 @@ pub struct ActionExtract {
     pub rest_acc: f32,
 }
 
+#[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
+pub struct ActionSalvage {
+    // accumulate the rest of salvage that is not enough to move one volume unit between runs
+    pub rest_acc: f32,
+}
+
 #[derive(Debug, Clone, Component, Serialize, Deserialize)]
 pub struct ActionJump {
     complete_time: Option<TotalTime>,
@@ -5,17 +5,43 @@ use super::*;
 
 use crate::game::events::{CommandSendEvent, EventKind, GEvent};
 
-use crate::game::dock::HasDocking;
+use crate::game::dock::{HasDocking, SizeClass};
+use crate::game::factions::{Ownership, QueryOwnership};
+use std::collections::HashSet;
 
-/// dock object into a hasdock. There is no validation, it assume (wrongly) that ship can dock
+/// Dock objects into a HasDocking when there is a free slot for its size. Objects without a slot
+/// are queued and wait at a holding position near the target until granted. Objects that do not fit
+/// any slot or are not accepted by its owner ownership filter are refused.
 pub fn system_dock(
     mut commands: Commands,
-    mut query_hasdock: Query<&mut HasDocking>,
-    query_action: Query<(Entity, &ActionActive), With<ActionDock>>,
+    mut query_hasdock: Query<(Entity, &mut HasDocking, Option<&LocationSpace>)>,
+    query_action: Query<(Entity, &ActionActive, Option<&SizeClass>), With<ActionDock>>,
+    query_ownership: QueryOwnership,
 ) {
     log::trace!("running");
 
-    for (obj_id, action) in &query_action {
+    // remove from queues objects that are not trying to dock anymore
+    let docking_requests: HashSet<(ObjId, ObjId)> = query_action
+        .iter()
+        .flat_map(|(obj_id, action, _)| match action.get_action() {
+            Action::Dock { target_id } => Some((obj_id, *target_id)),
+            _ => None,
+        })
+        .collect();
+
+    for (target_id, mut hasdock, _) in &mut query_hasdock {
+        if hasdock
+            .queue
+            .iter()
+            .any(|(obj_id, _)| !docking_requests.contains(&(*obj_id, target_id)))
+        {
+            hasdock
+                .queue
+                .retain(|(obj_id, _)| docking_requests.contains(&(*obj_id, target_id)));
+        }
+    }
+
+    for (obj_id, action, maybe_size) in &query_action {
         let target_id = match action.get_action() {
             Action::Dock { target_id } => target_id.clone(),
             _ => {
@@ -24,12 +50,68 @@ pub fn system_dock(
             }
         };
 
-        // update docked object (maybe move to a command?)
-        query_hasdock
+        let size = maybe_size.copied().unwrap_or_default();
+
+        let (_, mut hasdock, maybe_location) = query_hasdock
             .get_mut(target_id)
-            .expect("target has no docking component")
-            .docked
-            .push(obj_id);
+            .expect("target has no docking component");
+
+        if !hasdock.can_fit(size) {
+            log::debug!(
+                "{:?} has no slot for size {:?} at {:?}",
+                obj_id,
+                size,
+                target_id
+            );
+            commands
+                .entity(obj_id)
+                .remove::<ActionActive>()
+                .remove::<ActionDock>();
+            commands.add(CommandSendEvent::from(GEvent::new(
+                obj_id,
+                EventKind::DockRefused,
+            )));
+            continue;
+        }
+
+        if !Ownership::of(&query_ownership, obj_id).accept(&query_ownership, target_id) {
+            log::debug!("{:?} docking refused by {:?}", obj_id, target_id);
+            commands
+                .entity(obj_id)
+                .remove::<ActionActive>()
+                .remove::<ActionDock>();
+            commands.add(CommandSendEvent::from(GEvent::new(
+                obj_id,
+                EventKind::DockRefused,
+            )));
+            continue;
+        }
+
+        if !hasdock.request_dock(obj_id, size) {
+            let already_queued = hasdock.queue.iter().any(|(id, _)| *id == obj_id);
+            let index = hasdock.enqueue(obj_id, size);
+            if !already_queued {
+                log::debug!(
+                    "{:?} queued at {:?} in position {}",
+                    obj_id,
+                    target_id,
+                    index
+                );
+
+                if let Some(location) = maybe_location {
+                    commands.entity(obj_id).insert(LocationSpace {
+                        pos: HasDocking::holding_position(location.pos, index),
+                        sector_id: location.sector_id,
+                    });
+                }
+
+                commands.add(CommandSendEvent::from(GEvent::new(
+                    obj_id,
+                    EventKind::DockQueued,
+                )));
+            }
+            continue;
+        }
 
         log::debug!("{:?} docked at {:?}", obj_id, target_id);
 
@@ -43,6 +125,10 @@ pub fn system_dock(
             .remove::<ActionActive>()
             .remove::<ActionDock>();
 
+        commands.add(CommandSendEvent::from(GEvent::new(
+            obj_id,
+            EventKind::DockGranted,
+        )));
         commands.add(CommandSendEvent::from(GEvent::new(obj_id, EventKind::Dock)));
     }
 }
@@ -50,8 +136,9 @@ pub fn system_dock(
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::game::dock::HasDocking;
+    use crate::game::dock::{DockSlots, HasDocking};
     use crate::game::events::GEvents;
+    use crate::game::factions::{Faction, Owner, OwnershipFilter};
     use bevy_ecs::system::RunSystemOnce;
 
     use crate::game::utils::Position;
@@ -101,4 +188,169 @@ mod test {
         assert_eq!(1, station_has_dock.docked.len());
         assert_eq!(fleet_id, station_has_dock.docked[0]);
     }
+
+    #[test]
+    fn test_dock_system_should_queue_when_slots_are_full() {
+        let mut world = World::new();
+        world.insert_resource(GEvents::default());
+
+        let sector_0 = world.spawn_empty().id();
+        let station_location = LocationSpace {
+            pos: Position::ZERO,
+            sector_id: sector_0,
+        };
+
+        let station_id = world
+            .spawn_empty()
+            .insert(station_location.clone())
+            .insert(HasDocking::new(vec![DockSlots::new(SizeClass::Small, 1)]))
+            .id();
+
+        let new_fleet = |world: &mut World| {
+            world
+                .spawn_empty()
+                .insert(ActionActive(Action::Dock {
+                    target_id: station_id,
+                }))
+                .insert(ActionDock::default())
+                .insert(station_location.clone())
+                .id()
+        };
+        let fleet_0 = new_fleet(&mut world);
+        let fleet_1 = new_fleet(&mut world);
+
+        world.run_system_once(system_dock);
+
+        // first is docked, second wait at holding position
+        assert!(world.get::<LocationDocked>(fleet_0).is_some());
+        assert!(world.get::<LocationDocked>(fleet_1).is_none());
+        assert!(world.get::<ActionDock>(fleet_1).is_some());
+        let holding_pos = world.get::<LocationSpace>(fleet_1).unwrap().pos;
+        assert!(holding_pos.distance(Position::ZERO) > 0.1);
+        assert_eq!(
+            vec![(fleet_1, SizeClass::Small)],
+            world.get::<HasDocking>(station_id).unwrap().queue
+        );
+        let queued = world
+            .resource::<GEvents>()
+            .list()
+            .iter()
+            .filter(|e| e.id == fleet_1 && matches!(e.kind, EventKind::DockQueued))
+            .count();
+        assert_eq!(1, queued);
+
+        // still waiting while slot is occupied
+        world.run_system_once(system_dock);
+        assert!(world.get::<LocationDocked>(fleet_1).is_none());
+
+        // first undock and second is granted
+        world
+            .get_mut::<HasDocking>(station_id)
+            .unwrap()
+            .undock(fleet_0);
+        world.run_system_once(system_dock);
+        assert!(world.get::<LocationDocked>(fleet_1).is_some());
+        assert!(world
+            .get::<HasDocking>(station_id)
+            .unwrap()
+            .queue
+            .is_empty());
+        assert!(world
+            .resource::<GEvents>()
+            .list()
+            .iter()
+            .any(|e| e.id == fleet_1 && matches!(e.kind, EventKind::DockGranted)));
+    }
+
+    #[test]
+    fn test_dock_system_should_refuse_when_ownership_does_not_accept_target() {
+        let mut world = World::new();
+        world.insert_resource(GEvents::default());
+
+        let sector_0 = world.spawn_empty().id();
+        let station_location = LocationSpace {
+            pos: Position::ZERO,
+            sector_id: sector_0,
+        };
+
+        let faction_0 = world
+            .spawn(Faction {
+                ownership: OwnershipFilter::Own,
+                ..Default::default()
+            })
+            .id();
+        let faction_1 = world.spawn(Faction::default()).id();
+
+        let station_id = world
+            .spawn_empty()
+            .insert(station_location.clone())
+            .insert(HasDocking::default())
+            .insert(Owner::new(faction_1))
+            .id();
+
+        let fleet_id = world
+            .spawn_empty()
+            .insert(ActionActive(Action::Dock {
+                target_id: station_id,
+            }))
+            .insert(ActionDock::default())
+            .insert(station_location)
+            .insert(Owner::new(faction_0))
+            .id();
+
+        world.run_system_once(system_dock);
+
+        assert!(world.get::<LocationDocked>(fleet_id).is_none());
+        assert!(world.get::<ActionActive>(fleet_id).is_none());
+        assert!(world.get::<ActionDock>(fleet_id).is_none());
+        assert!(world
+            .resource::<GEvents>()
+            .list()
+            .iter()
+            .any(|e| e.id == fleet_id && matches!(e.kind, EventKind::DockRefused)));
+    }
+
+    #[test]
+    fn test_dock_system_should_refuse_when_size_does_not_fit() {
+        let mut world = World::new();
+        world.insert_resource(GEvents::default());
+
+        let sector_0 = world.spawn_empty().id();
+        let station_location = LocationSpace {
+            pos: Position::ZERO,
+            sector_id: sector_0,
+        };
+
+        let station_id = world
+            .spawn_empty()
+            .insert(station_location.clone())
+            .insert(HasDocking::new(vec![DockSlots::new(SizeClass::Small, 2)]))
+            .id();
+
+        let fleet_id = world
+            .spawn_empty()
+            .insert(ActionActive(Action::Dock {
+                target_id: station_id,
+            }))
+            .insert(ActionDock::default())
+            .insert(station_location)
+            .insert(SizeClass::Large)
+            .id();
+
+        world.run_system_once(system_dock);
+
+        assert!(world.get::<LocationDocked>(fleet_id).is_none());
+        assert!(world.get::<ActionActive>(fleet_id).is_none());
+        assert!(world.get::<ActionDock>(fleet_id).is_none());
+        assert!(world
+            .get::<HasDocking>(station_id)
+            .unwrap()
+            .queue
+            .is_empty());
+        assert!(world
+            .resource::<GEvents>()
+            .list()
+            .iter()
+            .any(|e| e.id == fleet_id && matches!(e.kind, EventKind::DockRefused)));
+    }
 }
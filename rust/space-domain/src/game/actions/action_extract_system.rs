@@ -1,5 +1,8 @@
 use crate::game::actions::{Action, ActionActive, ActionExtract};
 use crate::game::extractables::Extractable;
+use crate::game::locations::LocationSpace;
+use crate::game::maintenance::Wear;
+use crate::game::stats::{EconomyStats, StatKind};
 use crate::game::utils::DeltaTime;
 use crate::game::wares::Cargo;
 
@@ -8,13 +11,23 @@ use bevy_ecs::prelude::*;
 pub fn system_extract(
     mut commands: Commands,
     delta_time: Res<DeltaTime>,
-    mut query: Query<(Entity, &ActionActive, &mut ActionExtract, &mut Cargo)>,
-    query_extractables: Query<&Extractable>,
+    mut query: Query<(
+        Entity,
+        &ActionActive,
+        &mut ActionExtract,
+        &mut Cargo,
+        Option<&LocationSpace>,
+        Option<&Wear>,
+    )>,
+    mut query_extractables: Query<&mut Extractable>,
+    mut stats: Option<ResMut<EconomyStats>>,
 ) {
     log::trace!("running");
     let delta_time = *delta_time;
 
-    for (obj_id, active_action, mut action_extract, mut cargo) in &mut query {
+    for (obj_id, active_action, mut action_extract, mut cargo, maybe_location, maybe_wear) in
+        &mut query
+    {
         let (ware_id, target_id) = match &active_action.0 {
             Action::Extract { target_id, ware_id } => (*ware_id, *target_id),
             _other => {
@@ -23,7 +36,8 @@ pub fn system_extract(
             }
         };
 
-        let extractable = if let Some(extractable) = query_extractables.get(target_id).ok() {
+        let mut extractable = if let Some(extractable) = query_extractables.get_mut(target_id).ok()
+        {
             extractable
         } else {
             log::warn!(
@@ -46,11 +60,13 @@ pub fn system_extract(
         }
 
         let previous_rest_acc = action_extract.rest_acc;
-        let production = delta_time.as_f32() * extractable.accessibility + previous_rest_acc;
+        let efficiency = maybe_wear.map(|wear| wear.efficiency()).unwrap_or(1.0);
+        let production =
+            delta_time.as_f32() * extractable.accessibility * efficiency + previous_rest_acc;
 
         let amount_extracted = production.floor();
         action_extract.rest_acc = production - amount_extracted;
-        let amount_extracted = amount_extracted as u32;
+        let amount_extracted = extractable.get_available(amount_extracted as u32);
 
         let ware_id = match &active_action.0 {
             Action::Extract {
@@ -61,6 +77,11 @@ pub fn system_extract(
         };
 
         let amount_added = cargo.add_to_max(ware_id, amount_extracted);
+        extractable.deplete(amount_added);
+        if let Some(stats) = stats.as_mut() {
+            let sector_id = maybe_location.map(|l| l.sector_id);
+            stats.record(StatKind::Mined, obj_id, sector_id, ware_id, amount_added);
+        }
         let is_full = cargo.is_full();
         log::trace!(
                 "{:?} extracted {:?}, acc {:?}, total extracted {:?} with volume of {:?} and rest of {:?}, added {:?}, cargo now is {:?}/{:?}",
@@ -75,8 +96,16 @@ pub fn system_extract(
                 cargo.get_max(),
             );
 
-        if is_full {
-            log::debug!("{:?} cargo is full, stopping to extract", obj_id);
+        if is_full || extractable.is_depleted() {
+            if is_full {
+                log::debug!("{:?} cargo is full, stopping to extract", obj_id);
+            } else {
+                log::debug!(
+                    "{:?} {:?} is depleted, stopping to extract",
+                    obj_id,
+                    target_id
+                );
+            }
             commands
                 .entity(obj_id)
                 .remove::<ActionExtract>()
@@ -105,6 +134,7 @@ mod test {
             .insert(Extractable {
                 ware_id,
                 accessibility: 1.0,
+                reserve: None,
             })
             .id();
 
@@ -135,6 +165,48 @@ mod test {
         assert_complete(&ts.world, fleet_id);
     }
 
+    #[test]
+    fn test_extraction_should_deplete_reserve() {
+        let mut ts = TestSystemRunner::new(system_extract);
+
+        let ware_id = ts.world.spawn_empty().id();
+
+        let asteroid_id = ts
+            .world
+            .spawn_empty()
+            .insert(Extractable {
+                ware_id,
+                accessibility: 1.0,
+                reserve: Some(3),
+            })
+            .id();
+
+        let fleet_id = ts
+            .world
+            .spawn_empty()
+            .insert(ActionActive(Action::Extract {
+                target_id: asteroid_id,
+                ware_id,
+            }))
+            .insert(ActionExtract::default())
+            .insert(Cargo::new(5))
+            .id();
+
+        ts.tick_timed(DeltaTime(2.0));
+        assert_running(&ts.world, fleet_id);
+        assert_cargo(&ts.world, fleet_id, 2);
+
+        // only the rest of the reserve is extracted
+        ts.tick_timed(DeltaTime(2.0));
+        assert_cargo(&ts.world, fleet_id, 3);
+        assert_complete(&ts.world, fleet_id);
+        assert!(ts
+            .world
+            .get::<Extractable>(asteroid_id)
+            .unwrap()
+            .is_depleted());
+    }
+
     fn assert_running(world: &World, fleet_id: ObjId) {
         assert!(world.get::<ActionActive>(fleet_id).is_some());
         assert!(world.get::<ActionExtract>(fleet_id).is_some());
@@ -2,7 +2,9 @@ use bevy_ecs::prelude::*;
 
 use super::*;
 use crate::game::events::{CommandSendEvent, EventKind, GEvent};
+use crate::game::fuel::{FuelTank, EMPTY_TANK_SPEED_RATIO};
 use crate::game::locations::{LocationDocked, LocationSpace};
+use crate::game::maintenance::{Wear, WEAR_PER_JUMP};
 use crate::game::sectors::Jump;
 
 pub struct ActionJumpSystem;
@@ -10,7 +12,13 @@ pub struct ActionJumpSystem;
 pub fn system_jump(
     mut commands: Commands,
     total_time: Res<TotalTime>,
-    mut query: Query<(Entity, &ActionActive, &mut ActionJump)>,
+    mut query: Query<(
+        Entity,
+        &ActionActive,
+        &mut ActionJump,
+        Option<&mut Wear>,
+        Option<&mut FuelTank>,
+    )>,
     _query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
     query_jumps: Query<&Jump>,
 ) {
@@ -18,7 +26,7 @@ pub fn system_jump(
 
     let total_time = *total_time;
 
-    for (obj_id, action, mut action_jump) in &mut query {
+    for (obj_id, action, mut action_jump, maybe_wear, maybe_tank) in &mut query {
         let jump_id = match action.get_action() {
             Action::Jump { jump_id } => jump_id.clone(),
             other => {
@@ -42,6 +50,10 @@ pub fn system_jump(
                     jump.target_pos,
                 );
 
+                if let Some(mut wear) = maybe_wear {
+                    wear.add(WEAR_PER_JUMP);
+                }
+
                 commands
                     .entity(obj_id)
                     .insert(LocationSpace {
@@ -58,7 +70,16 @@ pub fn system_jump(
             }
             None => {
                 log::debug!("{:?} start to jump", obj_id);
-                action_jump.complete_time = Some(total_time.add(ACTION_JUMP_TOTAL_TIME));
+
+                // jumps without enough fuel are slower
+                let mut jump_time = ACTION_JUMP_TOTAL_TIME;
+                if let Some(mut tank) = maybe_tank {
+                    if !tank.consume_jump() {
+                        log::debug!("{:?} has not enough fuel to jump", obj_id);
+                        jump_time = DeltaTime(jump_time.as_f32() / EMPTY_TANK_SPEED_RATIO);
+                    }
+                }
+                action_jump.complete_time = Some(total_time.add(jump_time));
             }
         }
     }
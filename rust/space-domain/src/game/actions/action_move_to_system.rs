@@ -4,17 +4,33 @@ use super::super::locations::*;
 use super::*;
 
 use crate::game::events::{CommandSendEvent, EventKind, GEvent};
+use crate::game::fleets::FleetGroup;
+use crate::game::fuel::FuelTank;
+use crate::game::maintenance::{Wear, WEAR_PER_DISTANCE};
+use crate::game::wares::Cargo;
 
 pub fn system_move(
     delta_time: Res<DeltaTime>,
     mut commands: Commands,
-    mut query: Query<(Entity, &mut ActionActive, &Moveable), With<ActionMoveTo>>,
+    mut query: Query<
+        (
+            Entity,
+            &mut ActionActive,
+            &Moveable,
+            Option<&SpeedByMass>,
+            Option<&Cargo>,
+            Option<&FleetGroup>,
+            Option<&mut Wear>,
+            Option<&mut FuelTank>,
+        ),
+        With<ActionMoveTo>,
+    >,
     mut query_locations: Query<(Entity, Option<&mut LocationSpace>, Option<&LocationDocked>)>,
 ) {
     log::trace!("running");
 
     // refresh last position from a target id
-    for (obj_id, mut action, _) in &mut query {
+    for (obj_id, mut action, ..) in &mut query {
         match action.get_action_mut() {
             Action::MoveToTargetPos {
                 target_id,
@@ -39,7 +55,17 @@ pub fn system_move(
     }
 
     // update movement
-    for (obj_id, action, moveable) in &query {
+    for (
+        obj_id,
+        action,
+        moveable,
+        maybe_speed_by_mass,
+        maybe_cargo,
+        maybe_group,
+        mut maybe_wear,
+        mut maybe_tank,
+    ) in &mut query
+    {
         let target_pos = match action.get_action() {
             Action::MoveTo { pos } => *pos,
             Action::MoveToTargetPos { last_position, .. } if last_position.is_some() => {
@@ -64,11 +90,27 @@ pub fn system_move(
         };
 
         // compute movement
-        let speed = moveable.speed.as_f32();
+        let mut speed = moveable.effective_speed(
+            maybe_speed_by_mass,
+            maybe_cargo,
+            maybe_wear.as_deref(),
+            maybe_tank.as_deref(),
+        );
+        if let Some(group) = maybe_group {
+            speed = group.apply(speed);
+        }
+        let speed = speed.as_f32();
         let max_distance = speed * delta_time.as_f32();
 
         let (new_pos, complete) =
             crate::game::utils::move_towards(loc.pos, target_pos, max_distance);
+        let distance = loc.pos.distance(new_pos);
+        if let Some(wear) = maybe_wear.as_mut() {
+            wear.add(distance * WEAR_PER_DISTANCE);
+        }
+        if let Some(tank) = maybe_tank.as_mut() {
+            tank.consume_distance(distance);
+        }
         if complete {
             // if current move distance is bigger that distance to arrive, move to the position
             log::debug!("{:?} move complete", obj_id);
@@ -98,6 +140,7 @@ mod test {
 
     use super::*;
     use crate::game::utils::{Position, Speed};
+    use crate::game::wares::{WareUnit, WareUnits};
     use crate::test::{assert_v2, test_system, TestSystemRunner};
 
     #[test]
@@ -129,6 +172,48 @@ mod test {
         assert_v2(location.pos, Position::new(1.0, 0.0));
     }
 
+    #[test]
+    fn test_move_to_system_should_slow_down_by_cargo_mass() {
+        let (world, entity) = test_system(system_move, |world| {
+            let sector_0 = world.spawn_empty().id();
+            let ware_id = world.spawn_empty().id();
+
+            world.insert_resource(DeltaTime(1.0));
+
+            let mut cargo = Cargo::new(10);
+            cargo.set_ware_units(WareUnits::new(vec![(
+                ware_id,
+                WareUnit {
+                    volume: 1,
+                    mass: 1.0,
+                },
+            )]));
+            cargo.add(ware_id, 10).unwrap();
+
+            let entity = world
+                .spawn_empty()
+                .insert(ActionActive(Action::MoveTo {
+                    pos: Position::new(4.0, 0.0),
+                }))
+                .insert(ActionMoveTo::default())
+                .insert(LocationSpace {
+                    pos: Position::ZERO,
+                    sector_id: sector_0,
+                })
+                .insert(Moveable { speed: Speed(2.0) })
+                .insert(SpeedByMass {
+                    reference_mass: 10.0,
+                })
+                .insert(cargo)
+                .id();
+
+            entity
+        });
+
+        let location = world.get::<LocationSpace>(entity).unwrap();
+        assert_v2(location.pos, Position::new(1.0, 0.0));
+    }
+
     #[test]
     fn test_move_to_system_should_stop_on_arrival() {
         let (world, entity) = test_system(system_move, |world| {
@@ -21,6 +21,7 @@ pub fn system_action_request(
             .remove::<ActionMoveTo>()
             .remove::<ActionJump>()
             .remove::<ActionExtract>()
+            .remove::<ActionSalvage>()
             .remove::<ActionGeneric>();
 
         // update action
@@ -31,6 +32,7 @@ pub fn system_action_request(
             Action::MoveTo { .. } => entity.insert(ActionMoveTo::default()),
             Action::MoveToTargetPos { .. } => entity.insert(ActionMoveTo::default()),
             Action::Extract { .. } => entity.insert(ActionExtract::default()),
+            Action::Salvage { .. } => entity.insert(ActionSalvage::default()),
             _ => entity.insert(ActionGeneric {}),
         };
 
@@ -0,0 +1,149 @@
+use crate::game::actions::{Action, ActionActive, ActionSalvage};
+use crate::game::locations::LocationSpace;
+use crate::game::stats::EconomyStats;
+use crate::game::utils::DeltaTime;
+use crate::game::wares::{Cargo, Cargos, Volume, WareId};
+use crate::game::wrecks::Wreck;
+
+use bevy_ecs::prelude::*;
+
+/// Volume moved from the wreck into the ship cargo each second
+pub const SALVAGE_RATE: f32 = 2.0;
+
+pub fn system_salvage(
+    mut commands: Commands,
+    delta_time: Res<DeltaTime>,
+    mut query: Query<(Entity, &ActionActive, &mut ActionSalvage)>,
+    query_wrecks: Query<Entity, With<Wreck>>,
+    mut query_cargos: Query<&mut Cargo>,
+    query_locations: Query<&LocationSpace>,
+    mut stats: Option<ResMut<EconomyStats>>,
+) {
+    log::trace!("running");
+    let delta_time = *delta_time;
+
+    for (obj_id, active_action, mut action_salvage) in &mut query {
+        let target_id = match &active_action.0 {
+            Action::Salvage { target_id } => *target_id,
+            _other => {
+                log::warn!("{:?} unexpected action type {:?}", obj_id, active_action);
+                continue;
+            }
+        };
+
+        let production = delta_time.as_f32() * SALVAGE_RATE + action_salvage.rest_acc;
+        let budget = production.floor();
+        action_salvage.rest_acc = production - budget;
+        let budget = budget as Volume;
+
+        let wares: Vec<WareId> =
+            if query_wrecks.contains(target_id) && query_cargos.contains(obj_id) {
+                query_cargos
+                    .get(target_id)
+                    .map(|cargo| cargo.get_wares_ids().collect())
+                    .unwrap_or_default()
+            } else {
+                log::warn!(
+                    "{:?} try to salvage {:?} that is not a wreck",
+                    obj_id,
+                    target_id
+                );
+                vec![]
+            };
+
+        let sector_id = query_locations.get(target_id).ok().map(|l| l.sector_id);
+        let mut amount_moved = 0;
+        for ware_id in wares {
+            if amount_moved >= budget {
+                break;
+            }
+
+            let transfer = Cargos::move_only_up_to(
+                &mut query_cargos,
+                target_id,
+                obj_id,
+                &vec![ware_id],
+                budget - amount_moved,
+                stats.as_deref_mut(),
+                sector_id,
+            );
+            amount_moved += transfer
+                .moved
+                .iter()
+                .map(|i| i.get_amount())
+                .sum::<Volume>();
+        }
+
+        let is_full = query_cargos
+            .get(obj_id)
+            .map(|cargo| cargo.is_full())
+            .unwrap_or(true);
+        let is_empty = query_cargos
+            .get(target_id)
+            .map(|cargo| cargo.is_empty())
+            .unwrap_or(true);
+
+        log::trace!(
+            "{:?} salvaged {:?} from {:?}, full {:?}, wreck empty {:?}",
+            obj_id,
+            amount_moved,
+            target_id,
+            is_full,
+            is_empty,
+        );
+
+        if is_full || is_empty || (budget > 0 && amount_moved == 0) {
+            log::debug!("{:?} stopping to salvage {:?}", obj_id, target_id);
+            commands
+                .entity(obj_id)
+                .remove::<ActionSalvage>()
+                .remove::<ActionActive>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::utils::TotalTime;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_salvage_until_wreck_is_empty() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(1.0));
+
+        let ware_id = world.spawn_empty().id();
+
+        let mut wreck_cargo = Cargo::new(10);
+        wreck_cargo.add(ware_id, 3).unwrap();
+        let wreck_id = world
+            .spawn((
+                Wreck {
+                    decay_at: TotalTime(100.0),
+                },
+                wreck_cargo,
+            ))
+            .id();
+
+        let ship_id = world
+            .spawn((
+                Cargo::new(10),
+                ActionActive(Action::Salvage {
+                    target_id: wreck_id,
+                }),
+                ActionSalvage::default(),
+            ))
+            .id();
+
+        world.run_system_once(system_salvage);
+        assert_eq!(2, world.get::<Cargo>(ship_id).unwrap().get_amount(ware_id));
+        assert!(world.get::<ActionSalvage>(ship_id).is_some());
+
+        world.run_system_once(system_salvage);
+        assert_eq!(3, world.get::<Cargo>(ship_id).unwrap().get_amount(ware_id));
+        assert!(world.get::<Cargo>(wreck_id).unwrap().is_empty());
+        assert!(world.get::<ActionSalvage>(ship_id).is_none());
+        assert!(world.get::<ActionActive>(ship_id).is_none());
+    }
+}
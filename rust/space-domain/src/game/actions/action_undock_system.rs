@@ -6,19 +6,50 @@ use super::*;
 use crate::game::events::{CommandSendEvent, EventKind, GEvent};
 
 use crate::game::dock::HasDocking;
+use std::collections::HashSet;
 
+/// Undock objects into the parent position, when the parent has docking slots only one object
+/// leave each tick in the order they requested.
 pub fn system_undock(
     mut commands: Commands,
     query: Query<(Entity, Option<&LocationDocked>), With<ActionUndock>>,
     query_location: Query<&LocationSpace>,
-    mut query_hasdock: Query<&mut HasDocking>,
+    mut query_hasdock: Query<(Entity, &mut HasDocking)>,
 ) {
     log::trace!("running");
 
+    // remove from queues objects that are not trying to undock anymore
+    let undock_requests: HashSet<(ObjId, ObjId)> = query
+        .iter()
+        .flat_map(|(obj_id, maybe_docked)| maybe_docked.map(|docked| (obj_id, docked.parent_id)))
+        .collect();
+
+    for (target_id, mut hasdock) in &mut query_hasdock {
+        if hasdock
+            .undock_queue
+            .iter()
+            .any(|obj_id| !undock_requests.contains(&(*obj_id, target_id)))
+        {
+            hasdock
+                .undock_queue
+                .retain(|obj_id| undock_requests.contains(&(*obj_id, target_id)));
+        }
+    }
+
+    let mut launched = HashSet::new();
+
     for (obj_id, maybe_docked) in &query {
         if let Some(docked_at) = maybe_docked {
             match query_location.get(docked_at.parent_id).ok() {
                 Some(location) => {
+                    let (_, mut hasdock) = query_hasdock.get_mut(docked_at.parent_id).unwrap();
+                    let is_next = hasdock.request_undock(obj_id);
+                    if !hasdock.is_unlimited()
+                        && (!is_next || !launched.insert(docked_at.parent_id))
+                    {
+                        continue;
+                    }
+
                     log::debug!("{:?} un-docking from {:?}", obj_id, docked_at.parent_id);
                     commands
                         .get_entity(obj_id)
@@ -29,11 +60,7 @@ pub fn system_undock(
                         EventKind::Undock,
                     )));
 
-                    query_hasdock
-                        .get_mut(docked_at.parent_id)
-                        .unwrap()
-                        .docked
-                        .retain(|i_id| *i_id != obj_id);
+                    hasdock.undock(obj_id);
                 }
                 None => {
                     log::warn!("{:?} can not un-dock, parent is not in space", obj_id,);
@@ -58,8 +85,11 @@ mod test {
 
     use super::*;
 
+    use crate::game::dock::{DockSlots, SizeClass};
+    use crate::game::events::GEvents;
     use crate::game::utils::Position;
     use crate::test::{assert_v2, test_system};
+    use bevy_ecs::system::RunSystemOnce;
 
     #[test]
     fn test_undock_system_should_undock_if_docked() {
@@ -139,4 +169,65 @@ mod test {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn test_undock_system_should_undock_one_at_time_in_request_order() {
+        let mut world = World::new();
+        world.insert_resource(GEvents::default());
+
+        let sector_id = world.spawn_empty().id();
+        let station_id = world
+            .spawn_empty()
+            .insert(LocationSpace {
+                pos: Position::new(0.0, 0.0),
+                sector_id,
+            })
+            .insert(HasDocking::new(vec![DockSlots::new(SizeClass::Small, 2)]))
+            .id();
+
+        let new_docked = |world: &mut World| {
+            let fleet_id = world
+                .spawn_empty()
+                .insert(LocationDocked {
+                    parent_id: station_id,
+                })
+                .id();
+            assert!(world
+                .get_mut::<HasDocking>(station_id)
+                .unwrap()
+                .request_dock(fleet_id, SizeClass::Small));
+            fleet_id
+        };
+        let fleet_0 = new_docked(&mut world);
+        let fleet_1 = new_docked(&mut world);
+
+        let request_undock = |world: &mut World, fleet_id: ObjId| {
+            world
+                .entity_mut(fleet_id)
+                .insert(ActionActive(Action::Undock))
+                .insert(ActionUndock::default());
+        };
+
+        // fleet_1 requested first
+        world
+            .get_mut::<HasDocking>(station_id)
+            .unwrap()
+            .request_undock(fleet_1);
+        request_undock(&mut world, fleet_0);
+        request_undock(&mut world, fleet_1);
+
+        // only the first leave
+        world.run_system_once(system_undock);
+        assert!(world.get::<LocationDocked>(fleet_1).is_none());
+        assert!(world.get::<LocationDocked>(fleet_0).is_some());
+        assert!(world.get::<ActionUndock>(fleet_0).is_some());
+
+        world.run_system_once(system_undock);
+        assert!(world.get::<LocationDocked>(fleet_0).is_none());
+        assert!(world
+            .get::<HasDocking>(station_id)
+            .unwrap()
+            .undock_queue
+            .is_empty());
+    }
 }
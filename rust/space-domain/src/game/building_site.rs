@@ -1,3 +1,4 @@
+use crate::game::factions::Owner;
 use crate::game::loader::Loader;
 use crate::game::locations::LocationSpace;
 use crate::game::prefab::{Prefab, PrefabId};
@@ -30,12 +31,18 @@ impl LoadingMapEntity for BuildingSite {
 /// same location and destroy teh building site.
 pub fn system_building_site(
     mut commands: Commands,
-    mut query: Query<(Entity, &LocationSpace, &BuildingSite, &mut Cargo)>,
+    mut query: Query<(
+        Entity,
+        &LocationSpace,
+        &BuildingSite,
+        &mut Cargo,
+        Option<&Owner>,
+    )>,
     query_prefabs: Query<&Prefab>,
 ) {
     log::trace!("running");
 
-    for (obj_id, loc, building_site, mut cargo) in &mut query {
+    for (obj_id, loc, building_site, mut cargo, maybe_owner) in &mut query {
         if cargo.remove_all_or_none(&building_site.input).is_err() {
             continue;
         }
@@ -52,6 +59,7 @@ pub fn system_building_site(
         };
 
         new_obj.location_space = Some(loc.clone());
+        new_obj.owner = maybe_owner.copied();
 
         let new_obj_id = Loader::add_object(&mut commands, &new_obj);
 
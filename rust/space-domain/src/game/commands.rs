@@ -3,8 +3,11 @@ use std::collections::HashMap;
 use bevy_ecs::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::game::discovery::FactionDiscovery;
+use crate::game::dock::{Docking, QueryDocking};
+use crate::game::factions::{Ownership, QueryOwnership};
 use crate::game::locations::{EntityPerSectorIndex, Locations};
-use crate::game::wares::{Cargos, WareId};
+use crate::game::wares::{Cargo, Cargos, Volume, WareId};
 
 use super::actions::*;
 
@@ -13,9 +16,16 @@ use super::sectors::*;
 
 use crate::game::order::TradeOrders;
 use crate::game::save::LoadingMapEntity;
-use crate::game::utils::TotalTime;
+use crate::game::utils::{DeltaTime, TotalTime};
+use commons::math::P2;
 
+pub mod command_escort_system;
+pub mod command_explore_system;
+pub mod command_haul_system;
 pub mod command_mine_system;
+pub mod command_patrol_system;
+pub mod command_raid_system;
+pub mod command_salvage_system;
 pub mod command_trader_system;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,10 +86,210 @@ impl LoadingMapEntity for TradeState {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HaulAction {
+    /// take wares from the station
+    Load,
+    /// give wares to the station
+    Unload,
+}
+
+/// A stop of a haul route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HaulStep {
+    pub target_id: ObjId,
+    pub action: HaulAction,
+    pub ware_id: WareId,
+    /// max amount moved on each visit, unlimited when not defined
+    pub amount: Option<Volume>,
+    /// on load, amount that is always kept in the station. On unload, the max amount the station
+    /// is filled
+    pub threshold: Option<Volume>,
+}
+
+impl LoadingMapEntity for HaulStep {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.target_id.map_entity(entity_map);
+        self.ware_id.map_entity(entity_map);
+    }
+}
+
+/// A fixed route of steps executed in order, restarting once reach the end
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HaulState {
+    pub route: Vec<HaulStep>,
+    pub current: usize,
+}
+
+impl HaulState {
+    pub fn get_current_step(&self) -> Option<&HaulStep> {
+        if self.route.is_empty() {
+            None
+        } else {
+            self.route.get(self.current % self.route.len())
+        }
+    }
+
+    pub fn next_step(&mut self) {
+        if !self.route.is_empty() {
+            self.current = (self.current + 1) % self.route.len();
+        }
+    }
+}
+
+impl LoadingMapEntity for HaulState {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.route.map_entity(entity_map);
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum RaidState {
+    /// searching for traders carrying cargo
+    #[default]
+    Hunt,
+    Intercept {
+        target_id: ObjId,
+    },
+    /// carrying the loot to sell at the station
+    Retreat {
+        target_id: ObjId,
+        wares: Vec<WareId>,
+    },
+}
+
+impl LoadingMapEntity for RaidState {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        match self {
+            RaidState::Intercept { target_id } => target_id.map_entity(entity_map),
+            RaidState::Retreat { target_id, wares } => {
+                target_id.map_entity(entity_map);
+                wares.map_entity(entity_map);
+            }
+            RaidState::Hunt => {}
+        }
+    }
+}
+
+/// Collect wares from wrecks, delivering them to stations once full
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SalvageState {
+    pub wreck_id: Option<ObjId>,
+    pub deliver_target_id: Option<ObjId>,
+}
+
+impl LoadingMapEntity for SalvageState {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.wreck_id.map_entity(entity_map);
+        self.deliver_target_id.map_entity(entity_map);
+    }
+}
+
+/// Scout the nearest unexplored sector of the owner faction, jumping into it through the jump
+/// gate being approached
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExploreState {
+    pub jump_id: Option<JumpId>,
+}
+
+impl LoadingMapEntity for ExploreState {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.jump_id.map_entity(entity_map);
+    }
+}
+
+/// Where a patrol waypoint is, a fixed position in a sector or an object
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PatrolTarget {
+    Pos { sector_id: SectorId, pos: P2 },
+    Obj { target_id: ObjId },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatrolWaypoint {
+    pub target: PatrolTarget,
+    /// time waiting once arrive before move to the next waypoint
+    pub dwell: Option<DeltaTime>,
+}
+
+impl PatrolWaypoint {
+    pub fn pos(sector_id: SectorId, pos: P2) -> Self {
+        PatrolWaypoint {
+            target: PatrolTarget::Pos { sector_id, pos },
+            dwell: None,
+        }
+    }
+
+    pub fn obj(target_id: ObjId) -> Self {
+        PatrolWaypoint {
+            target: PatrolTarget::Obj { target_id },
+            dwell: None,
+        }
+    }
+
+    pub fn with_dwell(mut self, dwell: DeltaTime) -> Self {
+        self.dwell = Some(dwell);
+        self
+    }
+}
+
+impl LoadingMapEntity for PatrolWaypoint {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        match &mut self.target {
+            PatrolTarget::Pos { sector_id, .. } => sector_id.map_entity(entity_map),
+            PatrolTarget::Obj { target_id } => target_id.map_entity(entity_map),
+        }
+    }
+}
+
+/// Waypoints visited in order, restarting once reach the end
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatrolState {
+    pub waypoints: Vec<PatrolWaypoint>,
+    pub current: usize,
+    /// navigation to the current waypoint was requested
+    pub moving: bool,
+    /// waiting at the current waypoint until the deadline
+    pub dwell_until: Option<TotalTime>,
+}
+
+impl PatrolState {
+    pub fn get_current_waypoint(&self) -> Option<&PatrolWaypoint> {
+        if self.waypoints.is_empty() {
+            None
+        } else {
+            self.waypoints.get(self.current % self.waypoints.len())
+        }
+    }
+
+    pub fn next_waypoint(&mut self) {
+        self.moving = false;
+        self.dwell_until = None;
+        if !self.waypoints.is_empty() {
+            self.current = (self.current + 1) % self.waypoints.len();
+        }
+    }
+}
+
+impl LoadingMapEntity for PatrolState {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.waypoints.map_entity(entity_map);
+    }
+}
+
 #[derive(Debug, Clone, Component, Serialize, Deserialize)]
 pub enum Command {
     Mine(MineState),
     Trade(TradeState),
+    Haul(HaulState),
+    Patrol(PatrolState),
+    /// follow the target ship, docking and jumping with it
+    Escort {
+        target_id: ObjId,
+    },
+    Raid(RaidState),
+    Salvage(SalvageState),
+    Explore(ExploreState),
 }
 
 impl Command {
@@ -104,6 +314,131 @@ impl Command {
     pub fn trade() -> Command {
         Command::Trade(Default::default())
     }
+
+    pub fn haul(route: Vec<HaulStep>) -> Command {
+        Command::Haul(HaulState { route, current: 0 })
+    }
+
+    pub fn as_haul(&self) -> Option<&HaulState> {
+        match self {
+            Command::Haul(state) => Some(state),
+            _ => None,
+        }
+    }
+
+    pub fn patrol(waypoints: Vec<PatrolWaypoint>) -> Command {
+        Command::Patrol(PatrolState {
+            waypoints,
+            ..Default::default()
+        })
+    }
+
+    pub fn as_patrol(&self) -> Option<&PatrolState> {
+        match self {
+            Command::Patrol(state) => Some(state),
+            _ => None,
+        }
+    }
+
+    pub fn raid() -> Command {
+        Command::Raid(Default::default())
+    }
+
+    pub fn as_raid(&self) -> Option<&RaidState> {
+        match self {
+            Command::Raid(state) => Some(state),
+            _ => None,
+        }
+    }
+
+    pub fn salvage() -> Command {
+        Command::Salvage(Default::default())
+    }
+
+    pub fn as_salvage(&self) -> Option<&SalvageState> {
+        match self {
+            Command::Salvage(state) => Some(state),
+            _ => None,
+        }
+    }
+
+    pub fn explore() -> Command {
+        Command::Explore(Default::default())
+    }
+
+    pub fn as_explore(&self) -> Option<&ExploreState> {
+        match self {
+            Command::Explore(state) => Some(state),
+            _ => None,
+        }
+    }
+
+    /// Drop any reference to a removed object, targets are cleared to be searched again and
+    /// route steps using it are removed. Return true if the command was referencing the object,
+    /// an escort of the object can not continue and must be removed by the caller
+    pub fn forget_target(&mut self, obj_id: ObjId) -> bool {
+        match self {
+            Command::Mine(state) => {
+                let found =
+                    state.mine_target_id == Some(obj_id) || state.deliver_target_id == Some(obj_id);
+                if found {
+                    *state = Default::default();
+                }
+                found
+            }
+            Command::Trade(state) => match state {
+                TradeState::PickUp { target_id, .. } | TradeState::Deliver { target_id, .. }
+                    if *target_id == obj_id =>
+                {
+                    *state = TradeState::Idle;
+                    true
+                }
+                _ => false,
+            },
+            Command::Haul(state) => {
+                let count = state.route.len();
+                state.route.retain(|step| step.target_id != obj_id);
+                count != state.route.len()
+            }
+            Command::Patrol(state) => {
+                let count = state.waypoints.len();
+                state
+                    .waypoints
+                    .retain(|waypoint| waypoint.target != PatrolTarget::Obj { target_id: obj_id });
+                let found = count != state.waypoints.len();
+                if found {
+                    state.moving = false;
+                    state.dwell_until = None;
+                }
+                found
+            }
+            Command::Escort { target_id } => *target_id == obj_id,
+            Command::Raid(state) => match state {
+                RaidState::Intercept { target_id } | RaidState::Retreat { target_id, .. }
+                    if *target_id == obj_id =>
+                {
+                    *state = RaidState::Hunt;
+                    true
+                }
+                _ => false,
+            },
+            Command::Salvage(state) => {
+                let found =
+                    state.wreck_id == Some(obj_id) || state.deliver_target_id == Some(obj_id);
+                if found {
+                    *state = Default::default();
+                }
+                found
+            }
+            Command::Explore(state) => {
+                let found = state.jump_id == Some(obj_id);
+                if found {
+                    *state = Default::default();
+                }
+                found
+            }
+        }
+    }
 }
 
 impl LoadingMapEntity for Command {
@@ -111,6 +446,12 @@ impl LoadingMapEntity for Command {
         match self {
             Command::Mine(state) => state.map_entity(entity_map),
             Command::Trade(state) => state.map_entity(entity_map),
+            Command::Haul(state) => state.map_entity(entity_map),
+            Command::Patrol(state) => state.map_entity(entity_map),
+            Command::Escort { target_id } => target_id.map_entity(entity_map),
+            Command::Raid(state) => state.map_entity(entity_map),
+            Command::Salvage(state) => state.map_entity(entity_map),
+            Command::Explore(state) => state.map_entity(entity_map),
         }
     }
 }
@@ -119,10 +460,28 @@ pub struct FleetCommands;
 
 impl FleetCommands {}
 
+/// How much urgency of a trade order weight over distance when choosing a target
+pub const URGENCY_WEIGHT: f32 = 10.0;
+
+/// How much each second of expected docking queue weight over distance when choosing a target
+pub const QUEUE_TIME_WEIGHT: f32 = 0.2;
+
+/// How much the danger of a sector weight over distance when choosing a target
+pub const DANGER_WEIGHT: f32 = 2.0;
+
+/// Search the nearest and most urgent station providing or requesting wares, accepted by the
+/// ownership and with the shortest docking queue for the object
+#[allow(clippy::too_many_arguments)]
 pub fn search_orders_target(
     sectors_index: &EntityPerSectorIndex,
     sector_id: SectorId,
     orders: &Query<&TradeOrders>,
+    cargos: &Query<&mut Cargo>,
+    query_ownership: &QueryOwnership,
+    ownership: &Ownership,
+    discovery: Option<&FactionDiscovery>,
+    query_docking: &QueryDocking,
+    obj_id: ObjId,
     wares_filter: Option<&Vec<WareId>>,
     already_targeting: Vec<ObjId>,
     to_pickup: bool,
@@ -140,30 +499,50 @@ pub fn search_orders_target(
         );
     }
 
-    let candidates = sectors_index.search_nearest_stations(sector_id).flat_map(
-        |(_sector_id, distance, obj_id)| {
-            let order = orders.get(obj_id).ok().map(|orders| {
-                if to_pickup {
-                    orders.is_provide()
-                } else {
-                    orders.is_request_any(wares_filter.unwrap())
-                }
-            });
+    let urgency_of = |orders: &TradeOrders, cargo: &Cargo| -> f32 {
+        if to_pickup {
+            orders
+                .wares_provider()
+                .into_iter()
+                .map(|ware_id| orders.provide_urgency(cargo, ware_id))
+                .fold(0.0, f32::max)
+        } else {
+            orders
+                .request_any(wares_filter.unwrap())
+                .into_iter()
+                .map(|ware_id| orders.request_urgency(cargo, ware_id))
+                .fold(0.0, f32::max)
+        }
+    };
 
-            match order {
-                Some(true) => {
-                    let active_traders =
-                        already_targeting.iter().filter(|id| **id == obj_id).count() as u32;
+    let candidates = sectors_index
+        .search_nearest_stations(sector_id, discovery)
+        .flat_map(|(_sector_id, distance, candidate_id)| {
+            if !ownership.accept(query_ownership, candidate_id) {
+                return None;
+            }
 
-                    let weight = distance + active_traders;
-                    Some((weight, obj_id))
-                }
-                _ => None,
+            let urgency = urgency_of(
+                orders.get(candidate_id).ok()?,
+                cargos.get(candidate_id).ok()?,
+            );
+            if urgency <= 0.0 {
+                return None;
             }
-        },
-    );
 
-    match crate::game::utils::next_lower(candidates) {
+            let queue_time = Docking::expected_queue_time(query_docking, obj_id, candidate_id)?;
+
+            let active_traders = already_targeting
+                .iter()
+                .filter(|id| **id == candidate_id)
+                .count() as u32;
+
+            let weight = (distance + active_traders) as f32 - URGENCY_WEIGHT * urgency
+                + QUEUE_TIME_WEIGHT * queue_time.as_f32();
+            Some((weight, candidate_id))
+        });
+
+    match crate::game::utils::lower(candidates) {
         Some(target_id) => {
             let wares = {
                 let orders = orders.get(target_id).unwrap();
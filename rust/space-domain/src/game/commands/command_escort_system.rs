@@ -0,0 +1,177 @@
+use crate::game::commands::Command;
+use crate::game::fleets::FleetMember;
+use crate::game::locations::{LocationDocked, LocationSpace, Locations};
+use crate::game::navigations::{NavRequest, Navigation};
+use crate::game::utils::V2;
+
+use bevy_ecs::prelude::*;
+
+/// Position relative to the target of escorts that are not member of its group
+pub const ESCORT_OFFSET: V2 = V2::new(-0.5, 0.0);
+
+/// Max distance from the formation position before the escort move again
+pub const ESCORT_TOLERANCE: f32 = 0.5;
+
+/// Keep each escort close to its target, following it through jump gates and docking at the same
+/// station. Members of a group keep their formation offset from the leader.
+pub fn system_command_escort(
+    mut commands: Commands,
+    query: Query<
+        (Entity, &Command, Option<&FleetMember>),
+        (Without<Navigation>, Without<NavRequest>),
+    >,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+) {
+    log::trace!("running");
+
+    for (id, command, maybe_member) in &query {
+        let target_id = match command {
+            Command::Escort { target_id } => *target_id,
+            _ => continue,
+        };
+
+        let Ok((_, target_space, target_docked)) = query_locations.get(target_id) else {
+            log::warn!("{:?} escort target {:?} not found", id, target_id);
+            commands.entity(id).remove::<Command>();
+            continue;
+        };
+
+        if let Some(docked) = target_docked {
+            if !Locations::is_docked_at(&query_locations, id, docked.parent_id) {
+                log::debug!(
+                    "{:?} following {:?} to dock at {:?}",
+                    id,
+                    target_id,
+                    docked.parent_id
+                );
+                commands.entity(id).insert(NavRequest::MoveAndDockAt {
+                    target_id: docked.parent_id,
+                });
+            }
+            continue;
+        }
+
+        // target is jumping
+        let Some(target_space) = target_space else {
+            continue;
+        };
+
+        let offset = maybe_member
+            .filter(|member| member.leader_id == target_id)
+            .map(|member| member.offset)
+            .unwrap_or(ESCORT_OFFSET);
+        let pos = target_space.pos + offset;
+
+        let in_formation = match query_locations.get(id) {
+            Ok((_, Some(location), _)) => {
+                location.sector_id == target_space.sector_id
+                    && location.pos.distance(pos) <= ESCORT_TOLERANCE
+            }
+            _ => false,
+        };
+
+        if !in_formation {
+            log::trace!("{:?} following {:?} to {:?}", id, target_id, pos);
+            commands.entity(id).insert(NavRequest::MoveToPos {
+                sector_id: target_space.sector_id,
+                pos,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_escort_should_follow_target_in_formation() {
+        let mut world = World::new();
+        let sector_0 = world.spawn_empty().id();
+        let sector_1 = world.spawn_empty().id();
+
+        let leader_id = world
+            .spawn(LocationSpace {
+                pos: V2::new(2.0, 2.0),
+                sector_id: sector_1,
+            })
+            .id();
+        let member_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id: sector_0,
+                },
+                FleetMember {
+                    leader_id,
+                    offset: V2::new(-1.0, 0.0),
+                },
+                Command::Escort {
+                    target_id: leader_id,
+                },
+            ))
+            .id();
+
+        // leader in other sector
+        world.run_system_once(system_command_escort);
+        assert_eq!(
+            Some(&NavRequest::MoveToPos {
+                sector_id: sector_1,
+                pos: V2::new(1.0, 2.0)
+            }),
+            world.get::<NavRequest>(member_id)
+        );
+
+        // already in formation
+        world
+            .entity_mut(member_id)
+            .remove::<NavRequest>()
+            .insert(LocationSpace {
+                pos: V2::new(1.0, 2.1),
+                sector_id: sector_1,
+            });
+        world.run_system_once(system_command_escort);
+        assert!(world.get::<NavRequest>(member_id).is_none());
+    }
+
+    #[test]
+    fn test_escort_should_dock_with_target() {
+        let mut world = World::new();
+        let sector_id = world.spawn_empty().id();
+        let station_id = world
+            .spawn(LocationSpace {
+                pos: V2::ZERO,
+                sector_id,
+            })
+            .id();
+        let target_id = world
+            .spawn(LocationDocked {
+                parent_id: station_id,
+            })
+            .id();
+        let escort_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::new(1.0, 0.0),
+                    sector_id,
+                },
+                Command::Escort { target_id },
+            ))
+            .id();
+
+        world.run_system_once(system_command_escort);
+        assert_eq!(
+            Some(&NavRequest::MoveAndDockAt {
+                target_id: station_id
+            }),
+            world.get::<NavRequest>(escort_id)
+        );
+
+        // escort removed once target is gone
+        world.despawn(target_id);
+        world.entity_mut(escort_id).remove::<NavRequest>();
+        world.run_system_once(system_command_escort);
+        assert!(world.get::<Command>(escort_id).is_none());
+    }
+}
@@ -0,0 +1,237 @@
+use crate::game::actions::{Action, ActionActive, ActionRequest};
+use crate::game::commands::Command;
+use crate::game::discovery::{Discoveries, FactionDiscovery};
+use crate::game::factions::Owner;
+use crate::game::locations::{LocationDocked, LocationSpace, Locations};
+use crate::game::navigations::{NavRequest, Navigation};
+use crate::game::sectors::{Jump, JumpId, Sector, SectorId};
+
+use bevy_ecs::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+/// Move scouts to the nearest discovered jump leading to a sector not explored by its faction and
+/// jump into it, once there the discovery system reveals the sector.
+///
+/// Scouts of factions without discovery already know everything and stay idle.
+pub fn system_command_explore(
+    mut commands: Commands,
+    mut query: Query<
+        (Entity, &mut Command, Option<&Owner>),
+        (
+            Without<Navigation>,
+            Without<NavRequest>,
+            Without<ActionActive>,
+            Without<ActionRequest>,
+        ),
+    >,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+    query_sectors: Query<&Sector>,
+    query_jumps: Query<(&Jump, &LocationSpace)>,
+    discoveries: Option<Res<Discoveries>>,
+) {
+    log::trace!("running");
+
+    for (obj_id, mut command, owner) in &mut query {
+        let state = match command.as_mut() {
+            Command::Explore(state) => state,
+            _ => continue,
+        };
+
+        let Some(discovery) = discoveries.as_ref().and_then(|d| d.get_by_owner(owner)) else {
+            log::trace!("{:?} has nothing to explore", obj_id);
+            continue;
+        };
+
+        let Some(location) = Locations::resolve_space_position(&query_locations, obj_id) else {
+            continue;
+        };
+
+        // sector just entered and not revealed yet
+        if !discovery.is_explored(location.sector_id) {
+            continue;
+        }
+
+        if let Some(jump_id) = state.jump_id {
+            match query_jumps.get(jump_id) {
+                Ok((jump, _)) if discovery.is_explored(jump.target_sector_id) => {
+                    log::debug!(
+                        "{:?} target sector {:?} was explored, searching a new one",
+                        obj_id,
+                        jump.target_sector_id
+                    );
+                    state.jump_id = None;
+                }
+                Ok((_, jump_location)) => {
+                    let is_at_jump = query_locations
+                        .get(obj_id)
+                        .ok()
+                        .and_then(|(_, space, _)| space)
+                        .map(|space| Locations::is_near(space, jump_location))
+                        .unwrap_or(false);
+
+                    if is_at_jump {
+                        log::debug!(
+                            "{:?} jumping into unexplored sector by {:?}",
+                            obj_id,
+                            jump_id
+                        );
+                        commands
+                            .entity(obj_id)
+                            .insert(ActionRequest(Action::Jump { jump_id }));
+                        state.jump_id = None;
+                    } else {
+                        commands
+                            .entity(obj_id)
+                            .insert(NavRequest::MoveToTarget { target_id: jump_id });
+                    }
+                    continue;
+                }
+                Err(_) => {
+                    log::warn!("{:?} explore jump {:?} not found", obj_id, jump_id);
+                    state.jump_id = None;
+                }
+            }
+        }
+
+        match search_unexplored_jump(&query_sectors, discovery, location.sector_id) {
+            Some(jump_id) => {
+                log::debug!(
+                    "{:?} moving to {:?} to explore a new sector",
+                    obj_id,
+                    jump_id
+                );
+                state.jump_id = Some(jump_id);
+                commands
+                    .entity(obj_id)
+                    .insert(NavRequest::MoveToTarget { target_id: jump_id });
+            }
+            None => {
+                log::trace!("{:?} found no sector left to explore", obj_id);
+            }
+        }
+    }
+}
+
+/// Search through the known jumps the nearest discovered jump leading to an unexplored sector
+fn search_unexplored_jump(
+    query_sectors: &Query<&Sector>,
+    discovery: &FactionDiscovery,
+    from: SectorId,
+) -> Option<JumpId> {
+    let mut visited = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(sector_id) = queue.pop_front() {
+        let Some(jumps) = query_sectors
+            .get(sector_id)
+            .ok()
+            .and_then(|sector| sector.jumps_cache.as_ref())
+        else {
+            continue;
+        };
+
+        if let Some(jump) = jumps.iter().find(|jump| {
+            discovery.is_discovered(jump.jump_id) && !discovery.is_explored(jump.to_sector)
+        }) {
+            return Some(jump.jump_id);
+        }
+
+        for jump in jumps {
+            if discovery.is_known(jump) && visited.insert(jump.to_sector) {
+                queue.push_back(jump.to_sector);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::factions::Faction;
+    use crate::game::sectors::test_scenery::setup_sector_scenery;
+    use crate::game::utils::V2;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_explore_should_move_to_and_jump_into_unexplored_sector() {
+        let mut world = World::new();
+        let scenery = setup_sector_scenery(&mut world);
+
+        let faction_id = world.spawn(Faction::default()).id();
+        let mut discovery = FactionDiscovery::default();
+        discovery.sectors.insert(scenery.sector_0);
+        discovery.sectors.insert(scenery.sector_1);
+        discovery.jumps.insert(scenery.jump_0_to_1);
+        discovery.jumps.insert(scenery.jump_1_to_0);
+        discovery.jumps.insert(scenery.jump_1_to_2);
+        let mut discoveries = Discoveries::default();
+        discoveries.factions.insert(faction_id, discovery);
+        world.insert_resource(discoveries);
+
+        let ship_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id: scenery.sector_0,
+                },
+                Owner::new(faction_id),
+                Command::explore(),
+            ))
+            .id();
+
+        world.run_system_once(system_command_explore);
+        assert_eq!(
+            Some(&NavRequest::MoveToTarget {
+                target_id: scenery.jump_1_to_2
+            }),
+            world.get::<NavRequest>(ship_id)
+        );
+
+        // arrive at the jump
+        world
+            .entity_mut(ship_id)
+            .remove::<NavRequest>()
+            .insert(LocationSpace {
+                pos: scenery.jump_1_to_2_pos,
+                sector_id: scenery.sector_1,
+            });
+
+        world.run_system_once(system_command_explore);
+        assert_eq!(
+            Some(&Action::Jump {
+                jump_id: scenery.jump_1_to_2
+            }),
+            world.get::<ActionRequest>(ship_id).map(|i| i.get_action())
+        );
+        assert_eq!(
+            None,
+            world
+                .get::<Command>(ship_id)
+                .and_then(|i| i.as_explore())
+                .unwrap()
+                .jump_id
+        );
+    }
+
+    #[test]
+    fn test_explore_should_stay_idle_when_everything_is_known() {
+        let mut world = World::new();
+        let scenery = setup_sector_scenery(&mut world);
+        world.insert_resource(Discoveries::default());
+
+        let ship_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id: scenery.sector_0,
+                },
+                Command::explore(),
+            ))
+            .id();
+
+        world.run_system_once(system_command_explore);
+        assert!(world.get::<NavRequest>(ship_id).is_none());
+    }
+}
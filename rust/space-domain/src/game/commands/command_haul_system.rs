@@ -0,0 +1,239 @@
+use crate::game::commands::{Command, HaulAction};
+use crate::game::locations::{LocationDocked, LocationSpace, Locations};
+use crate::game::navigations::{NavRequest, Navigation};
+use crate::game::stats::EconomyStats;
+use crate::game::wares::{Cargo, Cargos, Volume};
+
+use bevy_ecs::prelude::*;
+
+/// Run the route of each hauler, moving to the current step station and loading or unloading
+/// the ware once docked
+pub fn system_command_haul(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Command), (Without<Navigation>, Without<NavRequest>)>,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+    mut query_cargos: Query<&mut Cargo>,
+    mut stats: Option<ResMut<EconomyStats>>,
+) {
+    log::trace!("running");
+
+    for (id, mut command) in &mut query {
+        let state = match command.as_mut() {
+            Command::Haul(state) => state,
+            _ => continue,
+        };
+
+        let Some(step) = state.get_current_step().cloned() else {
+            continue;
+        };
+
+        if !Locations::is_docked_at(&query_locations, id, step.target_id) {
+            log::debug!(
+                "{:?} navigating to haul step {:?} at {:?}",
+                id,
+                state.current,
+                step.target_id,
+            );
+            commands.entity(id).insert(NavRequest::MoveAndDockAt {
+                target_id: step.target_id,
+            });
+            continue;
+        }
+
+        let Ok(station_cargo) = query_cargos.get(step.target_id) else {
+            log::warn!(
+                "{:?} haul target {:?} has no cargo, skipping step",
+                id,
+                step.target_id
+            );
+            state.next_step();
+            continue;
+        };
+
+        let station_amount = station_cargo.get_amount(step.ware_id);
+        let (from_id, to_id, limit) = match step.action {
+            HaulAction::Load => {
+                let keep = step.threshold.unwrap_or(0);
+                (step.target_id, id, station_amount.saturating_sub(keep))
+            }
+            HaulAction::Unload => {
+                let room = step
+                    .threshold
+                    .map(|max| max.saturating_sub(station_cargo.get_stored_amount(step.ware_id)))
+                    .unwrap_or(Volume::MAX);
+                (id, step.target_id, room)
+            }
+        };
+
+        let limit = limit.min(step.amount.unwrap_or(Volume::MAX));
+        let wares = vec![step.ware_id];
+        let sector_id = Locations::resolve_space_position(&query_locations, step.target_id)
+            .map(|l| l.sector_id);
+        let transfer = if limit == Volume::MAX {
+            Cargos::move_only(
+                &mut query_cargos,
+                from_id,
+                to_id,
+                &wares,
+                stats.as_deref_mut(),
+                sector_id,
+            )
+        } else if limit > 0 {
+            Cargos::move_only_up_to(
+                &mut query_cargos,
+                from_id,
+                to_id,
+                &wares,
+                limit,
+                stats.as_deref_mut(),
+                sector_id,
+            )
+        } else {
+            Default::default()
+        };
+
+        log::info!(
+            "{:?} haul step {:?} {:?} at {:?}, moved {:?}",
+            id,
+            state.current,
+            step.action,
+            step.target_id,
+            transfer,
+        );
+
+        state.next_step();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::commands::HaulStep;
+    use crate::game::loader::Loader;
+    use crate::game::objects::ObjId;
+    use crate::game::utils::V2;
+    use crate::game::wares::WareId;
+    use bevy_ecs::system::RunSystemOnce;
+
+    struct SceneryResult {
+        hauler_id: ObjId,
+        station_0: ObjId,
+        station_1: ObjId,
+        ware_id: WareId,
+    }
+
+    fn setup_scenery(
+        world: &mut World,
+        route: impl Fn(&SceneryResult) -> Vec<HaulStep>,
+    ) -> SceneryResult {
+        let sector_id = world.spawn_empty().id();
+        let ware_id = world.spawn_empty().id();
+
+        let mut add_station = |amount| {
+            let mut cargo = Cargo::new(100);
+            if amount > 0 {
+                cargo.add(ware_id, amount).unwrap();
+            }
+            world
+                .spawn((
+                    LocationSpace {
+                        pos: V2::ZERO,
+                        sector_id,
+                    },
+                    cargo,
+                ))
+                .id()
+        };
+
+        let station_0 = add_station(50);
+        let station_1 = add_station(0);
+
+        let hauler_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id,
+                },
+                Cargo::new(30),
+            ))
+            .id();
+
+        let scenery = SceneryResult {
+            hauler_id,
+            station_0,
+            station_1,
+            ware_id,
+        };
+
+        world
+            .entity_mut(hauler_id)
+            .insert(Command::haul(route(&scenery)));
+
+        scenery
+    }
+
+    fn load_and_unload(scenery: &SceneryResult) -> Vec<HaulStep> {
+        vec![
+            HaulStep {
+                target_id: scenery.station_0,
+                action: HaulAction::Load,
+                ware_id: scenery.ware_id,
+                amount: None,
+                threshold: Some(40),
+            },
+            HaulStep {
+                target_id: scenery.station_1,
+                action: HaulAction::Unload,
+                ware_id: scenery.ware_id,
+                amount: Some(6),
+                threshold: None,
+            },
+        ]
+    }
+
+    fn get_amount(world: &World, obj_id: ObjId, ware_id: WareId) -> Volume {
+        world.get::<Cargo>(obj_id).unwrap().get_amount(ware_id)
+    }
+
+    fn get_current(world: &World, obj_id: ObjId) -> usize {
+        world
+            .get::<Command>(obj_id)
+            .unwrap()
+            .as_haul()
+            .unwrap()
+            .current
+    }
+
+    #[test]
+    fn test_command_haul_should_navigate_to_current_step() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world, load_and_unload);
+
+        world.run_system_once(system_command_haul);
+
+        Loader::assert_nav_request_dock_at(&world, scenery.hauler_id, scenery.station_0);
+        assert_eq!(0, get_current(&world, scenery.hauler_id));
+    }
+
+    #[test]
+    fn test_command_haul_should_load_and_unload_respecting_threshold_and_amount() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world, load_and_unload);
+
+        Loader::set_docked_at(&mut world, scenery.hauler_id, scenery.station_0);
+        world.run_system_once(system_command_haul);
+
+        assert_eq!(40, get_amount(&world, scenery.station_0, scenery.ware_id));
+        assert_eq!(10, get_amount(&world, scenery.hauler_id, scenery.ware_id));
+        assert_eq!(1, get_current(&world, scenery.hauler_id));
+
+        Loader::set_docked_at(&mut world, scenery.hauler_id, scenery.station_1);
+        world.run_system_once(system_command_haul);
+
+        assert_eq!(6, get_amount(&world, scenery.station_1, scenery.ware_id));
+        assert_eq!(4, get_amount(&world, scenery.hauler_id, scenery.ware_id));
+
+        // route loops back to the first step
+        assert_eq!(0, get_current(&world, scenery.hauler_id));
+    }
+}
@@ -1,10 +1,14 @@
 use bevy_ecs::prelude::*;
 
 use super::*;
+use crate::game::discovery::Discoveries;
+use crate::game::dock::QueryDocking;
 use crate::game::extractables::Extractable;
 use crate::game::locations::{EntityPerSectorIndex, LocationDocked, LocationOrbit, LocationSpace};
-use crate::game::navigations::{NavRequest, Navigation};
+use crate::game::navigations::{NavRequest, NavRequestFailed, Navigation};
 use crate::game::order::TradeOrders;
+use crate::game::prices::{Credits, Prices, WarePrice};
+use crate::game::stats::EconomyStats;
 use crate::game::wares::{Cargo, WareId};
 use commons::unwrap_or_continue;
 
@@ -25,10 +29,16 @@ pub struct CommandMineSystem;
 /// - deliver cargo
 ///
 ///
+#[allow(clippy::too_many_arguments)]
 pub fn system_command_mine(
     mut commands: Commands,
     mut query: Query<
-        (Entity, Option<&LocationOrbit>, &mut Command),
+        (
+            Entity,
+            Option<&LocationOrbit>,
+            &mut Command,
+            Option<&NavRequestFailed>,
+        ),
         (
             Without<Navigation>,
             Without<ActionExtract>,
@@ -40,7 +50,13 @@ pub fn system_command_mine(
     query_extractables: Query<(Entity, &Extractable, &LocationSpace)>,
     query_orders: Query<&TradeOrders>,
     mut query_cargos: Query<&mut Cargo>,
+    mut query_credits: Query<&mut Credits>,
+    query_prices: Query<&WarePrice>,
+    query_ownership: QueryOwnership,
+    query_docking: QueryDocking,
     sector_index: Res<EntityPerSectorIndex>,
+    discoveries: Option<Res<Discoveries>>,
+    mut stats: Option<ResMut<EconomyStats>>,
 ) {
     log::trace!("running");
 
@@ -48,7 +64,7 @@ pub fn system_command_mine(
     let mut already_targets: HashMap<ObjId, u32> = HashMap::new();
 
     // collect all already target extractables to avoid funnel into same target
-    for (_, _, command) in &query {
+    for (_, _, command, _) in &query {
         match command {
             Command::Mine(mine) => {
                 if let Some(target_id) = &mine.mine_target_id {
@@ -59,13 +75,22 @@ pub fn system_command_mine(
         };
     }
 
-    for (id, maybe_orbit, mut command) in &mut query {
+    for (id, maybe_orbit, mut command, maybe_failed) in &mut query {
         let command = match command.as_mine_mut() {
             Some(mine) => mine,
             _ => continue,
         };
 
+        if maybe_failed.is_some_and(|failed| failed.is_out_of_fuel()) {
+            // wait a tick to be sent to refuel
+            log::debug!("{:?} can not reach mine target for lack of fuel", id);
+            commands.entity(id).remove::<NavRequestFailed>();
+            continue;
+        }
+
         let cargo = unwrap_or_continue!(query_cargos.get_mut(id).ok());
+        let ownership = Ownership::of(&query_ownership, id);
+        let discovery = Discoveries::of(discoveries.as_deref(), ownership.faction_id);
 
         if cargo.is_full() {
             // deliver cargo
@@ -84,6 +109,12 @@ pub fn system_command_mine(
                         &sector_index,
                         sector_id,
                         &query_orders,
+                        &query_cargos,
+                        &query_ownership,
+                        &ownership,
+                        discovery,
+                        &query_docking,
+                        id,
                         Some(&wares_to_deliver),
                         Vec::new(),
                         false,
@@ -136,22 +167,35 @@ pub fn system_command_mine(
                     .insert(NavRequest::MoveAndDockAt { target_id });
             }
         } else {
-            // cargo is not full
-            let target_id = match command.mine_target_id {
+            // cargo is not full, depleted targets are replaced
+            let current_target_id = command.mine_target_id.filter(|target_id| {
+                query_extractables
+                    .get(*target_id)
+                    .is_ok_and(|(_, extractable, _)| !extractable.is_depleted())
+            });
+
+            let target_id = match current_target_id {
                 Some(id) => id,
                 None => {
                     let sector_id = Locations::resolve_space_position(&query_locations, id)
                         .unwrap()
                         .sector_id;
 
-                    let target_id =
-                        match search_mine_target(&sector_index, &already_targets, sector_id) {
-                            Some(target_id) => target_id,
-                            None => {
-                                log::debug!("{:?} fail to find any target to mine, ignoring", id);
-                                continue;
-                            }
-                        };
+                    let target_id = match search_mine_target(
+                        &sector_index,
+                        &query_extractables,
+                        &query_ownership,
+                        &ownership,
+                        discovery,
+                        &already_targets,
+                        sector_id,
+                    ) {
+                        Some(target_id) => target_id,
+                        None => {
+                            log::debug!("{:?} fail to find any target to mine, ignoring", id);
+                            continue;
+                        }
+                    };
 
                     command.mine_target_id = Some(target_id);
                     command.deliver_target_id = None;
@@ -203,19 +247,49 @@ pub fn system_command_mine(
 
     // transfer all cargos
     for (from_id, to_id) in cargo_transfers {
-        let transfer = Cargos::move_all(&mut query_cargos, from_id, to_id);
-        log::info!("{:?} transfer {:?} to {:?}", from_id, transfer, to_id);
+        let wares: Vec<WareId> = query_cargos.get(from_id).unwrap().get_wares_ids().collect();
+        let prices =
+            Prices::list_buy_prices(&query_prices, query_cargos.get(to_id).unwrap(), &wares);
+        let sector_id =
+            Locations::resolve_space_position(&query_locations, to_id).map(|l| l.sector_id);
+        let transfer = Cargos::move_all(
+            &mut query_cargos,
+            from_id,
+            to_id,
+            stats.as_deref_mut(),
+            sector_id,
+        );
+        let value = Prices::transfer_value(&transfer, &prices);
+        Prices::settle(&mut query_credits, from_id, to_id, value);
+        log::info!(
+            "{:?} transfer {:?} to {:?} for {:?} credits",
+            from_id,
+            transfer,
+            to_id,
+            value
+        );
     }
 }
 
 fn search_mine_target(
     sectors_index: &EntityPerSectorIndex,
+    query_extractables: &Query<(Entity, &Extractable, &LocationSpace)>,
+    query_ownership: &QueryOwnership,
+    ownership: &Ownership,
+    discovery: Option<&FactionDiscovery>,
     already_targets: &HashMap<ObjId, u32>,
     sector_id: SectorId,
 ) -> Option<ObjId> {
-    // find nearest extractable
+    // find nearest extractable, free extractables are always accepted
     let mut candidates = sectors_index
-        .search_nearest_extractable(sector_id)
+        .search_nearest_extractable(sector_id, discovery)
+        .filter(|(_, _, obj_id)| {
+            (Ownership::get_owner(query_ownership, *obj_id).is_none()
+                || ownership.accept(query_ownership, *obj_id))
+                && query_extractables
+                    .get(*obj_id)
+                    .is_ok_and(|(_, extractable, _)| !extractable.is_depleted())
+        })
         .map(|(_, distance, obj_id)| {
             let count = already_targets.get(&obj_id).cloned().unwrap_or(0);
             let score = count * 10 + distance * 11;
@@ -233,6 +307,7 @@ fn search_mine_target(
 mod test {
     use super::*;
     use crate::game::dock::HasDocking;
+    use crate::game::factions::{Faction, Owner, OwnershipFilter};
     use crate::game::label::Label;
     use crate::game::loader::Loader;
     use crate::game::order::TRADE_ORDER_ID_EXTRACTABLE;
@@ -261,6 +336,7 @@ mod test {
             .insert(Extractable {
                 ware_id,
                 accessibility: 1.0,
+                reserve: None,
             })
             .id()
     }
@@ -358,6 +434,48 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_command_mine_should_accept_free_extractables_with_own_ownership() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+        let faction_id = world
+            .spawn(Faction {
+                ownership: OwnershipFilter::Own,
+                ..Default::default()
+            })
+            .id();
+        world
+            .entity_mut(scenery.miner_id)
+            .insert(Owner::new(faction_id));
+
+        world.run_system_once(system_command_mine);
+
+        let command = world.get::<Command>(scenery.miner_id).unwrap();
+        assert_eq!(
+            Some(scenery.asteroid_id),
+            command.as_mine().unwrap().mine_target_id
+        );
+    }
+
+    #[test]
+    fn test_command_mine_should_ignore_extractables_in_unexplored_sectors() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+        let faction_id = world.spawn(Faction::default()).id();
+        world
+            .entity_mut(scenery.miner_id)
+            .insert(Owner::new(faction_id));
+
+        let mut discoveries = Discoveries::default();
+        discoveries.factions.insert(faction_id, Default::default());
+        world.insert_resource(discoveries);
+
+        world.run_system_once(system_command_mine);
+
+        let command = world.get::<Command>(scenery.miner_id).unwrap();
+        assert_eq!(None, command.as_mine().unwrap().mine_target_id);
+    }
+
     #[test]
     fn test_command_mine_should_mine() {
         let mut world = World::new();
@@ -376,6 +494,45 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_command_mine_should_replace_depleted_target() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+        set_miner_to_asteroid_orbit(&mut world, &scenery);
+
+        let asteroid_1 = create_asteroid(
+            &mut world,
+            LocationSpace {
+                pos: V2::new(2.0, 0.0),
+                sector_id: scenery.sector_scenery.sector_0,
+            },
+            scenery.ware_id,
+        );
+        world
+            .resource_mut::<EntityPerSectorIndex>()
+            .add_extractable(scenery.sector_scenery.sector_0, asteroid_1);
+
+        world
+            .get_mut::<Command>(scenery.miner_id)
+            .unwrap()
+            .as_mine_mut()
+            .unwrap()
+            .mine_target_id = Some(scenery.asteroid_id);
+        world
+            .get_mut::<Extractable>(scenery.asteroid_id)
+            .unwrap()
+            .reserve = Some(0);
+
+        world.run_system_once(system_command_mine);
+
+        let command = world.get::<Command>(scenery.miner_id).unwrap();
+        assert_eq!(Some(asteroid_1), command.as_mine().unwrap().mine_target_id);
+        match world.get::<NavRequest>(scenery.miner_id) {
+            Some(NavRequest::OrbitTarget { target_id }) => assert_eq!(asteroid_1, *target_id),
+            other => panic!("invalid request {:?}", other),
+        }
+    }
+
     #[test]
     fn test_command_mine_should_be_wait_if_cargo_is_full_and_has_no_target_station() {
         let mut world = World::new();
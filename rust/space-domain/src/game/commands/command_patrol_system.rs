@@ -0,0 +1,214 @@
+use crate::game::commands::{Command, PatrolTarget};
+use crate::game::navigations::{NavRequest, NavRequestFailed, Navigation};
+use crate::game::utils::TotalTime;
+
+use bevy_ecs::prelude::*;
+
+/// Move each patrol ship through its waypoints in order, waiting the dwell time at each one.
+///
+/// A waypoint is reached once the navigation to it completes, waypoints that can not be reached
+/// are skipped.
+pub fn system_command_patrol(
+    mut commands: Commands,
+    total_time: Res<TotalTime>,
+    mut query: Query<
+        (Entity, &mut Command, Option<&NavRequestFailed>),
+        (Without<Navigation>, Without<NavRequest>),
+    >,
+) {
+    log::trace!("running");
+
+    let now = *total_time;
+
+    for (id, mut command, maybe_failed) in &mut query {
+        let state = match command.as_mut() {
+            Command::Patrol(state) => state,
+            _ => continue,
+        };
+
+        let Some(waypoint) = state.get_current_waypoint().cloned() else {
+            continue;
+        };
+
+        if let Some(deadline) = state.dwell_until {
+            if now.is_before(deadline) {
+                continue;
+            }
+
+            state.next_waypoint();
+            continue;
+        }
+
+        if state.moving && maybe_failed.is_some() {
+            log::warn!(
+                "{:?} skipping unreachable patrol waypoint {:?}",
+                id,
+                state.current
+            );
+            commands.entity(id).remove::<NavRequestFailed>();
+            state.moving = false;
+            state.next_waypoint();
+            continue;
+        }
+
+        if state.moving {
+            log::debug!("{:?} arrive at patrol waypoint {:?}", id, state.current);
+            match waypoint.dwell {
+                Some(dwell) => {
+                    state.moving = false;
+                    state.dwell_until = Some(now.add(dwell));
+                }
+                None => state.next_waypoint(),
+            }
+            continue;
+        }
+
+        log::debug!(
+            "{:?} navigating to patrol waypoint {:?} at {:?}",
+            id,
+            state.current,
+            waypoint.target,
+        );
+
+        let request = match waypoint.target {
+            PatrolTarget::Pos { sector_id, pos } => NavRequest::MoveToPos { sector_id, pos },
+            PatrolTarget::Obj { target_id } => NavRequest::MoveToTarget { target_id },
+        };
+        commands.entity(id).insert(request);
+        state.moving = true;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::commands::PatrolWaypoint;
+    use crate::game::locations::LocationSpace;
+    use crate::game::utils::{DeltaTime, V2};
+    use bevy_ecs::system::RunSystemOnce;
+
+    fn get_request(world: &World, id: Entity) -> Option<NavRequest> {
+        world.get::<NavRequest>(id).cloned()
+    }
+
+    fn get_current(world: &World, id: Entity) -> usize {
+        world
+            .get::<Command>(id)
+            .and_then(|command| command.as_patrol())
+            .unwrap()
+            .current
+    }
+
+    /// simulate the navigation completing
+    fn arrive(world: &mut World, id: Entity) {
+        world.entity_mut(id).remove::<NavRequest>();
+    }
+
+    #[test]
+    fn test_patrol_should_loop_through_waypoints_waiting_dwell() {
+        let mut world = World::new();
+        world.insert_resource(TotalTime(0.0));
+
+        let sector_id = world.spawn_empty().id();
+        let station_id = world
+            .spawn(LocationSpace {
+                pos: V2::new(5.0, 0.0),
+                sector_id,
+            })
+            .id();
+
+        let ship_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id,
+                },
+                Command::patrol(vec![
+                    PatrolWaypoint::pos(sector_id, V2::new(1.0, 1.0)).with_dwell(DeltaTime(2.0)),
+                    PatrolWaypoint::obj(station_id),
+                ]),
+            ))
+            .id();
+
+        // move to first waypoint
+        world.run_system_once(system_command_patrol);
+        assert_eq!(
+            Some(NavRequest::MoveToPos {
+                sector_id,
+                pos: V2::new(1.0, 1.0)
+            }),
+            get_request(&world, ship_id)
+        );
+
+        // arrive and wait
+        arrive(&mut world, ship_id);
+        world.run_system_once(system_command_patrol);
+        world.run_system_once(system_command_patrol);
+        assert_eq!(None, get_request(&world, ship_id));
+        assert_eq!(0, get_current(&world, ship_id));
+
+        // dwell complete, move to the object
+        world.insert_resource(TotalTime(3.0));
+        world.run_system_once(system_command_patrol);
+        world.run_system_once(system_command_patrol);
+        assert_eq!(1, get_current(&world, ship_id));
+        assert_eq!(
+            Some(NavRequest::MoveToTarget {
+                target_id: station_id
+            }),
+            get_request(&world, ship_id)
+        );
+
+        // without dwell, restart from the first waypoint
+        arrive(&mut world, ship_id);
+        world.run_system_once(system_command_patrol);
+        world.run_system_once(system_command_patrol);
+        assert_eq!(0, get_current(&world, ship_id));
+        assert!(matches!(
+            get_request(&world, ship_id),
+            Some(NavRequest::MoveToPos { .. })
+        ));
+    }
+
+    #[test]
+    fn test_patrol_should_skip_unreachable_waypoint_without_dwell() {
+        let mut world = World::new();
+        world.insert_resource(TotalTime(0.0));
+
+        let sector_id = world.spawn_empty().id();
+        let ship_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id,
+                },
+                Command::patrol(vec![
+                    PatrolWaypoint::pos(sector_id, V2::new(1.0, 1.0)).with_dwell(DeltaTime(2.0)),
+                    PatrolWaypoint::pos(sector_id, V2::new(2.0, 2.0)),
+                ]),
+            ))
+            .id();
+
+        world.run_system_once(system_command_patrol);
+
+        // simulate the navigation failing
+        let request = world.entity_mut(ship_id).take::<NavRequest>().unwrap();
+        world.entity_mut(ship_id).insert(NavRequestFailed {
+            request,
+            error: "unreachable",
+        });
+
+        world.run_system_once(system_command_patrol);
+        assert_eq!(1, get_current(&world, ship_id));
+        assert!(world.get::<NavRequestFailed>(ship_id).is_none());
+
+        world.run_system_once(system_command_patrol);
+        assert_eq!(
+            Some(NavRequest::MoveToPos {
+                sector_id,
+                pos: V2::new(2.0, 2.0)
+            }),
+            get_request(&world, ship_id)
+        );
+    }
+}
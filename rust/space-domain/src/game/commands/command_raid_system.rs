@@ -0,0 +1,529 @@
+use crate::game::commands::{search_orders_target, Command, RaidState};
+use crate::game::discovery::Discoveries;
+use crate::game::dock::QueryDocking;
+use crate::game::events::{CommandSendEvent, EventKind, GEvent};
+use crate::game::factions::{Ownership, QueryOwnership};
+use crate::game::locations::{EntityPerSectorIndex, LocationDocked, LocationSpace, Locations};
+use crate::game::navigations::{NavRequest, Navigation};
+use crate::game::objects::ObjId;
+use crate::game::order::TradeOrders;
+use crate::game::prices::{Credits, Prices, WarePrice};
+use crate::game::raiders::{Raider, SectorDanger, RAID_DANGER};
+use crate::game::sectors::SectorId;
+use crate::game::stats::EconomyStats;
+use crate::game::utils::V2;
+use crate::game::wares::{Cargo, Cargos, Volume, WareId};
+
+use bevy_ecs::prelude::*;
+
+/// Max distance to a trader to rob it
+pub const RAID_RANGE: f32 = 0.5;
+
+/// Share of each ware in the trader cargo taken on a raid
+pub const LOOT_RATIO: f32 = 0.5;
+
+/// Raiders hunt traders carrying cargo in the sector they are, rob part of the cargo once
+/// intercepted and retreat to their base to sell the loot, or to the nearest station buying it
+/// when the base is full or gone. Without traders to hunt they go back to their base.
+#[allow(clippy::too_many_arguments)]
+pub fn system_command_raid(
+    mut commands: Commands,
+    sectors_index: Res<EntityPerSectorIndex>,
+    query: Query<(Entity, &Command, &Raider), (Without<Navigation>, Without<NavRequest>)>,
+    query_commands: Query<(Entity, &Command)>,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+    mut query_cargos: Query<&mut Cargo>,
+    query_orders: Query<&TradeOrders>,
+    mut query_credits: Query<&mut Credits>,
+    query_prices: Query<&WarePrice>,
+    query_ownership: QueryOwnership,
+    query_docking: QueryDocking,
+    mut query_danger: Query<&mut SectorDanger>,
+    discoveries: Option<Res<Discoveries>>,
+    mut stats: Option<ResMut<EconomyStats>>,
+) {
+    log::trace!("running");
+
+    // traders already being intercepted
+    let mut targeted: Vec<ObjId> = query_commands
+        .iter()
+        .filter_map(|(_, command)| match command {
+            Command::Raid(RaidState::Intercept { target_id }) => Some(*target_id),
+            _ => None,
+        })
+        .collect();
+
+    for (id, command, raider) in &query {
+        let Some(state) = command.as_raid() else {
+            continue;
+        };
+
+        let Some(location) = Locations::resolve_space_position(&query_locations, id) else {
+            continue;
+        };
+
+        match state {
+            RaidState::Hunt => {
+                let cargo_full = query_cargos
+                    .get(id)
+                    .map(|cargo| cargo.is_full())
+                    .unwrap_or(true);
+                if cargo_full {
+                    let wares: Vec<WareId> = query_cargos
+                        .get(id)
+                        .map(|cargo| cargo.get_wares_ids().collect())
+                        .unwrap_or_default();
+                    if !wares.is_empty() {
+                        retreat(
+                            &mut commands,
+                            &sectors_index,
+                            &query_orders,
+                            &query_cargos,
+                            &query_ownership,
+                            &query_docking,
+                            discoveries.as_deref(),
+                            id,
+                            raider.base_id,
+                            location.sector_id,
+                            wares,
+                        );
+                    }
+                    continue;
+                }
+
+                let target = query_commands
+                    .iter()
+                    .filter(|(_, command)| matches!(command, Command::Trade(_)))
+                    .filter(|(target_id, _)| !targeted.contains(target_id))
+                    .filter(|(target_id, _)| {
+                        query_cargos
+                            .get(*target_id)
+                            .map(|cargo| !cargo.is_empty())
+                            .unwrap_or(false)
+                    })
+                    .filter_map(|(target_id, _)| match query_locations.get(target_id) {
+                        Ok((_, Some(target_location), _))
+                            if target_location.sector_id == location.sector_id =>
+                        {
+                            Some((target_id, target_location.pos.distance(location.pos)))
+                        }
+                        _ => None,
+                    })
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+
+                match target {
+                    Some((target_id, _)) => {
+                        log::debug!("{:?} raider intercepting {:?}", id, target_id);
+                        targeted.push(target_id);
+                        commands
+                            .entity(id)
+                            .insert(Command::Raid(RaidState::Intercept { target_id }))
+                            .insert(NavRequest::MoveToTarget { target_id });
+                    }
+                    None => match raider.base_id {
+                        Some(base_id) if Locations::is_docked_at(&query_locations, id, base_id) => {
+                        }
+                        Some(base_id) if query_cargos.contains(base_id) => {
+                            log::debug!("{:?} raider nothing to hunt, back to base", id);
+                            commands
+                                .entity(id)
+                                .insert(NavRequest::MoveAndDockAt { target_id: base_id });
+                        }
+                        _ if location.sector_id != raider.home_sector_id => {
+                            log::debug!("{:?} raider nothing to hunt, back to home sector", id);
+                            commands.entity(id).insert(NavRequest::MoveToPos {
+                                sector_id: raider.home_sector_id,
+                                pos: V2::ZERO,
+                            });
+                        }
+                        _ => {}
+                    },
+                }
+            }
+            RaidState::Intercept { target_id } => {
+                let target_id = *target_id;
+
+                let in_range = match query_locations.get(target_id) {
+                    Ok((_, Some(target_location), _)) => {
+                        target_location.sector_id == location.sector_id
+                            && target_location.pos.distance(location.pos) <= RAID_RANGE
+                    }
+                    _ => false,
+                };
+
+                if !in_range || !query_cargos.contains(target_id) {
+                    log::debug!("{:?} raider lost the target {:?}", id, target_id);
+                    commands.entity(id).insert(Command::raid());
+                    continue;
+                }
+
+                let loot = rob(
+                    &mut query_cargos,
+                    target_id,
+                    id,
+                    stats.as_deref_mut(),
+                    location.sector_id,
+                );
+                if loot.is_empty() {
+                    log::debug!("{:?} raider fail to rob {:?}", id, target_id);
+                    commands.entity(id).insert(Command::raid());
+                    continue;
+                }
+
+                log::info!("{:?} raider rob {:?} from {:?}", id, loot, target_id);
+                commands.add(CommandSendEvent::from(GEvent::new(
+                    target_id,
+                    EventKind::Raided,
+                )));
+
+                match query_danger.get_mut(location.sector_id) {
+                    Ok(mut danger) => danger.value += RAID_DANGER,
+                    Err(_) => {
+                        commands
+                            .entity(location.sector_id)
+                            .insert(SectorDanger { value: RAID_DANGER });
+                    }
+                }
+
+                let wares: Vec<WareId> = loot.iter().map(|(ware_id, _)| *ware_id).collect();
+                retreat(
+                    &mut commands,
+                    &sectors_index,
+                    &query_orders,
+                    &query_cargos,
+                    &query_ownership,
+                    &query_docking,
+                    discoveries.as_deref(),
+                    id,
+                    raider.base_id,
+                    location.sector_id,
+                    wares,
+                );
+            }
+            RaidState::Retreat { target_id, wares } => {
+                let target_id = *target_id;
+
+                if !Locations::is_docked_at(&query_locations, id, target_id) {
+                    commands
+                        .entity(id)
+                        .insert(NavRequest::MoveAndDockAt { target_id });
+                    continue;
+                }
+
+                Cargos::release(&mut query_cargos, id, target_id);
+                let prices = Prices::list_buy_prices(
+                    &query_prices,
+                    query_cargos.get(target_id).unwrap(),
+                    wares,
+                );
+                let transfer = Cargos::move_only(
+                    &mut query_cargos,
+                    id,
+                    target_id,
+                    wares,
+                    stats.as_deref_mut(),
+                    Some(location.sector_id),
+                );
+                let value = Prices::transfer_value(&transfer, &prices);
+                Prices::settle(&mut query_credits, id, target_id, value);
+
+                log::info!(
+                    "{:?} raider sell loot {:?} to station {:?} for {:?} credits",
+                    id,
+                    transfer,
+                    target_id,
+                    value,
+                );
+
+                // wares the station could not take are kept to sell later
+                let remaining: Vec<WareId> = query_cargos
+                    .get(id)
+                    .map(|cargo| cargo.get_wares_ids().collect())
+                    .unwrap_or_default();
+                if remaining.is_empty() || transfer.moved.is_empty() {
+                    commands.entity(id).insert(Command::raid());
+                } else {
+                    retreat(
+                        &mut commands,
+                        &sectors_index,
+                        &query_orders,
+                        &query_cargos,
+                        &query_ownership,
+                        &query_docking,
+                        discoveries.as_deref(),
+                        id,
+                        raider.base_id,
+                        location.sector_id,
+                        remaining,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Take part of each ware of the trader, including wares reserved for its trade
+fn rob(
+    query_cargos: &mut Query<&mut Cargo>,
+    target_id: ObjId,
+    raider_id: ObjId,
+    mut stats: Option<&mut EconomyStats>,
+    sector_id: SectorId,
+) -> Vec<(WareId, Volume)> {
+    let owners: Vec<ObjId> = query_cargos
+        .get(target_id)
+        .map(|cargo| {
+            cargo
+                .get_reservations()
+                .iter()
+                .map(|r| r.owner_id)
+                .collect()
+        })
+        .unwrap_or_default();
+    for owner_id in owners {
+        Cargos::release(query_cargos, target_id, owner_id);
+    }
+
+    let wares: Vec<(WareId, Volume)> = query_cargos
+        .get(target_id)
+        .map(|cargo| {
+            cargo
+                .get_wares_ids()
+                .map(|ware_id| (ware_id, cargo.get_amount(ware_id)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut loot = vec![];
+    for (ware_id, amount) in wares {
+        let amount = ((amount as f32) * LOOT_RATIO).ceil() as Volume;
+        let transfer = Cargos::move_only_up_to(
+            query_cargos,
+            target_id,
+            raider_id,
+            &vec![ware_id],
+            amount,
+            stats.as_deref_mut(),
+            Some(sector_id),
+        );
+        loot.extend(transfer.moved.iter().map(|i| (i.ware_id, i.amount)));
+    }
+    loot
+}
+
+/// Move to the base while it has space for the wares, otherwise to the best station buying them,
+/// hunting again if none is found
+#[allow(clippy::too_many_arguments)]
+fn retreat(
+    commands: &mut Commands,
+    sectors_index: &EntityPerSectorIndex,
+    query_orders: &Query<&TradeOrders>,
+    query_cargos: &Query<&mut Cargo>,
+    query_ownership: &QueryOwnership,
+    query_docking: &QueryDocking,
+    discoveries: Option<&Discoveries>,
+    id: ObjId,
+    base_id: Option<ObjId>,
+    sector_id: SectorId,
+    wares: Vec<WareId>,
+) {
+    let base_id = base_id.filter(|base_id| {
+        query_cargos.get(*base_id).is_ok_and(|cargo| {
+            wares
+                .iter()
+                .any(|ware_id| cargo.free_volume(*ware_id).unwrap_or(0) > 0)
+        })
+    });
+    if let Some(target_id) = base_id {
+        log::debug!("{:?} raider retreat to base {:?}", id, target_id);
+        commands
+            .entity(id)
+            .insert(Command::Raid(RaidState::Retreat { target_id, wares }))
+            .insert(NavRequest::MoveAndDockAt { target_id });
+        return;
+    }
+
+    let ownership = Ownership::of(query_ownership, id);
+    let target = search_orders_target(
+        sectors_index,
+        sector_id,
+        query_orders,
+        query_cargos,
+        query_ownership,
+        &ownership,
+        Discoveries::of(discoveries, ownership.faction_id),
+        query_docking,
+        id,
+        Some(&wares),
+        vec![],
+        false,
+    );
+
+    match target {
+        Some((target_id, wares)) if !wares.is_empty() => {
+            log::debug!(
+                "{:?} raider retreat to sell {:?} at {:?}",
+                id,
+                wares,
+                target_id
+            );
+            commands
+                .entity(id)
+                .insert(Command::Raid(RaidState::Retreat { target_id, wares }))
+                .insert(NavRequest::MoveAndDockAt { target_id });
+        }
+        _ => {
+            log::debug!("{:?} raider found no station to sell {:?}", id, wares);
+            commands.entity(id).insert(Command::raid());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::events::GEvents;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_raider_should_intercept_and_rob_trader() {
+        let mut world = World::new();
+        world.insert_resource(GEvents::default());
+        world.insert_resource(EntityPerSectorIndex::new());
+
+        let sector_id = world.spawn_empty().id();
+        let ware_id = world.spawn_empty().id();
+
+        let mut trader_cargo = Cargo::new(20);
+        trader_cargo.add(ware_id, 10).unwrap();
+        let trader_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::new(2.0, 0.0),
+                    sector_id,
+                },
+                trader_cargo,
+                Command::trade(),
+            ))
+            .id();
+
+        let raider_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id,
+                },
+                Cargo::new(20),
+                Command::raid(),
+                Raider {
+                    home_sector_id: sector_id,
+                    base_id: None,
+                },
+            ))
+            .id();
+
+        // find the trader
+        world.run_system_once(system_command_raid);
+        assert_eq!(
+            Some(&NavRequest::MoveToTarget {
+                target_id: trader_id
+            }),
+            world.get::<NavRequest>(raider_id)
+        );
+
+        // intercepted
+        world
+            .entity_mut(raider_id)
+            .remove::<NavRequest>()
+            .insert(LocationSpace {
+                pos: V2::new(2.0, 0.0),
+                sector_id,
+            });
+        world.run_system_once(system_command_raid);
+
+        assert_eq!(
+            5,
+            world.get::<Cargo>(trader_id).unwrap().get_amount(ware_id)
+        );
+        assert_eq!(
+            5,
+            world.get::<Cargo>(raider_id).unwrap().get_amount(ware_id)
+        );
+        assert!(world
+            .resource::<GEvents>()
+            .list()
+            .iter()
+            .any(|e| e.id == trader_id && e.kind == EventKind::Raided));
+        assert_eq!(
+            RAID_DANGER,
+            world.get::<SectorDanger>(sector_id).unwrap().value
+        );
+
+        // no station to sell, hunt again
+        assert!(matches!(
+            world.get::<Command>(raider_id),
+            Some(Command::Raid(RaidState::Hunt))
+        ));
+    }
+
+    #[test]
+    fn test_raider_should_retreat_to_base_after_rob() {
+        let mut world = World::new();
+        world.insert_resource(GEvents::default());
+        world.insert_resource(EntityPerSectorIndex::new());
+
+        let sector_id = world.spawn_empty().id();
+        let ware_id = world.spawn_empty().id();
+
+        let mut trader_cargo = Cargo::new(20);
+        trader_cargo.add(ware_id, 10).unwrap();
+        let trader_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id,
+                },
+                trader_cargo,
+                Command::trade(),
+            ))
+            .id();
+
+        let base_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::new(5.0, 0.0),
+                    sector_id,
+                },
+                Cargo::new(100),
+            ))
+            .id();
+
+        let raider_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id,
+                },
+                Cargo::new(20),
+                Command::Raid(RaidState::Intercept {
+                    target_id: trader_id,
+                }),
+                Raider {
+                    home_sector_id: sector_id,
+                    base_id: Some(base_id),
+                },
+            ))
+            .id();
+
+        world.run_system_once(system_command_raid);
+
+        assert!(matches!(
+            world.get::<Command>(raider_id),
+            Some(Command::Raid(RaidState::Retreat { target_id, .. })) if *target_id == base_id
+        ));
+        assert_eq!(
+            Some(&NavRequest::MoveAndDockAt { target_id: base_id }),
+            world.get::<NavRequest>(raider_id)
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::game::actions::{Action, ActionRequest, ActionSalvage};
+use crate::game::commands::{search_orders_target, Command};
+use crate::game::discovery::Discoveries;
+use crate::game::dock::QueryDocking;
+use crate::game::factions::{Ownership, QueryOwnership};
+use crate::game::locations::{EntityPerSectorIndex, LocationDocked, LocationSpace, Locations};
+use crate::game::navigations::{NavRequest, Navigation};
+use crate::game::objects::ObjId;
+use crate::game::order::TradeOrders;
+use crate::game::prices::{Credits, Prices, WarePrice};
+use crate::game::stats::EconomyStats;
+use crate::game::wares::{Cargo, Cargos, WareId};
+use crate::game::wrecks::Wreck;
+
+use bevy_ecs::prelude::*;
+
+/// Max distance to a wreck to start the salvage
+pub const SALVAGE_DISTANCE: f32 = 0.5;
+
+/// Salvage ships collect wares from the nearest wreck of the sector until full, then deliver
+/// them to a station requesting the wares. Wrecks already targeted by other ships are only used
+/// when there is no other.
+#[allow(clippy::too_many_arguments)]
+pub fn system_command_salvage(
+    mut commands: Commands,
+    mut query: Query<
+        (Entity, &mut Command),
+        (
+            Without<Navigation>,
+            Without<NavRequest>,
+            Without<ActionRequest>,
+            Without<ActionSalvage>,
+        ),
+    >,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+    query_wrecks: Query<(Entity, &LocationSpace), With<Wreck>>,
+    query_orders: Query<&TradeOrders>,
+    mut query_cargos: Query<&mut Cargo>,
+    mut query_credits: Query<&mut Credits>,
+    query_prices: Query<&WarePrice>,
+    query_ownership: QueryOwnership,
+    query_docking: QueryDocking,
+    sector_index: Res<EntityPerSectorIndex>,
+    discoveries: Option<Res<Discoveries>>,
+    mut stats: Option<ResMut<EconomyStats>>,
+) {
+    log::trace!("running");
+
+    let mut cargo_transfers = vec![];
+    let mut already_targets: Vec<ObjId> = query
+        .iter()
+        .filter_map(|(_, command)| command.as_salvage().and_then(|state| state.wreck_id))
+        .collect();
+
+    for (id, mut command) in &mut query {
+        let state = match command.as_mut() {
+            Command::Salvage(state) => state,
+            _ => continue,
+        };
+
+        let Some(location) = Locations::resolve_space_position(&query_locations, id) else {
+            continue;
+        };
+        let Ok(cargo) = query_cargos.get(id) else {
+            continue;
+        };
+        let is_empty = cargo.is_empty();
+
+        let wreck_id = if cargo.is_full() {
+            None
+        } else {
+            state
+                .wreck_id
+                .filter(|wreck_id| {
+                    query_wrecks.contains(*wreck_id)
+                        && query_cargos
+                            .get(*wreck_id)
+                            .is_ok_and(|cargo| !cargo.is_empty())
+                })
+                .or_else(|| search_wreck(&query_wrecks, &query_cargos, &already_targets, &location))
+        };
+
+        if let Some(wreck_id) = wreck_id {
+            if state.wreck_id != Some(wreck_id) {
+                log::debug!("{:?} setting salvage target to {:?}", id, wreck_id);
+                state.wreck_id = Some(wreck_id);
+                state.deliver_target_id = None;
+                already_targets.push(wreck_id);
+            }
+
+            let in_range = match (query_locations.get(id), query_wrecks.get(wreck_id)) {
+                (Ok((_, Some(obj_location), _)), Ok((_, wreck_location))) => {
+                    obj_location.sector_id == wreck_location.sector_id
+                        && obj_location.pos.distance(wreck_location.pos) <= SALVAGE_DISTANCE
+                }
+                _ => false,
+            };
+
+            if in_range {
+                log::debug!("{:?} start salvage of {:?}", id, wreck_id);
+                commands.entity(id).insert(ActionRequest(Action::Salvage {
+                    target_id: wreck_id,
+                }));
+            } else {
+                log::debug!("{:?} command to move to wreck {:?}", id, wreck_id);
+                commands.entity(id).insert(NavRequest::MoveToTarget {
+                    target_id: wreck_id,
+                });
+            }
+            continue;
+        }
+
+        state.wreck_id = None;
+        if is_empty {
+            log::trace!("{:?} no wreck to salvage, waiting", id);
+            continue;
+        }
+
+        // deliver cargo
+        let target_id = match state.deliver_target_id {
+            Some(target_id) => target_id,
+            None => {
+                let ownership = Ownership::of(&query_ownership, id);
+                let wares_to_deliver: Vec<WareId> = cargo.get_wares_ids().collect();
+
+                match search_orders_target(
+                    &sector_index,
+                    location.sector_id,
+                    &query_orders,
+                    &query_cargos,
+                    &query_ownership,
+                    &ownership,
+                    Discoveries::of(discoveries.as_deref(), ownership.faction_id),
+                    &query_docking,
+                    id,
+                    Some(&wares_to_deliver),
+                    Vec::new(),
+                    false,
+                ) {
+                    Some((target_id, _wares)) => {
+                        log::debug!("{:?} setting deliver target to {:?}", id, target_id);
+                        state.deliver_target_id = Some(target_id);
+                        target_id
+                    }
+                    None => {
+                        log::trace!("{:?} can not find a station to deliver, skipping", id);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        if Locations::is_docked_at(&query_locations, id, target_id) {
+            cargo_transfers.push((id, target_id));
+            state.deliver_target_id = None;
+        } else {
+            commands
+                .entity(id)
+                .insert(NavRequest::MoveAndDockAt { target_id });
+        }
+    }
+
+    // transfer all cargos
+    for (from_id, to_id) in cargo_transfers {
+        let wares: Vec<WareId> = query_cargos.get(from_id).unwrap().get_wares_ids().collect();
+        let prices =
+            Prices::list_buy_prices(&query_prices, query_cargos.get(to_id).unwrap(), &wares);
+        let sector_id =
+            Locations::resolve_space_position(&query_locations, to_id).map(|l| l.sector_id);
+        let transfer = Cargos::move_all(
+            &mut query_cargos,
+            from_id,
+            to_id,
+            stats.as_deref_mut(),
+            sector_id,
+        );
+        let value = Prices::transfer_value(&transfer, &prices);
+        Prices::settle(&mut query_credits, from_id, to_id, value);
+        log::info!(
+            "{:?} transfer salvage {:?} to {:?} for {:?} credits",
+            from_id,
+            transfer,
+            to_id,
+            value
+        );
+    }
+}
+
+/// Nearest wreck with wares in the same sector, preferring the ones not targeted
+fn search_wreck(
+    query_wrecks: &Query<(Entity, &LocationSpace), With<Wreck>>,
+    query_cargos: &Query<&mut Cargo>,
+    already_targets: &[ObjId],
+    location: &LocationSpace,
+) -> Option<ObjId> {
+    query_wrecks
+        .iter()
+        .filter(|(wreck_id, wreck_location)| {
+            wreck_location.sector_id == location.sector_id
+                && query_cargos
+                    .get(*wreck_id)
+                    .is_ok_and(|cargo| !cargo.is_empty())
+        })
+        .min_by(|(a_id, a_location), (b_id, b_location)| {
+            let a_key = (
+                already_targets.contains(a_id),
+                a_location.pos.distance(location.pos),
+            );
+            let b_key = (
+                already_targets.contains(b_id),
+                b_location.pos.distance(location.pos),
+            );
+            a_key.0.cmp(&b_key.0).then(a_key.1.total_cmp(&b_key.1))
+        })
+        .map(|(wreck_id, _)| wreck_id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::utils::{TotalTime, V2};
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_salvage_should_move_to_nearest_wreck_and_salvage() {
+        let mut world = World::new();
+        world.insert_resource(EntityPerSectorIndex::new());
+
+        let sector_id = world.spawn_empty().id();
+        let ware_id = world.spawn_empty().id();
+
+        let mut new_wreck = |world: &mut World, pos: V2| {
+            let mut cargo = Cargo::new(10);
+            cargo.add(ware_id, 5).unwrap();
+            world
+                .spawn((
+                    Wreck {
+                        decay_at: TotalTime(100.0),
+                    },
+                    LocationSpace { pos, sector_id },
+                    cargo,
+                ))
+                .id()
+        };
+        new_wreck(&mut world, V2::new(5.0, 0.0));
+        let wreck_id = new_wreck(&mut world, V2::new(2.0, 0.0));
+
+        let ship_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id,
+                },
+                Cargo::new(10),
+                Command::salvage(),
+            ))
+            .id();
+
+        world.run_system_once(system_command_salvage);
+        assert_eq!(
+            Some(&NavRequest::MoveToTarget {
+                target_id: wreck_id
+            }),
+            world.get::<NavRequest>(ship_id)
+        );
+
+        // arrive at wreck
+        world
+            .entity_mut(ship_id)
+            .remove::<NavRequest>()
+            .insert(LocationSpace {
+                pos: V2::new(2.0, 0.0),
+                sector_id,
+            });
+        world.run_system_once(system_command_salvage);
+        assert!(matches!(
+            world.get::<ActionRequest>(ship_id),
+            Some(ActionRequest(Action::Salvage { target_id })) if *target_id == wreck_id
+        ));
+    }
+}
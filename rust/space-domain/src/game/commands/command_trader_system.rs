@@ -1,25 +1,55 @@
-use crate::game::commands::{Command, TradeState};
+use crate::game::commands::{
+    Command, TradeState, DANGER_WEIGHT, QUEUE_TIME_WEIGHT, URGENCY_WEIGHT,
+};
+use crate::game::discovery::Discoveries;
+use crate::game::dock::{Docking, QueryDocking};
+use crate::game::factions::{Ownership, QueryOwnership};
 use crate::game::locations::{EntityPerSectorIndex, LocationDocked, LocationSpace, Locations};
-use crate::game::navigations::{NavRequest, Navigation};
+use crate::game::navigations::{NavRequest, NavRequestFailed, Navigation};
 use crate::game::objects::ObjId;
 use crate::game::order::TradeOrders;
+use crate::game::prices::{Credit, Credits, Prices, WarePrice};
+use crate::game::raiders::SectorDanger;
+use crate::game::sectors::{Jump, Sector, SectorId};
+use crate::game::stats::EconomyStats;
 
+use crate::game::utils;
 use crate::game::utils::{DeltaTime, TotalTime};
 use crate::game::wares::{Cargo, Cargos, WareId};
 
-use crate::game::utils;
 use bevy_ecs::prelude::*;
 use commons::unwrap_or_continue;
 use rand::RngCore;
+use std::collections::HashMap;
+
+/// Time until wares and space reserved by a trader are released if it never arrives
+const RESERVATION_TIMEOUT: DeltaTime = DeltaTime(120.0);
+
+/// How much the profit margin, relative to the ware base price, weight over distance when
+/// choosing a route
+const PROFIT_WEIGHT: f32 = 10.0;
 
+#[allow(clippy::too_many_arguments)]
 pub fn system_command_trade(
     total_time: Res<TotalTime>,
     sectors_index: Res<EntityPerSectorIndex>,
     mut commands: Commands,
-    query: Query<(Entity, &Command), (Without<Navigation>, Without<NavRequest>)>,
+    query: Query<
+        (Entity, &Command, Option<&NavRequestFailed>),
+        (Without<Navigation>, Without<NavRequest>),
+    >,
     query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
     mut query_cargos: Query<&mut Cargo>,
     query_orders: Query<&TradeOrders>,
+    mut query_credits: Query<&mut Credits>,
+    query_prices: Query<&WarePrice>,
+    query_ownership: QueryOwnership,
+    query_docking: QueryDocking,
+    query_danger: Query<&SectorDanger>,
+    query_sectors: Query<&Sector>,
+    query_jumps: Query<(&Jump, &LocationSpace)>,
+    discoveries: Option<Res<Discoveries>>,
+    mut stats: Option<ResMut<EconomyStats>>,
 ) {
     log::trace!("running");
 
@@ -38,12 +68,19 @@ pub fn system_command_trade(
     let total_time = *total_time;
 
     // split traders between states
-    for (id, command) in &query {
+    for (id, command, maybe_failed) in &query {
         let trade_state = match command {
             Command::Trade(state) => state,
             _ => continue,
         };
 
+        if maybe_failed.is_some_and(|failed| failed.is_out_of_fuel()) {
+            // wait a tick to be sent to refuel
+            log::debug!("{:?} can not reach trade target for lack of fuel", id);
+            commands.entity(id).remove::<NavRequestFailed>();
+            continue;
+        }
+
         let cargo = unwrap_or_continue!(query_cargos.get(id).ok());
 
         match trade_state {
@@ -83,60 +120,107 @@ pub fn system_command_trade(
         };
     }
 
+    // index stations buying each ware only when some trader is choosing a pickup
+    let buyers = if idlers_pickup.is_empty() {
+        BuyersByWare::new()
+    } else {
+        index_buyers_by_ware(&sectors_index, &query_orders, &query_cargos, &query_prices)
+    };
+
     // choose targets for pickup
-    for (id, _) in query.iter_many(idlers_pickup) {
+    for (id, _, _) in query.iter_many(idlers_pickup) {
         let sector_id = Locations::resolve_space_position(&query_locations, id)
             .unwrap()
             .sector_id;
+        let ownership = Ownership::of(&query_ownership, id);
+        let mut path_danger: HashMap<SectorId, f32> = HashMap::new();
 
         // search nearest stations that provided wares
-        let candidates = sectors_index.search_nearest_stations(sector_id).flat_map(
-            |(_sector_id, distance, candidate_id)| match query_orders
-                .get(candidate_id)
-                .ok()
-                .map(|orders| orders.wares_provider())
-            {
-                Some(wares) if !wares.is_empty() => {
-                    // check if station has cargo
-                    if let Some(station_cargo) = query_cargos.get(candidate_id).ok() {
-                        if wares
-                            .iter()
-                            .any(|ware_id| station_cargo.get_amount(*ware_id) > 0)
-                        {
-                            // check number of active trades already doing this route
-                            let count_active_delivers = pickup_targets
-                                .iter()
-                                .filter(|id| **id == candidate_id)
-                                .count()
-                                as u32;
-
-                            // wait based on random + distance + num of active delivers
-                            let luck = (rnd.next_u32() % 1000) as f32 / 1000.0f32;
-                            let weight: f32 = distance as f32 + count_active_delivers as f32 + luck;
-                            Some((weight, candidate_id))
-                        } else {
-                            None
+        let candidates = sectors_index
+            .search_nearest_stations(
+                sector_id,
+                Discoveries::of(discoveries.as_deref(), ownership.faction_id),
+            )
+            .filter(|(_, _, candidate_id)| ownership.accept(&query_ownership, *candidate_id))
+            .flat_map(|(candidate_sector_id, distance, candidate_id)| {
+                let orders = query_orders.get(candidate_id).ok()?;
+                let station_cargo = query_cargos.get(candidate_id).ok()?;
+
+                // find the most profitable and urgent ware the station has in stock
+                let score = orders
+                    .wares_provider()
+                    .iter()
+                    .filter(|ware_id| station_cargo.get_amount(**ware_id) > 0)
+                    .flat_map(|ware_id| {
+                        let urgency = orders.provide_urgency(station_cargo, *ware_id);
+                        if urgency <= 0.0 {
+                            return None;
                         }
-                    } else {
-                        None
-                    }
-                }
-                _ => None,
-            },
-        );
+
+                        let base = Prices::get_base_price(&query_prices, *ware_id);
+                        let sell_price = Prices::sell_price(base, station_cargo, *ware_id);
+                        // stations without a known buyer are still candidates, just without profit
+                        let buy_price = search_best_buy_price(
+                            &buyers,
+                            &query_ownership,
+                            &ownership,
+                            *ware_id,
+                            candidate_id,
+                        )
+                        .unwrap_or(sell_price);
+                        let margin = (buy_price - sell_price) as f32 / base as f32;
+                        Some(PROFIT_WEIGHT * margin + URGENCY_WEIGHT * urgency)
+                    })
+                    .max_by(|a, b| a.total_cmp(b))?;
+
+                // check number of active trades already doing this route
+                let count_active_delivers = pickup_targets
+                    .iter()
+                    .filter(|id| **id == candidate_id)
+                    .count() as u32;
+
+                let queue_time = Docking::expected_queue_time(&query_docking, id, candidate_id)?;
+
+                let danger = *path_danger.entry(candidate_sector_id).or_insert_with(|| {
+                    SectorDanger::of_path(
+                        &query_danger,
+                        &query_sectors,
+                        &query_jumps,
+                        sector_id,
+                        candidate_sector_id,
+                    )
+                });
+
+                // weight based on profit + urgency + random + distance + num of active delivers +
+                // docking queue + danger
+                let luck = (rnd.next_u32() % 1000) as f32 / 1000.0f32;
+                let weight: f32 = distance as f32 + count_active_delivers as f32 + luck - score
+                    + QUEUE_TIME_WEIGHT * queue_time.as_f32()
+                    + DANGER_WEIGHT * danger;
+                Some((weight, candidate_id))
+            })
+            .collect::<Vec<_>>();
 
         // take best candidate
-        match utils::next_lower(candidates) {
+        match utils::lower(candidates.into_iter()) {
             Some(target_id) => {
                 pickup_targets.push(target_id);
 
                 let wares = query_orders.get(target_id).unwrap().wares_provider();
+                let reserved = Cargos::reserve_only(
+                    &mut query_cargos,
+                    target_id,
+                    id,
+                    &wares,
+                    total_time.add(RESERVATION_TIMEOUT),
+                );
 
                 log::debug!(
-                    "{:?} found station {:?} to pickup {:?}",
+                    "{:?} found station {:?} to pickup {:?}, reserved {:?}",
                     id,
                     target_id,
                     wares,
+                    reserved,
                 );
 
                 commands
@@ -159,7 +243,7 @@ pub fn system_command_trade(
     }
 
     // choose targets for deliver
-    for (id, _) in query.iter_many(idlers_deliver) {
+    for (id, _, _) in query.iter_many(idlers_deliver) {
         let sector_id = Locations::resolve_space_position(&query_locations, id)
             .unwrap()
             .sector_id;
@@ -167,59 +251,91 @@ pub fn system_command_trade(
         let wares_in_cargo: Vec<WareId> = unwrap_or_continue!(query_cargos.get(id).ok())
             .get_wares_ids()
             .collect();
+        let ownership = Ownership::of(&query_ownership, id);
+        let mut path_danger: HashMap<SectorId, f32> = HashMap::new();
 
         // search nearest candidates that accept cargo
         let candidates = sectors_index
-            .search_nearest_stations(sector_id)
-            .flat_map(|(_sector_id, distance, obj_id)| {
-                match query_orders
-                    .get(obj_id)
-                    .ok()
-                    .map(|orders| orders.request_any(&wares_in_cargo))
-                {
-                    Some(wares) if !wares.is_empty() => {
-                        // weight deliver by random + distance + num active delivers
-                        let count_active_traders =
-                            pickup_targets.iter().filter(|id| **id == obj_id).count() as u32;
-
-                        let luck = (rnd.next_u32() % 1000) as f32 / 1000.0f32;
-                        let weight: f32 = distance as f32 + count_active_traders as f32 + luck;
-                        Some((weight, obj_id))
-                    }
-                    _ => None,
-                }
-            })
-            .filter(|(_weight, target_id)| {
-                if let Some(cargo) = query_cargos.get(*target_id).ok() {
-                    // check if any ware in cargo can be received by the stations
-                    wares_in_cargo.iter().any(|ware_id| {
-                        let amount = cargo.free_volume(*ware_id).unwrap_or(0);
-                        amount > 0
+            .search_nearest_stations(
+                sector_id,
+                Discoveries::of(discoveries.as_deref(), ownership.faction_id),
+            )
+            .filter(|(_, _, obj_id)| ownership.accept(&query_ownership, *obj_id))
+            .flat_map(|(candidate_sector_id, distance, obj_id)| {
+                let orders = query_orders.get(obj_id).ok()?;
+                let cargo = query_cargos.get(obj_id).ok()?;
+
+                // check if any ware in cargo can be received by the stations, and the best
+                // price and urgency for it
+                let score = orders
+                    .request_any(&wares_in_cargo)
+                    .iter()
+                    .filter(|ware_id| cargo.free_volume(**ware_id).unwrap_or(0) > 0)
+                    .flat_map(|ware_id| {
+                        let urgency = orders.request_urgency(cargo, *ware_id);
+                        if urgency <= 0.0 {
+                            return None;
+                        }
+
+                        let base = Prices::get_base_price(&query_prices, *ware_id);
+                        let price_ratio =
+                            Prices::buy_price(base, cargo, *ware_id) as f32 / base as f32;
+                        Some(PROFIT_WEIGHT * price_ratio + URGENCY_WEIGHT * urgency)
                     })
-                } else {
-                    false
-                }
-            });
+                    .max_by(|a, b| a.total_cmp(b))?;
+
+                let queue_time = Docking::expected_queue_time(&query_docking, id, obj_id)?;
+                let danger = *path_danger.entry(candidate_sector_id).or_insert_with(|| {
+                    SectorDanger::of_path(
+                        &query_danger,
+                        &query_sectors,
+                        &query_jumps,
+                        sector_id,
+                        candidate_sector_id,
+                    )
+                });
+
+                // weight deliver by price + urgency + random + distance + num active delivers +
+                // docking queue + danger
+                let count_active_traders =
+                    deliver_targets.iter().filter(|id| **id == obj_id).count() as u32;
+
+                let luck = (rnd.next_u32() % 1000) as f32 / 1000.0f32;
+                let weight: f32 = distance as f32 + count_active_traders as f32 + luck - score
+                    + QUEUE_TIME_WEIGHT * queue_time.as_f32()
+                    + DANGER_WEIGHT * danger;
+                Some((weight, obj_id))
+            })
+            .collect::<Vec<_>>();
 
-        match utils::next_lower(candidates) {
+        match utils::lower(candidates.into_iter()) {
             Some(target_id) => {
                 deliver_targets.push(target_id);
 
-                let wares_requests = query_orders.get(target_id).unwrap().wares_requests();
-                assert!(!wares_requests.is_empty(), "request wares can not be empty");
+                let orders = query_orders.get(target_id).unwrap();
+                let target_cargo = query_cargos.get(target_id).unwrap();
 
                 let wares = wares_in_cargo
                     .into_iter()
-                    .filter(|ware_id| wares_requests.contains(ware_id))
+                    .filter(|ware_id| orders.request_urgency(target_cargo, *ware_id) > 0.0)
                     .collect::<Vec<WareId>>();
 
                 assert!(!wares.is_empty());
 
+                let reserved = Cargos::reserve_only(
+                    &mut query_cargos,
+                    id,
+                    target_id,
+                    &wares,
+                    total_time.add(RESERVATION_TIMEOUT),
+                );
+
                 log::debug!(
-                    "{:?} found station {:?} to deliver {:?}",
+                    "{:?} found station {:?} to deliver {:?}, reserved {:?}",
                     id,
                     target_id,
                     wares,
+                    reserved,
                 );
 
                 commands
@@ -233,24 +349,39 @@ pub fn system_command_trade(
     }
 
     // deliver
-    for (id, command) in query.iter_many(deliver_traders) {
+    for (id, command, _) in query.iter_many(deliver_traders) {
         let (target_id, wares) = match &command {
             Command::Trade(TradeState::Deliver { target_id, wares }) => (*target_id, wares),
             _ => continue,
         };
 
         if Locations::is_docked_at(&query_locations, id, target_id) {
-            let transfer = Cargos::move_only(&mut query_cargos, id, target_id, wares);
+            Cargos::release(&mut query_cargos, id, target_id);
+            let prices =
+                Prices::list_buy_prices(&query_prices, query_cargos.get(target_id).unwrap(), wares);
+            let sector_id =
+                Locations::resolve_space_position(&query_locations, target_id).map(|l| l.sector_id);
+            let transfer = Cargos::move_only(
+                &mut query_cargos,
+                id,
+                target_id,
+                wares,
+                stats.as_deref_mut(),
+                sector_id,
+            );
+            let value = Prices::transfer_value(&transfer, &prices);
+            Prices::settle(&mut query_credits, id, target_id, value);
             if transfer.moved.is_empty() {
                 log::warn!("{:?} fail to deliver wares {:?} to station {:?}, trader cargo is {:?}, station cargo is {:?}", id, wares, target_id, query_cargos.get(id), 
                     query_cargos.get(target_id));
                 back_to_idle.push(id);
             } else {
                 log::info!(
-                    "{:?} deliver wares {:?} to station {:?}",
+                    "{:?} deliver wares {:?} to station {:?} for {:?} credits",
                     id,
                     transfer,
                     target_id,
+                    value,
                 );
                 back_to_idle.push(id);
             }
@@ -268,14 +399,31 @@ pub fn system_command_trade(
     }
 
     // pick up
-    for (id, command) in query.iter_many(pickup_traders) {
+    for (id, command, _) in query.iter_many(pickup_traders) {
         let (target_id, wares) = match &command {
             Command::Trade(TradeState::PickUp { target_id, wares }) => (*target_id, wares),
             _ => continue,
         };
 
         if Locations::is_docked_at(&query_locations, id, target_id) {
-            let transfer = Cargos::move_only(&mut query_cargos, target_id, id, wares);
+            Cargos::release(&mut query_cargos, id, target_id);
+            let prices = Prices::list_sell_prices(
+                &query_prices,
+                query_cargos.get(target_id).unwrap(),
+                wares,
+            );
+            let sector_id =
+                Locations::resolve_space_position(&query_locations, target_id).map(|l| l.sector_id);
+            let transfer = Cargos::move_only(
+                &mut query_cargos,
+                target_id,
+                id,
+                wares,
+                stats.as_deref_mut(),
+                sector_id,
+            );
+            let value = Prices::transfer_value(&transfer, &prices);
+            Prices::settle(&mut query_credits, target_id, id, value);
             if transfer.moved.is_empty() {
                 log::info!(
                     "{:?} fail to take wares {:?} from station {:?}, station cargo is {:?}",
@@ -287,10 +435,11 @@ pub fn system_command_trade(
                 back_to_idle.push(id);
             } else {
                 log::info!(
-                    "{:?} take wares {:?} from station {:?}",
+                    "{:?} take wares {:?} from station {:?} for {:?} credits",
                     id,
                     transfer,
                     target_id,
+                    value,
                 );
                 back_to_idle.push(id);
             }
@@ -307,22 +456,118 @@ pub fn system_command_trade(
         }
     }
 
-    // switch back to idle
+    // switch back to idle, releasing any reservation still pending
     for obj_id in back_to_idle {
+        match query.get(obj_id) {
+            Ok((_, Command::Trade(TradeState::PickUp { target_id, .. }), _))
+            | Ok((_, Command::Trade(TradeState::Deliver { target_id, .. }), _)) => {
+                Cargos::release(&mut query_cargos, obj_id, *target_id);
+            }
+            _ => {}
+        }
+
         log::trace!("{:?} command set to trade idle", obj_id);
         commands.entity(obj_id).insert(Command::trade());
     }
 }
 
+/// Release cargo reservations between traders and stations that are no longer the target of a
+/// trade command, like when the command was replaced, suspended or the trader destroyed
+pub fn system_command_trade_release_reservations(
+    query_commands: Query<&Command>,
+    mut query_cargos: Query<(Entity, &mut Cargo)>,
+) {
+    log::trace!("running");
+
+    let is_trading = |trader_id: ObjId, station_id: ObjId| match query_commands.get(trader_id) {
+        Ok(Command::Trade(TradeState::PickUp { target_id, .. }))
+        | Ok(Command::Trade(TradeState::Deliver { target_id, .. })) => *target_id == station_id,
+        _ => false,
+    };
+
+    for (obj_id, mut cargo) in &mut query_cargos {
+        if cargo.get_reservations().is_empty() {
+            continue;
+        }
+
+        let mut orphans: Vec<ObjId> = cargo
+            .get_reservations()
+            .iter()
+            .map(|r| r.owner_id)
+            .filter(|owner_id| !is_trading(*owner_id, obj_id) && !is_trading(obj_id, *owner_id))
+            .collect();
+        orphans.sort();
+        orphans.dedup();
+
+        for owner_id in orphans {
+            log::debug!(
+                "{:?} releasing cargo reservations of {:?}, not trading anymore",
+                obj_id,
+                owner_id
+            );
+            cargo.release(owner_id);
+        }
+    }
+}
+
+/// Stations still requesting each ware with the price they pay, sorted from the best price
+type BuyersByWare = HashMap<WareId, Vec<(Credit, ObjId)>>;
+
+fn index_buyers_by_ware(
+    sectors_index: &EntityPerSectorIndex,
+    query_orders: &Query<&TradeOrders>,
+    query_cargos: &Query<&mut Cargo>,
+    query_prices: &Query<&WarePrice>,
+) -> BuyersByWare {
+    let mut buyers = BuyersByWare::new();
+    for obj_id in sectors_index.index_stations.values().flatten() {
+        let orders = unwrap_or_continue!(query_orders.get(*obj_id).ok());
+        let cargo = unwrap_or_continue!(query_cargos.get(*obj_id).ok());
+        for ware_id in orders.wares_requests() {
+            if cargo.free_volume(ware_id).unwrap_or(0) == 0
+                || orders.request_urgency(cargo, ware_id) <= 0.0
+            {
+                continue;
+            }
+            let base = Prices::get_base_price(query_prices, ware_id);
+            buyers
+                .entry(ware_id)
+                .or_default()
+                .push((Prices::buy_price(base, cargo, ware_id), *obj_id));
+        }
+    }
+    for list in buyers.values_mut() {
+        list.sort_by_key(|(price, _)| std::cmp::Reverse(*price));
+    }
+    buyers
+}
+
+/// Best price paid by any station still requesting the ware and accepted by the ownership,
+/// ignoring the station selling it
+fn search_best_buy_price(
+    buyers: &BuyersByWare,
+    query_ownership: &QueryOwnership,
+    ownership: &Ownership,
+    ware_id: WareId,
+    seller_id: ObjId,
+) -> Option<Credit> {
+    buyers
+        .get(&ware_id)?
+        .iter()
+        .find(|(_, obj_id)| *obj_id != seller_id && ownership.accept(query_ownership, *obj_id))
+        .map(|(price, _)| *price)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     use crate::game::commands::Command;
     use crate::game::dock::HasDocking;
+    use crate::game::factions::{Faction, Owner, OwnershipFilter};
     use crate::game::locations::EntityPerSectorIndex;
     use crate::game::objects::ObjId;
-    use crate::game::order::{TradeOrders, TRADE_ORDER_ID_FACTORY};
+    use crate::game::order::{TradeOrder, TradeOrders, TRADE_ORDER_ID_FACTORY};
     use crate::game::sectors::SectorId;
     use crate::game::utils::TotalTime;
     use crate::game::wares::{Cargo, Volume, WareId};
@@ -431,6 +676,46 @@ mod test {
         Loader::assert_nav_request_dock_at(&world, scenery.trader_id, scenery.producer_station_id);
     }
 
+    #[test]
+    fn command_trade_when_empty_should_pickup_cargo_even_without_buyer() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        world.despawn(scenery.consumer_station_id);
+        let mut index = EntityPerSectorIndex::new();
+        index.add_stations(scenery.sector_id, scenery.producer_station_id);
+        world.insert_resource(index);
+
+        world.run_system_once(system_command_trade);
+        world.run_system_once(system_command_trade);
+
+        Loader::assert_nav_request_dock_at(&world, scenery.trader_id, scenery.producer_station_id);
+    }
+
+    #[test]
+    fn command_trade_should_ignore_stations_not_accepted_by_ownership() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        let faction_id = world
+            .spawn(Faction {
+                ownership: OwnershipFilter::Own,
+                ..Default::default()
+            })
+            .id();
+        let other_faction_id = world.spawn(Faction::default()).id();
+        world
+            .entity_mut(scenery.trader_id)
+            .insert(Owner::new(faction_id));
+        world
+            .entity_mut(scenery.producer_station_id)
+            .insert(Owner::new(other_faction_id));
+
+        world.run_system_once(system_command_trade);
+
+        Loader::assert_command_trade_delay(&world, scenery.trader_id);
+    }
+
     #[test]
     fn command_trade_when_empty_and_idle_and_station_is_empty_should_become_delay() {
         let mut world = World::new();
@@ -587,6 +872,34 @@ mod test {
         Loader::assert_nav_request_dock_at(&world, scenery.trader_id, scenery.consumer_station_id);
     }
 
+    #[test]
+    fn command_trade_when_full_should_avoid_dangerous_sectors() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        let safe_sector_id = world.spawn_empty().id();
+        let safe_station_id = add_station(
+            &mut world,
+            safe_sector_id,
+            TradeOrders::from_requested(TRADE_ORDER_ID_FACTORY, &[scenery.ware0_id]),
+        );
+        world
+            .resource_mut::<EntityPerSectorIndex>()
+            .add_stations(safe_sector_id, safe_station_id);
+        world
+            .entity_mut(scenery.sector_id)
+            .insert(SectorDanger { value: 10.0 });
+
+        Loader::add_cargo(&mut world, scenery.trader_id, scenery.ware0_id, SHIP_CARGO);
+
+        // back to idle
+        world.run_system_once(system_command_trade);
+        // create navigation
+        world.run_system_once(system_command_trade);
+
+        Loader::assert_nav_request_dock_at(&world, scenery.trader_id, safe_station_id);
+    }
+
     #[test]
     fn command_trade_when_full_with_pickup_should_move_to_deliver() {
         let mut world = World::new();
@@ -782,4 +1095,193 @@ mod test {
 
         assert_eq!(targets, expected);
     }
+
+    #[test]
+    fn command_trade_when_deliver_should_be_paid_by_station() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        world.entity_mut(scenery.trader_id).insert(Credits::new(0));
+        world
+            .entity_mut(scenery.consumer_station_id)
+            .insert(Credits::new(1000));
+
+        Loader::add_cargo(&mut world, scenery.trader_id, scenery.ware0_id, 10);
+        Loader::set_docked_at(&mut world, scenery.trader_id, scenery.consumer_station_id);
+
+        world.run_system_once(system_command_trade);
+        world.run_system_once(system_command_trade);
+
+        Loader::assert_cargo(&world, scenery.trader_id, scenery.ware0_id, 0);
+
+        let trader_balance = world
+            .get::<Credits>(scenery.trader_id)
+            .unwrap()
+            .get_balance();
+        let station_balance = world
+            .get::<Credits>(scenery.consumer_station_id)
+            .unwrap()
+            .get_balance();
+        assert!(trader_balance > 0);
+        assert_eq!(1000, trader_balance + station_balance);
+    }
+
+    #[test]
+    fn command_trade_should_pickup_from_cheapest_station() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        // expensive station with low stock
+        let station_id_2 = add_station(
+            &mut world,
+            scenery.sector_id,
+            TradeOrders::from_provided(TRADE_ORDER_ID_FACTORY, &[scenery.ware0_id]),
+        );
+        Loader::add_cargo(&mut world, station_id_2, scenery.ware0_id, 10);
+
+        world
+            .resource_mut::<EntityPerSectorIndex>()
+            .add_stations(scenery.sector_id, station_id_2);
+
+        world.run_system_once(system_command_trade);
+        world.run_system_once(system_command_trade);
+
+        Loader::assert_nav_request_dock_at(&world, scenery.trader_id, scenery.producer_station_id);
+    }
+
+    #[test]
+    fn command_trade_should_deliver_to_station_with_lower_stock() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        Loader::add_cargo(
+            &mut world,
+            scenery.consumer_station_id,
+            scenery.ware0_id,
+            1500,
+        );
+
+        let station_id_2 = add_station(
+            &mut world,
+            scenery.sector_id,
+            TradeOrders::from_requested(TRADE_ORDER_ID_FACTORY, &[scenery.ware0_id]),
+        );
+        world
+            .resource_mut::<EntityPerSectorIndex>()
+            .add_stations(scenery.sector_id, station_id_2);
+
+        Loader::add_cargo(&mut world, scenery.trader_id, scenery.ware0_id, SHIP_CARGO);
+
+        world.run_system_once(system_command_trade);
+        world.run_system_once(system_command_trade);
+
+        Loader::assert_nav_request_dock_at(&world, scenery.trader_id, station_id_2);
+    }
+
+    #[test]
+    fn command_trade_should_not_deliver_to_station_above_max_stock() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        let mut orders = TradeOrders::default();
+        orders.add_request_order(
+            TradeOrder::new(TRADE_ORDER_ID_FACTORY, scenery.ware0_id).with_stock(0.1, 0.5),
+        );
+        world.entity_mut(scenery.consumer_station_id).insert(orders);
+        Loader::add_cargo(
+            &mut world,
+            scenery.consumer_station_id,
+            scenery.ware0_id,
+            1200,
+        );
+
+        Loader::add_cargo(&mut world, scenery.trader_id, scenery.ware0_id, SHIP_CARGO);
+
+        world.run_system_once(system_command_trade);
+        world.run_system_once(system_command_trade);
+
+        Loader::assert_command_trade_idle(&world, scenery.trader_id);
+        Loader::assert_no_nav_request(&world, scenery.trader_id);
+    }
+
+    #[test]
+    fn command_trade_should_not_pickup_wares_reserved_by_other_trader() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        Loader::clear_cargo(&mut world, scenery.producer_station_id);
+        Loader::add_cargo(
+            &mut world,
+            scenery.producer_station_id,
+            scenery.ware0_id,
+            SHIP_CARGO,
+        );
+
+        let trader_id_2 = add_trader(&mut world, scenery.sector_id);
+
+        world.run_system_once(system_command_trade);
+
+        let mut delayed = 0;
+        for trader_id in [scenery.trader_id, trader_id_2] {
+            if let Some(Command::Trade(TradeState::Delay { .. })) =
+                Loader::get_active_command(&world, trader_id)
+            {
+                delayed += 1;
+            }
+        }
+        assert_eq!(1, delayed);
+
+        let station_cargo = world.get::<Cargo>(scenery.producer_station_id).unwrap();
+        assert_eq!(0, station_cargo.get_amount(scenery.ware0_id));
+        assert_eq!(
+            SHIP_CARGO,
+            station_cargo.get_stored_amount(scenery.ware0_id)
+        );
+    }
+
+    #[test]
+    fn command_trade_release_reservations_when_command_is_replaced() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        world.run_system_once(system_command_trade);
+        world.run_system_once(system_command_trade_release_reservations);
+        assert!(!world
+            .get::<Cargo>(scenery.producer_station_id)
+            .unwrap()
+            .get_reservations()
+            .is_empty());
+
+        world
+            .entity_mut(scenery.trader_id)
+            .insert(Command::haul(vec![]));
+        world.run_system_once(system_command_trade_release_reservations);
+
+        assert!(world
+            .get::<Cargo>(scenery.producer_station_id)
+            .unwrap()
+            .get_reservations()
+            .is_empty());
+        assert!(world
+            .get::<Cargo>(scenery.trader_id)
+            .unwrap()
+            .get_reservations()
+            .is_empty());
+    }
+
+    #[test]
+    fn command_trade_when_pickup_at_target_should_take_reserved_wares() {
+        let mut world = World::new();
+        let scenery = setup_scenery(&mut world);
+
+        // choose target and reserve
+        world.run_system_once(system_command_trade);
+
+        Loader::set_docked_at(&mut world, scenery.trader_id, scenery.producer_station_id);
+        world.run_system_once(system_command_trade);
+
+        Loader::assert_cargo(&world, scenery.trader_id, scenery.ware0_id, SHIP_CARGO);
+        let station_cargo = world.get::<Cargo>(scenery.producer_station_id).unwrap();
+        assert!(station_cargo.get_reservations().is_empty());
+    }
 }
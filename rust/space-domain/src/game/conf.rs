@@ -2,6 +2,10 @@ use serde::{Deserialize, Serialize};
 
 use space_galaxy::system_generator::UniverseCfg;
 
+use crate::game::prices::Credit;
+use crate::game::ship::ship_internals;
+use crate::game::ship::ship_internals::ShipComponentsConfig;
+
 pub type BlueprintCode = String;
 pub type Code = String;
 pub type Label = String;
@@ -20,18 +24,80 @@ pub fn load_str(buffer: &str) -> Result<Conf, String> {
     result.map_err(|err| format!("fail to load config from str by: {:?}", err))
 }
 
+/// Ship components are kept in their own file, load them into the prefabs
+pub fn load_ship_components(conf: &mut Conf, buffer: &str) -> Result<(), String> {
+    let result: ShipComponentsConfig = commons::hocon::load_str(buffer)
+        .map_err(|err| format!("fail to load ship components from str by: {:?}", err))?;
+    conf.prefabs.ship_components = result.components;
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Prefabs {
     pub wares: Vec<Ware>,
     pub receipts: Vec<Receipt>,
     pub fleets: Vec<Fleet>,
     pub stations: Vec<Station>,
+    #[serde(default)]
+    pub factions: Vec<Faction>,
+    #[serde(default)]
+    pub ship_components: Vec<ship_internals::Component>,
+    #[serde(default)]
+    pub ship_designs: Vec<ShipDesign>,
+}
+
+/// Ship built from components, its stats define the speed, storage, hull and production cost of
+/// the fleets using it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShipDesign {
+    pub code: Code,
+    pub components: Vec<ShipDesignComponent>,
+    /// layers of armor over the hull
+    #[serde(default)]
+    pub armor: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShipDesignComponent {
+    /// component name
+    pub component: String,
+    pub amount: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Ware {
     pub code: WareCode,
     pub label: Label,
+    #[serde(default)]
+    pub price: Option<Credit>,
+    #[serde(default)]
+    pub volume: Option<u32>,
+    #[serde(default)]
+    pub mass: Option<f32>,
+    /// "raw", "intermediate", "product", "fuel" or "ammo"
+    #[serde(default)]
+    pub category: Option<Code>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Faction {
+    pub code: Code,
+    pub label: Label,
+    /// which objects the faction ships trade, mine and dock with, "any", "own" or "own_or_free",
+    /// any when not defined
+    #[serde(default)]
+    pub ownership: Option<Code>,
+    /// when true the faction starts without knowledge of sectors and jumps and must explore them
+    #[serde(default)]
+    pub discovery: bool,
+    /// faction codes whose ships are engaged in combat, hostility goes both ways
+    #[serde(default)]
+    pub hostile: Vec<Code>,
+    /// ware codes, "category:<category>" or "tag:<tag>" not allowed by the faction
+    #[serde(default)]
+    pub restricted_wares: Vec<Code>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,8 +129,24 @@ pub struct ProductionCost {
 pub struct Fleet {
     pub code: FleetCode,
     pub label: Label,
-    pub speed: f32,
-    pub storage: u32,
+    /// ship design code, when defined speed, storage and production cost are computed from it
+    #[serde(default)]
+    pub design: Option<Code>,
+    #[serde(default)]
+    pub speed: Option<f32>,
+    #[serde(default)]
+    pub storage: Option<u32>,
+    /// when defined, speed is reduced by the cargo mass
+    #[serde(default)]
+    pub speed_reference_mass: Option<f32>,
+    /// "small", "medium" or "large", small when not defined
+    #[serde(default)]
+    pub size: Option<Code>,
+    #[serde(default)]
+    pub sensor: Option<Sensor>,
+    /// how easy it is detected by sensors, 1.0 when not defined
+    #[serde(default)]
+    pub signature: Option<f32>,
     pub production_cost: Option<ProductionCost>,
 }
 
@@ -75,9 +157,56 @@ pub struct Station {
     pub storage: f32,
     pub shipyard: Option<Shipyard>,
     pub factory: Option<Factory>,
+    #[serde(default)]
+    pub habitat: Option<Habitat>,
+    #[serde(default)]
+    pub repair: Option<Repair>,
+    /// how storage is split between the wares, equally when not defined
+    #[serde(default)]
+    pub storage_allocation: Option<StorageAllocation>,
+    /// wares always accepted by the station storage as ware codes, "category:<category>" or
+    /// "tag:<tag>"
+    #[serde(default)]
+    pub whitelist: Vec<Code>,
+    /// docking slots by ship size, unlimited when not defined
+    #[serde(default)]
+    pub docking: Vec<DockSlots>,
+    #[serde(default)]
+    pub sensor: Option<Sensor>,
+    /// how easy it is detected by sensors, 1.0 when not defined
+    #[serde(default)]
+    pub signature: Option<f32>,
     pub production_cost: Option<ProductionCost>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sensor {
+    pub range: f32,
+    pub strength: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DockSlots {
+    /// "small", "medium" or "large"
+    pub size: Code,
+    pub capacity: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageAllocation {
+    /// "equal", "quotas", "weighted" or "shared"
+    pub policy: Code,
+    /// volume of each ware for quotas or weight of each ware for weighted
+    #[serde(default)]
+    pub wares: Vec<StorageShare>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageShare {
+    pub ware: WareCode,
+    pub value: f32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Shipyard {
     pub production: f32,
@@ -85,7 +214,35 @@ pub struct Shipyard {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Factory {
-    pub receipt: Code,
+    pub receipts: Vec<Code>,
+    /// how to choose between receipts, "queue", "round_robin" or "demand", round robin when not
+    /// defined. A queue produces the receipts in the listed order
+    #[serde(default)]
+    pub policy: Option<Code>,
+    /// amount of parallel productions, default 1
+    #[serde(default)]
+    pub lines: Option<usize>,
+    /// production rate multiplier from station upgrades, default 1.0
+    #[serde(default)]
+    pub upgrade: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Habitat {
+    pub population: f32,
+    pub capacity: f32,
+    /// wares consumed by each 100 of population
+    pub consumption: Vec<ReceiptWare>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Repair {
+    /// work done per second
+    pub production: f32,
+    /// work required to repair a ship
+    pub work: f32,
+    /// wares consumed on each repair
+    pub cost: Vec<ReceiptWare>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -96,4 +253,95 @@ pub struct Params {
     pub prefab_station_solar: Code,
     pub prefab_ship_trade: FleetCode,
     pub prefab_ship_miner: FleetCode,
+    /// production rate of factories at sectors with the star kind
+    #[serde(default)]
+    pub star_kinds_production: Vec<StarKindProduction>,
+    /// reserve of asteroid fields with full resource amount, unlimited when not defined
+    #[serde(default)]
+    pub asteroid_reserve: Option<f32>,
+    /// seconds between each check to seed new asteroid fields, no respawn when not defined
+    #[serde(default)]
+    pub asteroid_respawn_time: Option<f32>,
+    /// when defined, habitable planets are created as colonies
+    #[serde(default)]
+    pub colonies: Option<Colonies>,
+    /// when defined, random sectors restrict some wares
+    #[serde(default)]
+    pub restricted_wares: Option<RestrictedWares>,
+    /// when defined, raiders spawn at random sectors and rob traders
+    #[serde(default)]
+    pub raiders: Option<Raiders>,
+    /// when defined, destroyed objects leave wrecks that can be salvaged
+    #[serde(default)]
+    pub wrecks: Option<Wrecks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wrecks {
+    /// fraction of the cargo left in the wreck
+    pub cargo_ratio: f32,
+    /// fraction of the production cost that can be recovered
+    pub materials_ratio: f32,
+    /// seconds until the wreck decay
+    pub decay_time: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Raiders {
+    /// fleet prefab of the raider ships
+    pub prefab: FleetCode,
+    /// faction owning the raiders
+    pub faction: Code,
+    /// probability of a sector spawn raiders
+    pub sector_prob: f32,
+    /// seconds between each spawn
+    pub spawn_time: f32,
+    /// max raiders alive spawned by each sector
+    pub max_per_sector: usize,
+    /// seconds added to the path cost of routes crossing a raiders sector
+    #[serde(default)]
+    pub travel_penalty: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestrictedWares {
+    /// ware codes, "category:<category>" or "tag:<tag>"
+    pub wares: Vec<Code>,
+    /// probability of a sector restrict the wares
+    pub sector_prob: f32,
+}
+
+/// Planets colonies, capacity is multiplied by the rates of the planet biome, atmosphere, ocean
+/// and by how close the gravity is from the ideal gravity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Colonies {
+    pub capacity: f32,
+    pub storage: f32,
+    /// initial population as ratio of the capacity
+    pub population: f32,
+    /// wares consumed by each 100 of population
+    pub consumption: Vec<ReceiptWare>,
+    #[serde(default)]
+    pub biomes: Vec<CapacityRate>,
+    #[serde(default)]
+    pub atmospheres: Vec<CapacityRate>,
+    #[serde(default)]
+    pub oceans: Vec<CapacityRate>,
+    pub ideal_gravity: f32,
+    /// gravity difference from the ideal where the capacity reach zero, gravity is ignored when
+    /// not defined
+    #[serde(default)]
+    pub gravity_tolerance: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityRate {
+    pub kind: String,
+    pub rate: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StarKindProduction {
+    pub kind: String,
+    pub rate: f32,
 }
@@ -0,0 +1,250 @@
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::game::astrobody::AstroBody;
+use crate::game::events::{EventKind, GEvent, GEvents};
+use crate::game::extractables::Extractable;
+use crate::game::factions::{Faction, FactionId, Owner};
+use crate::game::locations::{LocationDocked, LocationSpace, Locations};
+use crate::game::objects::ObjId;
+use crate::game::save::LoadingMapEntity;
+use crate::game::sectors::{JumpCache, JumpId, Sector, SectorId};
+use crate::game::station::Station;
+
+/// Sectors explored and jump gates discovered by a faction
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FactionDiscovery {
+    pub sectors: HashSet<SectorId>,
+    pub jumps: HashSet<JumpId>,
+}
+
+impl FactionDiscovery {
+    pub fn is_explored(&self, sector_id: SectorId) -> bool {
+        self.sectors.contains(&sector_id)
+    }
+
+    pub fn is_discovered(&self, jump_id: JumpId) -> bool {
+        self.jumps.contains(&jump_id)
+    }
+
+    /// A jump can be used on path finding once discovered and leading to an explored sector
+    pub fn is_known(&self, jump: &JumpCache) -> bool {
+        self.is_discovered(jump.jump_id) && self.is_explored(jump.to_sector)
+    }
+}
+
+/// Discovery state of factions that must explore the galaxy, other factions know every sector
+/// and jump
+#[derive(Debug, Clone, Default, Resource, Serialize, Deserialize)]
+pub struct Discoveries {
+    pub factions: HashMap<FactionId, FactionDiscovery>,
+}
+
+impl Discoveries {
+    pub fn get(&self, faction_id: FactionId) -> Option<&FactionDiscovery> {
+        self.factions.get(&faction_id)
+    }
+
+    /// Discovery of the faction owning the object, None when everything is known
+    pub fn get_by_owner(&self, owner: Option<&Owner>) -> Option<&FactionDiscovery> {
+        owner.and_then(|owner| self.get(owner.faction_id))
+    }
+
+    /// Discovery of the faction, None when discoveries are disabled or everything is known
+    pub fn of(
+        discoveries: Option<&Discoveries>,
+        faction_id: Option<FactionId>,
+    ) -> Option<&FactionDiscovery> {
+        discoveries.and_then(|d| d.get(faction_id?))
+    }
+}
+
+impl LoadingMapEntity for Discoveries {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        let factions = std::mem::take(&mut self.factions);
+        for (mut faction_id, discovery) in factions {
+            if !entity_map.contains_key(&faction_id) {
+                continue;
+            }
+            faction_id.map_entity(entity_map);
+
+            let new_discovery = FactionDiscovery {
+                sectors: discovery
+                    .sectors
+                    .into_iter()
+                    .filter_map(|id| entity_map.get(&id).copied())
+                    .collect(),
+                jumps: discovery
+                    .jumps
+                    .into_iter()
+                    .filter_map(|id| entity_map.get(&id).copied())
+                    .collect(),
+            };
+            self.factions.insert(faction_id, new_discovery);
+        }
+    }
+}
+
+/// Factions with discovery explore the sectors where they have objects, revealing the sector jumps
+/// and contents with a discovered event for each one
+pub fn system_discovery(
+    mut discoveries: ResMut<Discoveries>,
+    mut events: Option<ResMut<GEvents>>,
+    query_factions: Query<(Entity, &Faction)>,
+    query_owned: Query<(Entity, &Owner)>,
+    query_sectors: Query<&Sector>,
+    query_contents: Query<Entity, Or<(With<AstroBody>, With<Extractable>, With<Station>)>>,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+) {
+    log::trace!("running");
+
+    for (faction_id, faction) in &query_factions {
+        if faction.discovery && !discoveries.factions.contains_key(&faction_id) {
+            discoveries.factions.insert(faction_id, Default::default());
+        }
+    }
+
+    // sectors where each faction has objects and were not explored yet
+    let mut explored: Vec<(FactionId, SectorId)> = vec![];
+    for (obj_id, owner) in &query_owned {
+        let Some(discovery) = discoveries.factions.get(&owner.faction_id) else {
+            continue;
+        };
+        let Some(location) = Locations::resolve_space_position(&query_locations, obj_id) else {
+            continue;
+        };
+        if !discovery.is_explored(location.sector_id)
+            && !explored.contains(&(owner.faction_id, location.sector_id))
+        {
+            explored.push((owner.faction_id, location.sector_id));
+        }
+    }
+
+    for (faction_id, sector_id) in explored {
+        let discovery = discoveries.factions.get_mut(&faction_id).unwrap();
+        discovery.sectors.insert(sector_id);
+
+        let mut revealed: Vec<ObjId> = vec![sector_id];
+        if let Some(jumps) = query_sectors
+            .get(sector_id)
+            .ok()
+            .and_then(|sector| sector.jumps_cache.as_ref())
+        {
+            for jump in jumps {
+                if discovery.jumps.insert(jump.jump_id) {
+                    revealed.push(jump.jump_id);
+                }
+            }
+        }
+        for obj_id in &query_contents {
+            let in_sector = Locations::resolve_space_position(&query_locations, obj_id)
+                .map(|location| location.sector_id == sector_id)
+                .unwrap_or(false);
+            if in_sector {
+                revealed.push(obj_id);
+            }
+        }
+
+        log::debug!(
+            "{:?} explored sector {:?}, revealing {:?}",
+            faction_id,
+            sector_id,
+            revealed
+        );
+        if let Some(events) = events.as_mut() {
+            for obj_id in revealed {
+                events.push(GEvent::new(obj_id, EventKind::Discovered));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::utils::V2;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_discovery_should_explore_sectors_with_owned_objects() {
+        let mut world = World::new();
+        world.insert_resource(Discoveries::default());
+        world.insert_resource(GEvents::default());
+
+        let faction_id = world
+            .spawn(Faction {
+                discovery: true,
+                ..Default::default()
+            })
+            .id();
+        let other_faction_id = world.spawn(Faction::default()).id();
+
+        let sector_0 = world.spawn_empty().id();
+        let sector_1 = world.spawn_empty().id();
+        let jump_id = world
+            .spawn(LocationSpace {
+                pos: V2::ZERO,
+                sector_id: sector_0,
+            })
+            .id();
+        let mut sector = Sector::new(Default::default());
+        sector.jumps_cache = Some(vec![JumpCache {
+            jump_id,
+            to_sector: sector_1,
+        }]);
+        world.entity_mut(sector_0).insert(sector);
+        world
+            .entity_mut(sector_1)
+            .insert(Sector::new(Default::default()));
+
+        let station_id = world
+            .spawn((
+                Station::new(),
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id: sector_0,
+                },
+            ))
+            .id();
+        world.spawn((
+            Station::new(),
+            LocationSpace {
+                pos: V2::ZERO,
+                sector_id: sector_1,
+            },
+        ));
+        world.spawn((
+            Owner::new(faction_id),
+            LocationSpace {
+                pos: V2::ZERO,
+                sector_id: sector_0,
+            },
+        ));
+
+        world.run_system_once(system_discovery);
+
+        let discoveries = world.resource::<Discoveries>();
+        assert!(discoveries.get(other_faction_id).is_none());
+        let discovery = discoveries.get(faction_id).unwrap();
+        assert!(discovery.is_explored(sector_0));
+        assert!(!discovery.is_explored(sector_1));
+        assert!(discovery.is_discovered(jump_id));
+
+        let mut discovered: Vec<ObjId> = world
+            .resource_mut::<GEvents>()
+            .take()
+            .into_iter()
+            .filter(|e| e.kind == EventKind::Discovered)
+            .map(|e| e.id)
+            .collect();
+        discovered.sort();
+        let mut expected = vec![sector_0, jump_id, station_id];
+        expected.sort();
+        assert_eq!(expected, discovered);
+
+        // sectors are explored only once
+        world.run_system_once(system_discovery);
+        assert!(world.resource::<GEvents>().list().is_empty());
+    }
+}
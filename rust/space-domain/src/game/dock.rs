@@ -1,12 +1,214 @@
+use crate::game::objects::ObjId;
 use crate::game::save::LoadingMapEntity;
+use crate::game::utils::DeltaTime;
 use bevy_ecs::prelude::*;
+use commons::math::P2;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-/// Other objects can dock in this object
+/// Expected time a ship stay docked, used to estimate how long the ships in queue will wait
+pub const DOCK_AVERAGE_TIME: DeltaTime = DeltaTime(5.0);
+
+/// Distance from the station of the first holding position, each ring of holding positions get
+/// further away
+pub const HOLDING_DISTANCE: f32 = 0.5;
+const HOLDING_PER_RING: usize = 8;
+
+/// Size of a ship, ships can only dock in slots of the same size or bigger. Ships without it are
+/// small
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Component,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum SizeClass {
+    #[default]
+    Small,
+    Medium,
+    Large,
+}
+
+impl SizeClass {
+    /// Parse "small", "medium" or "large"
+    pub fn from_code(code: &str) -> Option<SizeClass> {
+        match code {
+            "small" => Some(SizeClass::Small),
+            "medium" => Some(SizeClass::Medium),
+            "large" => Some(SizeClass::Large),
+            _ => None,
+        }
+    }
+}
+
+/// Number of ships that can be docked at same time in slots of a size class
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DockSlots {
+    pub size: SizeClass,
+    pub capacity: usize,
+}
+
+impl DockSlots {
+    pub fn new(size: SizeClass, capacity: usize) -> Self {
+        DockSlots { size, capacity }
+    }
+}
+
+/// Other objects can dock in this object.
+///
+/// When no slots are defined, any number of objects can dock. Otherwise objects without a free slot
+/// wait in the queue and are granted in arrival order, and docked objects leave one at time in the
+/// order they requested to undock.
 #[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
 pub struct HasDocking {
     pub docked: Vec<Entity>,
+    #[serde(default)]
+    pub slots: Vec<DockSlots>,
+    /// slot size used by each docked object
+    #[serde(default)]
+    pub occupied: Vec<(Entity, SizeClass)>,
+    /// objects waiting for a free slot in arrival order
+    #[serde(default)]
+    pub queue: Vec<(Entity, SizeClass)>,
+    /// docked objects waiting to undock in request order
+    #[serde(default)]
+    pub undock_queue: Vec<Entity>,
+}
+
+impl HasDocking {
+    pub fn new(slots: Vec<DockSlots>) -> Self {
+        HasDocking {
+            slots,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    fn used(&self, slot: SizeClass) -> usize {
+        self.occupied.iter().filter(|(_, i)| *i == slot).count()
+    }
+
+    /// Slots that fit a ship of the given size
+    fn fitting_slots(&self, size: SizeClass) -> impl Iterator<Item = &DockSlots> {
+        self.slots.iter().filter(move |i| i.size >= size)
+    }
+
+    pub fn can_fit(&self, size: SizeClass) -> bool {
+        self.is_unlimited() || self.fitting_slots(size).any(|i| i.capacity > 0)
+    }
+
+    /// Smallest slot with free space that fit a ship of the given size
+    pub fn free_slot(&self, size: SizeClass) -> Option<SizeClass> {
+        self.fitting_slots(size)
+            .filter(|i| self.used(i.size) < i.capacity)
+            .map(|i| i.size)
+            .min()
+    }
+
+    /// Dock the object if there is a free slot for it and no object ahead in the queue is waiting
+    /// for the same slot, return false when the object must wait
+    pub fn request_dock(&mut self, obj_id: ObjId, size: SizeClass) -> bool {
+        if self.is_unlimited() {
+            self.remove_from_queue(obj_id);
+            self.docked.push(obj_id);
+            return true;
+        }
+
+        let slot = match self.free_slot(size) {
+            Some(slot) => slot,
+            None => return false,
+        };
+
+        let has_precedence = self
+            .queue
+            .iter()
+            .take_while(|(id, _)| *id != obj_id)
+            .any(|(_, other_size)| *other_size <= slot);
+        if has_precedence {
+            return false;
+        }
+
+        self.remove_from_queue(obj_id);
+        self.docked.push(obj_id);
+        self.occupied.push((obj_id, slot));
+        true
+    }
+
+    /// Add the object at the end of the queue if not yet there, returning its queue position
+    pub fn enqueue(&mut self, obj_id: ObjId, size: SizeClass) -> usize {
+        match self.queue.iter().position(|(id, _)| *id == obj_id) {
+            Some(index) => index,
+            None => {
+                self.queue.push((obj_id, size));
+                self.queue.len() - 1
+            }
+        }
+    }
+
+    pub fn remove_from_queue(&mut self, obj_id: ObjId) {
+        self.queue.retain(|(id, _)| *id != obj_id);
+    }
+
+    /// Remove the docked object releasing its slot
+    pub fn undock(&mut self, obj_id: ObjId) {
+        self.docked.retain(|id| *id != obj_id);
+        self.occupied.retain(|(id, _)| *id != obj_id);
+        self.undock_queue.retain(|id| *id != obj_id);
+    }
+
+    /// Add the docked object at the end of the undock queue if not yet there, return true when it
+    /// is the next to leave. Without slots any object can leave at any time.
+    pub fn request_undock(&mut self, obj_id: ObjId) -> bool {
+        if self.is_unlimited() {
+            return true;
+        }
+
+        if !self.undock_queue.contains(&obj_id) {
+            self.undock_queue.push(obj_id);
+        }
+        self.undock_queue.first() == Some(&obj_id)
+    }
+
+    /// Estimated time a ship of the given size arriving now would wait to dock, None when no slot
+    /// can fit it
+    pub fn expected_queue_time(&self, size: SizeClass) -> Option<DeltaTime> {
+        if self.is_unlimited() {
+            return Some(DeltaTime(0.0));
+        }
+
+        let capacity: usize = self.fitting_slots(size).map(|i| i.capacity).sum();
+        if capacity == 0 {
+            return None;
+        }
+
+        let used: usize = self.fitting_slots(size).map(|i| self.used(i.size)).sum();
+        let largest = self.fitting_slots(size).map(|i| i.size).max()?;
+        let waiting = self.queue.iter().filter(|(_, i)| *i <= largest).count();
+
+        let ahead = (used + waiting + 1).saturating_sub(capacity);
+        Some(DeltaTime(
+            ahead as f32 * DOCK_AVERAGE_TIME.as_f32() / capacity as f32,
+        ))
+    }
+
+    /// Position where the object at the queue index wait around the station
+    pub fn holding_position(station_pos: P2, index: usize) -> P2 {
+        let ring = (index / HOLDING_PER_RING) as f32;
+        let angle =
+            (index % HOLDING_PER_RING) as f32 * std::f32::consts::TAU / HOLDING_PER_RING as f32;
+        station_pos + P2::new(angle.cos(), angle.sin()) * HOLDING_DISTANCE * (1.0 + ring)
+    }
 }
 
 impl LoadingMapEntity for HasDocking {
@@ -14,5 +216,98 @@ impl LoadingMapEntity for HasDocking {
         self.docked
             .iter_mut()
             .for_each(|i| i.map_entity(entity_map));
+        self.occupied
+            .iter_mut()
+            .for_each(|(i, _)| i.map_entity(entity_map));
+        self.queue
+            .iter_mut()
+            .for_each(|(i, _)| i.map_entity(entity_map));
+        self.undock_queue
+            .iter_mut()
+            .for_each(|i| i.map_entity(entity_map));
+    }
+}
+
+/// Docking of stations and size of ships
+pub type QueryDocking<'w, 's> =
+    Query<'w, 's, (Option<&'static HasDocking>, Option<&'static SizeClass>)>;
+
+pub struct Docking;
+
+impl Docking {
+    pub fn get_size(query: &QueryDocking, obj_id: ObjId) -> SizeClass {
+        query
+            .get(obj_id)
+            .ok()
+            .and_then(|(_, size)| size)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Expected time the object would wait in the target queue, None when the target can not
+    /// receive it
+    pub fn expected_queue_time(
+        query: &QueryDocking,
+        obj_id: ObjId,
+        target_id: ObjId,
+    ) -> Option<DeltaTime> {
+        let size = Self::get_size(query, obj_id);
+        let (docking, _) = query.get(target_id).ok()?;
+        docking?.expected_queue_time(size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_docking_should_respect_slots_and_queue_order() {
+        let ship_0 = Entity::from_raw(0);
+        let ship_1 = Entity::from_raw(1);
+        let ship_2 = Entity::from_raw(2);
+        let ship_3 = Entity::from_raw(3);
+
+        let mut docking = HasDocking::new(vec![
+            DockSlots::new(SizeClass::Small, 1),
+            DockSlots::new(SizeClass::Large, 1),
+        ]);
+
+        // small ship take small slot first, next one the large
+        assert!(docking.request_dock(ship_0, SizeClass::Small));
+        assert!(docking.request_dock(ship_1, SizeClass::Small));
+        assert_eq!(
+            vec![(ship_0, SizeClass::Small), (ship_1, SizeClass::Large)],
+            docking.occupied
+        );
+
+        // full, large ship must wait
+        assert!(!docking.request_dock(ship_2, SizeClass::Large));
+        assert_eq!(0, docking.enqueue(ship_2, SizeClass::Large));
+        assert_eq!(
+            Some(DeltaTime(5.0)),
+            docking.expected_queue_time(SizeClass::Small)
+        );
+
+        // small slot released, the large ship waiting do not block small ships
+        docking.undock(ship_0);
+        assert!(docking.request_dock(ship_3, SizeClass::Small));
+
+        // large slot released, queued ship is granted
+        docking.undock(ship_1);
+        assert!(docking.request_dock(ship_2, SizeClass::Large));
+        assert!(docking.queue.is_empty());
+    }
+
+    #[test]
+    fn test_docking_should_not_fit_bigger_ships() {
+        let docking = HasDocking::new(vec![DockSlots::new(SizeClass::Small, 2)]);
+        assert!(!docking.can_fit(SizeClass::Medium));
+        assert_eq!(None, docking.expected_queue_time(SizeClass::Medium));
+        assert_eq!(
+            Some(DeltaTime(0.0)),
+            docking.expected_queue_time(SizeClass::Small)
+        );
+        assert!(HasDocking::default().can_fit(SizeClass::Large));
     }
 }
@@ -6,15 +6,35 @@ use bevy_ecs::system::{Command, Resource};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum EventKind {
     Add,
     Move,
     Jump,
     Dock,
+    /// waiting in queue for a free docking slot
+    DockQueued,
+    /// docking slot granted
+    DockGranted,
+    /// docking refused by the target
+    DockRefused,
     Undock,
     Deorbit,
     Orbit,
+    /// object was hit in combat
+    Hit,
+    /// object was destroyed in combat
+    Destroyed,
+    /// object joined a fleet group
+    FleetJoined,
+    /// object left its fleet group
+    FleetLeft,
+    /// object was robbed by raiders
+    Raided,
+    /// object was removed from the game
+    Removed,
+    /// sector, jump or object revealed by a faction exploration
+    Discovered,
 }
 
 #[derive(Debug, Clone, Event, Serialize, Deserialize)]
@@ -64,6 +84,8 @@ impl GEvents {
 
 impl LoadingMapEntity for GEvents {
     fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        // events of removed objects are discarded
+        self.queue.retain(|e| entity_map.contains_key(&e.id));
         self.queue.map_entity(entity_map);
     }
 }
@@ -1,14 +1,74 @@
 use crate::game::save::LoadingMapEntity;
 use bevy_ecs::prelude::*;
+use rand::prelude::*;
 use serde::{Deserialize, Serialize};
+use space_galaxy::system_generator::{self, BodyResource, UniverseCfg};
 use std::collections::HashMap;
 
-use crate::game::wares::{ResourceAccessibility, WareId};
+use crate::game::astrobody::{AstroBody, AstroBodyKind};
+use crate::game::code::HasCode;
+use crate::game::loader::Loader;
+use crate::game::locations::LocationSpace;
+use crate::game::objects::ObjId;
+use crate::game::sectors::Sector;
+use crate::game::utils::{DeltaTime, TotalTime};
+use crate::game::wares::{ResourceAccessibility, Volume, Ware, WareId, Wares, WaresByCode};
+use crate::game::wrecks::CommandDestroyObj;
+use commons::math::P2I;
 
 #[derive(Debug, Clone, Component, Serialize, Deserialize)]
 pub struct Extractable {
     pub ware_id: WareId,
     pub accessibility: ResourceAccessibility,
+    /// amount of wares that can still be extracted, unlimited when not defined
+    #[serde(default)]
+    pub reserve: Option<Volume>,
+}
+
+impl Extractable {
+    /// Create a extractable from the resources of a generated asteroid field. The reserve is the
+    /// max reserve scaled by the resource amount.
+    pub fn from_body_resources(
+        wares: &WaresByCode,
+        resources: &[BodyResource],
+        max_reserve: Option<f32>,
+    ) -> Option<Extractable> {
+        resources.iter().find_map(|body_resource| {
+            let ware_id = wares.get(body_resource.resource.as_str());
+            if ware_id.is_none() {
+                log::warn!(
+                    "could not find ware with name {:?} to be used as asteroid resources on {:?}",
+                    body_resource.resource,
+                    body_resource
+                );
+            }
+
+            ware_id.map(|ware_id| Extractable {
+                ware_id,
+                accessibility: 1.0,
+                reserve: max_reserve
+                    .map(|max| ((max * body_resource.amount).round() as Volume).max(1)),
+            })
+        })
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.reserve == Some(0)
+    }
+
+    /// Amount that can be extracted from the requested amount
+    pub fn get_available(&self, amount: Volume) -> Volume {
+        match self.reserve {
+            Some(reserve) => reserve.min(amount),
+            None => amount,
+        }
+    }
+
+    pub fn deplete(&mut self, amount: Volume) {
+        if let Some(reserve) = self.reserve.as_mut() {
+            *reserve = reserve.saturating_sub(amount);
+        }
+    }
 }
 
 impl LoadingMapEntity for Extractable {
@@ -16,3 +76,284 @@ impl LoadingMapEntity for Extractable {
         self.ware_id = entity_map[&self.ware_id];
     }
 }
+
+/// Periodically remove depleted asteroid fields and seed new ones into sectors that have less
+/// fields than the sector expected asteroids count.
+///
+/// Random values are derived from the seed, the sector coords and the respawn round, so the same
+/// seed produces the same fields after a game is loaded.
+#[derive(Resource, Debug, Clone)]
+pub struct ExtractablesRespawn {
+    pub universe_cfg: UniverseCfg,
+    pub interval: DeltaTime,
+    pub max_reserve: Option<f32>,
+    pub next_time: TotalTime,
+    pub seed: u64,
+}
+
+impl ExtractablesRespawn {
+    pub fn new(
+        universe_cfg: UniverseCfg,
+        interval: DeltaTime,
+        max_reserve: Option<f32>,
+        total_time: TotalTime,
+        seed: u64,
+    ) -> Self {
+        ExtractablesRespawn {
+            universe_cfg,
+            interval,
+            max_reserve,
+            next_time: total_time.add(interval),
+            seed,
+        }
+    }
+
+    /// Amount of asteroid fields a sector keeps, always the same for the same sector
+    pub fn expected_count(&self, coords: P2I) -> i32 {
+        let sector_seed = (coords.x as u64) << 32 | (coords.y as u32 as u64);
+        let mut rng = StdRng::seed_from_u64(self.seed ^ sector_seed);
+        self.universe_cfg
+            .asteroids_prob
+            .count_prob
+            .next_int(&mut rng)
+    }
+
+    /// Random sequence of the respawn happening at the given time
+    fn round_rng(&self, total_time: TotalTime) -> StdRng {
+        let round = (total_time.as_f64() / self.interval.as_f32() as f64) as u64;
+        StdRng::seed_from_u64(
+            self.seed
+                .wrapping_add(round.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        )
+    }
+}
+
+pub fn system_extractables_respawn(
+    mut commands: Commands,
+    total_time: Res<TotalTime>,
+    respawn: Option<ResMut<ExtractablesRespawn>>,
+    query_sectors: Query<(Entity, &Sector)>,
+    query_stars: Query<(Entity, &AstroBody, &LocationSpace)>,
+    query_extractables: Query<(Entity, &Extractable, &LocationSpace)>,
+    query_wares: Query<(Entity, With<Ware>, &HasCode)>,
+) {
+    log::trace!("running");
+
+    let Some(mut respawn) = respawn else {
+        return;
+    };
+
+    let total_time = *total_time;
+    if !total_time.is_after(respawn.next_time) {
+        return;
+    }
+    respawn.next_time = total_time.add(respawn.interval);
+
+    let mut active_per_sector: HashMap<Entity, i32> = HashMap::new();
+    let mut depleted: Vec<ObjId> = vec![];
+    for (obj_id, extractable, location) in &query_extractables {
+        if extractable.is_depleted() {
+            depleted.push(obj_id);
+        } else {
+            *active_per_sector.entry(location.sector_id).or_insert(0) += 1;
+        }
+    }
+
+    for obj_id in depleted {
+        log::debug!("{:?} asteroid field depleted, removing", obj_id);
+        commands.add(CommandDestroyObj {
+            obj_id,
+            leave_wreck: false,
+        });
+    }
+
+    let wares = Wares::list_wares_by_code(query_wares);
+    let mut rng = respawn.round_rng(total_time);
+
+    for (sector_id, sector) in &query_sectors {
+        let Some((star_id, _, _)) = query_stars
+            .iter()
+            .find(|(_, body, l)| body.kind == AstroBodyKind::Star && l.sector_id == sector_id)
+        else {
+            continue;
+        };
+
+        let expected = respawn.expected_count(sector.coords);
+        let active = active_per_sector.get(&sector_id).cloned().unwrap_or(0);
+        if active >= expected {
+            continue;
+        }
+
+        let body = system_generator::new_asteroid_field(&respawn.universe_cfg, &mut rng);
+        let system_generator::BodyDesc::AsteroidField { resources } = &body.desc else {
+            continue;
+        };
+
+        let Some(extractable) =
+            Extractable::from_body_resources(&wares, resources, respawn.max_reserve)
+        else {
+            log::warn!("fail to create asteroid field {:?}", body);
+            continue;
+        };
+
+        let new_obj = Loader::new_asteroid(sector_id)
+            .extractable(extractable)
+            .with_orbit(
+                star_id,
+                body.distance,
+                body.angle,
+                Loader::compute_orbit_speed(body.distance),
+                total_time,
+            );
+        let obj_id = Loader::add_object(&mut commands, &new_obj);
+        log::debug!(
+            "{:?} new asteroid field respawn at sector {:?}",
+            obj_id,
+            sector_id
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::events::GEvents;
+    use crate::game::locations::LocationOrbit;
+    use bevy_ecs::system::RunSystemOnce;
+    use commons::prob::{RDistrib, Weighted};
+    use space_galaxy::system_generator::{AstroProb, Resource};
+
+    fn new_universe_cfg() -> UniverseCfg {
+        let astro_prob = AstroProb {
+            count_prob: RDistrib::List { values: vec![2.0] },
+            distance_prob: RDistrib::MinMax(1.0, 20.0),
+            rotation_speed_prob: RDistrib::MinMax(0.1, 1.0),
+        };
+        let kinds = |value: &str| {
+            vec![Weighted {
+                prob: 1.0,
+                value: value.to_string(),
+            }]
+        };
+
+        UniverseCfg {
+            planets_prob: astro_prob.clone(),
+            moons_prob: astro_prob.clone(),
+            asteroids_prob: astro_prob,
+            biomes_kinds: kinds("barrent"),
+            atm_kinds: kinds("none"),
+            ocean_kinds: kinds("none"),
+            gravity_force: RDistrib::MinMax(0.1, 10.0),
+            star_size: RDistrib::MinMax(1.0, 5.0),
+            planet_size: RDistrib::MinMax(0.1, 10.0),
+            asteroid_size: RDistrib::MinMax(0.1, 0.5),
+            star_kinds: kinds("yellow"),
+            resources: vec![Resource {
+                kind: "ore".to_string(),
+                prob: 1.0,
+                always: vec![],
+                require: vec![],
+                forbidden: vec![],
+            }],
+            asteroid_atm: "vacuum".to_string(),
+            asteroid_biome: "none".to_string(),
+            system_resources_max: 1,
+            system_resources_amount: RDistrib::List { values: vec![0.5] },
+            system_distance_padding: 0.25,
+        }
+    }
+
+    #[test]
+    fn test_extractable_should_deplete_reserve() {
+        let mut world = World::new();
+        let ware_id = world.spawn_empty().id();
+
+        let mut extractable = Extractable {
+            ware_id,
+            accessibility: 1.0,
+            reserve: Some(3),
+        };
+        assert_eq!(2, extractable.get_available(2));
+        extractable.deplete(2);
+        assert_eq!(1, extractable.get_available(2));
+        extractable.deplete(1);
+        assert!(extractable.is_depleted());
+
+        extractable.reserve = None;
+        assert_eq!(10, extractable.get_available(10));
+        assert!(!extractable.is_depleted());
+    }
+
+    #[test]
+    fn test_respawn_should_seed_fields_until_expected_count() {
+        let mut world = World::new();
+        let ware_id = world
+            .spawn((
+                Ware {},
+                HasCode {
+                    code: "ore".to_string(),
+                },
+            ))
+            .id();
+        let sector_id = world.spawn(Sector::new(Default::default())).id();
+        let star_id = world
+            .spawn((
+                AstroBody {
+                    kind: AstroBodyKind::Star,
+                },
+                LocationSpace {
+                    pos: Default::default(),
+                    sector_id,
+                },
+            ))
+            .id();
+        world.spawn((
+            Extractable {
+                ware_id,
+                accessibility: 1.0,
+                reserve: Some(0),
+            },
+            LocationSpace {
+                pos: Default::default(),
+                sector_id,
+            },
+        ));
+
+        world.insert_resource(TotalTime(0.0));
+        world.insert_resource(GEvents::default());
+        world.insert_resource(ExtractablesRespawn::new(
+            new_universe_cfg(),
+            DeltaTime(10.0),
+            Some(100.0),
+            TotalTime(0.0),
+            0,
+        ));
+
+        let count_active = |world: &mut World| {
+            world
+                .query::<&Extractable>()
+                .iter(world)
+                .filter(|e| !e.is_depleted())
+                .count()
+        };
+
+        // not yet time to respawn
+        world.run_system_once(system_extractables_respawn);
+        assert_eq!(0, count_active(&mut world));
+
+        for i in 1..=3 {
+            world.insert_resource(TotalTime(i as f64 * 10.0));
+            world.run_system_once(system_extractables_respawn);
+        }
+        assert_eq!(2, count_active(&mut world));
+
+        // depleted field was removed
+        assert_eq!(2, world.query::<&Extractable>().iter(&world).count());
+
+        for (extractable, orbit) in world.query::<(&Extractable, &LocationOrbit)>().iter(&world) {
+            assert_eq!(ware_id, extractable.ware_id);
+            assert_eq!(Some(50), extractable.reserve);
+            assert_eq!(star_id, orbit.parent_id);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::code::HasCode;
+use crate::game::objects::ObjId;
+use crate::game::save::LoadingMapEntity;
+
+pub type FactionId = Entity;
+
+/// Which objects the ships of a faction can trade, mine and dock with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum OwnershipFilter {
+    /// any object, owned or not
+    #[default]
+    Any,
+    /// only objects owned by the same faction
+    Own,
+    /// objects owned by the same faction or without owner
+    OwnOrFree,
+}
+
+impl OwnershipFilter {
+    /// Parse "any", "own" or "own_or_free"
+    pub fn from_code(code: &str) -> Option<OwnershipFilter> {
+        match code {
+            "any" => Some(OwnershipFilter::Any),
+            "own" => Some(OwnershipFilter::Own),
+            "own_or_free" => Some(OwnershipFilter::OwnOrFree),
+            _ => None,
+        }
+    }
+
+    pub fn accept(&self, faction_id: Option<FactionId>, target: Option<FactionId>) -> bool {
+        match self {
+            OwnershipFilter::Any => true,
+            OwnershipFilter::Own => faction_id.is_some() && faction_id == target,
+            OwnershipFilter::OwnOrFree => target.is_none() || faction_id == target,
+        }
+    }
+}
+
+/// A faction is an entity that own ships, stations and building sites
+#[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
+pub struct Faction {
+    pub ownership: OwnershipFilter,
+    /// faction ships only know the sectors and jumps it has explored
+    #[serde(default)]
+    pub discovery: bool,
+    /// factions whose ships are engaged in combat, hostility goes both ways
+    #[serde(default)]
+    pub hostile: Vec<FactionId>,
+}
+
+impl Faction {
+    pub fn is_hostile(&self, faction_id: FactionId) -> bool {
+        self.hostile.contains(&faction_id)
+    }
+}
+
+impl LoadingMapEntity for Faction {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.hostile.map_entity(entity_map);
+    }
+}
+
+/// Faction that own the object
+#[derive(Debug, Clone, Copy, Component, PartialEq, Serialize, Deserialize)]
+pub struct Owner {
+    pub faction_id: FactionId,
+}
+
+impl Owner {
+    pub fn new(faction_id: FactionId) -> Self {
+        Owner { faction_id }
+    }
+}
+
+impl LoadingMapEntity for Owner {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.faction_id.map_entity(entity_map);
+    }
+}
+
+/// Owner of objects and the faction of owners
+pub type QueryOwnership<'w, 's> = Query<'w, 's, (Option<&'static Owner>, Option<&'static Faction>)>;
+
+/// Owner and ownership filter of an object, used to filter the targets of its searches. Objects
+/// without owner accept any target.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Ownership {
+    pub faction_id: Option<FactionId>,
+    pub filter: OwnershipFilter,
+}
+
+impl Ownership {
+    pub fn of(query: &QueryOwnership, obj_id: ObjId) -> Self {
+        let faction_id = Self::get_owner(query, obj_id);
+        let filter = faction_id
+            .and_then(|faction_id| query.get(faction_id).ok())
+            .and_then(|(_, faction)| faction)
+            .map(|faction| faction.ownership)
+            .unwrap_or_default();
+
+        Ownership { faction_id, filter }
+    }
+
+    pub fn get_owner(query: &QueryOwnership, obj_id: ObjId) -> Option<FactionId> {
+        query
+            .get(obj_id)
+            .ok()
+            .and_then(|(owner, _)| owner)
+            .map(|owner| owner.faction_id)
+    }
+
+    pub fn accept(&self, query: &QueryOwnership, target_id: ObjId) -> bool {
+        self.filter
+            .accept(self.faction_id, Self::get_owner(query, target_id))
+    }
+}
+
+pub struct Factions;
+
+impl Factions {
+    pub fn list_factions_by_code(
+        query: Query<(Entity, &HasCode), With<Faction>>,
+    ) -> HashMap<String, FactionId> {
+        query
+            .iter()
+            .map(|(id, code)| (code.code.clone(), id))
+            .collect()
+    }
+
+    /// Two factions are hostile when any of them declares the other as hostile
+    pub fn is_hostile(query: &Query<&Faction>, faction_0: FactionId, faction_1: FactionId) -> bool {
+        let declares = |from: FactionId, to: FactionId| {
+            query
+                .get(from)
+                .map(|faction| faction.is_hostile(to))
+                .unwrap_or(false)
+        };
+        faction_0 != faction_1 && (declares(faction_0, faction_1) || declares(faction_1, faction_0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_ownership_filter() {
+        let mut world = World::new();
+        let faction_0 = world.spawn_empty().id();
+        let faction_1 = world.spawn_empty().id();
+
+        let own = OwnershipFilter::Own;
+        assert!(own.accept(Some(faction_0), Some(faction_0)));
+        assert!(!own.accept(Some(faction_0), Some(faction_1)));
+        assert!(!own.accept(Some(faction_0), None));
+        assert!(!own.accept(None, None));
+
+        let own_or_free = OwnershipFilter::OwnOrFree;
+        assert!(own_or_free.accept(Some(faction_0), Some(faction_0)));
+        assert!(!own_or_free.accept(Some(faction_0), Some(faction_1)));
+        assert!(own_or_free.accept(Some(faction_0), None));
+
+        assert!(OwnershipFilter::Any.accept(Some(faction_0), Some(faction_1)));
+    }
+
+    #[test]
+    fn test_ownership_should_use_owner_faction_filter() {
+        let mut world = World::new();
+        let faction_id = world
+            .spawn(Faction {
+                ownership: OwnershipFilter::Own,
+                ..Default::default()
+            })
+            .id();
+        let ship_id = world.spawn(Owner::new(faction_id)).id();
+        let own_station = world.spawn(Owner::new(faction_id)).id();
+        let free_station = world.spawn_empty().id();
+        let free_ship = world.spawn_empty().id();
+
+        let (ownership, accept_own, accept_free) =
+            world.run_system_once(move |query: QueryOwnership| {
+                let ownership = Ownership::of(&query, ship_id);
+                (
+                    ownership,
+                    ownership.accept(&query, own_station),
+                    ownership.accept(&query, free_station),
+                )
+            });
+        assert_eq!(Some(faction_id), ownership.faction_id);
+        assert!(accept_own);
+        assert!(!accept_free);
+
+        let accept = world.run_system_once(move |query: QueryOwnership| {
+            Ownership::of(&query, free_ship).accept(&query, own_station)
+        });
+        assert!(accept);
+    }
+
+    #[test]
+    fn test_factions_hostility_should_go_both_ways() {
+        let mut world = World::new();
+        let faction_0 = world.spawn(Faction::default()).id();
+        let faction_1 = world
+            .spawn(Faction {
+                hostile: vec![faction_0],
+                ..Default::default()
+            })
+            .id();
+        let faction_2 = world.spawn(Faction::default()).id();
+
+        let hostility = world.run_system_once(move |query: Query<&Faction>| {
+            (
+                Factions::is_hostile(&query, faction_0, faction_1),
+                Factions::is_hostile(&query, faction_1, faction_0),
+                Factions::is_hostile(&query, faction_0, faction_2),
+                Factions::is_hostile(&query, faction_1, faction_1),
+            )
+        });
+        assert_eq!((true, true, false, false), hostility);
+    }
+}
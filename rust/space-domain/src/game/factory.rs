@@ -1,7 +1,14 @@
+use crate::game::habitat::Habitat;
+use crate::game::locations::LocationSpace;
+use crate::game::order::{TradeOrder, TradeOrders, TRADE_ORDER_ID_FACTORY};
 use crate::game::save::LoadingMapEntity;
+use crate::game::sectors::SectorId;
+use crate::game::stats::{EconomyStats, StatKind};
+use crate::game::utils;
 use crate::game::utils::{DeltaTime, TotalTime};
-use crate::game::wares::{Cargo, WareAmount, WareId};
+use crate::game::wares::{Cargo, CargoDistributionDirty, WareAmount, WareId};
 use bevy_ecs::prelude::*;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -30,83 +37,501 @@ impl LoadingMapEntity for Receipt {
     }
 }
 
+/// How a factory with many receipts choose the next one to produce
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReceiptPolicy {
+    /// produce the receipts by index in the given order, restarting once reach the end
+    Queue(Vec<usize>),
+    /// produce each receipt in turn
+    RoundRobin,
+    /// produce the receipt with all inputs available and most requested outputs
+    Demand,
+}
+
+impl ReceiptPolicy {
+    /// Parse policies by code, "queue" produce all receipts in the order they were defined
+    pub fn from_code(code: &str, receipts_len: usize) -> Option<ReceiptPolicy> {
+        match code {
+            "queue" => Some(ReceiptPolicy::Queue((0..receipts_len).collect())),
+            "round_robin" => Some(ReceiptPolicy::RoundRobin),
+            "demand" => Some(ReceiptPolicy::Demand),
+            _ => None,
+        }
+    }
+}
+
+/// Origin of a production rate modifier, each source has at most one value
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RateSource {
+    /// station upgrades, set from conf or the api
+    Upgrade,
+    /// population of habitats in the same sector
+    Workforce,
+    /// sector bonus like the star kind
+    Sector,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FactoryState {
+    /// waiting for inputs to start a production
+    Idle,
+    Producing,
+    /// a production is complete but the output can not be added to the cargo
+    Stalled,
+}
+
+/// A production in progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductionLine {
+    /// receipt index
+    pub receipt: usize,
+    pub end_time: TotalTime,
+}
+
+/// Production rate bonus for all factories in a sector
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct ProductionBonus {
+    pub rate: f32,
+}
+
 #[derive(Debug, Clone, Component, Serialize, Deserialize)]
 pub struct Factory {
-    pub production: Receipt,
-    pub production_time: Option<TotalTime>,
+    receipts: Vec<Receipt>,
+    policy: ReceiptPolicy,
+    /// receipt index selected for next production
+    current: usize,
+    /// next position in the queue when policy is a queue
+    queue_index: usize,
+    /// max number of productions in parallel
+    lines: usize,
+    producing: Vec<ProductionLine>,
+    modifiers: Vec<(RateSource, f32)>,
+    state: FactoryState,
+    dirt_trade_order: bool,
 }
 
 impl Factory {
+    /// Factories with less priority request inputs of receipts that are not the current one
+    pub const STANDBY_INPUT_PRIORITY: f32 = 0.5;
+    /// Factories are refilled with priority below min and stop requesting above max stock
+    pub const INPUT_MIN_STOCK: f32 = 0.25;
+    pub const INPUT_MAX_STOCK: f32 = 0.9;
+    /// Sector population required to reach the full workforce bonus
+    pub const WORKFORCE_POPULATION: f32 = 1000.0;
+    /// Rate bonus of factories in a sector with the full workforce
+    pub const WORKFORCE_MAX_BONUS: f32 = 0.5;
+
     pub fn new(production: Receipt) -> Self {
         Factory {
-            production,
-            production_time: None,
+            receipts: vec![production],
+            policy: ReceiptPolicy::RoundRobin,
+            current: 0,
+            queue_index: 0,
+            lines: 1,
+            producing: vec![],
+            modifiers: vec![],
+            state: FactoryState::Idle,
+            dirt_trade_order: false,
+        }
+    }
+
+    pub fn with_receipt(mut self, receipt: Receipt) -> Self {
+        self.receipts.push(receipt);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: ReceiptPolicy) -> Self {
+        self.set_policy(policy);
+        self
+    }
+
+    pub fn with_lines(mut self, lines: usize) -> Self {
+        self.set_lines(lines);
+        self
+    }
+
+    pub fn get_lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Change the amount of parallel productions, productions in progress are not interrupted
+    pub fn set_lines(&mut self, lines: usize) {
+        self.lines = lines.max(1);
+    }
+
+    /// Production rate multiplier from all sources, 2.0 produce in half of the receipt time
+    pub fn get_rate(&self) -> f32 {
+        self.modifiers.iter().map(|(_, value)| *value).product()
+    }
+
+    pub fn get_rate_modifier(&self, source: RateSource) -> Option<f32> {
+        self.modifiers
+            .iter()
+            .find(|(s, _)| *s == source)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn set_rate_modifier(&mut self, source: RateSource, value: f32) {
+        match self.modifiers.iter_mut().find(|(s, _)| *s == source) {
+            Some((_, current)) => *current = value,
+            None => self.modifiers.push((source, value)),
+        }
+    }
+
+    pub fn remove_rate_modifier(&mut self, source: RateSource) {
+        self.modifiers.retain(|(s, _)| *s != source);
+    }
+
+    /// Rate modifier given the population living in the factory sector
+    pub fn compute_workforce_rate(population: f32) -> f32 {
+        1.0 + Self::WORKFORCE_MAX_BONUS * (population / Self::WORKFORCE_POPULATION).clamp(0.0, 1.0)
+    }
+
+    pub fn get_state(&self) -> FactoryState {
+        self.state
+    }
+
+    pub fn get_receipts(&self) -> &Vec<Receipt> {
+        &self.receipts
+    }
+
+    pub fn get_policy(&self) -> &ReceiptPolicy {
+        &self.policy
+    }
+
+    /// Change the policy, invalid receipt indexes in a queue are ignored
+    pub fn set_policy(&mut self, policy: ReceiptPolicy) {
+        self.policy = match policy {
+            ReceiptPolicy::Queue(queue) => ReceiptPolicy::Queue(
+                queue
+                    .into_iter()
+                    .filter(|i| *i < self.receipts.len())
+                    .collect(),
+            ),
+            other => other,
+        };
+        self.queue_index = 0;
+        if let ReceiptPolicy::Queue(queue) = &self.policy {
+            if let Some(index) = queue.first() {
+                self.current = *index;
+            }
+        }
+        self.dirt_trade_order = true;
+    }
+
+    pub fn get_current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn get_current_receipt(&self) -> &Receipt {
+        &self.receipts[self.current]
+    }
+
+    /// Manually switch the receipt for next production, a production in progress is not affected.
+    /// Return false when the index is invalid.
+    pub fn set_current(&mut self, index: usize) -> bool {
+        if index >= self.receipts.len() {
+            return false;
+        }
+        if self.current != index {
+            self.current = index;
+            self.dirt_trade_order = true;
+        }
+        true
+    }
+
+    pub fn is_producing(&self) -> bool {
+        !self.producing.is_empty()
+    }
+
+    pub fn get_producing(&self) -> &Vec<ProductionLine> {
+        &self.producing
+    }
+
+    /// Receipt of the production that will complete first
+    pub fn get_producing_receipt(&self) -> Option<&Receipt> {
+        self.get_next_completion()
+            .map(|line| &self.receipts[line.receipt])
+    }
+
+    /// Time when the next production is completed
+    pub fn get_production_time(&self) -> Option<TotalTime> {
+        self.get_next_completion().map(|line| line.end_time)
+    }
+
+    fn get_next_completion(&self) -> Option<&ProductionLine> {
+        self.producing
+            .iter()
+            .min_by(|a, b| a.end_time.as_f64().total_cmp(&b.end_time.as_f64()))
+    }
+
+    /// Receipts index that can be choose by the policy
+    pub fn get_scheduled(&self) -> Vec<usize> {
+        match &self.policy {
+            ReceiptPolicy::Queue(queue) if !queue.is_empty() => {
+                queue.iter().copied().unique().collect()
+            }
+            _ => (0..self.receipts.len()).collect(),
         }
     }
 
     pub fn get_cargos_allocation(&self) -> Vec<WareId> {
         let mut result = Vec::new();
-        result.extend(self.production.input.iter().map(|i| i.ware_id));
-        result.extend(self.production.output.iter().map(|i| i.ware_id));
-        result
+        for index in self.get_scheduled() {
+            let receipt = &self.receipts[index];
+            result.extend(receipt.input.iter().map(|i| i.ware_id));
+            result.extend(receipt.output.iter().map(|i| i.ware_id));
+        }
+        result.into_iter().unique().collect()
+    }
+
+    /// Request inputs from scheduled receipts, the current one with full priority, and provide
+    /// outputs of any receipt to clean up what was produced before a switch.
+    pub fn update_trade_orders(&self, orders: &mut TradeOrders) {
+        orders.remove_by_id(TRADE_ORDER_ID_FACTORY);
+
+        let current_inputs = self.get_current_receipt().request_wares_id();
+        for index in self.get_scheduled() {
+            for ware_id in self.receipts[index].request_wares_id() {
+                let priority = if current_inputs.contains(&ware_id) {
+                    1.0
+                } else {
+                    Self::STANDBY_INPUT_PRIORITY
+                };
+
+                orders.add_request_order(
+                    TradeOrder::new(TRADE_ORDER_ID_FACTORY, ware_id)
+                        .with_stock(Self::INPUT_MIN_STOCK, Self::INPUT_MAX_STOCK)
+                        .with_priority(priority),
+                );
+            }
+        }
+
+        for receipt in &self.receipts {
+            for ware_id in receipt.provide_wares_id() {
+                orders.add_provider(TRADE_ORDER_ID_FACTORY, ware_id);
+            }
+        }
+    }
+
+    /// Move to the next receipt once a production start
+    fn select_next_by_order(&mut self) {
+        let previous = self.current;
+        match &self.policy {
+            ReceiptPolicy::Queue(queue) if !queue.is_empty() => {
+                self.queue_index = (self.queue_index + 1) % queue.len();
+                self.current = queue[self.queue_index];
+            }
+            ReceiptPolicy::RoundRobin => {
+                self.current = (self.current + 1) % self.receipts.len();
+            }
+            _ => {}
+        }
+        if previous != self.current {
+            self.dirt_trade_order = true;
+        }
+    }
+
+    /// Choose the receipt with all inputs available and outputs with more demand, keep the current
+    /// one when no receipt can be produced
+    fn select_next_by_demand(&mut self, cargo: &Cargo, demand: &HashMap<WareId, f32>) {
+        let best = self
+            .receipts
+            .iter()
+            .enumerate()
+            .filter(|(_, receipt)| {
+                receipt
+                    .input
+                    .iter()
+                    .all(|wa| cargo.get_amount(wa.ware_id) >= wa.amount)
+            })
+            .map(|(index, receipt)| {
+                let score: f32 = receipt
+                    .output
+                    .iter()
+                    .map(|wa| demand.get(&wa.ware_id).copied().unwrap_or(0.0))
+                    .sum();
+                (-score, index)
+            });
+
+        if let Some(index) = utils::lower(best) {
+            if index != self.current {
+                self.current = index;
+                self.dirt_trade_order = true;
+            }
+        }
     }
 }
 
 impl LoadingMapEntity for Factory {
     fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
-        self.production.map_entity(entity_map);
+        self.receipts.map_entity(entity_map);
     }
 }
 
+/// Sum of request urgency of all stations for each ware
+fn compute_wares_demand(query_orders: &Query<(&TradeOrders, &Cargo)>) -> HashMap<WareId, f32> {
+    let mut demand = HashMap::new();
+    for (orders, cargo) in query_orders {
+        for ware_id in orders.wares_requests() {
+            *demand.entry(ware_id).or_insert(0.0) += orders.request_urgency(cargo, ware_id);
+        }
+    }
+    demand
+}
+
 pub fn system_factory(
+    mut commands: Commands,
     total_time: Res<TotalTime>,
-    mut query: Query<(Entity, &mut Cargo, &mut Factory)>,
+    mut queries: ParamSet<(
+        Query<(
+            Entity,
+            &mut Cargo,
+            &mut Factory,
+            Option<&mut TradeOrders>,
+            Option<&LocationSpace>,
+        )>,
+        Query<(&TradeOrders, &Cargo)>,
+    )>,
+    query_bonus: Query<&ProductionBonus>,
+    query_habitats: Query<(&Habitat, &LocationSpace)>,
+    mut stats: Option<ResMut<EconomyStats>>,
 ) {
     log::trace!("running");
 
     let total_time = *total_time;
 
-    for (entity, mut cargo, mut factory) in &mut query {
-        match factory.production_time {
-            Some(time) if total_time.is_after(time) => {
-                // production ready
-                match cargo.add_all_or_none(&factory.production.output) {
-                    Ok(()) => {
-                        log::debug!(
-                            "{:?} factory complete production, adding cargo: {:?}",
-                            entity,
-                            &factory.production.output,
-                        );
-                        factory.production_time = None;
-                    }
-                    Err(err) => {
-                        log::warn!(
-                            "{:?} factory complete production, but fail to add cargo by {:?}",
-                            entity,
-                            err
-                        );
+    let mut population_per_sector: HashMap<SectorId, f32> = HashMap::new();
+    for (habitat, location) in &query_habitats {
+        *population_per_sector.entry(location.sector_id).or_default() += habitat.population;
+    }
+
+    // demand is only required when a factory choose production by demand
+    let demand = if queries
+        .p0()
+        .iter()
+        .any(|(_, _, factory, ..)| factory.policy == ReceiptPolicy::Demand)
+    {
+        compute_wares_demand(&queries.p1())
+    } else {
+        HashMap::new()
+    };
+
+    for (entity, mut cargo, mut factory, maybe_orders, maybe_location) in &mut queries.p0() {
+        let sector_id = maybe_location.map(|l| l.sector_id);
+
+        // complete productions
+        let mut stalled = false;
+        let mut index = 0;
+        while index < factory.producing.len() {
+            let line = &factory.producing[index];
+            if !total_time.is_after(line.end_time) {
+                // producing
+                index += 1;
+                continue;
+            }
+
+            let output = factory.receipts[line.receipt].output.clone();
+            match cargo.add_all_or_none(&output) {
+                Ok(()) => {
+                    log::debug!(
+                        "{:?} factory complete production, adding cargo: {:?}",
+                        entity,
+                        output,
+                    );
+                    factory.producing.remove(index);
+                    if let Some(stats) = stats.as_mut() {
+                        stats.record_all(StatKind::Produced, entity, sector_id, &output);
                     }
                 }
+                Err(err) => {
+                    log::trace!(
+                        "{:?} factory complete production, but fail to add cargo by {:?}",
+                        entity,
+                        err
+                    );
+                    stalled = true;
+                    index += 1;
+                }
             }
+        }
 
-            Some(_time) => {
-                // producing
-                log::trace!("{:?} factory producing", entity);
+        // update sector bonus
+        match sector_id.and_then(|id| query_bonus.get(id).ok()) {
+            Some(bonus) => factory.set_rate_modifier(RateSource::Sector, bonus.rate),
+            None => factory.remove_rate_modifier(RateSource::Sector),
+        }
+
+        // update workforce from sector population
+        match sector_id.and_then(|id| population_per_sector.get(&id)) {
+            Some(population) => factory.set_rate_modifier(
+                RateSource::Workforce,
+                Factory::compute_workforce_rate(*population),
+            ),
+            None => factory.remove_rate_modifier(RateSource::Workforce),
+        }
+
+        // start new productions in free lines, a stalled factory wait until output can be stored
+        let rate = factory.get_rate();
+        while !stalled && rate > 0.0 && factory.producing.len() < factory.lines {
+            if factory.policy == ReceiptPolicy::Demand {
+                factory.select_next_by_demand(&cargo, &demand);
             }
 
-            None => {
-                // check if have enough cargo to start a new production
-                match cargo.remove_all_or_none(&factory.production.input) {
-                    Ok(()) => {
-                        let end_time = total_time.add(factory.production.time);
-                        log::trace!("{entity:?} factory start production, ends at {end_time:?}");
-                        factory.production_time = Some(end_time);
-                    }
-                    Err(err) => {
-                        log::trace!("{entity:?} factory skipping production by {err:?}");
+            // check if have enough cargo to start a new production
+            let receipt = factory.get_current_receipt();
+            match cargo.remove_all_or_none(&receipt.input) {
+                Ok(()) => {
+                    let end_time = total_time.add(DeltaTime(receipt.time.as_f32() / rate));
+                    log::trace!(
+                        "{entity:?} factory start production of {:?}, ends at {end_time:?}",
+                        receipt.label
+                    );
+                    if let Some(stats) = stats.as_mut() {
+                        stats.record_all(StatKind::Consumed, entity, sector_id, &receipt.input);
                     }
+                    let line = ProductionLine {
+                        receipt: factory.current,
+                        end_time,
+                    };
+                    factory.producing.push(line);
+                    factory.select_next_by_order();
+                }
+                Err(err) => {
+                    log::trace!("{entity:?} factory skipping production by {err:?}");
+                    break;
+                }
+            }
+        }
+
+        let state = if stalled {
+            FactoryState::Stalled
+        } else if factory.is_producing() {
+            FactoryState::Producing
+        } else {
+            FactoryState::Idle
+        };
+        if factory.state != state {
+            log::debug!("{:?} factory change state to {:?}", entity, state);
+            factory.state = state;
+        }
+
+        if factory.dirt_trade_order {
+            factory.dirt_trade_order = false;
+
+            match maybe_orders {
+                Some(mut orders) => {
+                    factory.update_trade_orders(&mut orders);
+                    log::debug!(
+                        "{:?} factory switch to receipt {:?}, trade orders updated",
+                        entity,
+                        factory.get_current_receipt().label
+                    );
                 }
+                None => log::warn!("{:?} has no trade orders", entity),
             }
+
+            commands.entity(entity).insert(CargoDistributionDirty {});
         }
     }
 }
@@ -123,6 +548,19 @@ mod test {
     const TOTAL_CARGO: Volume = 200;
     const PRODUCED_PLATE: Volume = 10;
 
+    #[test]
+    fn test_receipt_policy_from_code_should_queue_all_receipts() {
+        assert_eq!(
+            Some(ReceiptPolicy::Queue(vec![0, 1, 2])),
+            ReceiptPolicy::from_code("queue", 3)
+        );
+        assert_eq!(
+            Some(ReceiptPolicy::Demand),
+            ReceiptPolicy::from_code("demand", 3)
+        );
+        assert_eq!(None, ReceiptPolicy::from_code("unknown", 3));
+    }
+
     #[test]
     fn test_factory_system_should_not_start_production_without_enough_cargo() {
         run_factory(0, 0, 3.0, None, None, 0, 0, 0);
@@ -207,14 +645,15 @@ mod test {
 
         world.insert_resource(TotalTime(total_time));
 
-        let obj_id = world
-            .spawn_empty()
-            .insert(cargo)
-            .insert(Factory {
-                production,
-                production_time: production_time.map(|time| TotalTime(time)),
-            })
-            .id();
+        let mut factory = Factory::new(production);
+        if let Some(time) = production_time {
+            factory.producing.push(ProductionLine {
+                receipt: 0,
+                end_time: TotalTime(time),
+            });
+        }
+
+        let obj_id = world.spawn_empty().insert(cargo).insert(factory).id();
 
         world.run_system_once(super::system_factory);
 
@@ -230,8 +669,248 @@ mod test {
         let factory = entity_ref.get::<Factory>().unwrap().clone();
         assert_eq!(
             expect_produce_at,
-            factory.production_time.map(|i| i.as_f64()),
+            factory.get_production_time().map(|i| i.as_f64()),
             "fail for production time"
         );
     }
+
+    fn new_receipt(label: &str, input: Vec<WareAmount>, output: Vec<WareAmount>) -> Receipt {
+        Receipt {
+            label: label.to_string(),
+            input,
+            output,
+            time: DeltaTime(1.0),
+        }
+    }
+
+    #[test]
+    fn test_factory_round_robin_should_switch_receipt_on_each_production() {
+        let mut world = World::new();
+        let ware_0 = world.spawn_empty().id();
+        let ware_1 = world.spawn_empty().id();
+
+        let factory = Factory::new(new_receipt("a", vec![], vec![WareAmount::new(ware_0, 1)]))
+            .with_receipt(new_receipt("b", vec![], vec![WareAmount::new(ware_1, 1)]));
+
+        world.insert_resource(TotalTime(0.0));
+        let obj_id = world
+            .spawn_empty()
+            .insert(Cargo::new(10))
+            .insert(factory)
+            .insert(TradeOrders::default())
+            .id();
+
+        world.run_system_once(super::system_factory);
+        let factory = world.get::<Factory>(obj_id).unwrap();
+        assert_eq!("a", factory.get_producing_receipt().unwrap().label);
+        assert_eq!(1, factory.get_current_index());
+        assert!(world.get::<CargoDistributionDirty>(obj_id).is_some());
+
+        world.insert_resource(TotalTime(2.0));
+        world.run_system_once(super::system_factory);
+        let factory = world.get::<Factory>(obj_id).unwrap();
+        assert_eq!("b", factory.get_producing_receipt().unwrap().label);
+        assert_eq!(0, factory.get_current_index());
+        assert_eq!(1, world.get::<Cargo>(obj_id).unwrap().get_amount(ware_0));
+    }
+
+    #[test]
+    fn test_factory_queue_should_follow_order() {
+        let mut world = World::new();
+        let ware_0 = world.spawn_empty().id();
+
+        let mut factory = Factory::new(new_receipt("a", vec![], vec![WareAmount::new(ware_0, 1)]))
+            .with_receipt(new_receipt("b", vec![], vec![WareAmount::new(ware_0, 1)]))
+            .with_policy(ReceiptPolicy::Queue(vec![1, 1, 0, 5]));
+        assert_eq!(&ReceiptPolicy::Queue(vec![1, 1, 0]), factory.get_policy());
+        assert_eq!(1, factory.get_current_index());
+
+        let mut produced = vec![];
+        for _ in 0..4 {
+            produced.push(factory.get_current_index());
+            factory.select_next_by_order();
+        }
+        assert_eq!(vec![1, 1, 0, 1], produced);
+    }
+
+    #[test]
+    fn test_factory_demand_should_choose_available_and_most_requested() {
+        let mut world = World::new();
+        let ore_id = world.spawn_empty().id();
+        let energy_id = world.spawn_empty().id();
+        let plate_id = world.spawn_empty().id();
+        let gas_id = world.spawn_empty().id();
+
+        let mut factory = Factory::new(new_receipt(
+            "energy",
+            vec![],
+            vec![WareAmount::new(energy_id, 1)],
+        ))
+        .with_receipt(new_receipt(
+            "plate",
+            vec![WareAmount::new(ore_id, 1)],
+            vec![WareAmount::new(plate_id, 1)],
+        ))
+        .with_receipt(new_receipt(
+            "gas",
+            vec![WareAmount::new(ore_id, 100)],
+            vec![WareAmount::new(gas_id, 1)],
+        ))
+        .with_policy(ReceiptPolicy::Demand);
+
+        let mut cargo = Cargo::new(100);
+        cargo.add(ore_id, 10).unwrap();
+
+        let mut demand = HashMap::new();
+        demand.insert(plate_id, 1.0);
+        demand.insert(gas_id, 5.0);
+
+        factory.select_next_by_demand(&cargo, &demand);
+        assert_eq!("plate", factory.get_current_receipt().label);
+
+        demand.insert(energy_id, 2.0);
+        factory.select_next_by_demand(&cargo, &demand);
+        assert_eq!("energy", factory.get_current_receipt().label);
+    }
+
+    #[test]
+    fn test_factory_trade_orders_should_prioritize_current_receipt() {
+        let mut world = World::new();
+        let ore_id = world.spawn_empty().id();
+        let energy_id = world.spawn_empty().id();
+        let plate_id = world.spawn_empty().id();
+
+        let mut factory = Factory::new(new_receipt(
+            "a",
+            vec![WareAmount::new(ore_id, 1)],
+            vec![WareAmount::new(plate_id, 1)],
+        ))
+        .with_receipt(new_receipt(
+            "b",
+            vec![WareAmount::new(energy_id, 1)],
+            vec![WareAmount::new(plate_id, 1)],
+        ));
+
+        let cargo = Cargo::new(100);
+        let mut orders = TradeOrders::default();
+        factory.update_trade_orders(&mut orders);
+        assert!(orders.request_urgency(&cargo, ore_id) > orders.request_urgency(&cargo, energy_id));
+        assert_eq!(vec![plate_id], orders.wares_provider());
+
+        assert!(factory.set_current(1));
+        factory.update_trade_orders(&mut orders);
+        assert!(orders.request_urgency(&cargo, ore_id) < orders.request_urgency(&cargo, energy_id));
+    }
+
+    #[test]
+    fn test_factory_should_run_parallel_lines_with_rate_modifiers() {
+        let mut world = World::new();
+        let ore_id = world.spawn_empty().id();
+        let plate_id = world.spawn_empty().id();
+        let sector_id = world.spawn(ProductionBonus { rate: 2.0 }).id();
+
+        let mut factory = Factory::new(Receipt {
+            label: "plate".to_string(),
+            input: vec![WareAmount::new(ore_id, 1)],
+            output: vec![WareAmount::new(plate_id, 1)],
+            time: DeltaTime(4.0),
+        })
+        .with_lines(2);
+        factory.set_rate_modifier(RateSource::Upgrade, 2.0);
+
+        let mut cargo = Cargo::new(10);
+        cargo.add(ore_id, 3).unwrap();
+
+        world.insert_resource(TotalTime(0.0));
+        let obj_id = world
+            .spawn_empty()
+            .insert(cargo)
+            .insert(factory)
+            .insert(LocationSpace {
+                pos: Default::default(),
+                sector_id,
+            })
+            .id();
+
+        world.run_system_once(super::system_factory);
+
+        let factory = world.get::<Factory>(obj_id).unwrap();
+        assert_eq!(4.0, factory.get_rate());
+        assert_eq!(2, factory.get_producing().len());
+        assert_eq!(Some(TotalTime(1.0)), factory.get_production_time());
+        assert_eq!(FactoryState::Producing, factory.get_state());
+        assert_eq!(1, world.get::<Cargo>(obj_id).unwrap().get_amount(ore_id));
+    }
+
+    #[test]
+    fn test_factory_should_get_workforce_bonus_from_sector_habitats() {
+        let mut world = World::new();
+        let sector_id = world.spawn_empty().id();
+        let location = LocationSpace {
+            pos: Default::default(),
+            sector_id,
+        };
+
+        world.insert_resource(TotalTime(0.0));
+        world.spawn((
+            Habitat::new(
+                Factory::WORKFORCE_POPULATION / 2.0,
+                Factory::WORKFORCE_POPULATION,
+                vec![],
+            ),
+            location.clone(),
+        ));
+        let obj_id = world
+            .spawn((
+                Cargo::new(10),
+                Factory::new(new_receipt("plate", vec![], vec![])),
+                location,
+            ))
+            .id();
+
+        world.run_system_once(super::system_factory);
+
+        let factory = world.get::<Factory>(obj_id).unwrap();
+        assert_eq!(
+            Some(1.0 + Factory::WORKFORCE_MAX_BONUS / 2.0),
+            factory.get_rate_modifier(RateSource::Workforce)
+        );
+    }
+
+    #[test]
+    fn test_factory_should_be_stalled_when_output_do_not_fit() {
+        let mut world = World::new();
+        let plate_id = world.spawn_empty().id();
+
+        let mut factory = Factory::new(new_receipt(
+            "plate",
+            vec![],
+            vec![WareAmount::new(plate_id, 5)],
+        ));
+        factory.producing.push(ProductionLine {
+            receipt: 0,
+            end_time: TotalTime(0.0),
+        });
+
+        let mut cargo = Cargo::new(10);
+        cargo.add(plate_id, 8).unwrap();
+
+        world.insert_resource(TotalTime(1.0));
+        let obj_id = world.spawn_empty().insert(cargo).insert(factory).id();
+
+        world.run_system_once(super::system_factory);
+        let factory = world.get::<Factory>(obj_id).unwrap();
+        assert_eq!(FactoryState::Stalled, factory.get_state());
+        assert_eq!(1, factory.get_producing().len());
+
+        world
+            .get_mut::<Cargo>(obj_id)
+            .unwrap()
+            .remove(plate_id, 8)
+            .unwrap();
+        world.run_system_once(super::system_factory);
+        let factory = world.get::<Factory>(obj_id).unwrap();
+        assert_eq!(FactoryState::Producing, factory.get_state());
+        assert_eq!(5, world.get::<Cargo>(obj_id).unwrap().get_amount(plate_id));
+    }
 }
@@ -1,5 +1,354 @@
 use bevy_ecs::prelude::*;
+use bevy_ecs::system::Command;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::commands;
+use crate::game::events::{EventKind, GEvent, GEvents};
+use crate::game::locations::QueryEffectiveSpeed;
+use crate::game::navigations::NavRequest;
+use crate::game::objects::ObjId;
+use crate::game::save::LoadingMapEntity;
+use crate::game::utils::{Speed, V2};
 
 #[derive(Debug, Clone, Component, Serialize, Deserialize)]
 pub struct Fleet {}
+
+/// Leader of a group of ships. Members escort the leader, so orders given to the leader are shared
+/// by the whole group, and the leader moves at the speed of the slowest ship.
+#[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
+pub struct FleetGroup {
+    pub members: Vec<ObjId>,
+    /// effective speed of the slowest ship in the group, refreshed every tick
+    #[serde(default)]
+    pub speed: Option<Speed>,
+}
+
+impl FleetGroup {
+    /// Limit the speed of the leader to the group speed
+    pub fn apply(&self, speed: Speed) -> Speed {
+        match self.speed {
+            Some(group_speed) if group_speed.as_f32() < speed.as_f32() => group_speed,
+            _ => speed,
+        }
+    }
+}
+
+impl LoadingMapEntity for FleetGroup {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.members.map_entity(entity_map);
+    }
+}
+
+/// Member of a group, keeping its formation offset from the leader position
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct FleetMember {
+    pub leader_id: ObjId,
+    pub offset: V2,
+}
+
+impl LoadingMapEntity for FleetMember {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.leader_id.map_entity(entity_map);
+    }
+}
+
+/// Add the member into the leader group, leaving any previous group and replacing its command
+/// by an escort of the leader
+pub struct CommandJoinFleetGroup {
+    pub leader_id: ObjId,
+    pub member_id: ObjId,
+    pub offset: V2,
+}
+
+impl Command for CommandJoinFleetGroup {
+    fn apply(self, world: &mut World) {
+        if let Err(err) = Fleets::join_group(world, self.leader_id, self.member_id, self.offset) {
+            log::warn!(
+                "{:?} can not join group of {:?}, {}",
+                self.member_id,
+                self.leader_id,
+                err
+            );
+        }
+    }
+}
+
+/// Remove the member from its group
+pub struct CommandLeaveFleetGroup {
+    pub member_id: ObjId,
+}
+
+impl Command for CommandLeaveFleetGroup {
+    fn apply(self, world: &mut World) {
+        Fleets::leave_group(world, self.member_id);
+    }
+}
+
+pub struct Fleets;
+
+impl Fleets {
+    /// Add the member into the leader group, leaving any previous group and replacing its command
+    /// by an escort of the leader
+    pub fn join_group(
+        world: &mut World,
+        leader_id: ObjId,
+        member_id: ObjId,
+        offset: V2,
+    ) -> Result<(), &'static str> {
+        if leader_id == member_id
+            || world.get::<FleetMember>(leader_id).is_some()
+            || world.get::<FleetGroup>(member_id).is_some()
+        {
+            return Err("groups can not be nested");
+        }
+
+        if world.get_entity(leader_id).is_none() || world.get_entity(member_id).is_none() {
+            return Err("obj not found");
+        }
+
+        Self::leave_group(world, member_id);
+
+        let mut leader = world.entity_mut(leader_id);
+        match leader.get_mut::<FleetGroup>() {
+            Some(mut group) => group.members.push(member_id),
+            None => {
+                leader.insert(FleetGroup {
+                    members: vec![member_id],
+                    speed: None,
+                });
+            }
+        }
+
+        world
+            .entity_mut(member_id)
+            .insert((
+                FleetMember { leader_id, offset },
+                commands::Command::Escort {
+                    target_id: leader_id,
+                },
+            ))
+            .remove::<NavRequest>();
+
+        log::debug!("{:?} joined group of {:?}", member_id, leader_id);
+        world
+            .resource_mut::<GEvents>()
+            .push(GEvent::new(member_id, EventKind::FleetJoined));
+        Ok(())
+    }
+
+    /// Remove the member from its group, dropping the escort of the leader and the group once it
+    /// has no members. Return false if the object was not in a group
+    pub fn leave_group(world: &mut World, member_id: ObjId) -> bool {
+        let Some(member) = world.get::<FleetMember>(member_id).cloned() else {
+            return false;
+        };
+
+        if let Some(mut leader) = world.get_entity_mut(member.leader_id) {
+            if let Some(mut group) = leader.get_mut::<FleetGroup>() {
+                group.members.retain(|id| *id != member_id);
+                if group.members.is_empty() {
+                    leader.remove::<FleetGroup>();
+                }
+            }
+        }
+
+        let mut entity = world.entity_mut(member_id);
+        entity.remove::<FleetMember>();
+        if let Some(commands::Command::Escort { target_id }) = entity.get::<commands::Command>() {
+            if *target_id == member.leader_id {
+                entity.remove::<commands::Command>();
+            }
+        }
+
+        log::debug!("{:?} left group of {:?}", member_id, member.leader_id);
+        world
+            .resource_mut::<GEvents>()
+            .push(GEvent::new(member_id, EventKind::FleetLeft));
+        true
+    }
+}
+
+/// Keep groups consistent with its members and refresh the group speed
+pub fn system_fleet_groups(
+    mut commands: Commands,
+    mut query_groups: Query<(Entity, &mut FleetGroup)>,
+    query_members: Query<(Entity, &FleetMember)>,
+    query_speeds: QueryEffectiveSpeed,
+) {
+    log::trace!("running");
+
+    for (leader_id, mut group) in &mut query_groups {
+        group.members.retain(|member_id| {
+            query_members
+                .get(*member_id)
+                .map(|(_, member)| member.leader_id == leader_id)
+                .unwrap_or(false)
+        });
+
+        if group.members.is_empty() {
+            commands.entity(leader_id).remove::<FleetGroup>();
+            continue;
+        }
+
+        group.speed = std::iter::once(leader_id)
+            .chain(group.members.iter().copied())
+            .filter_map(|id| query_speeds.get(id).ok())
+            .map(|(moveable, speed_by_mass, cargo, wear, tank)| {
+                moveable.effective_speed(speed_by_mass, cargo, wear, tank)
+            })
+            .min_by(|a, b| a.as_f32().total_cmp(&b.as_f32()));
+    }
+
+    // members of leaders that are gone
+    for (member_id, member) in &query_members {
+        if query_groups.get(member.leader_id).is_err() {
+            commands.add(CommandLeaveFleetGroup { member_id });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::commands::Command as ShipCommand;
+    use crate::game::locations::Moveable;
+    use crate::game::maintenance::Wear;
+    use crate::game::save::{load_world, save_world};
+    use crate::game::stats::EconomyStats;
+    use crate::game::utils::{Tick, TotalTime};
+    use bevy_ecs::system::RunSystemOnce;
+
+    fn new_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(TotalTime(0.0));
+        world.insert_resource(GEvents::default());
+        world.insert_resource(Tick::default());
+        world.insert_resource(EconomyStats::default());
+        world
+    }
+
+    fn join(world: &mut World, leader_id: ObjId, member_id: ObjId) {
+        CommandJoinFleetGroup {
+            leader_id,
+            member_id,
+            offset: V2::new(-1.0, 0.0),
+        }
+        .apply(world);
+    }
+
+    fn take_events(world: &mut World) -> Vec<(ObjId, EventKind)> {
+        world
+            .resource_mut::<GEvents>()
+            .take()
+            .into_iter()
+            .map(|e| (e.id, e.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_fleet_group_join_and_leave() {
+        let mut world = new_world();
+        let leader_id = world.spawn(Moveable { speed: Speed(2.0) }).id();
+        let member_0 = world.spawn(Moveable { speed: Speed(1.0) }).id();
+        let member_1 = world.spawn(Moveable { speed: Speed(3.0) }).id();
+
+        join(&mut world, leader_id, member_0);
+        join(&mut world, leader_id, member_1);
+        // nested groups are ignored
+        join(&mut world, member_0, leader_id);
+
+        assert_eq!(
+            vec![
+                (member_0, EventKind::FleetJoined),
+                (member_1, EventKind::FleetJoined)
+            ],
+            take_events(&mut world)
+        );
+        assert!(matches!(
+            world.get::<ShipCommand>(member_0),
+            Some(ShipCommand::Escort { target_id }) if *target_id == leader_id
+        ));
+
+        // group move at slowest speed
+        world.run_system_once(system_fleet_groups);
+        let group = world.get::<FleetGroup>(leader_id).unwrap();
+        assert_eq!(vec![member_0, member_1], group.members);
+        assert_eq!(1.0, group.apply(Speed(2.0)).as_f32());
+
+        // last member leaving remove the group
+        CommandLeaveFleetGroup {
+            member_id: member_0,
+        }
+        .apply(&mut world);
+        assert!(world.get::<ShipCommand>(member_0).is_none());
+        assert!(world.get::<FleetMember>(member_0).is_none());
+
+        world.despawn(member_1);
+        world.run_system_once(system_fleet_groups);
+        world.run_system_once(system_fleet_groups);
+        assert!(world.get::<FleetGroup>(leader_id).is_none());
+        assert_eq!(
+            vec![(member_0, EventKind::FleetLeft)],
+            take_events(&mut world)
+        );
+    }
+
+    #[test]
+    fn test_fleet_group_speed_should_use_effective_speed() {
+        let mut world = new_world();
+        let leader_id = world.spawn(Moveable { speed: Speed(2.0) }).id();
+        let wear = Wear { value: 1.0 };
+        let expected = wear.apply(Speed(3.0));
+        let member_id = world.spawn((Moveable { speed: Speed(3.0) }, wear)).id();
+        join(&mut world, leader_id, member_id);
+
+        world.run_system_once(system_fleet_groups);
+
+        let group = world.get::<FleetGroup>(leader_id).unwrap();
+        assert_eq!(expected.as_f32().min(2.0), group.speed.unwrap().as_f32());
+    }
+
+    #[test]
+    fn test_fleet_group_should_leave_when_leader_is_gone() {
+        let mut world = new_world();
+        let leader_id = world.spawn_empty().id();
+        let member_id = world.spawn_empty().id();
+        join(&mut world, leader_id, member_id);
+        take_events(&mut world);
+
+        world.despawn(leader_id);
+        world.run_system_once(system_fleet_groups);
+
+        assert!(world.get::<FleetMember>(member_id).is_none());
+        assert!(world.get::<ShipCommand>(member_id).is_none());
+        assert_eq!(
+            vec![(member_id, EventKind::FleetLeft)],
+            take_events(&mut world)
+        );
+    }
+
+    #[test]
+    fn test_fleet_group_save_and_load() {
+        let mut world = new_world();
+        let leader_id = world.spawn_empty().id();
+        let member_id = world.spawn_empty().id();
+        join(&mut world, leader_id, member_id);
+
+        let save_data = save_world(&mut world);
+        // spawn some entities to force ids to be remapped
+        let mut world = World::new();
+        world.spawn_empty();
+        load_world(&mut world, save_data);
+
+        let (new_leader_id, group) = world.query::<(Entity, &FleetGroup)>().single(&world);
+        let group = group.clone();
+        let (new_member_id, member) = world.query::<(Entity, &FleetMember)>().single(&world);
+        assert_eq!(vec![new_member_id], group.members);
+        assert_eq!(new_leader_id, member.leader_id);
+        assert!(matches!(
+            world.get::<ShipCommand>(new_member_id),
+            Some(ShipCommand::Escort { target_id }) if *target_id == new_leader_id
+        ));
+    }
+}
@@ -0,0 +1,518 @@
+use bevy_ecs::prelude::*;
+use commons::math::P2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::actions::{ActionActive, ActionRequest};
+use crate::game::commands::{Command, QUEUE_TIME_WEIGHT};
+use crate::game::discovery::Discoveries;
+use crate::game::dock::{Docking, QueryDocking};
+use crate::game::factions::{Owner, Ownership, QueryOwnership};
+use crate::game::locations::{EntityPerSectorIndex, LocationDocked, LocationSpace, Locations};
+use crate::game::navigations::{NavRequest, NavRequestFailed, Navigation};
+use crate::game::objects::ObjId;
+use crate::game::order::TradeOrders;
+use crate::game::prices::{Credit, Credits, Prices, WarePrice};
+use crate::game::save::LoadingMapEntity;
+use crate::game::sectors::{find_path_raw, FindPathParams, Jump, PathLeg, Sector};
+use crate::game::stats::{EconomyStats, StatKind};
+use crate::game::utils::Speed;
+use crate::game::wares::{Cargo, Volume, WareId};
+
+/// Fuel consumed on each jump, as the distance that would be moved with the same fuel
+pub const JUMP_FUEL_DISTANCE: f32 = 50.0;
+
+/// Ratio of the speed kept by ships with an empty tank
+pub const EMPTY_TANK_SPEED_RATIO: f32 = 0.1;
+
+/// Tank ratio from which ships with a command go to refuel
+pub const REFUEL_THRESHOLD: f32 = 0.3;
+
+/// Fuel held by a ship, consumed on movement and jumps. Ships with an empty tank crawl.
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct FuelTank {
+    pub ware_id: WareId,
+    pub amount: f32,
+    pub capacity: f32,
+    /// fuel consumed for each unit of distance moved
+    pub consume: f32,
+}
+
+impl FuelTank {
+    /// Create a full tank
+    pub fn new(ware_id: WareId, capacity: f32, consume: f32) -> Self {
+        FuelTank {
+            ware_id,
+            amount: capacity,
+            capacity,
+            consume,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amount <= 0.0
+    }
+
+    pub fn jump_cost(&self) -> f32 {
+        self.consume * JUMP_FUEL_DISTANCE
+    }
+
+    /// Fuel required to move the distance and perform the jumps
+    pub fn required(&self, distance: f32, jumps: usize) -> f32 {
+        distance * self.consume + jumps as f32 * self.jump_cost()
+    }
+
+    /// Check if there is fuel to move through the sectors path, moving inside a sector is always
+    /// possible
+    pub fn can_travel(&self, from_pos: P2, path: &[PathLeg], to_pos: P2) -> bool {
+        if path.is_empty() {
+            return true;
+        }
+
+        let mut distance = 0.0;
+        let mut pos = from_pos;
+        for leg in path {
+            distance += pos.distance(leg.jump_pos);
+            pos = leg.target_pos;
+        }
+        distance += pos.distance(to_pos);
+
+        self.required(distance, path.len()) <= self.amount
+    }
+
+    pub fn consume_distance(&mut self, distance: f32) {
+        self.amount = (self.amount - distance * self.consume).max(0.0);
+    }
+
+    /// Consume the fuel of a jump, return false when there was not enough fuel
+    pub fn consume_jump(&mut self) -> bool {
+        let cost = self.jump_cost();
+        let enough = self.amount >= cost;
+        self.amount = (self.amount - cost).max(0.0);
+        enough
+    }
+
+    pub fn apply(&self, speed: Speed) -> Speed {
+        if self.is_empty() && self.consume > 0.0 {
+            Speed(speed.as_f32() * EMPTY_TANK_SPEED_RATIO)
+        } else {
+            speed
+        }
+    }
+
+    pub fn require_refuel(&self) -> bool {
+        self.amount < self.capacity * REFUEL_THRESHOLD
+    }
+
+    /// Whole units of fuel that fit into the tank
+    pub fn get_missing(&self) -> Volume {
+        (self.capacity - self.amount).max(0.0).floor() as Volume
+    }
+}
+
+impl LoadingMapEntity for FuelTank {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.ware_id.map_entity(entity_map);
+    }
+}
+
+/// Ship going to refuel at a station. Its command is suspended until it refuels.
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct Refuel {
+    pub target_id: ObjId,
+    pub command: Option<Command>,
+}
+
+impl LoadingMapEntity for Refuel {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.target_id.map_entity(entity_map);
+        self.command.map_entity(entity_map);
+    }
+}
+
+impl Refuel {
+    /// Remove the refuel restoring the suspended command
+    pub fn complete(commands: &mut Commands, obj_id: ObjId, refuel: &Refuel) {
+        let mut entity = commands.entity(obj_id);
+        entity.remove::<Refuel>();
+        if let Some(command) = refuel.command.clone() {
+            entity.insert(command);
+        }
+    }
+}
+
+/// Idle ships with a command and low fuel, or that failed to navigate for lack of fuel, are sent
+/// to the nearest station providing fuel. Stations in other sectors are only considered when the
+/// ship has fuel for the path.
+#[allow(clippy::too_many_arguments)]
+pub fn system_refuel_request(
+    mut commands: Commands,
+    query: Query<
+        (Entity, &FuelTank, &Command, Option<&NavRequestFailed>),
+        (
+            Without<Refuel>,
+            Without<Navigation>,
+            Without<NavRequest>,
+            Without<ActionActive>,
+            Without<ActionRequest>,
+        ),
+    >,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+    query_orders: Query<(&TradeOrders, &Cargo)>,
+    query_ownership: QueryOwnership,
+    query_docking: QueryDocking,
+    query_sectors: Query<&Sector>,
+    query_jumps: Query<(&Jump, &LocationSpace)>,
+    query_owner: Query<&Owner>,
+    discoveries: Option<Res<Discoveries>>,
+    sector_index: Res<EntityPerSectorIndex>,
+) {
+    log::trace!("running");
+
+    for (obj_id, tank, command, maybe_failed) in &query {
+        if !tank.require_refuel() && !maybe_failed.is_some_and(|f| f.is_out_of_fuel()) {
+            continue;
+        }
+
+        let Some(location) = Locations::resolve_space_position(&query_locations, obj_id) else {
+            continue;
+        };
+
+        let discovery = discoveries
+            .as_deref()
+            .and_then(|d| d.get_by_owner(query_owner.get(obj_id).ok()));
+        let ownership = Ownership::of(&query_ownership, obj_id);
+        let candidates = sector_index
+            .search_nearest_stations(location.sector_id, discovery)
+            .flat_map(|(_, distance, candidate_id)| {
+                let (orders, cargo) = query_orders.get(candidate_id).ok()?;
+                if !orders.wares_provider().contains(&tank.ware_id)
+                    || cargo.get_amount(tank.ware_id) == 0
+                    || !ownership.accept(&query_ownership, candidate_id)
+                {
+                    return None;
+                }
+                if distance > 0 {
+                    let candidate_location =
+                        Locations::resolve_space_position(&query_locations, candidate_id)?;
+                    let path = find_path_raw(
+                        &query_sectors,
+                        &query_jumps,
+                        discovery,
+                        FindPathParams::new(location.sector_id, candidate_location.sector_id),
+                    )?;
+                    if !tank.can_travel(location.pos, &path, candidate_location.pos) {
+                        return None;
+                    }
+                }
+                let queue_time =
+                    Docking::expected_queue_time(&query_docking, obj_id, candidate_id)?;
+                Some((
+                    distance as f32 + QUEUE_TIME_WEIGHT * queue_time.as_f32(),
+                    candidate_id,
+                ))
+            });
+
+        let Some(target_id) = crate::game::utils::lower(candidates) else {
+            log::trace!("{:?} require fuel but no station provides it", obj_id);
+            continue;
+        };
+
+        log::debug!(
+            "{:?} with fuel {:?} going to refuel at {:?}",
+            obj_id,
+            tank.amount,
+            target_id
+        );
+        commands
+            .entity(obj_id)
+            .remove::<Command>()
+            .insert(Refuel {
+                target_id,
+                command: Some(command.clone()),
+            })
+            .insert(NavRequest::MoveAndDockAt { target_id });
+    }
+}
+
+/// Fill the tank of ships docked at their refuel station, paying the station sell price. The
+/// refuel is canceled when the station can not be reached, letting the ship crawl with its command.
+pub fn system_refuel(
+    mut commands: Commands,
+    mut query: Query<
+        (Entity, &Refuel, &mut FuelTank, Option<&NavRequestFailed>),
+        (Without<Navigation>, Without<NavRequest>),
+    >,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+    mut query_cargos: Query<&mut Cargo>,
+    mut query_credits: Query<&mut Credits>,
+    query_prices: Query<&WarePrice>,
+    mut stats: Option<ResMut<EconomyStats>>,
+) {
+    log::trace!("running");
+
+    for (obj_id, refuel, mut tank, maybe_failed) in &mut query {
+        let target_id = refuel.target_id;
+        let Ok(mut cargo) = query_cargos.get_mut(target_id) else {
+            log::warn!(
+                "{:?} refuel target {:?} has no cargo, canceling",
+                obj_id,
+                target_id
+            );
+            Refuel::complete(&mut commands, obj_id, refuel);
+            continue;
+        };
+
+        if !Locations::is_docked_at(&query_locations, obj_id, target_id) {
+            if maybe_failed.is_some() {
+                log::warn!(
+                    "{:?} can not reach refuel station {:?}, canceling",
+                    obj_id,
+                    target_id
+                );
+                commands.entity(obj_id).remove::<NavRequestFailed>();
+                Refuel::complete(&mut commands, obj_id, refuel);
+                continue;
+            }
+
+            log::debug!(
+                "{:?} not docked at refuel station {:?}, moving",
+                obj_id,
+                target_id
+            );
+            commands
+                .entity(obj_id)
+                .insert(NavRequest::MoveAndDockAt { target_id });
+            continue;
+        }
+
+        let amount = tank.get_missing().min(cargo.get_amount(tank.ware_id));
+        let price = query_prices
+            .get(tank.ware_id)
+            .map(|price| Prices::sell_price(price.base, &cargo, tank.ware_id))
+            .unwrap_or(0);
+        if amount > 0 && cargo.remove(tank.ware_id, amount).is_ok() {
+            tank.amount += amount as f32;
+            Prices::settle(
+                &mut query_credits,
+                target_id,
+                obj_id,
+                price * amount as Credit,
+            );
+
+            if let Some(stats) = stats.as_mut() {
+                let sector_id = Locations::resolve_space_position(&query_locations, target_id)
+                    .map(|l| l.sector_id);
+                stats.record(StatKind::Moved, target_id, sector_id, tank.ware_id, amount);
+            }
+        }
+
+        log::debug!(
+            "{:?} refuel {:?} at {:?}, tank {:?}/{:?}",
+            obj_id,
+            amount,
+            target_id,
+            tank.amount,
+            tank.capacity
+        );
+        Refuel::complete(&mut commands, obj_id, refuel);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::dock::HasDocking;
+    use crate::game::navigations::NAV_ERROR_NOT_ENOUGH_FUEL;
+    use crate::game::order::TRADE_ORDER_ID_FACTORY;
+    use crate::game::utils::TotalTime;
+    use bevy_ecs::system::RunSystemOnce;
+
+    /// Station providing fuel and a ship with fuel amount in the same sector
+    fn setup(world: &mut World, amount: f32) -> (ObjId, ObjId, WareId) {
+        let sector_id = world.spawn(Sector::new(Default::default())).id();
+        let ware_id = world.spawn(WarePrice { base: 10 }).id();
+
+        let mut cargo = Cargo::new(100);
+        cargo.add(ware_id, 50).unwrap();
+        let station_id = world
+            .spawn((
+                LocationSpace {
+                    pos: Default::default(),
+                    sector_id,
+                },
+                HasDocking::default(),
+                TradeOrders::from_provided(TRADE_ORDER_ID_FACTORY, &[ware_id]),
+                cargo,
+                Credits::new(0),
+            ))
+            .id();
+
+        let mut sector_index = EntityPerSectorIndex::new();
+        sector_index.add_stations(sector_id, station_id);
+        world.insert_resource(sector_index);
+
+        let mut tank = FuelTank::new(ware_id, 20.0, 0.1);
+        tank.amount = amount;
+        let ship_id = world
+            .spawn((
+                LocationSpace {
+                    pos: Default::default(),
+                    sector_id,
+                },
+                tank,
+                Command::mine(),
+                Credits::new(1000),
+            ))
+            .id();
+
+        (station_id, ship_id, ware_id)
+    }
+
+    #[test]
+    fn test_fuel_tank_consume() {
+        let mut tank = FuelTank::new(Entity::from_raw(0), 10.0, 0.1);
+        tank.consume_distance(20.0);
+        assert_eq!(8.0, tank.amount);
+        assert_eq!(10.0, tank.apply(Speed(10.0)).as_f32());
+        assert!(tank.consume_jump());
+        assert_eq!(3.0, tank.amount);
+        assert!(!tank.consume_jump());
+        assert!(tank.is_empty());
+        assert_eq!(
+            10.0 * EMPTY_TANK_SPEED_RATIO,
+            tank.apply(Speed(10.0)).as_f32()
+        );
+        assert_eq!(10, tank.get_missing());
+    }
+
+    #[test]
+    fn test_fuel_tank_can_travel_path() {
+        let tank = FuelTank::new(Entity::from_raw(0), 10.0, 0.1);
+        let leg = || PathLeg {
+            sector_id: Entity::from_raw(1),
+            jump_id: Entity::from_raw(2),
+            jump_pos: P2::new(10.0, 0.0),
+            target_sector_id: Entity::from_raw(3),
+            target_pos: P2::ZERO,
+        };
+
+        assert!(tank.can_travel(P2::ZERO, &[], P2::new(1000.0, 0.0)));
+        // 5.0 to move and 5.0 to jump
+        assert!(tank.can_travel(P2::ZERO, &[leg()], P2::new(40.0, 0.0)));
+        assert!(!tank.can_travel(P2::ZERO, &[leg(), leg()], P2::ZERO));
+    }
+
+    #[test]
+    fn test_ship_with_low_fuel_should_refuel_and_resume_command() {
+        let mut world = World::new();
+        let (station_id, ship_id, ware_id) = setup(&mut world, 2.5);
+
+        world.run_system_once(system_refuel_request);
+        assert!(world.get::<Command>(ship_id).is_none());
+        assert_eq!(
+            Some(&NavRequest::MoveAndDockAt {
+                target_id: station_id
+            }),
+            world.get::<NavRequest>(ship_id)
+        );
+
+        // arrive at the station
+        world
+            .entity_mut(ship_id)
+            .remove::<(NavRequest, LocationSpace)>()
+            .insert(LocationDocked {
+                parent_id: station_id,
+            });
+
+        world.run_system_once(system_refuel);
+        assert_eq!(19.5, world.get::<FuelTank>(ship_id).unwrap().amount);
+        assert_eq!(
+            33,
+            world.get::<Cargo>(station_id).unwrap().get_amount(ware_id)
+        );
+        assert!(world.get::<Credits>(station_id).unwrap().get_balance() > 0);
+        assert!(world.get::<Refuel>(ship_id).is_none());
+        assert!(world.get::<Command>(ship_id).is_some());
+    }
+
+    #[test]
+    fn test_refuel_should_not_sell_reserved_fuel() {
+        let mut world = World::new();
+        let (station_id, ship_id, ware_id) = setup(&mut world, 2.5);
+        let trader_id = world.spawn_empty().id();
+
+        // all fuel is reserved by a trader, the station is not a candidate
+        world.get_mut::<Cargo>(station_id).unwrap().reserve_out(
+            trader_id,
+            ware_id,
+            50,
+            TotalTime(100.0),
+        );
+        world.run_system_once(system_refuel_request);
+        assert!(world.get::<Refuel>(ship_id).is_none());
+
+        let mut cargo = world.get_mut::<Cargo>(station_id).unwrap();
+        cargo.release(trader_id);
+        cargo.reserve_out(trader_id, ware_id, 45, TotalTime(100.0));
+        world.run_system_once(system_refuel_request);
+        assert_eq!(station_id, world.get::<Refuel>(ship_id).unwrap().target_id);
+
+        world
+            .entity_mut(ship_id)
+            .remove::<(NavRequest, LocationSpace)>()
+            .insert(LocationDocked {
+                parent_id: station_id,
+            });
+
+        world.run_system_once(system_refuel);
+        assert_eq!(7.5, world.get::<FuelTank>(ship_id).unwrap().amount);
+        let cargo = world.get::<Cargo>(station_id).unwrap();
+        assert_eq!(0, cargo.get_amount(ware_id));
+        assert_eq!(45, cargo.get_stored_amount(ware_id));
+    }
+
+    #[test]
+    fn test_ship_failing_navigation_for_fuel_should_refuel() {
+        let mut world = World::new();
+        let (station_id, ship_id, _) = setup(&mut world, 15.0);
+
+        world.run_system_once(system_refuel_request);
+        assert!(world.get::<Refuel>(ship_id).is_none());
+
+        world.entity_mut(ship_id).insert(NavRequestFailed {
+            request: NavRequest::MoveAndDockAt {
+                target_id: station_id,
+            },
+            error: NAV_ERROR_NOT_ENOUGH_FUEL,
+        });
+        world.run_system_once(system_refuel_request);
+        assert_eq!(station_id, world.get::<Refuel>(ship_id).unwrap().target_id);
+    }
+
+    #[test]
+    fn test_refuel_should_cancel_when_station_is_unreachable() {
+        let mut world = World::new();
+        let (station_id, ship_id, _) = setup(&mut world, 2.5);
+
+        world.entity_mut(ship_id).remove::<Command>().insert((
+            Refuel {
+                target_id: station_id,
+                command: Some(Command::mine()),
+            },
+            NavRequestFailed {
+                request: NavRequest::MoveAndDockAt {
+                    target_id: station_id,
+                },
+                error: "no path",
+            },
+        ));
+
+        world.run_system_once(system_refuel);
+        assert!(world.get::<Refuel>(ship_id).is_none());
+        assert!(world.get::<NavRequestFailed>(ship_id).is_none());
+        assert!(world.get::<NavRequest>(ship_id).is_none());
+        assert!(world.get::<Command>(ship_id).is_some());
+        assert_eq!(2.5, world.get::<FuelTank>(ship_id).unwrap().amount);
+    }
+}
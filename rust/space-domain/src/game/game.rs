@@ -1,5 +1,7 @@
 use crate::game::bevy_utils::WorldExt;
+use crate::game::discovery::Discoveries;
 use crate::game::events::{GEvent, GEvents};
+use crate::game::factions::{FactionId, Factions};
 use crate::game::label::Label;
 use crate::game::loader::Loader;
 use crate::game::locations::{
@@ -8,11 +10,15 @@ use crate::game::locations::{
 use crate::game::new_obj::NewObj;
 use crate::game::objects::ObjId;
 use crate::game::sectors::{Sector, SectorId};
-use crate::game::utils::{DeltaTime, Tick, TotalTime};
+use crate::game::sensors::{FactionsVisibility, Sighting, Visible};
+use crate::game::ship::ship_internals::Components;
+use crate::game::stats::EconomyStats;
+use crate::game::utils::{DeltaTime, Seed, Tick, TotalTime, V2};
 use crate::game::wares::WareAmount;
 use crate::game::{
-    actions, building_site, commands, conf, factory, label, loader, locations, navigations, orbit,
-    save, scenery_random, sectors, shipyard, wares,
+    actions, building_site, commands, conf, discovery, extractables, factory, fleets, fuel,
+    habitat, label, loader, locations, maintenance, navigations, orbit, raiders, save,
+    scenery_random, sectors, sensors, ship, shipyard, station, stats, wares, wrecks,
 };
 use bevy_ecs::prelude::*;
 use bevy_ecs::system::{RunSystemOnce, SystemState};
@@ -76,13 +82,54 @@ impl Game {
         game.world.init_resource::<Events<GEvent>>();
         game.world.insert_resource(EntityPerSectorIndex::new());
         game.world.insert_resource(Tick::default());
+        game.world.insert_resource(EconomyStats::default());
+        game.world.insert_resource(wares::WareUnits::default());
+        game.world.insert_resource(FactionsVisibility::default());
+        game.world.insert_resource(Discoveries::default());
+
+        // before
+        game.scheduler
+            .add_systems(wares::system_cargo_ware_units.in_set(SystemSeq::Before));
+        game.scheduler.add_systems(
+            wares::system_cargo_release_expired_reservations.in_set(SystemSeq::Before),
+        );
+        game.scheduler.add_systems(
+            commands::command_trader_system::system_command_trade_release_reservations
+                .in_set(SystemSeq::Before),
+        );
+        game.scheduler
+            .add_systems(fleets::system_fleet_groups.in_set(SystemSeq::Before));
+        game.scheduler
+            .add_systems(raiders::system_sector_danger_decay.in_set(SystemSeq::Before));
+        game.scheduler
+            .add_systems(wrecks::system_wrecks_decay.in_set(SystemSeq::Before));
 
         // ai
         game.scheduler
             .add_systems(commands::command_mine_system::system_command_mine.in_set(SystemSeq::Ai));
+        game.scheduler
+            .add_systems(maintenance::system_maintenance_request.in_set(SystemSeq::Ai));
+        game.scheduler
+            .add_systems(fuel::system_refuel_request.in_set(SystemSeq::Ai));
         game.scheduler.add_systems(
             commands::command_trader_system::system_command_trade.in_set(SystemSeq::Ai),
         );
+        game.scheduler
+            .add_systems(commands::command_haul_system::system_command_haul.in_set(SystemSeq::Ai));
+        game.scheduler.add_systems(
+            commands::command_patrol_system::system_command_patrol.in_set(SystemSeq::Ai),
+        );
+        game.scheduler.add_systems(
+            commands::command_escort_system::system_command_escort.in_set(SystemSeq::Ai),
+        );
+        game.scheduler
+            .add_systems(commands::command_raid_system::system_command_raid.in_set(SystemSeq::Ai));
+        game.scheduler.add_systems(
+            commands::command_salvage_system::system_command_salvage.in_set(SystemSeq::Ai),
+        );
+        game.scheduler.add_systems(
+            commands::command_explore_system::system_command_explore.in_set(SystemSeq::Ai),
+        );
         // changes
         game.scheduler
             .add_systems(building_site::system_building_site.in_set(SystemSeq::Changes));
@@ -95,14 +142,28 @@ impl Game {
         );
         game.scheduler
             .add_systems(factory::system_factory.in_set(SystemSeq::Changes));
+        game.scheduler
+            .add_systems(habitat::system_habitat.in_set(SystemSeq::Changes));
         game.scheduler
             .add_systems(shipyard::system_shipyard.in_set(SystemSeq::Changes));
+        game.scheduler
+            .add_systems(maintenance::system_repair.in_set(SystemSeq::Changes));
+        game.scheduler
+            .add_systems(fuel::system_refuel.in_set(SystemSeq::Changes));
         game.scheduler
             .add_systems(orbit::system_compute_orbits.in_set(SystemSeq::Changes));
+        game.scheduler
+            .add_systems(extractables::system_extractables_respawn.in_set(SystemSeq::Changes));
+        game.scheduler
+            .add_systems(raiders::system_raiders_spawn.in_set(SystemSeq::Changes));
+        game.scheduler
+            .add_systems(ship::ship_combat_system::system_ship_combat.in_set(SystemSeq::Changes));
         game.scheduler
             .add_systems(actions::action_dock_system::system_dock.in_set(SystemSeq::Changes));
         game.scheduler
             .add_systems(actions::action_extract_system::system_extract.in_set(SystemSeq::Changes));
+        game.scheduler
+            .add_systems(actions::action_salvage_system::system_salvage.in_set(SystemSeq::Changes));
         game.scheduler
             .add_systems(actions::action_jump_system::system_jump.in_set(SystemSeq::Changes));
         game.scheduler
@@ -111,8 +172,12 @@ impl Game {
             actions::action_request_handler_system::system_action_request
                 .in_set(SystemSeq::Changes),
         );
-        game.scheduler
-            .add_systems(actions::action_undock_system::system_undock.in_set(SystemSeq::Changes));
+        game.scheduler.add_systems(
+            actions::action_undock_system::system_undock
+                .in_set(SystemSeq::Changes)
+                // release docking slots before queued ships are granted
+                .before(actions::action_dock_system::system_dock),
+        );
         game.scheduler
             .add_systems(actions::actions_system::system_actions.in_set(SystemSeq::Changes));
         // after
@@ -124,6 +189,12 @@ impl Game {
             .add_systems(sectors::system_update_sectors_index.in_set(SystemSeq::After));
         game.scheduler
             .add_systems(system_tick_new_objects.in_set(SystemSeq::After));
+        game.scheduler
+            .add_systems(stats::system_economy_stats.in_set(SystemSeq::After));
+        game.scheduler
+            .add_systems(sensors::system_visibility.in_set(SystemSeq::After));
+        game.scheduler
+            .add_systems(discovery::system_discovery.in_set(SystemSeq::After));
 
         game
     }
@@ -131,14 +202,15 @@ impl Game {
     pub fn new(params: NewGameParams) -> Game {
         log::info!("starting a new game");
 
-        let system_generator_conf = include_str!("../../../data/game.conf");
-        let cfg = conf::load_str(system_generator_conf).expect("fail to read config file");
+        let cfg = Self::load_conf();
 
         let mut game = Game::empty();
 
         game.world.run_commands(|mut commands| {
             loader::load_prefabs(&mut commands, &cfg.prefabs);
         });
+        game.world
+            .insert_resource(Components::from_list(&cfg.prefabs.ship_components));
 
         scenery_random::load_random(
             &mut game,
@@ -146,12 +218,17 @@ impl Game {
                 size: (params.galaxy_size.x as usize, params.galaxy_size.y as usize),
                 seed: params.seed,
                 fleets: params.extra_fleets,
-                universe_cfg: cfg.system_generator.unwrap(),
+                universe_cfg: cfg.system_generator.clone().unwrap(),
                 initial_condition: scenery_random::InitialCondition::Minimal,
-                params: cfg.params,
+                params: cfg.params.clone(),
             },
         );
 
+        game.world.insert_resource(Seed(params.seed));
+        game.insert_extractables_respawn(&cfg, params.seed);
+        game.insert_raiders_spawn(&cfg);
+        game.insert_wreck_params(&cfg);
+
         game
     }
 
@@ -160,9 +237,82 @@ impl Game {
         let mut game = Game::empty();
         save::load_world(&mut game.world, data);
         game.reindex_sectors();
+        let cfg = Self::load_conf();
+        game.world
+            .insert_resource(Components::from_list(&cfg.prefabs.ship_components));
+        let seed = game.world.resource::<Seed>().0;
+        game.insert_extractables_respawn(&cfg, seed);
+        game.insert_raiders_spawn(&cfg);
+        game.insert_wreck_params(&cfg);
         Ok(game)
     }
 
+    fn load_conf() -> conf::Conf {
+        let system_generator_conf = include_str!("../../../data/game.conf");
+        let mut cfg = conf::load_str(system_generator_conf).expect("fail to read config file");
+        let ship_components_conf = include_str!("../../../data/ship_components.conf");
+        conf::load_ship_components(&mut cfg, ship_components_conf)
+            .expect("fail to read ship components file");
+        cfg
+    }
+
+    fn insert_extractables_respawn(&mut self, cfg: &conf::Conf, seed: u64) {
+        let (Some(universe_cfg), Some(respawn_time)) = (
+            cfg.system_generator.clone(),
+            cfg.params.asteroid_respawn_time,
+        ) else {
+            return;
+        };
+
+        let total_time = *self.world.resource::<TotalTime>();
+        self.world
+            .insert_resource(extractables::ExtractablesRespawn::new(
+                universe_cfg,
+                DeltaTime(respawn_time),
+                cfg.params.asteroid_reserve,
+                total_time,
+                seed,
+            ));
+    }
+
+    fn insert_raiders_spawn(&mut self, cfg: &conf::Conf) {
+        let Some(raiders) = &cfg.params.raiders else {
+            return;
+        };
+
+        let new_obj = Loader::new_by_prefab_code(&mut self.world, raiders.prefab.clone())
+            .unwrap_or_else(|| panic!("raiders prefab {} not found", raiders.prefab));
+        let faction_id = self
+            .world
+            .run_system_once(Factions::list_factions_by_code)
+            .get(&raiders.faction)
+            .copied();
+        if faction_id.is_none() {
+            log::warn!("raiders faction {} not found", raiders.faction);
+        }
+
+        let total_time = *self.world.resource::<TotalTime>();
+        self.world.insert_resource(raiders::RaidersSpawn::new(
+            new_obj,
+            faction_id,
+            DeltaTime(raiders.spawn_time),
+            raiders.max_per_sector,
+            total_time,
+        ));
+    }
+
+    fn insert_wreck_params(&mut self, cfg: &conf::Conf) {
+        let Some(wrecks) = &cfg.params.wrecks else {
+            return;
+        };
+
+        self.world.insert_resource(wrecks::WreckParams {
+            cargo_ratio: wrecks.cargo_ratio,
+            materials_ratio: wrecks.materials_ratio,
+            decay_time: DeltaTime(wrecks.decay_time),
+        });
+    }
+
     pub fn tick(&mut self, delta_time: DeltaTime) {
         // update tick
         self.world.get_resource_mut::<Tick>().unwrap().increment();
@@ -245,11 +395,83 @@ impl Game {
             .collect()
     }
 
+    /// Objects at the sector seen by the faction, remembered objects are listed at their last
+    /// sighting position
+    pub fn list_at_sector_visible_by(
+        &self,
+        sector_id: SectorId,
+        faction_id: FactionId,
+        include_remembered: bool,
+    ) -> Vec<Entity> {
+        self.world
+            .resource::<FactionsVisibility>()
+            .get(faction_id)
+            .map(|i| i.list_at_sector(sector_id, include_remembered))
+            .unwrap_or_default()
+    }
+
+    pub fn get_visibility(&self, faction_id: FactionId, obj_id: ObjId) -> Visible {
+        self.world
+            .resource::<FactionsVisibility>()
+            .get_visible(faction_id, obj_id)
+    }
+
+    pub fn get_sighting(&self, faction_id: FactionId, obj_id: ObjId) -> Option<Sighting> {
+        self.world
+            .resource::<FactionsVisibility>()
+            .get(faction_id)
+            .and_then(|i| i.get_sighting(obj_id))
+            .cloned()
+    }
+
+    /// Sectors are explored by factions with discovery, other factions know every sector
+    pub fn is_sector_explored(&self, faction_id: FactionId, sector_id: SectorId) -> bool {
+        self.world
+            .resource::<Discoveries>()
+            .get(faction_id)
+            .map(|i| i.is_explored(sector_id))
+            .unwrap_or(true)
+    }
+
     pub fn resolve_space_position(&mut self, obj_id: ObjId) -> Option<LocationSpace> {
         self.world
             .run_system_once_with(obj_id, Locations::resolve_space_position_system)
     }
 
+    /// Remove the object from the game cleaning all references to it, a wreck with part of its
+    /// cargo and materials is left in its place. Return the wreck id, if any.
+    ///
+    /// Only fleets, stations and wrecks can be destroyed, sectors, jumps and factions are still
+    /// referenced by many objects.
+    pub fn destroy_obj(&mut self, obj_id: ObjId) -> Result<Option<ObjId>, &'static str> {
+        let entity = self.world.get_entity(obj_id).ok_or("obj not found")?;
+        if !entity.contains::<fleets::Fleet>()
+            && !entity.contains::<station::Station>()
+            && !entity.contains::<wrecks::Wreck>()
+        {
+            return Err("only fleets, stations and wrecks can be destroyed");
+        }
+
+        let wreck_id = wrecks::Wrecks::destroy(&mut self.world, obj_id, true)?;
+        self.world.run_system_once(update_entity_per_sector_index);
+        Ok(wreck_id)
+    }
+
+    /// Add the member into the leader group, escorting it at the formation offset
+    pub fn join_fleet_group(
+        &mut self,
+        leader_id: ObjId,
+        member_id: ObjId,
+        offset: V2,
+    ) -> Result<(), &'static str> {
+        fleets::Fleets::join_group(&mut self.world, leader_id, member_id, offset)
+    }
+
+    /// Remove the member from its group, return false if it was not in a group
+    pub fn leave_fleet_group(&mut self, member_id: ObjId) -> bool {
+        fleets::Fleets::leave_group(&mut self.world, member_id)
+    }
+
     pub fn save_to_string(&mut self) -> String {
         save::save_world(&mut self.world)
     }
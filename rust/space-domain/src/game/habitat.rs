@@ -0,0 +1,255 @@
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use space_galaxy::system_generator::Planet;
+use std::collections::HashMap;
+
+use crate::game::conf;
+use crate::game::locations::LocationSpace;
+use crate::game::order::{TradeOrders, TRADE_ORDER_ID_HABITAT};
+use crate::game::save::LoadingMapEntity;
+use crate::game::stats::{EconomyStats, StatKind};
+use crate::game::utils::{DeltaTime, TotalTime};
+use crate::game::wares::{Cargo, Volume, WareAmount};
+
+/// Time between each consumption of a habitat
+pub const CONSUMPTION_TIME: DeltaTime = DeltaTime(10.0);
+
+/// Amount of population that consumes the wares defined in the habitat consumption
+pub const POPULATION_UNIT: f32 = 100.0;
+
+/// Population ratio that grows each consumption when all wares were supplied
+pub const GROWTH_RATE: f32 = 0.02;
+
+/// Population ratio lost each consumption when no ware was supplied
+pub const DECLINE_RATE: f32 = 0.05;
+
+/// A population living in a station or planet that consumes wares to grow.
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct Habitat {
+    pub population: f32,
+    pub capacity: f32,
+    /// wares consumed by each population unit on every consumption
+    pub consumption: Vec<WareAmount>,
+    /// ratio of the wares supplied on the last consumption
+    pub supply: f32,
+    pub next_consumption: Option<TotalTime>,
+}
+
+impl Habitat {
+    pub fn new(population: f32, capacity: f32, consumption: Vec<WareAmount>) -> Self {
+        Habitat {
+            population: population.min(capacity),
+            capacity,
+            consumption,
+            supply: 1.0,
+            next_consumption: None,
+        }
+    }
+
+    pub fn update_trade_orders(&self, orders: &mut TradeOrders) {
+        for wa in &self.consumption {
+            orders.add_request(TRADE_ORDER_ID_HABITAT, wa.ware_id);
+        }
+    }
+
+    /// Wares required by the current population on the next consumption
+    pub fn get_demand(&self) -> Vec<WareAmount> {
+        self.consumption
+            .iter()
+            .map(|wa| {
+                let amount = (wa.amount as f32 * self.population / POPULATION_UNIT).ceil();
+                WareAmount::new(wa.ware_id, amount as Volume)
+            })
+            .filter(|wa| wa.amount > 0)
+            .collect()
+    }
+
+    /// Grow when all the demand was supplied, otherwise decline proportional to the missing wares
+    pub fn update_population(&mut self, supply: f32) {
+        self.supply = supply.clamp(0.0, 1.0);
+
+        if self.supply >= 1.0 {
+            let growth = (self.population * GROWTH_RATE).max(1.0);
+            self.population = (self.population + growth).min(self.capacity);
+        } else {
+            let decline = self.population * DECLINE_RATE * (1.0 - self.supply);
+            self.population = (self.population - decline).max(0.0);
+        }
+
+        // capacity can be lower than current population
+        self.population = self.population.min(self.capacity);
+    }
+}
+
+impl LoadingMapEntity for Habitat {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.consumption.map_entity(entity_map);
+    }
+}
+
+/// Population capacity of a planet given the colonies configuration. Kinds not listed in the
+/// configuration don't change the capacity.
+pub fn compute_planet_capacity(cfg: &conf::Colonies, planet: &Planet) -> f32 {
+    fn rate_of(rates: &[conf::CapacityRate], kind: &str) -> f32 {
+        rates
+            .iter()
+            .find(|i| i.kind == kind)
+            .map(|i| i.rate)
+            .unwrap_or(1.0)
+    }
+
+    let gravity_rate = match cfg.gravity_tolerance {
+        Some(tolerance) if tolerance > 0.0 => {
+            (1.0 - (planet.gravity - cfg.ideal_gravity).abs() / tolerance).clamp(0.0, 1.0)
+        }
+        _ => 1.0,
+    };
+
+    cfg.capacity
+        * rate_of(&cfg.biomes, &planet.biome)
+        * rate_of(&cfg.atmospheres, &planet.atmosphere)
+        * rate_of(&cfg.oceans, &planet.ocean)
+        * gravity_rate
+}
+
+pub fn system_habitat(
+    total_time: Res<TotalTime>,
+    mut query: Query<(Entity, &mut Habitat, &mut Cargo, Option<&LocationSpace>)>,
+    mut stats: Option<ResMut<EconomyStats>>,
+) {
+    log::trace!("running");
+
+    let total_time = *total_time;
+
+    for (obj_id, mut habitat, mut cargo, maybe_location) in &mut query {
+        match habitat.next_consumption {
+            Some(time) if !total_time.is_after(time) => continue,
+            Some(_) => {}
+            None => {
+                // first consumption happens after a full period
+                habitat.next_consumption = Some(total_time.add(CONSUMPTION_TIME));
+                continue;
+            }
+        }
+
+        let demand = habitat.get_demand();
+
+        let mut supply = 1.0f32;
+        for wa in &demand {
+            let amount = cargo.get_amount(wa.ware_id).min(wa.amount);
+            if amount > 0 {
+                cargo
+                    .remove(wa.ware_id, amount)
+                    .expect("fail to remove available ware from habitat cargo");
+            }
+
+            if let Some(stats) = stats.as_mut() {
+                let sector_id = maybe_location.map(|l| l.sector_id);
+                stats.record(StatKind::Consumed, obj_id, sector_id, wa.ware_id, amount);
+            }
+
+            supply = supply.min(amount as f32 / wa.amount as f32);
+        }
+
+        let previous = habitat.population;
+        habitat.update_population(supply);
+        habitat.next_consumption = Some(total_time.add(CONSUMPTION_TIME));
+
+        log::trace!(
+            "{:?} habitat consumed {:?} with supply {:?}, population from {:?} to {:?}",
+            obj_id,
+            demand,
+            supply,
+            previous,
+            habitat.population,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+
+    fn setup(world: &mut World, stock: Volume) -> (Entity, Entity) {
+        let ware_id = world.spawn_empty().id();
+
+        let mut cargo = Cargo::new(1000);
+        if stock > 0 {
+            cargo.add(ware_id, stock).unwrap();
+        }
+
+        let habitat = Habitat::new(200.0, 1000.0, vec![WareAmount::new(ware_id, 5)]);
+        let obj_id = world.spawn((habitat, cargo)).id();
+
+        world.insert_resource(TotalTime(0.0));
+        world.run_system_once(system_habitat);
+        world.insert_resource(TotalTime(CONSUMPTION_TIME.as_f32() as f64));
+        world.run_system_once(system_habitat);
+
+        (obj_id, ware_id)
+    }
+
+    #[test]
+    fn test_habitat_should_consume_and_grow_when_supplied() {
+        let mut world = World::new();
+        let (obj_id, ware_id) = setup(&mut world, 15);
+
+        let habitat = world.get::<Habitat>(obj_id).unwrap();
+        assert_eq!(1.0, habitat.supply);
+        assert_eq!(204.0, habitat.population);
+        assert_eq!(5, world.get::<Cargo>(obj_id).unwrap().get_amount(ware_id));
+    }
+
+    #[test]
+    fn test_habitat_should_decline_when_not_supplied() {
+        let mut world = World::new();
+        let (obj_id, ware_id) = setup(&mut world, 5);
+
+        let habitat = world.get::<Habitat>(obj_id).unwrap();
+        assert_eq!(0.5, habitat.supply);
+        assert_eq!(195.0, habitat.population);
+        assert_eq!(0, world.get::<Cargo>(obj_id).unwrap().get_amount(ware_id));
+    }
+
+    #[test]
+    fn test_habitat_should_not_grow_above_capacity() {
+        let mut habitat = Habitat::new(2000.0, 100.0, vec![]);
+        assert_eq!(100.0, habitat.population);
+        habitat.update_population(1.0);
+        assert_eq!(100.0, habitat.population);
+    }
+
+    #[test]
+    fn test_compute_planet_capacity() {
+        let cfg = conf::Colonies {
+            capacity: 1000.0,
+            storage: 100.0,
+            population: 0.5,
+            consumption: vec![],
+            biomes: vec![conf::CapacityRate {
+                kind: "barrent".to_string(),
+                rate: 0.5,
+            }],
+            atmospheres: vec![conf::CapacityRate {
+                kind: "toxic".to_string(),
+                rate: 0.0,
+            }],
+            oceans: vec![],
+            ideal_gravity: 1.0,
+            gravity_tolerance: Some(2.0),
+        };
+
+        let mut planet = Planet {
+            atmosphere: "none".to_string(),
+            gravity: 2.0,
+            biome: "barrent".to_string(),
+            ocean: "none".to_string(),
+            resources: vec![],
+        };
+        assert_eq!(250.0, compute_planet_capacity(&cfg, &planet));
+
+        planet.atmosphere = "toxic".to_string();
+        assert_eq!(0.0, compute_planet_capacity(&cfg, &planet));
+    }
+}
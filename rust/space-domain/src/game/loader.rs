@@ -15,25 +15,34 @@ use crate::game::astrobody::{AstroBody, AstroBodyKind};
 use crate::game::building_site::BuildingSite;
 use crate::game::code::{Code, HasCode};
 use crate::game::commands::{Command, TradeState};
-use crate::game::dock::HasDocking;
+use crate::game::dock::{DockSlots, HasDocking, SizeClass};
 use crate::game::events::{CommandSendEvent, EventKind, GEvent};
 use crate::game::extractables::Extractable;
-use crate::game::factory::{Factory, Receipt};
+use crate::game::factions::{Faction, FactionId, OwnershipFilter};
+use crate::game::factory::{Factory, RateSource, Receipt, ReceiptPolicy};
 use crate::game::fleets::Fleet;
+use crate::game::fuel::FuelTank;
+use crate::game::habitat::Habitat;
 use crate::game::label::Label;
-use crate::game::locations::{LocationDocked, LocationOrbit, LocationSpace, Moveable};
+use crate::game::locations::{LocationDocked, LocationOrbit, LocationSpace, Moveable, SpeedByMass};
+use crate::game::maintenance::{RepairDock, Wear};
 use crate::game::navigations::{NavRequest, Navigation, NavigationPlan};
 use crate::game::new_obj::NewObj;
 use crate::game::objects::ObjId;
 use crate::game::orbit::Orbits;
-use crate::game::order::{TradeOrders, TRADE_ORDER_ID_BUILDING_SITE, TRADE_ORDER_ID_FACTORY};
+use crate::game::order::{TradeOrders, TRADE_ORDER_ID_BUILDING_SITE};
 use crate::game::prefab::{Prefab, PrefabId};
+use crate::game::prices::{Credits, WarePrice, DEFAULT_SHIP_CREDITS, DEFAULT_STATION_CREDITS};
 use crate::game::sectors::{Jump, JumpId, Sector, SectorId};
+use crate::game::ship::ship_internals::{
+    Components, ShipInstance, ShipInstanceId, ShipSpec, TeamId,
+};
 use crate::game::shipyard::{ProductionOrder, Shipyard};
 use crate::game::station::Station;
 use crate::game::utils::{DeltaTime, Speed, TotalTime, V2};
 use crate::game::wares::{
-    Cargo, CargoDistributionDirty, Volume, Ware, WareAmount, WareId, WaresByCode,
+    Cargo, CargoDistributionDirty, RestrictedWares, StorageAllocation, Volume, Ware, WareAmount,
+    WareCategory, WareId, WareKind, WareSelector, WareUnit, WaresByCode,
 };
 use crate::game::{bevy_utils, conf, prefab};
 
@@ -55,6 +64,7 @@ impl Loader {
             .extractable(Extractable {
                 ware_id,
                 accessibility: 10.0,
+                reserve: None,
             });
         Loader::add_object(commands, &asteroid)
     }
@@ -94,12 +104,28 @@ impl Loader {
         Loader::add_object(commands, &Self::new_factory(sector_id, pos, receipt))
     }
 
+    pub fn add_faction(
+        commands: &mut Commands,
+        code: &str,
+        label: &str,
+        faction: Faction,
+    ) -> FactionId {
+        Loader::add_object(
+            commands,
+            &NewObj::new()
+                .with_faction(faction)
+                .with_code(code)
+                .with_label(label),
+        )
+    }
+
     pub fn new_station() -> NewObj {
         NewObj::new()
             .with_label("station".to_string())
             .with_cargo_size(100)
             .with_station()
             .with_docking()
+            .with_credits(DEFAULT_STATION_CREDITS)
     }
 
     pub fn new_factory(sector_id: SectorId, pos: V2, receipt: Receipt) -> NewObj {
@@ -156,6 +182,7 @@ impl Loader {
             .can_dock()
             .with_label(label)
             .with_fleet()
+            .with_credits(DEFAULT_SHIP_CREDITS)
     }
 
     // pub fn new_ship2(docked_at: ObjId, speed: f32, label: String) -> NewObj {
@@ -296,7 +323,11 @@ impl Loader {
         }
 
         if new_obj.docking {
-            builder.insert(HasDocking::default());
+            builder.insert(HasDocking::new(new_obj.docking_slots.clone()));
+        }
+
+        if let Some(size_class) = new_obj.size_class {
+            builder.insert(size_class);
         }
 
         if let Some(orbit) = new_obj.location_orbit.as_ref() {
@@ -327,6 +358,7 @@ impl Loader {
 
         if new_obj.fleet {
             builder.insert(Fleet {});
+            builder.insert(Wear::default());
         }
 
         if let Some(sector_pos) = &new_obj.sector {
@@ -356,12 +388,29 @@ impl Loader {
 
         if let Some(factory) = &new_obj.factory {
             builder.insert(factory.clone());
-            for wa in &factory.production.input {
-                orders.add_request(TRADE_ORDER_ID_FACTORY, wa.ware_id);
-            }
-            for wa in &factory.production.output {
-                orders.add_provider(TRADE_ORDER_ID_FACTORY, wa.ware_id);
-            }
+            factory.update_trade_orders(&mut orders);
+        }
+
+        if let Some(habitat) = &new_obj.habitat {
+            builder.insert(habitat.clone());
+            habitat.update_trade_orders(&mut orders);
+        }
+
+        if let Some(repair_dock) = &new_obj.repair_dock {
+            builder.insert(repair_dock.clone());
+            repair_dock.update_trade_orders(&mut orders);
+        }
+
+        if let Some(fuel_tank) = &new_obj.fuel_tank {
+            builder.insert(fuel_tank.clone());
+        }
+
+        if let Some(sensor) = new_obj.sensor {
+            builder.insert(sensor);
+        }
+
+        if let Some(signature) = new_obj.signature {
+            builder.insert(signature);
         }
 
         if let Some(_) = new_obj.star {
@@ -380,6 +429,46 @@ impl Loader {
             builder.insert(Ware {});
         }
 
+        if let Some(price) = new_obj.ware_price {
+            builder.insert(WarePrice { base: price });
+        }
+
+        if let Some(ware_unit) = new_obj.ware_unit {
+            builder.insert(ware_unit);
+        }
+
+        if let Some(kind) = &new_obj.ware_kind {
+            builder.insert(kind.clone());
+        }
+
+        if let Some(restricted) = &new_obj.restricted_wares {
+            builder.insert(restricted.clone());
+        }
+
+        if let Some(faction) = &new_obj.faction {
+            builder.insert(faction.clone());
+        }
+
+        if let Some(owner) = new_obj.owner {
+            builder.insert(owner);
+        }
+
+        if let Some(ship_instance) = &new_obj.ship_instance {
+            builder.insert(ship_instance.clone());
+        }
+
+        if let Some(wreck) = &new_obj.wreck {
+            builder.insert(wreck.clone());
+        }
+
+        if let Some(reference_mass) = new_obj.speed_by_mass {
+            builder.insert(SpeedByMass { reference_mass });
+        }
+
+        if let Some(credits) = new_obj.credits {
+            builder.insert(Credits::new(credits));
+        }
+
         if let Some(building_site) = &new_obj.building_site {
             builder.insert(building_site.clone());
             for ware_id in &building_site.input {
@@ -727,7 +816,7 @@ fn into_wareamount(wares_by_code: &WaresByCode, code: &str, amount: u32) -> Ware
     }
 }
 
-fn into_wareamount_list(
+pub fn into_wareamount_list(
     wares_by_code: &WaresByCode,
     list: &[conf::ReceiptWare],
 ) -> Vec<WareAmount> {
@@ -736,15 +825,123 @@ fn into_wareamount_list(
         .collect()
 }
 
+/// Create the ship spec of a design, panics if any component is unknown or the ship is invalid
+pub fn into_ship_spec(components: &Components, design: &conf::ShipDesign) -> ShipSpec {
+    let ship_components = design
+        .components
+        .iter()
+        .map(|i| {
+            let component = components
+                .find_by_name(&i.component)
+                .unwrap_or_else(|| panic!("ship component {} not found", i.component));
+            (component.id, i.amount)
+        })
+        .collect();
+
+    let spec = ShipSpec::new(components, ship_components, design.armor);
+    if let Err(errors) = spec.is_valid() {
+        panic!("invalid ship design {}: {:?}", design.code, errors);
+    }
+    spec
+}
+
+pub fn into_ware_selectors(wares_by_code: &WaresByCode, codes: &[Code]) -> Vec<WareSelector> {
+    codes
+        .iter()
+        .map(|code| {
+            WareSelector::from_code(wares_by_code, code)
+                .unwrap_or_else(|| panic!("invalid ware selector {}", code))
+        })
+        .collect()
+}
+
+pub fn into_dock_slots(slots: &[conf::DockSlots]) -> Vec<DockSlots> {
+    slots
+        .iter()
+        .map(|slot| {
+            let size = SizeClass::from_code(&slot.size)
+                .unwrap_or_else(|| panic!("invalid docking size {}", slot.size));
+            DockSlots::new(size, slot.capacity)
+        })
+        .collect()
+}
+
 pub fn load_prefabs(commands: &mut Commands, prefabs: &conf::Prefabs) {
     // generate wares and collect index
     let mut wares_by_code: HashMap<Code, WareId> = Default::default();
     for ware in &prefabs.wares {
-        let ware_id = Loader::add_ware(commands, ware.code.clone(), ware.label.clone());
+        let mut new_obj = NewObj::new()
+            .with_ware()
+            .with_code(ware.code.clone())
+            .with_label(ware.label.clone());
+        if let Some(price) = ware.price {
+            new_obj = new_obj.with_ware_price(price);
+        }
+        if ware.volume.is_some() || ware.mass.is_some() {
+            let default = WareUnit::default();
+            new_obj = new_obj.with_ware_unit(
+                ware.volume.unwrap_or(default.volume),
+                ware.mass.unwrap_or(default.mass),
+            );
+        }
+        if ware.category.is_some() || !ware.tags.is_empty() {
+            let category = ware.category.as_ref().map(|code| {
+                WareCategory::from_code(code)
+                    .unwrap_or_else(|| panic!("invalid ware category {}", code))
+            });
+            new_obj = new_obj.with_ware_kind(WareKind::new(category, ware.tags.clone()));
+        }
+        let ware_id = Loader::add_object(commands, &new_obj);
         wares_by_code.insert(ware.code.clone(), ware_id);
     }
     let wares_by_code = WaresByCode::from(wares_by_code);
 
+    // ships tanks are filled with the first ware of fuel category
+    let fuel_ware_id = prefabs
+        .wares
+        .iter()
+        .find(|ware| ware.category.as_deref() == Some("fuel"))
+        .and_then(|ware| wares_by_code.get(ware.code.as_str()));
+
+    // generate factions
+    let mut factions_by_code: HashMap<&str, (FactionId, Faction)> = HashMap::new();
+    for faction in &prefabs.factions {
+        let mut new_faction = Faction::default();
+        if let Some(code) = &faction.ownership {
+            new_faction.ownership = OwnershipFilter::from_code(code)
+                .unwrap_or_else(|| panic!("invalid faction ownership {}", code));
+        }
+        new_faction.discovery = faction.discovery;
+        let faction_id =
+            Loader::add_faction(commands, &faction.code, &faction.label, new_faction.clone());
+        factions_by_code.insert(faction.code.as_str(), (faction_id, new_faction));
+        if !faction.restricted_wares.is_empty() {
+            let selectors = into_ware_selectors(&wares_by_code, &faction.restricted_wares);
+            commands
+                .entity(faction_id)
+                .insert(RestrictedWares::new(selectors));
+        }
+    }
+
+    // hostility refers to other factions, resolved once all of them exist
+    for faction in prefabs.factions.iter().filter(|i| !i.hostile.is_empty()) {
+        let (faction_id, new_faction) = &factions_by_code[faction.code.as_str()];
+        let hostile = faction
+            .hostile
+            .iter()
+            .map(|code| {
+                factions_by_code
+                    .get(code.as_str())
+                    .map(|(id, _)| *id)
+                    .unwrap_or_else(|| panic!("hostile faction {} not found", code))
+            })
+            .collect();
+        commands.entity(*faction_id).insert(Faction {
+            hostile,
+            ..new_faction.clone()
+        });
+    }
+
     // generate receipts
     let mut receipts: HashMap<String, Receipt> = Default::default();
 
@@ -760,18 +957,85 @@ pub fn load_prefabs(commands: &mut Commands, prefabs: &conf::Prefabs) {
         );
     }
 
+    // load ship components and designs
+    let components = Components::from_list(&prefabs.ship_components);
+    let designs: HashMap<&str, ShipSpec> = prefabs
+        .ship_designs
+        .iter()
+        .map(|design| (design.code.as_str(), into_ship_spec(&components, design)))
+        .collect();
+
     // load fleets prefabs
     for fleet in &prefabs.fleets {
         let mut obj = NewObj::new()
-            .with_cargo_size(fleet.storage)
-            .with_speed(Speed(fleet.speed))
-            .with_label(fleet.label.clone());
+            .with_label(fleet.label.clone())
+            .with_credits(DEFAULT_SHIP_CREDITS);
+
+        match &fleet.design {
+            Some(code) => {
+                let spec = designs
+                    .get(code.as_str())
+                    .unwrap_or_else(|| panic!("fleet {} design {} not found", fleet.code, code));
+
+                let (work, cost) = spec.production_cost(&components);
+                let cost = cost
+                    .iter()
+                    .map(|i| into_wareamount(&wares_by_code, i.ware.as_str(), i.amount))
+                    .collect();
+
+                obj = obj
+                    .with_cargo_size(spec.stats.cargo)
+                    .with_speed(spec.stats.speed)
+                    .with_production_cost(work, cost)
+                    .with_ship_instance(ShipInstance::new(
+                        &components,
+                        ShipInstanceId(0),
+                        spec.clone(),
+                        TeamId(0),
+                    ));
+
+                if let Some(ware_id) = fuel_ware_id.filter(|_| spec.stats.fuel_hold > 0.0) {
+                    obj = obj.with_fuel_tank(FuelTank::new(
+                        ware_id,
+                        spec.stats.fuel_hold,
+                        spec.stats.fuel_consume,
+                    ));
+                }
+            }
+            None => {
+                let (Some(speed), Some(storage)) = (fleet.speed, fleet.storage) else {
+                    panic!(
+                        "fleet {} must define a design or speed and storage",
+                        fleet.code
+                    );
+                };
+                obj = obj.with_cargo_size(storage).with_speed(Speed(speed));
+
+                if let Some(prod_cost) = fleet.production_cost.as_ref() {
+                    obj = obj.with_production_cost(
+                        prod_cost.work,
+                        into_wareamount_list(&wares_by_code, &prod_cost.cost),
+                    );
+                }
+            }
+        }
 
-        if let Some(prod_cost) = fleet.production_cost.as_ref() {
-            obj = obj.with_production_cost(
-                prod_cost.work,
-                into_wareamount_list(&wares_by_code, &prod_cost.cost),
-            );
+        if let Some(reference_mass) = fleet.speed_reference_mass {
+            obj = obj.with_speed_by_mass(reference_mass);
+        }
+
+        if let Some(code) = &fleet.size {
+            let size_class =
+                SizeClass::from_code(code).unwrap_or_else(|| panic!("invalid fleet size {}", code));
+            obj = obj.with_size_class(size_class);
+        }
+
+        if let Some(sensor) = &fleet.sensor {
+            obj = obj.with_sensor(sensor.range, sensor.strength);
+        }
+
+        if let Some(signature) = fleet.signature {
+            obj = obj.with_signature(signature);
         }
 
         Loader::add_prefab(commands, &fleet.code, &fleet.label, obj, true, false);
@@ -779,11 +1043,32 @@ pub fn load_prefabs(commands: &mut Commands, prefabs: &conf::Prefabs) {
 
     // create stations prefabs
     for station in &prefabs.stations {
+        let mut cargo = Cargo::new(station.storage as Volume);
+        if let Some(allocation) = &station.storage_allocation {
+            let values = allocation
+                .wares
+                .iter()
+                .map(|share| {
+                    let ware_id = wares_by_code
+                        .get(share.ware.as_str())
+                        .unwrap_or_else(|| panic!("ware {} not found", share.ware));
+                    (ware_id, share.value)
+                })
+                .collect();
+            let allocation = StorageAllocation::from_code(&allocation.policy, values)
+                .unwrap_or_else(|| panic!("invalid storage allocation {}", allocation.policy));
+            cargo.set_allocation(allocation);
+        }
+        if !station.whitelist.is_empty() {
+            cargo.set_whitelist_selectors(into_ware_selectors(&wares_by_code, &station.whitelist));
+        }
+
         let mut obj = NewObj::new()
             .with_label(station.label.clone())
             .with_station()
-            .with_cargo_size(station.storage as u32)
-            .with_docking();
+            .with_cargo(cargo)
+            .with_docking_slots(into_dock_slots(&station.docking))
+            .with_credits(DEFAULT_STATION_CREDITS);
 
         if let Some(data) = &station.shipyard {
             let mut shipyard = Shipyard::new();
@@ -792,15 +1077,49 @@ pub fn load_prefabs(commands: &mut Commands, prefabs: &conf::Prefabs) {
         }
 
         if let Some(factory) = &station.factory {
-            let receipt = receipts
-                .get(factory.receipt.as_str())
-                .unwrap_or_else(|| panic!("receipt {} not found", factory.receipt))
-                .clone();
-
-            obj = obj.with_factory(Factory {
-                production: receipt,
-                production_time: None,
+            let mut station_receipts = factory.receipts.iter().map(|code| {
+                receipts
+                    .get(code.as_str())
+                    .unwrap_or_else(|| panic!("receipt {} not found", code))
+                    .clone()
             });
+
+            let first = station_receipts
+                .next()
+                .unwrap_or_else(|| panic!("station {} factory has no receipts", station.code));
+            let mut new_factory = Factory::new(first);
+            for receipt in station_receipts {
+                new_factory = new_factory.with_receipt(receipt);
+            }
+            if let Some(code) = &factory.policy {
+                let policy = ReceiptPolicy::from_code(code, factory.receipts.len())
+                    .unwrap_or_else(|| panic!("invalid factory policy {}", code));
+                new_factory = new_factory.with_policy(policy);
+            }
+            if let Some(lines) = factory.lines {
+                new_factory = new_factory.with_lines(lines);
+            }
+            if let Some(upgrade) = factory.upgrade {
+                new_factory.set_rate_modifier(RateSource::Upgrade, upgrade);
+            }
+
+            obj = obj.with_factory(new_factory);
+        }
+
+        if let Some(habitat) = &station.habitat {
+            obj = obj.with_habitat(Habitat::new(
+                habitat.population,
+                habitat.capacity,
+                into_wareamount_list(&wares_by_code, &habitat.consumption),
+            ));
+        }
+
+        if let Some(repair) = &station.repair {
+            obj = obj.with_repair_dock(RepairDock::new(
+                repair.production,
+                repair.work,
+                into_wareamount_list(&wares_by_code, &repair.cost),
+            ));
         }
 
         if let Some(prod_cost) = station.production_cost.as_ref() {
@@ -810,6 +1129,16 @@ pub fn load_prefabs(commands: &mut Commands, prefabs: &conf::Prefabs) {
             );
         }
 
+        if let Some(sensor) = &station.sensor {
+            obj = obj.with_sensor(sensor.range, sensor.strength);
+        }
+
+        if let Some(signature) = station.signature {
+            obj = obj.with_signature(signature);
+        }
+
         Loader::add_prefab(commands, &station.code, &station.label, obj, false, true);
     }
+
+    commands.insert_resource(components);
 }
@@ -7,9 +7,13 @@ use super::objects::*;
 use super::sectors::*;
 use crate::game::utils::*;
 
+use crate::game::discovery::FactionDiscovery;
 use crate::game::dock::HasDocking;
 use crate::game::extractables::Extractable;
+use crate::game::fuel::FuelTank;
+use crate::game::maintenance::Wear;
 use crate::game::save::LoadingMapEntity;
+use crate::game::wares::{Cargo, Mass};
 
 #[derive(Debug, Clone, Component, Serialize, Deserialize)]
 pub struct LocationSpace {
@@ -67,6 +71,59 @@ pub struct Moveable {
     pub speed: Speed,
 }
 
+impl Moveable {
+    /// Speed reduced by the cargo mass, wear and lack of fuel
+    pub fn effective_speed(
+        &self,
+        speed_by_mass: Option<&SpeedByMass>,
+        cargo: Option<&Cargo>,
+        wear: Option<&Wear>,
+        tank: Option<&FuelTank>,
+    ) -> Speed {
+        let mut speed = match (speed_by_mass, cargo) {
+            (Some(speed_by_mass), Some(cargo)) => speed_by_mass.apply(self.speed, cargo.get_mass()),
+            _ => self.speed,
+        };
+        if let Some(wear) = wear {
+            speed = wear.apply(speed);
+        }
+        if let Some(tank) = tank {
+            speed = tank.apply(speed);
+        }
+        speed
+    }
+}
+
+/// Components used to compute the effective speed of a moveable object
+pub type QueryEffectiveSpeed<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Moveable,
+        Option<&'static SpeedByMass>,
+        Option<&'static Cargo>,
+        Option<&'static Wear>,
+        Option<&'static FuelTank>,
+    ),
+>;
+
+/// Optional scale of `Moveable` speed by the cargo mass. A cargo with the reference mass moves
+/// at half speed.
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct SpeedByMass {
+    pub reference_mass: Mass,
+}
+
+impl SpeedByMass {
+    pub fn apply(&self, speed: Speed, mass: Mass) -> Speed {
+        if self.reference_mass <= 0.0 {
+            return speed;
+        }
+
+        Speed(speed.0 / (1.0 + mass / self.reference_mass))
+    }
+}
+
 pub trait SectorDistanceIndex {
     fn distance(&self, a: SectorId, b: SectorId) -> u32;
 }
@@ -121,13 +178,16 @@ impl EntityPerSectorIndex {
     }
 
     // TODO: return properly distance, not only 0 or 1
-    /// returns the sector_id, distance, object_id
+    /// returns the sector_id, distance, object_id. When a discovery is provided only explored
+    /// sectors are searched
     pub fn search_nearest_extractable<'a>(
         &'a self,
         from_sector_id: SectorId,
+        discovery: Option<&'a FactionDiscovery>,
     ) -> impl Iterator<Item = (SectorId, u32, ObjId)> + 'a {
         self.index_extractables
             .iter()
+            .filter(move |(sector_id, _)| discovery.is_none_or(|d| d.is_explored(**sector_id)))
             .flat_map(move |(&sector_id, list)| {
                 // TODO: remove the collect
                 list.iter()
@@ -141,12 +201,15 @@ impl EntityPerSectorIndex {
     }
 
     // TODO: should be a iterator from nearest to far
+    /// When a discovery is provided only explored sectors are searched
     pub fn search_nearest_stations<'a>(
         &'a self,
         from_sector_id: SectorId,
+        discovery: Option<&'a FactionDiscovery>,
     ) -> impl Iterator<Item = (SectorId, u32, ObjId)> + 'a {
         self.index_stations
             .iter()
+            .filter(move |(sector_id, _)| discovery.is_none_or(|d| d.is_explored(**sector_id)))
             .flat_map(move |(&sector_id, list)| {
                 list.iter()
                     .map(|id| {
@@ -238,7 +301,7 @@ pub fn update_entity_per_sector_index(
         // log::trace!("indexing {:?} at {:?}", entity, sector_id);
         index.add(sector_id, obj_id);
 
-        if maybe_extratable.is_some() {
+        if maybe_extratable.is_some_and(|extractable| !extractable.is_depleted()) {
             // log::trace!("indexing extractable {:?} at {:?}", entity, sector_id);
             index.add_extractable(sector_id, obj_id);
         }
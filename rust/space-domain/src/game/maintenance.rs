@@ -0,0 +1,496 @@
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::actions::{ActionActive, ActionRequest};
+use crate::game::commands::{Command, QUEUE_TIME_WEIGHT};
+use crate::game::discovery::Discoveries;
+use crate::game::dock::{Docking, QueryDocking};
+use crate::game::factions::{Ownership, QueryOwnership};
+use crate::game::locations::{EntityPerSectorIndex, LocationDocked, LocationSpace, Locations};
+use crate::game::navigations::{NavRequest, NavRequestFailed, Navigation};
+use crate::game::objects::ObjId;
+use crate::game::order::{TradeOrders, TRADE_ORDER_ID_REPAIR};
+use crate::game::save::LoadingMapEntity;
+use crate::game::stats::{EconomyStats, StatKind};
+use crate::game::utils::{DeltaTime, Speed};
+use crate::game::wares::{Cargo, WareAmount};
+use crate::game::work::WorkUnit;
+
+/// Wear gained for each unit of distance moved
+pub const WEAR_PER_DISTANCE: f32 = 0.0005;
+
+/// Wear gained on each jump
+pub const WEAR_PER_JUMP: f32 = 0.01;
+
+/// Ratio of speed and extraction rate lost when fully worn
+pub const WEAR_MAX_PENALTY: f32 = 0.5;
+
+/// Wear from which ships with a command are sent to maintenance
+pub const WEAR_MAINTENANCE_THRESHOLD: f32 = 0.5;
+
+/// Seconds a docked ship waits for the repair wares before giving up of the maintenance
+pub const MAINTENANCE_MAX_WAIT: f32 = 60.0;
+
+/// Degradation of a ship, from 0.0 when new to 1.0 when fully worn
+#[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
+pub struct Wear {
+    pub value: f32,
+}
+
+impl Wear {
+    pub fn add(&mut self, amount: f32) {
+        self.value = (self.value + amount).clamp(0.0, 1.0);
+    }
+
+    /// Multiplier applied to speed and extraction rate
+    pub fn efficiency(&self) -> f32 {
+        1.0 - self.value * WEAR_MAX_PENALTY
+    }
+
+    pub fn apply(&self, speed: Speed) -> Speed {
+        Speed(speed.as_f32() * self.efficiency())
+    }
+
+    pub fn require_maintenance(&self) -> bool {
+        self.value >= WEAR_MAINTENANCE_THRESHOLD
+    }
+}
+
+/// Station facility that repairs docked ships, each repair consumes the cost wares from the
+/// station cargo and the work is done at production per second.
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct RepairDock {
+    pub production: WorkUnit,
+    pub work: WorkUnit,
+    pub cost: Vec<WareAmount>,
+}
+
+impl RepairDock {
+    pub fn new(production: WorkUnit, work: WorkUnit, cost: Vec<WareAmount>) -> Self {
+        RepairDock {
+            production,
+            work,
+            cost,
+        }
+    }
+
+    pub fn update_trade_orders(&self, orders: &mut TradeOrders) {
+        for wa in &self.cost {
+            orders.add_request(TRADE_ORDER_ID_REPAIR, wa.ware_id);
+        }
+    }
+
+    /// Check if the station cargo has the wares for a repair
+    pub fn can_repair(&self, cargo: &Cargo) -> bool {
+        self.cost
+            .iter()
+            .all(|wa| cargo.get_amount(wa.ware_id) >= wa.amount)
+    }
+}
+
+impl LoadingMapEntity for RepairDock {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.cost.map_entity(entity_map);
+    }
+}
+
+/// Ship going to or being repaired at a repair dock. Its command is suspended until the repair
+/// is complete.
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct Maintenance {
+    pub target_id: ObjId,
+    pub command: Option<Command>,
+    /// work missing to complete the repair, None until the repair wares are consumed
+    pub pending_work: Option<WorkUnit>,
+    /// seconds docked waiting for the repair wares
+    #[serde(default)]
+    pub wait_time: f32,
+}
+
+impl LoadingMapEntity for Maintenance {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.target_id.map_entity(entity_map);
+        self.command.map_entity(entity_map);
+    }
+}
+
+impl Maintenance {
+    /// Cancel the maintenance restoring the suspended command
+    pub fn complete(commands: &mut Commands, obj_id: ObjId, maintenance: &Maintenance) {
+        let mut entity = commands.entity(obj_id);
+        entity.remove::<Maintenance>();
+        if let Some(command) = maintenance.command.clone() {
+            entity.insert(command);
+        }
+    }
+}
+
+/// Idle ships with a command and worn above the threshold are sent to the nearest repair dock
+/// with the wares for the repair
+#[allow(clippy::too_many_arguments)]
+pub fn system_maintenance_request(
+    mut commands: Commands,
+    query: Query<
+        (Entity, &Wear, &Command),
+        (
+            Without<Maintenance>,
+            Without<Navigation>,
+            Without<NavRequest>,
+            Without<ActionActive>,
+            Without<ActionRequest>,
+        ),
+    >,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+    query_repair_docks: Query<(&RepairDock, &Cargo)>,
+    query_ownership: QueryOwnership,
+    query_docking: QueryDocking,
+    sector_index: Res<EntityPerSectorIndex>,
+    discoveries: Option<Res<Discoveries>>,
+) {
+    log::trace!("running");
+
+    for (obj_id, wear, command) in &query {
+        if !wear.require_maintenance() {
+            continue;
+        }
+
+        let Some(location) = Locations::resolve_space_position(&query_locations, obj_id) else {
+            continue;
+        };
+
+        let ownership = Ownership::of(&query_ownership, obj_id);
+        let candidates = sector_index
+            .search_nearest_stations(
+                location.sector_id,
+                Discoveries::of(discoveries.as_deref(), ownership.faction_id),
+            )
+            .flat_map(|(_, distance, candidate_id)| {
+                let (repair_dock, cargo) = query_repair_docks.get(candidate_id).ok()?;
+                if !repair_dock.can_repair(cargo)
+                    || !ownership.accept(&query_ownership, candidate_id)
+                {
+                    return None;
+                }
+                let queue_time =
+                    Docking::expected_queue_time(&query_docking, obj_id, candidate_id)?;
+                Some((
+                    distance as f32 + QUEUE_TIME_WEIGHT * queue_time.as_f32(),
+                    candidate_id,
+                ))
+            });
+
+        let Some(target_id) = crate::game::utils::lower(candidates) else {
+            log::trace!(
+                "{:?} require maintenance but no repair dock was found",
+                obj_id
+            );
+            continue;
+        };
+
+        log::debug!(
+            "{:?} with wear {:?} going to maintenance at {:?}",
+            obj_id,
+            wear.value,
+            target_id
+        );
+        commands
+            .entity(obj_id)
+            .remove::<Command>()
+            .insert(Maintenance {
+                target_id,
+                command: Some(command.clone()),
+                pending_work: None,
+                wait_time: 0.0,
+            })
+            .insert(NavRequest::MoveAndDockAt { target_id });
+    }
+}
+
+/// Repair ships docked at their repair dock, consuming the repair wares from the station and
+/// progressing the work like shipyard production.
+///
+/// The maintenance is canceled when the repair dock can not be reached or the wares are not
+/// delivered in MAINTENANCE_MAX_WAIT.
+pub fn system_repair(
+    mut commands: Commands,
+    delta_time: Res<DeltaTime>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Maintenance,
+            &mut Wear,
+            Option<&NavRequestFailed>,
+        ),
+        (Without<Navigation>, Without<NavRequest>),
+    >,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+    mut query_repair_docks: Query<(&RepairDock, &mut Cargo)>,
+    mut stats: Option<ResMut<EconomyStats>>,
+) {
+    log::trace!("running");
+
+    let delta_time = *delta_time;
+
+    for (obj_id, mut maintenance, mut wear, maybe_failed) in &mut query {
+        let target_id = maintenance.target_id;
+        let Ok((repair_dock, mut cargo)) = query_repair_docks.get_mut(target_id) else {
+            log::warn!(
+                "{:?} maintenance target {:?} is not a repair dock, canceling",
+                obj_id,
+                target_id
+            );
+            Maintenance::complete(&mut commands, obj_id, &maintenance);
+            continue;
+        };
+
+        if !Locations::is_docked_at(&query_locations, obj_id, target_id) {
+            if maybe_failed.is_some() {
+                log::warn!(
+                    "{:?} can not reach repair dock {:?}, canceling",
+                    obj_id,
+                    target_id
+                );
+                commands.entity(obj_id).remove::<NavRequestFailed>();
+                Maintenance::complete(&mut commands, obj_id, &maintenance);
+                continue;
+            }
+
+            log::debug!(
+                "{:?} not docked at repair dock {:?}, moving",
+                obj_id,
+                target_id
+            );
+            commands
+                .entity(obj_id)
+                .insert(NavRequest::MoveAndDockAt { target_id });
+            continue;
+        }
+
+        match maintenance.pending_work {
+            None => {
+                if cargo.remove_all_or_none(&repair_dock.cost).is_err() {
+                    maintenance.wait_time += delta_time.as_f32();
+                    if maintenance.wait_time > MAINTENANCE_MAX_WAIT {
+                        log::debug!(
+                            "{:?} repair wares not available at {:?}, canceling",
+                            obj_id,
+                            target_id
+                        );
+                        Maintenance::complete(&mut commands, obj_id, &maintenance);
+                    } else {
+                        log::trace!("{:?} waiting for repair wares at {:?}", obj_id, target_id);
+                    }
+                    continue;
+                }
+
+                if let Some(stats) = stats.as_mut() {
+                    let sector_id = Locations::resolve_space_position(&query_locations, target_id)
+                        .map(|l| l.sector_id);
+                    stats.record_all(StatKind::Consumed, target_id, sector_id, &repair_dock.cost);
+                }
+
+                log::debug!("{:?} start repair at {:?}", obj_id, target_id);
+                maintenance.pending_work = Some(repair_dock.work);
+            }
+            Some(pending_work) => {
+                let pending_work = pending_work - repair_dock.production * delta_time.as_f32();
+                if pending_work > 0.0 {
+                    maintenance.pending_work = Some(pending_work);
+                    continue;
+                }
+
+                log::debug!("{:?} repair complete at {:?}", obj_id, target_id);
+                wear.value = 0.0;
+                Maintenance::complete(&mut commands, obj_id, &maintenance);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::dock::HasDocking;
+    use crate::game::sectors::Sector;
+    use bevy_ecs::system::RunSystemOnce;
+
+    fn setup(world: &mut World) -> (ObjId, ObjId, ObjId) {
+        world.insert_resource(DeltaTime(1.0));
+
+        let sector_id = world.spawn(Sector::new(Default::default())).id();
+        let ware_id = world.spawn_empty().id();
+
+        let mut cargo = Cargo::new(100);
+        cargo.add(ware_id, 15).unwrap();
+        let station_id = world
+            .spawn((
+                LocationSpace {
+                    pos: Default::default(),
+                    sector_id,
+                },
+                HasDocking::default(),
+                RepairDock::new(1.0, 2.0, vec![WareAmount::new(ware_id, 10)]),
+                cargo,
+            ))
+            .id();
+
+        let mut sector_index = EntityPerSectorIndex::new();
+        sector_index.add_stations(sector_id, station_id);
+        world.insert_resource(sector_index);
+
+        let ship_id = world
+            .spawn((
+                LocationSpace {
+                    pos: Default::default(),
+                    sector_id,
+                },
+                Wear { value: 0.8 },
+                Command::mine(),
+            ))
+            .id();
+
+        (station_id, ship_id, ware_id)
+    }
+
+    #[test]
+    fn test_wear_should_reduce_efficiency() {
+        let mut wear = Wear::default();
+        assert_eq!(1.0, wear.efficiency());
+        wear.add(2.0);
+        assert_eq!(1.0, wear.value);
+        assert_eq!(1.0 - WEAR_MAX_PENALTY, wear.efficiency());
+        assert!(wear.require_maintenance());
+    }
+
+    #[test]
+    fn test_worn_ship_should_be_repaired_and_resume_command() {
+        let mut world = World::new();
+        let (station_id, ship_id, ware_id) = setup(&mut world);
+
+        world.run_system_once(system_maintenance_request);
+        assert!(world.get::<Command>(ship_id).is_none());
+        assert_eq!(
+            Some(&NavRequest::MoveAndDockAt {
+                target_id: station_id
+            }),
+            world.get::<NavRequest>(ship_id)
+        );
+
+        // arrive at the station
+        world
+            .entity_mut(ship_id)
+            .remove::<(NavRequest, LocationSpace)>()
+            .insert(LocationDocked {
+                parent_id: station_id,
+            });
+
+        world.run_system_once(system_repair);
+        assert_eq!(
+            5,
+            world.get::<Cargo>(station_id).unwrap().get_amount(ware_id)
+        );
+        assert_eq!(
+            Some(2.0),
+            world.get::<Maintenance>(ship_id).unwrap().pending_work
+        );
+
+        world.run_system_once(system_repair);
+        world.run_system_once(system_repair);
+        assert_eq!(0.0, world.get::<Wear>(ship_id).unwrap().value);
+        assert!(world.get::<Maintenance>(ship_id).is_none());
+        assert!(world.get::<Command>(ship_id).is_some());
+    }
+
+    #[test]
+    fn test_repair_should_wait_for_wares() {
+        let mut world = World::new();
+        let (station_id, ship_id, _) = setup(&mut world);
+
+        world.entity_mut(ship_id).remove::<LocationSpace>().insert((
+            LocationDocked {
+                parent_id: station_id,
+            },
+            Maintenance {
+                target_id: station_id,
+                command: None,
+                pending_work: None,
+                wait_time: 0.0,
+            },
+        ));
+        world.get_mut::<Cargo>(station_id).unwrap().clear();
+
+        world.run_system_once(system_repair);
+        assert_eq!(
+            None,
+            world.get::<Maintenance>(ship_id).unwrap().pending_work
+        );
+        assert_eq!(0.8, world.get::<Wear>(ship_id).unwrap().value);
+    }
+
+    #[test]
+    fn test_repair_should_cancel_when_wares_are_not_delivered() {
+        let mut world = World::new();
+        let (station_id, ship_id, _) = setup(&mut world);
+
+        world
+            .entity_mut(ship_id)
+            .remove::<(LocationSpace, Command)>()
+            .insert((
+                LocationDocked {
+                    parent_id: station_id,
+                },
+                Maintenance {
+                    target_id: station_id,
+                    command: Some(Command::mine()),
+                    pending_work: None,
+                    wait_time: 0.0,
+                },
+            ));
+        world.get_mut::<Cargo>(station_id).unwrap().clear();
+
+        world.insert_resource(DeltaTime(MAINTENANCE_MAX_WAIT + 1.0));
+        world.run_system_once(system_repair);
+        assert!(world.get::<Maintenance>(ship_id).is_none());
+        assert!(world.get::<Command>(ship_id).is_some());
+        assert_eq!(0.8, world.get::<Wear>(ship_id).unwrap().value);
+    }
+
+    #[test]
+    fn test_maintenance_request_should_ignore_docks_without_repair_wares() {
+        let mut world = World::new();
+        let (station_id, ship_id, _) = setup(&mut world);
+        world.get_mut::<Cargo>(station_id).unwrap().clear();
+
+        world.run_system_once(system_maintenance_request);
+        assert!(world.get::<Maintenance>(ship_id).is_none());
+        assert!(world.get::<Command>(ship_id).is_some());
+    }
+
+    #[test]
+    fn test_repair_should_cancel_when_dock_is_unreachable() {
+        let mut world = World::new();
+        let (station_id, ship_id, _) = setup(&mut world);
+
+        world.entity_mut(ship_id).remove::<Command>().insert((
+            Maintenance {
+                target_id: station_id,
+                command: Some(Command::mine()),
+                pending_work: None,
+                wait_time: 0.0,
+            },
+            NavRequestFailed {
+                request: NavRequest::MoveAndDockAt {
+                    target_id: station_id,
+                },
+                error: "no path",
+            },
+        ));
+
+        world.run_system_once(system_repair);
+        assert!(world.get::<Maintenance>(ship_id).is_none());
+        assert!(world.get::<NavRequestFailed>(ship_id).is_none());
+        assert!(world.get::<NavRequest>(ship_id).is_none());
+        assert!(world.get::<Command>(ship_id).is_some());
+    }
+}
@@ -6,33 +6,43 @@ pub mod code;
 pub mod commands;
 pub mod conf;
 pub mod data;
+pub mod discovery;
 pub mod dock;
 pub mod events;
 pub mod extractables;
+pub mod factions;
 pub mod factory;
 pub mod fleets;
+pub mod fuel;
 pub mod game;
+pub mod habitat;
 pub mod jsons;
 pub mod label;
 pub mod loader;
 pub mod locations;
+pub mod maintenance;
 pub mod navigations;
 pub mod new_obj;
 pub mod objects;
 pub mod orbit;
 pub mod order;
 pub mod prefab;
+pub mod prices;
 pub mod production_cost;
+pub mod raiders;
 pub mod save;
 pub mod save_manager;
 pub mod sceneries;
 pub mod scenery_random;
 pub mod sectors;
+pub mod sensors;
 pub mod ship;
 pub mod shipyard;
 pub mod station;
+pub mod stats;
 pub mod utils;
 pub mod wares;
 pub mod work;
+pub mod wrecks;
 
 pub use bevy_ecs::entity::Entity;
@@ -5,7 +5,12 @@ use crate::game::sectors::{FindPathParams, Jump, Sector, SectorId};
 
 use bevy_ecs::prelude::*;
 
-use crate::game::locations::{LocationDocked, LocationOrbit, LocationSpace, Locations};
+use crate::game::discovery::Discoveries;
+use crate::game::factions::Owner;
+use crate::game::fuel::FuelTank;
+use crate::game::locations::{
+    LocationDocked, LocationOrbit, LocationSpace, Locations, QueryEffectiveSpeed,
+};
 use crate::game::save::LoadingMapEntity;
 use crate::game::sectors;
 use commons::math::P2;
@@ -31,6 +36,16 @@ impl Navigation {
     pub fn take_next(&mut self) -> Option<Action> {
         self.plan.path.pop_front()
     }
+
+    /// Check if the request or any action of the plan use the object
+    pub fn is_using(&self, obj_id: ObjId) -> bool {
+        self.request.get_target_id() == Some(obj_id)
+            || self
+                .plan
+                .path
+                .iter()
+                .any(|action| action.get_target_id() == Some(obj_id))
+    }
 }
 
 impl LoadingMapEntity for Navigation {
@@ -40,6 +55,23 @@ impl LoadingMapEntity for Navigation {
     }
 }
 
+/// Navigation error of plans with jumps out of the fuel range
+pub const NAV_ERROR_NOT_ENOUGH_FUEL: &str = "not enough fuel to reach the target";
+
+/// Last navigation request of the object that could not be planned, removed once a next request
+/// is planned
+#[derive(Debug, Clone, Component)]
+pub struct NavRequestFailed {
+    pub request: NavRequest,
+    pub error: &'static str,
+}
+
+impl NavRequestFailed {
+    pub fn is_out_of_fuel(&self) -> bool {
+        self.error == NAV_ERROR_NOT_ENOUGH_FUEL
+    }
+}
+
 #[derive(Debug, Clone, Component, PartialEq, Serialize, Deserialize)]
 pub enum NavRequest {
     OrbitTarget { target_id: ObjId },
@@ -48,6 +80,17 @@ pub enum NavRequest {
     MoveToPos { sector_id: SectorId, pos: P2 },
 }
 
+impl NavRequest {
+    pub fn get_target_id(&self) -> Option<ObjId> {
+        match self {
+            NavRequest::OrbitTarget { target_id }
+            | NavRequest::MoveToTarget { target_id }
+            | NavRequest::MoveAndDockAt { target_id } => Some(*target_id),
+            NavRequest::MoveToPos { .. } => None,
+        }
+    }
+}
+
 impl LoadingMapEntity for NavRequest {
     fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
         match self {
@@ -86,28 +129,42 @@ impl LoadingMapEntity for NavigationPlan {
 
 pub struct Navigations;
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_plan_system(
     In((obj_id, request)): In<(Entity, NavRequest)>,
     query_entity: Query<(Option<&LocationDocked>, Option<&LocationOrbit>)>,
     query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
     query_sectors: Query<&Sector>,
     query_jumps: Query<(&Jump, &LocationSpace)>,
+    query_fuel: Query<&FuelTank>,
+    query_speed: QueryEffectiveSpeed,
+    query_owner: Query<&Owner>,
+    discoveries: Option<Res<Discoveries>>,
 ) -> Result<NavigationPlan, &'static str> {
     create_plan(
         &query_entity,
         &query_locations,
         &query_sectors,
         &query_jumps,
+        &query_fuel,
+        &query_speed,
+        &query_owner,
+        discoveries.as_deref(),
         obj_id,
         &request,
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_plan(
     query_entity: &Query<(Option<&LocationDocked>, Option<&LocationOrbit>)>,
     query_locations: &Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
     query_sectors: &Query<&Sector>,
     query_jumps: &Query<(&Jump, &LocationSpace)>,
+    query_fuel: &Query<&FuelTank>,
+    query_speed: &QueryEffectiveSpeed,
+    query_owner: &Query<&Owner>,
+    discoveries: Option<&Discoveries>,
     obj_id: Entity,
     request: &NavRequest,
 ) -> Result<NavigationPlan, &'static str> {
@@ -144,13 +201,33 @@ pub fn create_plan(
         },
     };
 
+    // factions with discovery only travel through what they have explored
+    let discovery = discoveries.and_then(|d| d.get_by_owner(query_owner.get(obj_id).ok()));
+
     let sector_path = sectors::find_path_raw(
         query_sectors,
         query_jumps,
-        FindPathParams::new(from_location.sector_id, to_location.sector_id),
+        discovery,
+        FindPathParams::new(from_location.sector_id, to_location.sector_id)
+            .with_positions(from_location.pos, to_location.pos)
+            .with_speed(
+                query_speed
+                    .get(obj_id)
+                    .map(|(moveable, speed_by_mass, cargo, wear, tank)| {
+                        moveable.effective_speed(speed_by_mass, cargo, wear, tank)
+                    })
+                    .unwrap_or(sectors::PATH_DEFAULT_SPEED),
+            ),
     )
     .ok_or("fail to find jump path between sectors")?;
 
+    // plans with jumps must be in the fuel range, moving inside a sector is always possible
+    if let Ok(tank) = query_fuel.get(obj_id) {
+        if !tank.can_travel(from_location.pos, &sector_path, to_location.pos) {
+            return Err(NAV_ERROR_NOT_ENOUGH_FUEL);
+        }
+    }
+
     for leg in &sector_path {
         path.push_back(Action::MoveToTargetPos {
             target_id: leg.jump_id,
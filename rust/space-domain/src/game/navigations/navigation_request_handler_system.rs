@@ -2,13 +2,18 @@ use bevy_ecs::prelude::*;
 
 use super::super::locations::*;
 use super::*;
+use crate::game::discovery::Discoveries;
+use crate::game::factions::Owner;
+use crate::game::fuel::FuelTank;
 use crate::game::game::SYSTEM_TIMEOUT;
 use crate::game::sectors::{Jump, Sector};
 
 ///
 /// Setup navigation for the request
 /// - check for inconsistencies
+/// - requests that can not be planned are marked with NavRequestFailed
 ///
+#[allow(clippy::too_many_arguments)]
 pub fn system_navigation_request(
     mut commands: Commands,
     query: Query<(Entity, &NavRequest)>,
@@ -16,6 +21,10 @@ pub fn system_navigation_request(
     query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
     query_sectors: Query<&Sector>,
     query_jumps: Query<(&Jump, &LocationSpace)>,
+    query_fuel: Query<&FuelTank>,
+    query_speed: QueryEffectiveSpeed,
+    query_owner: Query<&Owner>,
+    discoveries: Option<Res<Discoveries>>,
 ) {
     log::trace!("running");
 
@@ -32,6 +41,10 @@ pub fn system_navigation_request(
             &query_locations,
             &query_sectors,
             &query_jumps,
+            &query_fuel,
+            &query_speed,
+            &query_owner,
+            discoveries.as_deref(),
             id,
             request,
         ) {
@@ -43,6 +56,10 @@ pub fn system_navigation_request(
                     request,
                     err
                 );
+                commands.entity(id).insert(NavRequestFailed {
+                    request: request.clone(),
+                    error: err,
+                });
                 continue;
             }
         };
@@ -54,10 +71,13 @@ pub fn system_navigation_request(
             plan,
         );
 
-        commands.entity(id).insert(Navigation {
-            request: request.clone(),
-            plan,
-        });
+        commands
+            .entity(id)
+            .insert(Navigation {
+                request: request.clone(),
+                plan,
+            })
+            .remove::<NavRequestFailed>();
 
         if timeout.is_timeout() {
             log::warn!("navigation request timeout");
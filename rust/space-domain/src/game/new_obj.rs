@@ -7,16 +7,25 @@ use crate::game::production_cost::ProductionCost;
 use commons::math::{Rad, P2, P2I};
 
 use crate::game::commands::Command;
+use crate::game::dock::{DockSlots, SizeClass};
 use crate::game::extractables::Extractable;
+use crate::game::factions::{Faction, FactionId, Owner};
 use crate::game::factory::Factory;
+use crate::game::fuel::FuelTank;
+use crate::game::habitat::Habitat;
 use crate::game::locations::*;
+use crate::game::maintenance::RepairDock;
 use crate::game::objects::ObjId;
+use crate::game::prices::Credit;
 use crate::game::save::LoadingMapEntity;
 use crate::game::sectors::*;
+use crate::game::sensors::{Sensor, Signature};
+use crate::game::ship::ship_internals::ShipInstance;
 use crate::game::shipyard::Shipyard;
 use crate::game::utils::*;
-use crate::game::wares::{Cargo, Volume, WareAmount};
+use crate::game::wares::{Cargo, Mass, RestrictedWares, Volume, WareAmount, WareKind, WareUnit};
 use crate::game::work::WorkUnit;
+use crate::game::wrecks::Wreck;
 
 #[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
 pub struct NewObj {
@@ -28,6 +37,8 @@ pub struct NewObj {
     pub can_dock: bool,
     pub fleet: bool,
     pub docking: bool,
+    pub docking_slots: Vec<DockSlots>,
+    pub size_class: Option<SizeClass>,
     pub station: bool,
     pub sector: Option<P2I>,
     pub jump_to: Option<(SectorId, P2)>,
@@ -44,6 +55,21 @@ pub struct NewObj {
     pub location_orbit: Option<LocationOrbit>,
     pub building_site: Option<BuildingSite>,
     pub production_cost: Option<ProductionCost>,
+    pub credits: Option<Credit>,
+    pub ware_price: Option<Credit>,
+    pub ware_unit: Option<WareUnit>,
+    pub ware_kind: Option<WareKind>,
+    pub restricted_wares: Option<RestrictedWares>,
+    pub speed_by_mass: Option<Mass>,
+    pub habitat: Option<Habitat>,
+    pub faction: Option<Faction>,
+    pub owner: Option<Owner>,
+    pub ship_instance: Option<ShipInstance>,
+    pub wreck: Option<Wreck>,
+    pub repair_dock: Option<RepairDock>,
+    pub fuel_tank: Option<FuelTank>,
+    pub sensor: Option<Sensor>,
+    pub signature: Option<Signature>,
 }
 
 impl NewObj {
@@ -86,6 +112,17 @@ impl NewObj {
         self
     }
 
+    pub fn with_docking_slots(mut self, slots: Vec<DockSlots>) -> Self {
+        self.docking = true;
+        self.docking_slots = slots;
+        self
+    }
+
+    pub fn with_size_class(mut self, size_class: SizeClass) -> Self {
+        self.size_class = Some(size_class);
+        self
+    }
+
     pub fn can_dock(mut self) -> Self {
         self.can_dock = true;
         self
@@ -146,11 +183,86 @@ impl NewObj {
         self
     }
 
+    pub fn with_ware_price(mut self, price: Credit) -> Self {
+        self.ware_price = Some(price);
+        self
+    }
+
+    pub fn with_ware_unit(mut self, volume: Volume, mass: Mass) -> Self {
+        self.ware_unit = Some(WareUnit { volume, mass });
+        self
+    }
+
+    pub fn with_ware_kind(mut self, kind: WareKind) -> Self {
+        self.ware_kind = Some(kind);
+        self
+    }
+
+    pub fn with_restricted_wares(mut self, restricted: RestrictedWares) -> Self {
+        self.restricted_wares = Some(restricted);
+        self
+    }
+
+    pub fn with_faction(mut self, faction: Faction) -> Self {
+        self.faction = Some(faction);
+        self
+    }
+
+    pub fn with_owner(mut self, faction_id: FactionId) -> Self {
+        self.owner = Some(Owner::new(faction_id));
+        self
+    }
+
+    pub fn with_speed_by_mass(mut self, reference_mass: Mass) -> Self {
+        self.speed_by_mass = Some(reference_mass);
+        self
+    }
+
+    pub fn with_credits(mut self, credits: Credit) -> Self {
+        self.credits = Some(credits);
+        self
+    }
+
     pub fn with_factory(mut self, factory: Factory) -> Self {
         self.factory = Some(factory);
         self
     }
 
+    pub fn with_ship_instance(mut self, ship_instance: ShipInstance) -> Self {
+        self.ship_instance = Some(ship_instance);
+        self
+    }
+
+    pub fn with_habitat(mut self, habitat: Habitat) -> Self {
+        self.habitat = Some(habitat);
+        self
+    }
+
+    pub fn with_wreck(mut self, wreck: Wreck) -> Self {
+        self.wreck = Some(wreck);
+        self
+    }
+
+    pub fn with_repair_dock(mut self, repair_dock: RepairDock) -> Self {
+        self.repair_dock = Some(repair_dock);
+        self
+    }
+
+    pub fn with_fuel_tank(mut self, fuel_tank: FuelTank) -> Self {
+        self.fuel_tank = Some(fuel_tank);
+        self
+    }
+
+    pub fn with_sensor(mut self, range: f32, strength: f32) -> Self {
+        self.sensor = Some(Sensor { range, strength });
+        self
+    }
+
+    pub fn with_signature(mut self, value: f32) -> Self {
+        self.signature = Some(Signature { value });
+        self
+    }
+
     pub fn with_code<IntoString: Into<String>>(mut self, code: IntoString) -> Self {
         self.code = Some(code.into());
         self
@@ -205,5 +317,10 @@ impl LoadingMapEntity for NewObj {
         self.location_orbit.map_entity(entity_map);
         self.building_site.map_entity(entity_map);
         self.production_cost.map_entity(entity_map);
+        self.habitat.map_entity(entity_map);
+        self.repair_dock.map_entity(entity_map);
+        self.fuel_tank.map_entity(entity_map);
+        self.restricted_wares.map_entity(entity_map);
+        self.owner.map_entity(entity_map);
     }
 }
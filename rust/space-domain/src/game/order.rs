@@ -4,7 +4,7 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::game::wares::WareId;
+use crate::game::wares::{Cargo, WareId, WareKinds, WareSelector};
 
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TradeOrderId(u16);
@@ -13,11 +13,112 @@ pub const TRADE_ORDER_ID_SHIPYARD: TradeOrderId = TradeOrderId(0);
 pub const TRADE_ORDER_ID_FACTORY: TradeOrderId = TradeOrderId(1);
 pub const TRADE_ORDER_ID_EXTRACTABLE: TradeOrderId = TradeOrderId(2);
 pub const TRADE_ORDER_ID_BUILDING_SITE: TradeOrderId = TradeOrderId(3);
+pub const TRADE_ORDER_ID_HABITAT: TradeOrderId = TradeOrderId(4);
+pub const TRADE_ORDER_ID_REPAIR: TradeOrderId = TradeOrderId(5);
+pub const TRADE_ORDER_ID_RAIDER_BASE: TradeOrderId = TradeOrderId(6);
+
+/// A single ware provided or requested by an object.
+///
+/// Stock levels are fractions of the storage available for the ware in the object cargo. Requests
+/// are urgent below `min_stock` and satisfied at `max_stock`, providers have nothing to offer
+/// below `min_stock` and are urgent at `max_stock`.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "TradeOrderData")]
+pub struct TradeOrder {
+    pub id: TradeOrderId,
+    pub ware_id: WareId,
+    pub min_stock: f32,
+    pub max_stock: f32,
+    pub priority: f32,
+}
+
+impl TradeOrder {
+    pub fn new(id: TradeOrderId, ware_id: WareId) -> Self {
+        TradeOrder {
+            id,
+            ware_id,
+            min_stock: 0.0,
+            max_stock: 1.0,
+            priority: 1.0,
+        }
+    }
+
+    /// Thresholds are clamped into 0.0..1.0, an empty or inverted range is ignored and the
+    /// order keeps its current thresholds
+    pub fn with_stock(mut self, min_stock: f32, max_stock: f32) -> Self {
+        let min_stock = min_stock.clamp(0.0, 1.0);
+        let max_stock = max_stock.clamp(0.0, 1.0);
+        if min_stock >= max_stock {
+            log::warn!(
+                "invalid stock range {:?}..{:?} for {:?}, ignoring",
+                min_stock,
+                max_stock,
+                self
+            );
+            return self;
+        }
+        self.min_stock = min_stock;
+        self.max_stock = max_stock;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: f32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// 0.0 when stock is at or above max_stock, up to priority when at or below min_stock
+    pub fn request_urgency(&self, stock: f32) -> f32 {
+        let value = (self.max_stock - stock) / (self.max_stock - self.min_stock);
+        value.clamp(0.0, 1.0) * self.priority
+    }
+
+    /// 0.0 when stock is at or below min_stock, up to priority when at or above max_stock
+    pub fn provide_urgency(&self, stock: f32) -> f32 {
+        let value = (stock - self.min_stock) / (self.max_stock - self.min_stock);
+        value.clamp(0.0, 1.0) * self.priority
+    }
+}
+
+/// Serialized forms of a `TradeOrder`, older saves stored orders as `(id, ware_id)` tuples
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TradeOrderData {
+    Legacy(TradeOrderId, WareId),
+    Current {
+        id: TradeOrderId,
+        ware_id: WareId,
+        min_stock: f32,
+        max_stock: f32,
+        priority: f32,
+    },
+}
+
+impl From<TradeOrderData> for TradeOrder {
+    fn from(value: TradeOrderData) -> Self {
+        match value {
+            TradeOrderData::Legacy(id, ware_id) => TradeOrder::new(id, ware_id),
+            TradeOrderData::Current {
+                id,
+                ware_id,
+                min_stock,
+                max_stock,
+                priority,
+            } => TradeOrder {
+                id,
+                ware_id,
+                min_stock,
+                max_stock,
+                priority,
+            },
+        }
+    }
+}
 
 #[derive(Clone, Debug, Component, Default, PartialEq, Serialize, Deserialize)]
 pub struct TradeOrders {
-    provided: Vec<(TradeOrderId, WareId)>,
-    requested: Vec<(TradeOrderId, WareId)>,
+    provided: Vec<TradeOrder>,
+    requested: Vec<TradeOrder>,
 }
 
 impl TradeOrders {
@@ -26,7 +127,7 @@ impl TradeOrders {
             provided: provided
                 .iter()
                 .copied()
-                .map(|ware_id| (order_id, ware_id))
+                .map(|ware_id| TradeOrder::new(order_id, ware_id))
                 .collect(),
             requested: vec![],
         }
@@ -37,7 +138,7 @@ impl TradeOrders {
             requested: requested
                 .iter()
                 .copied()
-                .map(|ware_id| (order_id, ware_id))
+                .map(|ware_id| TradeOrder::new(order_id, ware_id))
                 .collect(),
             provided: vec![],
         }
@@ -47,13 +148,21 @@ impl TradeOrders {
         self.provided.is_empty() && self.requested.is_empty()
     }
 
+    pub fn get_requested(&self) -> &Vec<TradeOrder> {
+        &self.requested
+    }
+
+    pub fn get_provided(&self) -> &Vec<TradeOrder> {
+        &self.provided
+    }
+
     pub fn wares_requests(&self) -> Vec<WareId> {
-        let wares: Vec<WareId> = self.requested.iter().map(|(_, ware_id)| *ware_id).collect();
+        let wares: Vec<WareId> = self.requested.iter().map(|i| i.ware_id).collect();
         wares.into_iter().unique().collect()
     }
 
     pub fn wares_provider(&self) -> Vec<WareId> {
-        let wares: Vec<WareId> = self.provided.iter().map(|(_, ware_id)| *ware_id).collect();
+        let wares: Vec<WareId> = self.provided.iter().map(|i| i.ware_id).collect();
         wares.into_iter().unique().collect()
     }
 
@@ -66,11 +175,17 @@ impl TradeOrders {
     }
 
     pub fn is_requesting_ware(&self, ware_id: WareId) -> bool {
-        self.requested.iter().find(|i| i.1 == ware_id).is_some()
+        self.requested
+            .iter()
+            .find(|i| i.ware_id == ware_id)
+            .is_some()
     }
 
     pub fn is_providing_ware(&self, ware_id: WareId) -> bool {
-        self.provided.iter().find(|i| i.1 == ware_id).is_some()
+        self.provided
+            .iter()
+            .find(|i| i.ware_id == ware_id)
+            .is_some()
     }
 
     pub fn request_any(&self, wares: &[WareId]) -> Vec<WareId> {
@@ -89,63 +204,250 @@ impl TradeOrders {
         !self.request_any(wares).is_empty()
     }
 
+    /// Requested wares that match any of the selectors
+    pub fn request_selected(&self, kinds: &WareKinds, selectors: &[WareSelector]) -> Vec<WareId> {
+        self.wares_requests()
+            .into_iter()
+            .filter(|ware_id| kinds.matches(selectors, *ware_id))
+            .collect()
+    }
+
+    /// Provided wares that match any of the selectors
+    pub fn provide_selected(&self, kinds: &WareKinds, selectors: &[WareSelector]) -> Vec<WareId> {
+        self.wares_provider()
+            .into_iter()
+            .filter(|ware_id| kinds.matches(selectors, *ware_id))
+            .collect()
+    }
+
+    /// Highest urgency between all requests of the ware giving the current cargo
+    pub fn request_urgency(&self, cargo: &Cargo, ware_id: WareId) -> f32 {
+        let stock = stock_level(cargo, ware_id);
+        self.requested
+            .iter()
+            .filter(|i| i.ware_id == ware_id)
+            .map(|i| i.request_urgency(stock))
+            .fold(0.0, f32::max)
+    }
+
+    /// Highest urgency between all providers of the ware giving the current cargo
+    pub fn provide_urgency(&self, cargo: &Cargo, ware_id: WareId) -> f32 {
+        let stock = stock_level(cargo, ware_id);
+        self.provided
+            .iter()
+            .filter(|i| i.ware_id == ware_id)
+            .map(|i| i.provide_urgency(stock))
+            .fold(0.0, f32::max)
+    }
+
     pub fn add_request(&mut self, order_id: TradeOrderId, ware_id: WareId) {
+        self.add_request_order(TradeOrder::new(order_id, ware_id));
+    }
+
+    pub fn add_provider(&mut self, order_id: TradeOrderId, ware_id: WareId) {
+        self.add_provider_order(TradeOrder::new(order_id, ware_id));
+    }
+
+    pub fn add_request_order(&mut self, order: TradeOrder) {
         if self
             .requested
             .iter()
-            .find(|(i_order_id, i_ware_id)| order_id == *i_order_id && *i_ware_id == ware_id)
+            .find(|i| i.id == order.id && i.ware_id == order.ware_id)
             .is_some()
         {
             return;
         }
 
-        self.requested.push((order_id, ware_id));
+        self.requested.push(order);
 
         log::debug!("trade order updated by add_request {:?}", self);
     }
 
-    pub fn add_provider(&mut self, order_id: TradeOrderId, ware_id: WareId) {
+    pub fn add_provider_order(&mut self, order: TradeOrder) {
         if self
             .provided
             .iter()
-            .find(|(i_order_id, i_ware_id)| order_id == *i_order_id && *i_ware_id == ware_id)
+            .find(|i| i.id == order.id && i.ware_id == order.ware_id)
             .is_some()
         {
             return;
         }
 
-        self.provided.push((order_id, ware_id));
+        self.provided.push(order);
         log::debug!("trade order updated by add_provide {:?}", self);
     }
 
     pub fn remove_request(&mut self, order_id: TradeOrderId, ware_id: WareId) {
-        self.provided
-            .retain(|(i_order_id, i_ware_id)| order_id != *i_order_id || *i_ware_id == ware_id);
+        self.requested
+            .retain(|i| i.id != order_id || i.ware_id != ware_id);
         log::debug!("trade order updated by remove_request {:?}", self);
     }
 
     pub fn remove_provider(&mut self, order_id: TradeOrderId, ware_id: WareId) {
         self.provided
-            .retain(|(i_order_id, i_ware_id)| order_id != *i_order_id || *i_ware_id == ware_id);
+            .retain(|i| i.id != order_id || i.ware_id != ware_id);
         log::debug!("trade order updated by remove_provide{:?}", self);
     }
 
     pub fn remove_by_id(&mut self, order_id: TradeOrderId) {
-        self.provided
-            .retain(|(i_order_id, _)| *i_order_id == order_id);
-        self.provided
-            .retain(|(i_order_id, _)| *i_order_id == order_id);
+        self.provided.retain(|i| i.id != order_id);
+        self.requested.retain(|i| i.id != order_id);
         log::debug!("trade order updated by remove_by_id {:?}", self);
     }
 }
 
+/// Stock of a ware as a fraction of the storage available for it
+fn stock_level(cargo: &Cargo, ware_id: WareId) -> f32 {
+    let stock = cargo.get_amount(ware_id);
+    let capacity = stock + cargo.free_volume(ware_id).unwrap_or(0);
+    if capacity == 0 {
+        1.0
+    } else {
+        stock as f32 / capacity as f32
+    }
+}
+
 impl LoadingMapEntity for TradeOrders {
     fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
         self.provided
             .iter_mut()
-            .for_each(|i| i.1.map_entity(entity_map));
+            .for_each(|i| i.ware_id.map_entity(entity_map));
         self.requested
             .iter_mut()
-            .for_each(|i| i.1.map_entity(entity_map));
+            .for_each(|i| i.ware_id.map_entity(entity_map));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_request_urgency_should_decrease_with_stock() {
+        let order = TradeOrder::new(TRADE_ORDER_ID_FACTORY, Entity::from_raw(0))
+            .with_stock(0.2, 0.8)
+            .with_priority(2.0);
+
+        assert_eq!(2.0, order.request_urgency(0.0));
+        assert_eq!(2.0, order.request_urgency(0.2));
+        assert_eq!(1.0, order.request_urgency(0.5));
+        assert_eq!(0.0, order.request_urgency(0.8));
+        assert_eq!(0.0, order.request_urgency(1.0));
+    }
+
+    #[test]
+    fn test_provide_urgency_should_increase_with_stock() {
+        let order =
+            TradeOrder::new(TRADE_ORDER_ID_FACTORY, Entity::from_raw(0)).with_stock(0.2, 0.8);
+
+        assert_eq!(0.0, order.provide_urgency(0.0));
+        assert_eq!(0.0, order.provide_urgency(0.2));
+        assert_eq!(0.5, order.provide_urgency(0.5));
+        assert_eq!(1.0, order.provide_urgency(1.0));
+    }
+
+    #[test]
+    fn test_with_stock_should_ignore_invalid_range() {
+        let order =
+            TradeOrder::new(TRADE_ORDER_ID_FACTORY, Entity::from_raw(0)).with_stock(0.8, 0.2);
+        assert_eq!(0.0, order.min_stock);
+        assert_eq!(1.0, order.max_stock);
+
+        let order =
+            TradeOrder::new(TRADE_ORDER_ID_FACTORY, Entity::from_raw(0)).with_stock(-1.0, 2.0);
+        assert_eq!(0.0, order.min_stock);
+        assert_eq!(1.0, order.max_stock);
+    }
+
+    #[test]
+    fn test_trade_orders_urgency_should_use_cargo_share() {
+        let ware_0 = Entity::from_raw(0);
+        let ware_1 = Entity::from_raw(1);
+
+        let mut cargo = Cargo::new(100);
+        cargo.set_whitelist(vec![ware_0, ware_1]);
+        cargo.add(ware_0, 40).unwrap();
+        cargo.add(ware_1, 10).unwrap();
+
+        let orders = TradeOrders::from_requested(TRADE_ORDER_ID_FACTORY, &[ware_0, ware_1]);
+        assert!(orders.request_urgency(&cargo, ware_1) > orders.request_urgency(&cargo, ware_0));
+        assert_eq!(0.0, orders.provide_urgency(&cargo, ware_0));
+    }
+
+    #[test]
+    fn test_remove_orders() {
+        let ware_0 = Entity::from_raw(0);
+        let ware_1 = Entity::from_raw(1);
+
+        let mut orders = TradeOrders::from_requested(TRADE_ORDER_ID_FACTORY, &[ware_0, ware_1]);
+        orders.add_provider(TRADE_ORDER_ID_SHIPYARD, ware_0);
+        orders.add_request(TRADE_ORDER_ID_SHIPYARD, ware_0);
+
+        orders.remove_request(TRADE_ORDER_ID_FACTORY, ware_0);
+        assert_eq!(vec![ware_1, ware_0], orders.wares_requests());
+
+        orders.remove_by_id(TRADE_ORDER_ID_SHIPYARD);
+        assert_eq!(vec![ware_1], orders.wares_requests());
+        assert!(!orders.is_provide());
+    }
+
+    #[test]
+    fn test_remove_request_should_keep_providers_and_other_wares() {
+        let ware_0 = Entity::from_raw(0);
+        let ware_1 = Entity::from_raw(1);
+
+        let mut orders = TradeOrders::from_requested(TRADE_ORDER_ID_FACTORY, &[ware_0, ware_1]);
+        orders.add_provider(TRADE_ORDER_ID_FACTORY, ware_0);
+
+        orders.remove_request(TRADE_ORDER_ID_FACTORY, ware_0);
+        assert_eq!(vec![ware_1], orders.wares_requests());
+        assert_eq!(vec![ware_0], orders.wares_provider());
+
+        orders.remove_provider(TRADE_ORDER_ID_FACTORY, ware_0);
+        assert_eq!(vec![ware_1], orders.wares_requests());
+        assert!(!orders.is_provide());
+    }
+
+    #[test]
+    fn test_remove_by_id_should_keep_other_orders() {
+        let ware_0 = Entity::from_raw(0);
+        let ware_1 = Entity::from_raw(1);
+
+        let mut orders = TradeOrders::from_requested(TRADE_ORDER_ID_SHIPYARD, &[ware_0]);
+        orders.add_provider(TRADE_ORDER_ID_SHIPYARD, ware_1);
+        orders.add_request(TRADE_ORDER_ID_FACTORY, ware_1);
+        orders.add_provider(TRADE_ORDER_ID_FACTORY, ware_0);
+
+        orders.remove_by_id(TRADE_ORDER_ID_SHIPYARD);
+        assert_eq!(vec![ware_1], orders.wares_requests());
+        assert_eq!(vec![ware_0], orders.wares_provider());
+    }
+
+    #[test]
+    fn test_trade_orders_should_load_legacy_tuples() {
+        let ware_0 = Entity::from_raw(0);
+        let ware_1 = Entity::from_raw(1);
+
+        let json = format!(
+            r#"{{"provided":[[1,{}]],"requested":[[0,{}]]}}"#,
+            ware_0.to_bits(),
+            ware_1.to_bits()
+        );
+        let orders: TradeOrders = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            vec![TradeOrder::new(TRADE_ORDER_ID_FACTORY, ware_0)],
+            *orders.get_provided()
+        );
+        assert_eq!(
+            vec![TradeOrder::new(TRADE_ORDER_ID_SHIPYARD, ware_1)],
+            *orders.get_requested()
+        );
+
+        let expected = TradeOrders {
+            provided: vec![TradeOrder::new(TRADE_ORDER_ID_FACTORY, ware_0).with_stock(0.2, 0.8)],
+            requested: vec![],
+        };
+        let json = serde_json::to_string(&expected).unwrap();
+        assert_eq!(expected, serde_json::from_str(&json).unwrap());
     }
 }
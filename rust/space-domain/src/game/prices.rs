@@ -0,0 +1,221 @@
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::objects::ObjId;
+use crate::game::wares::{Cargo, CargoTransfer, WareId};
+
+pub type Credit = i64;
+
+/// Base price used when a ware has no price defined
+pub const DEFAULT_WARE_PRICE: Credit = 10;
+
+/// Initial balance of new stations and ships
+pub const DEFAULT_STATION_CREDITS: Credit = 10_000;
+pub const DEFAULT_SHIP_CREDITS: Credit = 1_000;
+
+/// Difference between the price a station ask to sell and pay to buy a ware
+const PRICE_SPREAD: f32 = 0.1;
+
+/// Credits account of a station or company. Balance can become negative, a station in debt
+/// keep trading until its sales cover it.
+#[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
+pub struct Credits {
+    balance: Credit,
+}
+
+impl Credits {
+    pub fn new(balance: Credit) -> Self {
+        Credits { balance }
+    }
+
+    pub fn get_balance(&self) -> Credit {
+        self.balance
+    }
+
+    pub fn add(&mut self, amount: Credit) {
+        self.balance += amount;
+    }
+
+    pub fn remove(&mut self, amount: Credit) {
+        self.balance -= amount;
+    }
+}
+
+/// Base price of a ware, added to ware entities
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct WarePrice {
+    pub base: Credit,
+}
+
+pub struct Prices;
+
+impl Prices {
+    pub fn get_base_price(query_prices: &Query<&WarePrice>, ware_id: WareId) -> Credit {
+        query_prices
+            .get(ware_id)
+            .map(|p| p.base)
+            .unwrap_or(DEFAULT_WARE_PRICE)
+    }
+
+    /// Price of a ware giving the stock in the cargo. Empty storage double the price of a full
+    /// storage, going from 150% to 50% of the base price.
+    pub fn compute_price(base: Credit, cargo: &Cargo, ware_id: WareId) -> f32 {
+        let stock = cargo.get_amount(ware_id);
+        let capacity = stock + cargo.free_volume(ware_id).unwrap_or(0);
+        if capacity == 0 {
+            return base as f32;
+        }
+
+        let ratio = stock as f32 / capacity as f32;
+        base as f32 * (1.5 - ratio)
+    }
+
+    /// Price a station ask for each unit of ware taken from its cargo
+    pub fn sell_price(base: Credit, cargo: &Cargo, ware_id: WareId) -> Credit {
+        let price = Prices::compute_price(base, cargo, ware_id) * (1.0 + PRICE_SPREAD * 0.5);
+        (price.round() as Credit).max(1)
+    }
+
+    /// Price a station pay for each unit of ware received into its cargo
+    pub fn buy_price(base: Credit, cargo: &Cargo, ware_id: WareId) -> Credit {
+        let price = Prices::compute_price(base, cargo, ware_id) * (1.0 - PRICE_SPREAD * 0.5);
+        (price.round() as Credit).max(1)
+    }
+
+    /// Price per unit a station pay for each ware
+    pub fn list_buy_prices(
+        query_prices: &Query<&WarePrice>,
+        cargo: &Cargo,
+        wares: &[WareId],
+    ) -> Vec<(WareId, Credit)> {
+        wares
+            .iter()
+            .map(|ware_id| {
+                let base = Prices::get_base_price(query_prices, *ware_id);
+                (*ware_id, Prices::buy_price(base, cargo, *ware_id))
+            })
+            .collect()
+    }
+
+    /// Price per unit a station ask for each ware
+    pub fn list_sell_prices(
+        query_prices: &Query<&WarePrice>,
+        cargo: &Cargo,
+        wares: &[WareId],
+    ) -> Vec<(WareId, Credit)> {
+        wares
+            .iter()
+            .map(|ware_id| {
+                let base = Prices::get_base_price(query_prices, *ware_id);
+                (*ware_id, Prices::sell_price(base, cargo, *ware_id))
+            })
+            .collect()
+    }
+
+    /// Total value of a transfer, wares without price are free
+    pub fn transfer_value(transfer: &CargoTransfer, prices: &[(WareId, Credit)]) -> Credit {
+        transfer
+            .moved
+            .iter()
+            .map(|wa| {
+                let price = prices
+                    .iter()
+                    .find(|(ware_id, _)| *ware_id == wa.ware_id)
+                    .map(|(_, price)| *price)
+                    .unwrap_or(0);
+                wa.amount as Credit * price
+            })
+            .sum()
+    }
+
+    /// Move credits from buyer to seller. The transfer is only settle when both have an account.
+    pub fn settle(
+        query_credits: &mut Query<&mut Credits>,
+        seller_id: ObjId,
+        buyer_id: ObjId,
+        value: Credit,
+    ) -> bool {
+        if value == 0 || !query_credits.contains(seller_id) || !query_credits.contains(buyer_id) {
+            return false;
+        }
+
+        query_credits.get_mut(buyer_id).unwrap().remove(value);
+        query_credits.get_mut(seller_id).unwrap().add(value);
+
+        log::debug!("{:?} paid {:?} credits to {:?}", buyer_id, value, seller_id);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::wares::WareAmount;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_price_should_decrease_with_stock() {
+        let ware_id = Entity::from_raw(0);
+
+        let mut cargo = Cargo::new(100);
+        let empty_price = Prices::compute_price(10, &cargo, ware_id);
+
+        cargo.add(ware_id, 50).unwrap();
+        let half_price = Prices::compute_price(10, &cargo, ware_id);
+
+        cargo.add(ware_id, 50).unwrap();
+        let full_price = Prices::compute_price(10, &cargo, ware_id);
+
+        assert_eq!(15.0, empty_price);
+        assert_eq!(10.0, half_price);
+        assert_eq!(5.0, full_price);
+    }
+
+    #[test]
+    fn test_station_should_sell_more_expensive_than_buy() {
+        let ware_id = Entity::from_raw(0);
+        let mut cargo = Cargo::new(100);
+        cargo.add(ware_id, 50).unwrap();
+
+        assert!(Prices::sell_price(100, &cargo, ware_id) > Prices::buy_price(100, &cargo, ware_id));
+    }
+
+    #[test]
+    fn test_settle_should_move_credits_between_accounts() {
+        let mut world = World::new();
+        let seller_id = world.spawn(Credits::new(100)).id();
+        let buyer_id = world.spawn(Credits::new(100)).id();
+        let no_account_id = world.spawn_empty().id();
+
+        let ware_id = world.spawn(WarePrice { base: 10 }).id();
+        let transfer = CargoTransfer {
+            moved: vec![WareAmount::new(ware_id, 10)],
+        };
+
+        world.run_system_once(
+            move |mut query_credits: Query<&mut Credits>, query_prices: Query<&WarePrice>| {
+                // station with empty cargo pay close to 150% of the base price
+                let prices =
+                    Prices::list_buy_prices(&query_prices, &Cargo::new(100), &vec![ware_id]);
+                let value = Prices::transfer_value(&transfer, &prices);
+                assert_eq!(140, value);
+                assert!(Prices::settle(
+                    &mut query_credits,
+                    seller_id,
+                    buyer_id,
+                    value
+                ));
+                assert!(!Prices::settle(
+                    &mut query_credits,
+                    seller_id,
+                    no_account_id,
+                    value
+                ));
+            },
+        );
+
+        assert_eq!(240, world.get::<Credits>(seller_id).unwrap().get_balance());
+        assert_eq!(-40, world.get::<Credits>(buyer_id).unwrap().get_balance());
+    }
+}
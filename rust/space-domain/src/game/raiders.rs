@@ -0,0 +1,286 @@
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::commands::Command;
+use crate::game::factions::FactionId;
+use crate::game::loader::Loader;
+use crate::game::locations::LocationSpace;
+use crate::game::new_obj::NewObj;
+use crate::game::objects::ObjId;
+use crate::game::order::{TradeOrders, TRADE_ORDER_ID_RAIDER_BASE};
+use crate::game::save::LoadingMapEntity;
+use crate::game::sectors::{find_path_raw, FindPathParams, Jump, Sector, SectorId};
+use crate::game::station::Station;
+use crate::game::utils::{DeltaTime, TotalTime, V2};
+use crate::game::wares::{Volume, Ware};
+
+/// Danger added to a sector on each raid
+pub const RAID_DANGER: f32 = 1.0;
+
+/// Time for the danger of a sector to fall to half
+pub const DANGER_HALF_LIFE: DeltaTime = DeltaTime(300.0);
+
+/// Storage of the raiders base
+pub const RAIDER_BASE_STORAGE: Volume = 500;
+
+/// Sector where raiders spawn, the base is created with the first raider
+#[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
+pub struct RaiderSector {
+    /// station where the raiders of the sector sell the loot
+    #[serde(default)]
+    pub base_id: Option<ObjId>,
+}
+
+impl LoadingMapEntity for RaiderSector {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.base_id.map_entity(entity_map);
+    }
+}
+
+/// Ship hunting traders, it retreats to its base when there is nothing to hunt
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct Raider {
+    pub home_sector_id: SectorId,
+    #[serde(default)]
+    pub base_id: Option<ObjId>,
+}
+
+impl LoadingMapEntity for Raider {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.home_sector_id.map_entity(entity_map);
+        self.base_id.map_entity(entity_map);
+    }
+}
+
+/// How dangerous traders consider a sector, increased on each raid and decaying over time
+#[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
+pub struct SectorDanger {
+    pub value: f32,
+}
+
+impl SectorDanger {
+    pub fn of(query: &Query<&SectorDanger>, sector_id: SectorId) -> f32 {
+        query.get(sector_id).map(|i| i.value).unwrap_or(0.0)
+    }
+
+    /// Danger of every sector crossed from a sector to the target sector, including both. When
+    /// there is no path only the target sector is considered
+    pub fn of_path(
+        query: &Query<&SectorDanger>,
+        query_sectors: &Query<&Sector>,
+        query_jumps: &Query<(&Jump, &LocationSpace)>,
+        from: SectorId,
+        to: SectorId,
+    ) -> f32 {
+        if query.is_empty() {
+            return 0.0;
+        }
+
+        if query_sectors.get(from).is_err() || query_sectors.get(to).is_err() {
+            return Self::of(query, to);
+        }
+
+        match find_path_raw(
+            query_sectors,
+            query_jumps,
+            None,
+            FindPathParams::new(from, to),
+        ) {
+            Some(path) => {
+                Self::of(query, from)
+                    + path
+                        .iter()
+                        .map(|leg| Self::of(query, leg.target_sector_id))
+                        .sum::<f32>()
+            }
+            None => Self::of(query, to),
+        }
+    }
+}
+
+/// Periodically spawn raiders at raider sectors
+#[derive(Debug, Clone, Resource)]
+pub struct RaidersSpawn {
+    pub new_obj: NewObj,
+    pub faction_id: Option<FactionId>,
+    pub interval: DeltaTime,
+    pub max_per_sector: usize,
+    pub next_time: TotalTime,
+}
+
+impl RaidersSpawn {
+    pub fn new(
+        new_obj: NewObj,
+        faction_id: Option<FactionId>,
+        interval: DeltaTime,
+        max_per_sector: usize,
+        total_time: TotalTime,
+    ) -> Self {
+        RaidersSpawn {
+            new_obj,
+            faction_id,
+            interval,
+            max_per_sector,
+            next_time: total_time.add(interval),
+        }
+    }
+}
+
+/// Spawn raiders at each raider sector up to the max, creating the raiders base of the sector
+/// when it does not exist. The base buys the loot and provides it to any trader.
+pub fn system_raiders_spawn(
+    mut commands: Commands,
+    total_time: Res<TotalTime>,
+    spawn: Option<ResMut<RaidersSpawn>>,
+    mut query_sectors: Query<(Entity, &mut RaiderSector), With<Sector>>,
+    query_raiders: Query<&Raider>,
+    query_stations: Query<(), With<Station>>,
+    query_wares: Query<Entity, With<Ware>>,
+) {
+    log::trace!("running");
+
+    let Some(mut spawn) = spawn else {
+        return;
+    };
+
+    let total_time = *total_time;
+    if !total_time.is_after(spawn.next_time) {
+        return;
+    }
+    spawn.next_time = total_time.add(spawn.interval);
+
+    let mut alive_per_sector: HashMap<SectorId, usize> = HashMap::new();
+    for raider in &query_raiders {
+        *alive_per_sector.entry(raider.home_sector_id).or_insert(0) += 1;
+    }
+
+    for (sector_id, mut raider_sector) in &mut query_sectors {
+        if alive_per_sector.get(&sector_id).cloned().unwrap_or(0) >= spawn.max_per_sector {
+            continue;
+        }
+
+        let base_id = match raider_sector.base_id {
+            Some(base_id) if query_stations.contains(base_id) => base_id,
+            _ => {
+                let base_id = add_base(&mut commands, &query_wares, spawn.faction_id, sector_id);
+                log::debug!("{:?} raiders base created at {:?}", base_id, sector_id);
+                raider_sector.base_id = Some(base_id);
+                base_id
+            }
+        };
+
+        let mut new_obj = spawn
+            .new_obj
+            .clone()
+            .with_fleet()
+            .can_dock()
+            .at_position(sector_id, V2::ZERO)
+            .with_command(Command::raid());
+        if let Some(faction_id) = spawn.faction_id {
+            new_obj = new_obj.with_owner(faction_id);
+        }
+
+        let obj_id = Loader::add_object(&mut commands, &new_obj);
+        commands.entity(obj_id).insert(Raider {
+            home_sector_id: sector_id,
+            base_id: Some(base_id),
+        });
+        log::debug!("{:?} raider spawn at sector {:?}", obj_id, sector_id);
+    }
+}
+
+fn add_base(
+    commands: &mut Commands,
+    query_wares: &Query<Entity, With<Ware>>,
+    faction_id: Option<FactionId>,
+    sector_id: SectorId,
+) -> ObjId {
+    let mut new_obj = Loader::new_station()
+        .with_label("raiders base")
+        .with_cargo_size(RAIDER_BASE_STORAGE)
+        .at_position(sector_id, V2::ZERO);
+    if let Some(faction_id) = faction_id {
+        new_obj = new_obj.with_owner(faction_id);
+    }
+
+    let mut orders = TradeOrders::default();
+    for ware_id in query_wares {
+        orders.add_provider(TRADE_ORDER_ID_RAIDER_BASE, ware_id);
+    }
+
+    let base_id = Loader::add_object(commands, &new_obj);
+    commands.entity(base_id).insert(orders);
+    base_id
+}
+
+pub fn system_sector_danger_decay(delta_time: Res<DeltaTime>, mut query: Query<&mut SectorDanger>) {
+    log::trace!("running");
+
+    let decay = 0.5f32.powf(delta_time.as_f32() / DANGER_HALF_LIFE.as_f32());
+    for mut danger in &mut query {
+        danger.value *= decay;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::events::GEvents;
+    use crate::game::utils::Speed;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_raiders_spawn_should_respect_max_per_sector() {
+        let mut world = World::new();
+        world.insert_resource(GEvents::default());
+        world.insert_resource(TotalTime(0.0));
+
+        let sector_0 = world
+            .spawn((Sector::new(Default::default()), RaiderSector::default()))
+            .id();
+        world.spawn(Sector::new(Default::default()));
+
+        let new_obj = Loader::new_ship(1.0, "raider".to_string()).with_speed(Speed(2.0));
+        world.insert_resource(RaidersSpawn::new(
+            new_obj,
+            None,
+            DeltaTime(1.0),
+            2,
+            TotalTime(0.0),
+        ));
+
+        for i in 0..5 {
+            world.insert_resource(TotalTime(i as f64 * 2.0));
+            world.run_system_once(system_raiders_spawn);
+        }
+
+        let raiders = world
+            .query::<(&Raider, &Command)>()
+            .iter(&world)
+            .map(|(raider, command)| (raider.home_sector_id, command.as_raid().is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(sector_0, true), (sector_0, true)], raiders);
+
+        // a single base is created and shared by the raiders
+        let base_id = world.get::<RaiderSector>(sector_0).unwrap().base_id;
+        assert!(base_id.is_some());
+        assert_eq!(1, world.query::<&Station>().iter(&world).count());
+        assert!(world
+            .query::<&Raider>()
+            .iter(&world)
+            .all(|raider| raider.base_id == base_id));
+    }
+
+    #[test]
+    fn test_sector_danger_should_decay() {
+        let mut world = World::new();
+        world.insert_resource(DeltaTime(DANGER_HALF_LIFE.as_f32()));
+        let sector_id = world.spawn(SectorDanger { value: 4.0 }).id();
+
+        world.run_system_once(system_sector_danger_decay);
+
+        let value = world.get::<SectorDanger>(sector_id).unwrap().value;
+        assert!((value - 2.0).abs() < 0.001);
+    }
+}
@@ -1,27 +1,38 @@
 use crate::game::actions::{
     ActionActive, ActionDock, ActionExtract, ActionGeneric, ActionJump, ActionMoveTo,
-    ActionRequest, ActionUndock,
+    ActionRequest, ActionSalvage, ActionUndock,
 };
 use crate::game::astrobody::AstroBody;
 use crate::game::building_site::BuildingSite;
 use crate::game::code::HasCode;
 use crate::game::commands::Command;
-use crate::game::dock::HasDocking;
+use crate::game::discovery::Discoveries;
+use crate::game::dock::{HasDocking, SizeClass};
 use crate::game::events::GEvents;
 use crate::game::extractables::Extractable;
-use crate::game::factory::Factory;
-use crate::game::fleets::Fleet;
+use crate::game::factions::{Faction, Owner};
+use crate::game::factory::{Factory, ProductionBonus};
+use crate::game::fleets::{Fleet, FleetGroup, FleetMember};
+use crate::game::fuel::{FuelTank, Refuel};
+use crate::game::habitat::Habitat;
 use crate::game::label::Label;
-use crate::game::locations::{LocationDocked, LocationOrbit, LocationSpace, Moveable};
+use crate::game::locations::{LocationDocked, LocationOrbit, LocationSpace, Moveable, SpeedByMass};
+use crate::game::maintenance::{Maintenance, RepairDock, Wear};
 use crate::game::navigations::{NavRequest, Navigation};
 use crate::game::order::TradeOrders;
 use crate::game::prefab::Prefab;
+use crate::game::prices::{Credits, WarePrice};
 use crate::game::production_cost::ProductionCost;
+use crate::game::raiders::{Raider, RaiderSector, SectorDanger};
 use crate::game::sectors::{Jump, Sector};
+use crate::game::sensors::{FactionsVisibility, Sensor, Signature};
+use crate::game::ship::ship_internals::ShipInstance;
 use crate::game::shipyard::Shipyard;
 use crate::game::station::Station;
-use crate::game::utils::{Tick, TotalTime};
-use crate::game::wares::{Cargo, Ware};
+use crate::game::stats::EconomyStats;
+use crate::game::utils::{Seed, Tick, TotalTime};
+use crate::game::wares::{Cargo, RestrictedWares, Ware, WareKind, WareUnit};
+use crate::game::wrecks::Wreck;
 use bevy_ecs::prelude::*;
 use commons::jsons::JsonValueExtra;
 use serde::{Deserialize, Serialize};
@@ -64,8 +75,14 @@ pub struct ObjData {
     pub location_space: Option<LocationSpace>,
     pub location_docked: Option<LocationDocked>,
     pub fleet: Option<Fleet>,
+    pub fleet_group: Option<FleetGroup>,
+    pub fleet_member: Option<FleetMember>,
+    pub raider: Option<Raider>,
+    pub raider_sector: Option<RaiderSector>,
+    pub sector_danger: Option<SectorDanger>,
     pub moveable: Option<Moveable>,
     pub docking: Option<HasDocking>,
+    pub size_class: Option<SizeClass>,
     pub station: Option<Station>,
     pub sector: Option<Sector>,
     pub jump_to: Option<Jump>,
@@ -82,6 +99,7 @@ pub struct ObjData {
     pub action_undock: Option<ActionUndock>,
     pub action_dock: Option<ActionDock>,
     pub action_extract: Option<ActionExtract>,
+    pub action_salvage: Option<ActionSalvage>,
     pub action_move_to: Option<ActionMoveTo>,
     pub action_jump: Option<ActionJump>,
     pub action_generic: Option<ActionGeneric>,
@@ -89,6 +107,25 @@ pub struct ObjData {
     pub navigation_request: Option<NavRequest>,
     pub trade_order: Option<TradeOrders>,
     pub prefab: Option<Prefab>,
+    pub credits: Option<Credits>,
+    pub ware_price: Option<WarePrice>,
+    pub ware_unit: Option<WareUnit>,
+    pub speed_by_mass: Option<SpeedByMass>,
+    pub production_bonus: Option<ProductionBonus>,
+    pub habitat: Option<Habitat>,
+    pub ware_kind: Option<WareKind>,
+    pub restricted_wares: Option<RestrictedWares>,
+    pub faction: Option<Faction>,
+    pub owner: Option<Owner>,
+    pub ship_instance: Option<ShipInstance>,
+    pub wreck: Option<Wreck>,
+    pub wear: Option<Wear>,
+    pub repair_dock: Option<RepairDock>,
+    pub maintenance: Option<Maintenance>,
+    pub fuel_tank: Option<FuelTank>,
+    pub refuel: Option<Refuel>,
+    pub sensor: Option<Sensor>,
+    pub signature: Option<Signature>,
 }
 
 impl LoadingMapEntity for ObjData {
@@ -112,6 +149,18 @@ impl LoadingMapEntity for ObjData {
         self.navigation_request.map_entity(entity_map);
         self.trade_order.map_entity(entity_map);
         self.prefab.map_entity(entity_map);
+        self.habitat.map_entity(entity_map);
+        self.restricted_wares.map_entity(entity_map);
+        self.faction.map_entity(entity_map);
+        self.owner.map_entity(entity_map);
+        self.fleet_group.map_entity(entity_map);
+        self.fleet_member.map_entity(entity_map);
+        self.raider.map_entity(entity_map);
+        self.raider_sector.map_entity(entity_map);
+        self.repair_dock.map_entity(entity_map);
+        self.maintenance.map_entity(entity_map);
+        self.fuel_tank.map_entity(entity_map);
+        self.refuel.map_entity(entity_map);
     }
 }
 
@@ -119,13 +168,24 @@ impl LoadingMapEntity for ObjData {
 pub struct SaveData {
     pub tick: Tick,
     pub total_time: TotalTime,
+    #[serde(default)]
+    pub seed: Seed,
     pub events: GEvents,
+    #[serde(default)]
+    pub economy_stats: EconomyStats,
+    #[serde(default)]
+    pub visibility: FactionsVisibility,
+    #[serde(default)]
+    pub discoveries: Discoveries,
     pub objects: Vec<ObjData>,
 }
 
 impl LoadingMapEntity for SaveData {
     fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
         self.events.map_entity(entity_map);
+        self.economy_stats.map_entity(entity_map);
+        self.visibility.map_entity(entity_map);
+        self.discoveries.map_entity(entity_map);
         self.objects.map_entity(entity_map);
     }
 }
@@ -136,7 +196,17 @@ pub fn save_world(world: &mut World) -> String {
     let mut save_data = SaveData::default();
     save_data.tick = *world.resource::<Tick>();
     save_data.total_time = *world.resource::<TotalTime>();
+    save_data.seed = world.get_resource::<Seed>().copied().unwrap_or_default();
     save_data.events = world.resource::<GEvents>().clone();
+    save_data.economy_stats = world.resource::<EconomyStats>().clone();
+    save_data.visibility = world
+        .get_resource::<FactionsVisibility>()
+        .cloned()
+        .unwrap_or_default();
+    save_data.discoveries = world
+        .get_resource::<Discoveries>()
+        .cloned()
+        .unwrap_or_default();
 
     for e in world.query::<Entity>().iter(world) {
         let mut obj_data = ObjData::default();
@@ -186,7 +256,11 @@ pub fn load_world(world: &mut World, save_data: String) {
     log::trace!("loading resources");
     world.insert_resource(data.tick);
     world.insert_resource(data.total_time);
+    world.insert_resource(data.seed);
     world.insert_resource(data.events);
+    world.insert_resource(data.economy_stats);
+    world.insert_resource(data.visibility);
+    world.insert_resource(data.discoveries);
 
     // insert objects
     log::trace!("loading components");
@@ -205,6 +279,7 @@ pub fn load_world(world: &mut World, save_data: String) {
 mod test {
     use super::*;
     use crate::game::actions::Action;
+    use crate::game::stats::{StatKey, StatKind, StatScope};
     use crate::game::utils::V2;
     use crate::test::assert_v2;
     use bevy_ecs::entity::Entity;
@@ -220,6 +295,8 @@ mod test {
         let mut world = World::new();
         world.insert_resource(TotalTime(33.0));
         world.insert_resource(GEvents::default());
+        world.insert_resource(Tick::default());
+        world.insert_resource(EconomyStats::default());
 
         let sector_id = world.spawn_empty().id();
         let obj_id = world
@@ -267,4 +344,76 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_save_and_load_cargo_reservations() {
+        let mut world = World::new();
+        world.insert_resource(TotalTime(0.0));
+        world.insert_resource(GEvents::default());
+        world.insert_resource(Tick::default());
+        world.insert_resource(EconomyStats::default());
+
+        // spawn some entities to force ids to be remapped
+        let ware_id = world.spawn(Label::from("ware")).id();
+        let trader_id = world.spawn(Cargo::new(10)).id();
+        let mut cargo = Cargo::new(10);
+        cargo.add(ware_id, 5).unwrap();
+        cargo.reserve_out(trader_id, ware_id, 3, TotalTime(10.0));
+        world.spawn(cargo);
+
+        let save_data = save_world(&mut world);
+
+        world = World::new();
+        world.spawn_empty();
+        load_world(&mut world, save_data);
+
+        let mut ware_query = world.query_filtered::<Entity, With<Label>>();
+        let new_ware_id = ware_query.single(&world);
+
+        let reservations = world
+            .query::<&Cargo>()
+            .iter(&world)
+            .flat_map(|cargo| cargo.get_reservations().clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(1, reservations.len());
+        assert_eq!(new_ware_id, reservations[0].ware_id);
+        assert_ne!(trader_id, reservations[0].owner_id);
+        assert_eq!(3, reservations[0].amount);
+    }
+
+    #[test]
+    fn test_save_and_load_economy_stats() {
+        let mut world = World::new();
+        world.insert_resource(TotalTime(0.0));
+        world.insert_resource(GEvents::default());
+        world.insert_resource(Tick::default());
+
+        let ware_id = world.spawn(Label::from("ware")).id();
+        let station_id = world.spawn(Station {}).id();
+        let mut stats = EconomyStats::default();
+        stats.record(StatKind::Produced, station_id, None, ware_id, 4);
+        stats.sample(TotalTime(10.0), vec![]);
+        world.insert_resource(stats);
+
+        let save_data = save_world(&mut world);
+
+        world = World::new();
+        world.spawn_empty();
+        load_world(&mut world, save_data);
+
+        let new_ware_id = world.query_filtered::<Entity, With<Label>>().single(&world);
+        let new_station_id = world
+            .query_filtered::<Entity, With<Station>>()
+            .single(&world);
+
+        let stats = world.resource::<EconomyStats>();
+        let key = StatKey {
+            kind: StatKind::Produced,
+            ware_id: new_ware_id,
+            scope: StatScope::Obj(new_station_id),
+        };
+        assert_eq!(4, stats.get_total(&key));
+        assert_eq!(vec![4], stats.get_history(&key));
+    }
 }
@@ -1,13 +1,20 @@
 use crate::game::bevy_utils::WorldExt;
 use crate::game::extractables::Extractable;
+use crate::game::factions::{FactionId, Factions, Owner};
+use crate::game::factory::ProductionBonus;
 use crate::game::game::Game;
+use crate::game::habitat::Habitat;
 use crate::game::loader::Loader;
 use crate::game::locations::LocationOrbit;
+use crate::game::objects::ObjId;
 use crate::game::orbit::Orbits;
+use crate::game::prices::DEFAULT_STATION_CREDITS;
+use crate::game::raiders::RaiderSector;
+use crate::game::sectors::{Sector, SectorId};
 use crate::game::shipyard::Shipyard;
-use crate::game::utils::TotalTime;
-use crate::game::wares::Wares;
-use crate::game::{conf, loader, sectors, shipyard};
+use crate::game::utils::{DeltaTime, TotalTime};
+use crate::game::wares::{RestrictedWares, Volume, Wares};
+use crate::game::{conf, habitat, loader, sectors, shipyard};
 use bevy_ecs::prelude::*;
 use bevy_ecs::system::{RunSystemOnce, SystemState};
 use commons::math::{P2, P2I, V2, V2I};
@@ -38,7 +45,7 @@ pub fn load_random(game: &mut Game, cfg: &RandomMapCfg) {
 
     // create sectors
     generate_sectors(world, cfg.size, rng.gen());
-    add_bodies_to_sectors(world, rng.gen(), &cfg.universe_cfg);
+    add_bodies_to_sectors(world, rng.gen(), &cfg.universe_cfg, &cfg.params);
     match cfg.initial_condition {
         InitialCondition::Random {
             station_per_sector_density,
@@ -53,41 +60,57 @@ pub fn load_random(game: &mut Game, cfg: &RandomMapCfg) {
 
         // find shipyards
         {
-            let mut st: SystemState<Query<(Entity, With<Shipyard>)>> = SystemState::new(world);
+            let mut st: SystemState<Query<(Entity, Option<&Owner>), With<Shipyard>>> =
+                SystemState::new(world);
             let query = st.get(world);
-            shipyards.extend(query.iter().map(|(id, _)| id));
+            shipyards.extend(query.iter().map(|(id, owner)| (id, owner.copied())));
         }
 
         game.world.run_commands(|mut commands| {
+            // ships are owned by the shipyard owner
+            let set_owner = |commands: &mut Commands, obj_id: ObjId, owner: Option<Owner>| {
+                if let Some(owner) = owner {
+                    commands.entity(obj_id).insert(owner);
+                }
+            };
+
             // add mandatory ships
-            let shipyard = *commons::prob::select(&mut rng, &shipyards).unwrap();
-            Loader::add_ship_miner(&mut commands, shipyard, 0.75, format!("miner-{}", 0));
-            let shipyard = *commons::prob::select(&mut rng, &shipyards).unwrap();
-            Loader::add_ship_trader(&mut commands, shipyard, 1.0, format!("trader-{}", 0));
+            let (shipyard, owner) = *commons::prob::select(&mut rng, &shipyards).unwrap();
+            let obj_id =
+                Loader::add_ship_miner(&mut commands, shipyard, 0.75, format!("miner-{}", 0));
+            set_owner(&mut commands, obj_id, owner);
+            let (shipyard, owner) = *commons::prob::select(&mut rng, &shipyards).unwrap();
+            let obj_id =
+                Loader::add_ship_trader(&mut commands, shipyard, 1.0, format!("trader-{}", 0));
+            set_owner(&mut commands, obj_id, owner);
 
             for i in 0..cfg.fleets {
-                let shipyard = *commons::prob::select(&mut rng, &shipyards).unwrap();
+                let (shipyard, owner) = *commons::prob::select(&mut rng, &shipyards).unwrap();
                 let choose = rng.gen_range(0..=1);
                 let code = i + 2;
-                if choose == 0 {
-                    Loader::add_ship_miner(
-                        &mut commands,
-                        shipyard,
-                        0.75,
-                        format!("miner-{}", code),
-                    );
+                let obj_id = if choose == 0 {
+                    Loader::add_ship_miner(&mut commands, shipyard, 0.75, format!("miner-{}", code))
                 } else {
                     Loader::add_ship_trader(
                         &mut commands,
                         shipyard,
                         1.0,
                         format!("trader-{}", code),
-                    );
-                }
+                    )
+                };
+                set_owner(&mut commands, obj_id, owner);
             }
         });
     }
 
+    if let Some(restricted) = &cfg.params.restricted_wares {
+        add_restricted_wares_to_sectors(&mut game.world, rng.gen(), restricted);
+    }
+
+    if let Some(raiders) = &cfg.params.raiders {
+        add_raiders_to_sectors(&mut game.world, rng.gen(), raiders);
+    }
+
     // update index
     game.reindex_sectors();
 }
@@ -123,6 +146,19 @@ pub fn generate_sectors(world: &mut World, size: (usize, usize), seed: u64) {
                 continue;
             }
 
+            // the path finding heuristic counts one jump for each sector of distance, jumps must
+            // only connect neighbour sectors
+            let (pos_a, pos_b) = (
+                sectors_by_index[j.sector_a].1,
+                sectors_by_index[j.sector_b].1,
+            );
+            assert!(
+                (pos_a.x - pos_b.x).abs() <= 1 && (pos_a.y - pos_b.y).abs() <= 1,
+                "jump between sectors {:?} and {:?} that are not neighbours",
+                pos_a,
+                pos_b
+            );
+
             Loader::add_jump(
                 &mut commands,
                 sectors_by_index[j.sector_a].0,
@@ -136,10 +172,51 @@ pub fn generate_sectors(world: &mut World, size: (usize, usize), seed: u64) {
     world.run_system_once(sectors::system_update_sectors_index);
 }
 
+fn add_restricted_wares_to_sectors(
+    world: &mut World,
+    seed: u64,
+    restricted: &conf::RestrictedWares,
+) {
+    let mut rng: StdRng = SeedableRng::seed_from_u64(seed);
+    let sectors_id = world.run_system_once(sectors::list);
+    let wares = world.run_system_once(Wares::list_wares_by_code);
+    let selectors = loader::into_ware_selectors(&wares, &restricted.wares);
+
+    for sector_id in sectors_id {
+        if rng.gen::<f32>() >= restricted.sector_prob {
+            continue;
+        }
+
+        log::debug!("sector {:?} restrict wares {:?}", sector_id, selectors);
+        world
+            .entity_mut(sector_id)
+            .insert(RestrictedWares::new(selectors.clone()));
+    }
+}
+
+fn add_raiders_to_sectors(world: &mut World, seed: u64, raiders: &conf::Raiders) {
+    let mut rng: StdRng = SeedableRng::seed_from_u64(seed);
+    let sectors_id = world.run_system_once(sectors::list);
+
+    for sector_id in sectors_id {
+        if rng.gen::<f32>() >= raiders.sector_prob {
+            continue;
+        }
+
+        log::debug!("sector {:?} spawn raiders", sector_id);
+        let mut entity = world.entity_mut(sector_id);
+        entity.insert(RaiderSector::default());
+        if let Some(mut sector) = entity.get_mut::<Sector>() {
+            sector.travel_penalty = DeltaTime(raiders.travel_penalty);
+        }
+    }
+}
+
 fn add_bodies_to_sectors(
     world: &mut World,
     seed: u64,
     universe_cfg: &system_generator::UniverseCfg,
+    params: &conf::Params,
 ) {
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed);
     let total_time = *world.resource::<TotalTime>();
@@ -153,38 +230,54 @@ fn add_bodies_to_sectors(
         let mut new_bodies = vec![];
         for body in &system.bodies {
             let maybe_obj_id = match &body.desc {
-                system_generator::BodyDesc::Star { .. } => {
+                system_generator::BodyDesc::Star { kind } => {
+                    if let Some(production) = params
+                        .star_kinds_production
+                        .iter()
+                        .find(|i| &i.kind == kind)
+                    {
+                        world.entity_mut(sector_id).insert(ProductionBonus {
+                            rate: production.rate,
+                        });
+                    }
+
                     let new_obj = Loader::new_star(sector_id);
                     Some(Loader::add_object_from_world(world, &new_obj))
                 }
                 system_generator::BodyDesc::AsteroidField { resources } => {
-                    let maybe_ware_id = resources
-                        .iter()
-                        .flat_map(|body_resource| {
-                            let ware_id = wares.get(body_resource.resource.as_str());
-                            if ware_id.is_none() {
-                                log::warn!(
-                                    "could not find ware with name {:?} to be used as asteroid resources on {:?}",
-                                    body_resource.resource, body_resource
-                                );
-                            }
-                            ware_id
-                        })
-                        .next();
-
-                    if let Some(ware_id) = maybe_ware_id {
-                        let new_obj = Loader::new_asteroid(sector_id).extractable(Extractable {
-                            ware_id,
-                            accessibility: 1.0,
-                        });
+                    let maybe_extractable = Extractable::from_body_resources(
+                        &wares,
+                        resources,
+                        params.asteroid_reserve,
+                    );
+
+                    if let Some(extractable) = maybe_extractable {
+                        let new_obj = Loader::new_asteroid(sector_id).extractable(extractable);
                         Some(Loader::add_object_from_world(world, &new_obj))
                     } else {
                         log::warn!("fail to create asteroid field {:?}", body);
                         None
                     }
                 }
-                system_generator::BodyDesc::Planet(_) => {
-                    let new_obj = Loader::new_planet(sector_id);
+                system_generator::BodyDesc::Planet(planet) => {
+                    let mut new_obj = Loader::new_planet(sector_id);
+                    if let Some(colonies) = &params.colonies {
+                        let capacity = habitat::compute_planet_capacity(colonies, planet);
+                        if capacity >= habitat::POPULATION_UNIT {
+                            let consumption =
+                                loader::into_wareamount_list(&wares, &colonies.consumption);
+                            new_obj = new_obj
+                                .with_label("colony")
+                                .with_cargo_size(colonies.storage as Volume)
+                                .with_credits(DEFAULT_STATION_CREDITS)
+                                .with_docking()
+                                .with_habitat(Habitat::new(
+                                    capacity * colonies.population,
+                                    capacity,
+                                    consumption,
+                                ));
+                        }
+                    }
                     Some(Loader::add_object_from_world(world, &new_obj))
                 }
             };
@@ -290,20 +383,48 @@ fn add_stations_random(
     Orbits::update_orbits(world);
 }
 
+/// Add a mothership in the first sector, when factions are defined, each faction receive its own
+/// mothership in a different sector
 fn add_mothership(world: &mut World, seed: u64, params: &conf::Params) {
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed);
 
     // get first sector
-    let sector_id = world
+    let first_sector_id = world
         .run_system_once_with(V2I::new(0, 0), sectors::get_sector_by_coords)
         .expect("sector not found at coords");
 
-    // add shipyard
-    let new_obj = Loader::new_by_prefab_code(world, params.prefab_mothership.clone())
-        .expect("fail to create mothership")
-        .at_position(sector_id, V2::ZERO);
-    let obj_id = Loader::add_object_from_world(world, &new_obj);
-    _ = loader::set_orbit_random_body(world, obj_id, rng.next_u64());
+    let mut factions: Vec<Option<FactionId>> = world
+        .run_system_once(Factions::list_factions_by_code)
+        .into_values()
+        .map(Some)
+        .collect();
+    factions.sort();
+    if factions.is_empty() {
+        factions.push(None);
+    }
+
+    let mut free_sectors: Vec<SectorId> = world
+        .run_system_once(sectors::list)
+        .into_iter()
+        .filter(|id| *id != first_sector_id)
+        .collect();
+
+    for (i, faction_id) in factions.into_iter().enumerate() {
+        let sector_id = if i == 0 || free_sectors.is_empty() {
+            first_sector_id
+        } else {
+            free_sectors.remove(rng.gen_range(0..free_sectors.len()))
+        };
+
+        let mut new_obj = Loader::new_by_prefab_code(world, params.prefab_mothership.clone())
+            .expect("fail to create mothership")
+            .at_position(sector_id, V2::ZERO);
+        if let Some(faction_id) = faction_id {
+            new_obj = new_obj.with_owner(faction_id);
+        }
+        let obj_id = Loader::add_object_from_world(world, &new_obj);
+        _ = loader::set_orbit_random_body(world, obj_id, rng.next_u64());
+    }
 }
 
 fn add_stations_minimal(world: &mut World, seed: u64, params: &conf::Params) {
@@ -340,6 +461,7 @@ fn add_stations_minimal(world: &mut World, seed: u64, params: &conf::Params) {
 mod test {
     use super::*;
     use crate::game::game::NewGameParams;
+    use crate::game::prices::Credits;
 
     #[test]
     pub fn test_random_scenery() {
@@ -349,7 +471,10 @@ mod test {
 
         let path = "../data/game.conf";
         let file = std::fs::read_to_string(path).expect("fail to read config file");
-        let cfg = conf::load_str(&file).expect("fail to read config file");
+        let mut cfg = conf::load_str(&file).expect("fail to read config file");
+        let file = std::fs::read_to_string("../data/ship_components.conf")
+            .expect("fail to read ship components file");
+        conf::load_ship_components(&mut cfg, &file).expect("fail to read ship components file");
 
         let mut game = Game::empty();
         game.world
@@ -367,5 +492,11 @@ mod test {
         };
 
         load_random(&mut game, &rcfg);
+
+        // colonies are paid for the wares they receive
+        let mut query = game
+            .world
+            .query_filtered::<Option<&Credits>, With<Habitat>>();
+        assert!(query.iter(&game.world).all(|credits| credits.is_some()));
     }
 }
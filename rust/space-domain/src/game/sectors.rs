@@ -6,6 +6,8 @@ use commons::math::{IntoP2Ext, P2, P2I};
 use serde::{Deserialize, Serialize};
 use std::time::Instant;
 
+use crate::game::actions::ACTION_JUMP_TOTAL_TIME;
+use crate::game::discovery::FactionDiscovery;
 use crate::game::locations::LocationSpace;
 use crate::game::objects::ObjId;
 use crate::game::save::LoadingMapEntity;
@@ -43,6 +45,9 @@ impl LoadingMapEntity for JumpCache {
 pub struct Sector {
     pub coords: P2I,
     pub jumps_cache: Option<Vec<JumpCache>>,
+    /// extra time added to the path cost of routes crossing the sector
+    #[serde(default)]
+    pub travel_penalty: DeltaTime,
 }
 
 impl Sector {
@@ -50,6 +55,7 @@ impl Sector {
         Sector {
             coords,
             jumps_cache: None,
+            travel_penalty: DeltaTime::default(),
         }
     }
 }
@@ -110,19 +116,24 @@ pub fn find_path_from_world(
     from: SectorId,
     to: SectorId,
 ) -> Option<Vec<PathLeg>> {
-    world.run_system_once_with(
-        FindPathParams {
-            from,
-            to,
-            algorithm: 0,
-        },
-        find_path,
-    )
+    world.run_system_once_with(FindPathParams::new(from, to), find_path)
 }
 
+/// Speed used to convert distances into travel time when the ship speed is not provided
+pub const PATH_DEFAULT_SPEED: Speed = Speed(1.0);
+
+/// Path costs are integers in fractions of a second
+const PATH_COST_SCALE: f32 = 100.0;
+
 pub struct FindPathParams {
     pub from: SectorId,
     pub to: SectorId,
+    /// position at the from sector, without it the distance to the first jump is ignored
+    pub from_pos: Option<P2>,
+    /// position at the to sector, without it the distance from the last jump is ignored
+    pub to_pos: Option<P2>,
+    pub speed: Speed,
+    /// 0 for the travel time weighted search, others search by number of jumps
     pub algorithm: u8,
 }
 
@@ -131,9 +142,32 @@ impl FindPathParams {
         FindPathParams {
             from,
             to,
+            from_pos: None,
+            to_pos: None,
+            speed: PATH_DEFAULT_SPEED,
             algorithm: 0,
         }
     }
+
+    pub fn with_positions(mut self, from_pos: P2, to_pos: P2) -> Self {
+        self.from_pos = Some(from_pos);
+        self.to_pos = Some(to_pos);
+        self
+    }
+
+    pub fn with_speed(mut self, speed: Speed) -> Self {
+        self.speed = speed;
+        self
+    }
+}
+
+/// Node of the travel time search, the sector and the jump used to enter it. The arrived node
+/// include the travel to the target position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PathNode {
+    sector_id: SectorId,
+    entry_jump_id: Option<JumpId>,
+    arrived: bool,
 }
 
 pub fn find_path(
@@ -141,12 +175,15 @@ pub fn find_path(
     sectors: Query<&Sector>,
     jumps: Query<(&Jump, &LocationSpace)>,
 ) -> Option<Vec<PathLeg>> {
-    find_path_raw(&sectors, &jumps, params)
+    find_path_raw(&sectors, &jumps, None, params)
 }
 
+/// Find the sectors path, by default the one with the lower travel time. When a discovery is
+/// provided only explored sectors and discovered jumps are used
 pub fn find_path_raw(
     sectors: &Query<&Sector>,
     jumps: &Query<(&Jump, &LocationSpace)>,
+    discovery: Option<&FactionDiscovery>,
     params: FindPathParams,
 ) -> Option<Vec<PathLeg>> {
     use itertools::Itertools;
@@ -156,116 +193,107 @@ pub fn find_path_raw(
         return Some(vec![]);
     }
 
+    let is_known = |jump: &JumpCache| discovery.map(|d| d.is_known(jump)).unwrap_or(true);
+
     let mut count = 0;
     let to_coords = sectors.get(params.to).unwrap().coords.as_p2();
 
-    let path: Vec<SectorId> = {
-        if params.algorithm == 0 {
-            pathfinding::prelude::astar(
-                &params.from,
-                |current| {
-                    let sector = sectors.get(*current).unwrap();
-                    let jump_cache = sector.jumps_cache.as_ref();
-                    let successors: Vec<(SectorId, u32)> = jump_cache
-                        .expect("sector jump cache is empty")
-                        .iter()
-                        .map(|i| (i.to_sector, 1))
-                        .collect();
-                    count += 1;
-                    successors
-                },
-                |current| {
-                    let current_coords = sectors.get(*current).unwrap().coords.as_p2();
-                    (to_coords - current_coords).length_squared() as u32 / 1000u32
-                },
-                |current| *current == params.to,
-            )
-            .map(|(path, _cost)| path)?
-        } else if params.algorithm == 1 {
-            pathfinding::prelude::astar(
-                &params.from,
-                |current| {
-                    let sector = sectors.get(*current).unwrap();
-                    let jump_cache = sector.jumps_cache.as_ref();
-                    let successors: Vec<(SectorId, u32)> = jump_cache
-                        .expect("sector jump cache is empty")
-                        .iter()
-                        .map(|i| (i.to_sector, 1))
-                        .collect();
-                    count += 1;
-                    successors
-                },
-                |current| {
-                    let current_coords = sectors.get(*current).unwrap().coords;
-                    (pathfinding::prelude::absdiff(current_coords.x as f32, to_coords.x)
-                        + pathfinding::prelude::absdiff(current_coords.y as f32, to_coords.y))
-                        as u32
-                },
-                |current| *current == params.to,
-            )
-            .map(|(path, _cost)| path)?
-        } else if params.algorithm == 3 {
-            pathfinding::prelude::astar(
-                &params.from,
-                |current| {
-                    let sector = sectors.get(*current).unwrap();
-                    let jump_cache = sector.jumps_cache.as_ref();
-                    let successors: Vec<(SectorId, u32)> = jump_cache
-                        .expect("sector jump cache is empty")
-                        .iter()
-                        .map(|i| (i.to_sector, 1))
-                        .collect();
-                    count += 1;
-                    successors
-                },
-                |current| {
-                    let current_coords = sectors.get(*current).unwrap().coords;
-                    (pathfinding::prelude::absdiff(current_coords.x as f32, to_coords.x)
-                        + pathfinding::prelude::absdiff(current_coords.y as f32, to_coords.y))
-                        as u32
-                },
-                |current| *current == params.to,
-            )
-            .map(|(path, _cost)| path)?
-        } else {
-            pathfinding::prelude::bfs(
-                &params.from,
-                |current| {
-                    let sector = sectors.get(*current).unwrap();
-                    let jump_cache = sector.jumps_cache.as_ref();
-                    let successors: Vec<SectorId> = jump_cache
-                        .expect("sector jump cache is empty")
-                        .iter()
-                        .map(|i| i.to_sector)
-                        .collect();
-                    count += 1;
-                    successors
-                },
-                |current| *current == params.to,
-            )?
-        }
+    let jump_path: Vec<JumpId> = if params.algorithm == 0 {
+        find_path_by_travel_time(sectors, jumps, &is_known, &params, &mut count)?
+    } else {
+        let path: Vec<SectorId> = {
+            if params.algorithm == 1 {
+                pathfinding::prelude::astar(
+                    &params.from,
+                    |current| {
+                        let sector = sectors.get(*current).unwrap();
+                        let jump_cache = sector.jumps_cache.as_ref();
+                        let successors: Vec<(SectorId, u32)> = jump_cache
+                            .expect("sector jump cache is empty")
+                            .iter()
+                            .filter(|i| is_known(i))
+                            .map(|i| (i.to_sector, 1))
+                            .collect();
+                        count += 1;
+                        successors
+                    },
+                    |current| {
+                        let current_coords = sectors.get(*current).unwrap().coords;
+                        (pathfinding::prelude::absdiff(current_coords.x as f32, to_coords.x)
+                            + pathfinding::prelude::absdiff(current_coords.y as f32, to_coords.y))
+                            as u32
+                    },
+                    |current| *current == params.to,
+                )
+                .map(|(path, _cost)| path)?
+            } else if params.algorithm == 3 {
+                pathfinding::prelude::astar(
+                    &params.from,
+                    |current| {
+                        let sector = sectors.get(*current).unwrap();
+                        let jump_cache = sector.jumps_cache.as_ref();
+                        let successors: Vec<(SectorId, u32)> = jump_cache
+                            .expect("sector jump cache is empty")
+                            .iter()
+                            .filter(|i| is_known(i))
+                            .map(|i| (i.to_sector, 1))
+                            .collect();
+                        count += 1;
+                        successors
+                    },
+                    |current| {
+                        let current_coords = sectors.get(*current).unwrap().coords;
+                        (pathfinding::prelude::absdiff(current_coords.x as f32, to_coords.x)
+                            + pathfinding::prelude::absdiff(current_coords.y as f32, to_coords.y))
+                            as u32
+                    },
+                    |current| *current == params.to,
+                )
+                .map(|(path, _cost)| path)?
+            } else {
+                pathfinding::prelude::bfs(
+                    &params.from,
+                    |current| {
+                        let sector = sectors.get(*current).unwrap();
+                        let jump_cache = sector.jumps_cache.as_ref();
+                        let successors: Vec<SectorId> = jump_cache
+                            .expect("sector jump cache is empty")
+                            .iter()
+                            .filter(|i| is_known(i))
+                            .map(|i| i.to_sector)
+                            .collect();
+                        count += 1;
+                        successors
+                    },
+                    |current| *current == params.to,
+                )?
+            }
+        };
+
+        path.into_iter()
+            .tuple_windows()
+            .map(|(from, to)| {
+                sectors
+                    .get(from)
+                    .unwrap()
+                    .jumps_cache
+                    .as_ref()
+                    .and_then(|i| i.iter().find(|j| j.to_sector == to && is_known(j)))
+                    .unwrap()
+                    .jump_id
+            })
+            .collect()
     };
 
     let mut result = vec![];
-    for (from, to) in path.into_iter().tuple_windows() {
-        let sector = sectors.get(from).unwrap();
-
-        let jc = sector
-            .jumps_cache
-            .as_ref()
-            .and_then(|i| i.iter().find(|j| j.to_sector == to))
-            .unwrap();
-
-        let (jump, location) = jumps.get(jc.jump_id).expect("jump_id not found");
-        let jump_pos = location.pos;
-        let jump_target_pos = jump.target_pos;
-
+    for jump_id in jump_path {
+        let (jump, location) = jumps.get(jump_id).expect("jump_id not found");
         result.push(PathLeg {
-            sector_id: from,
-            jump_id: jc.jump_id,
-            jump_pos: jump_pos,
-            target_sector_id: to,
-            target_pos: jump_target_pos,
+            sector_id: location.sector_id,
+            jump_id,
+            jump_pos: location.pos,
+            target_sector_id: jump.target_sector_id,
+            target_pos: jump.target_pos,
         });
     }
 
@@ -287,6 +315,105 @@ pub fn find_path_raw(
     Some(result)
 }
 
+/// Search the jumps of the fastest path, the cost of each jump is the time to move from the sector
+/// entry point to the jump, the jump time and the penalty of the sector it leads to
+fn find_path_by_travel_time(
+    sectors: &Query<&Sector>,
+    jumps: &Query<(&Jump, &LocationSpace)>,
+    is_known: &impl Fn(&JumpCache) -> bool,
+    params: &FindPathParams,
+    count: &mut u32,
+) -> Option<Vec<JumpId>> {
+    let speed = params.speed.as_f32().max(0.001);
+    let to_cost = |time: f32| (time * PATH_COST_SCALE).round() as u32;
+    let entry_pos = |node: &PathNode| match node.entry_jump_id {
+        Some(jump_id) => jumps.get(jump_id).ok().map(|(jump, _)| jump.target_pos),
+        None => params.from_pos,
+    };
+    let travel_time = |from: Option<P2>, to: Option<P2>| match (from, to) {
+        (Some(from), Some(to)) => from.distance(to) / speed,
+        _ => 0.0,
+    };
+
+    let start = PathNode {
+        sector_id: params.from,
+        entry_jump_id: None,
+        arrived: false,
+    };
+
+    // galaxy jumps connect neighbour sectors, so each sector of distance requires at least a jump
+    let to_coords = sectors.get(params.to).unwrap().coords;
+    let heuristic = |current: &PathNode| {
+        let coords = sectors.get(current.sector_id).unwrap().coords;
+        let jumps_required = (coords.x - to_coords.x)
+            .abs()
+            .max((coords.y - to_coords.y).abs());
+        to_cost(jumps_required as f32 * ACTION_JUMP_TOTAL_TIME.as_f32())
+    };
+
+    let (path, _cost) = pathfinding::prelude::astar(
+        &start,
+        |current| {
+            *count += 1;
+
+            let mut successors: Vec<(PathNode, u32)> = vec![];
+            if current.arrived {
+                return successors;
+            }
+
+            let pos = entry_pos(current);
+            if current.sector_id == params.to {
+                successors.push((
+                    PathNode {
+                        arrived: true,
+                        ..*current
+                    },
+                    to_cost(travel_time(pos, params.to_pos)),
+                ));
+                return successors;
+            }
+
+            let sector = sectors.get(current.sector_id).unwrap();
+            for jc in sector
+                .jumps_cache
+                .as_ref()
+                .expect("sector jump cache is empty")
+                .iter()
+                .filter(|i| is_known(i))
+            {
+                let Ok((_, location)) = jumps.get(jc.jump_id) else {
+                    continue;
+                };
+                let penalty = sectors
+                    .get(jc.to_sector)
+                    .map(|i| i.travel_penalty.as_f32())
+                    .unwrap_or(0.0);
+                let time = travel_time(pos, Some(location.pos))
+                    + ACTION_JUMP_TOTAL_TIME.as_f32()
+                    + penalty;
+                successors.push((
+                    PathNode {
+                        sector_id: jc.to_sector,
+                        entry_jump_id: Some(jc.jump_id),
+                        arrived: false,
+                    },
+                    to_cost(time),
+                ));
+            }
+            successors
+        },
+        heuristic,
+        |current| current.arrived,
+    )?;
+
+    Some(
+        path.into_iter()
+            .filter(|node| !node.arrived)
+            .filter_map(|node| node.entry_jump_id)
+            .collect(),
+    )
+}
+
 pub fn get_sector_by_coords(input: In<P2I>, query: Query<(Entity, &Sector)>) -> Option<Entity> {
     query.iter().find_map(|(id, sector)| {
         if sector.coords.eq(&input.0) {
@@ -305,12 +432,17 @@ pub fn list(query: Query<(Entity, With<Sector>)>) -> Vec<Entity> {
 mod test {
     use super::test_scenery::setup_sector_scenery;
 
-    use crate::game::sectors::{system_update_sectors_index, FindPathParams, PathLeg, SectorId};
+    use crate::game::discovery::FactionDiscovery;
+    use crate::game::locations::LocationSpace;
+    use crate::game::sectors::{
+        find_path_raw, system_update_sectors_index, FindPathParams, Jump, PathLeg, Sector, SectorId,
+    };
 
     use bevy_ecs::prelude::*;
 
     use crate::game::events::GEvents;
 
+    use crate::game::utils::{DeltaTime, Speed, V2};
     use bevy_ecs::system::RunSystemOnce;
     use commons::math::P2I;
     use std::time::Instant;
@@ -375,9 +507,8 @@ mod test {
             let start = Instant::now();
             world.run_system_once_with(
                 FindPathParams {
-                    from: from,
-                    to: to,
                     algorithm: alg,
+                    ..FindPathParams::new(from, to)
                 },
                 super::find_path,
             );
@@ -400,6 +531,104 @@ mod test {
         // );
     }
 
+    #[test]
+    fn test_find_path_should_only_use_discovered_sectors_and_jumps() {
+        let mut world = World::new();
+        let sector_scenery = setup_sector_scenery(&mut world);
+
+        let mut discovery = FactionDiscovery::default();
+        discovery.sectors.insert(sector_scenery.sector_0);
+        discovery.sectors.insert(sector_scenery.sector_1);
+        discovery.jumps.insert(sector_scenery.jump_0_to_1);
+
+        let find = |world: &mut World, discovery: FactionDiscovery, to: SectorId| {
+            world.run_system_once_with(
+                (discovery, FindPathParams::new(sector_scenery.sector_0, to)),
+                |In((discovery, params)): In<(FactionDiscovery, FindPathParams)>,
+                 sectors: Query<&Sector>,
+                 jumps: Query<(&Jump, &LocationSpace)>| {
+                    find_path_raw(&sectors, &jumps, Some(&discovery), params)
+                },
+            )
+        };
+
+        let path = find(&mut world, discovery.clone(), sector_scenery.sector_1).unwrap();
+        assert_eq!(1, path.len());
+
+        // jump discovered but sector not explored
+        discovery.jumps.insert(sector_scenery.jump_1_to_2);
+        assert!(find(&mut world, discovery.clone(), sector_scenery.sector_2).is_none());
+
+        // sector explored but jump not discovered
+        discovery.jumps.remove(&sector_scenery.jump_1_to_2);
+        discovery.sectors.insert(sector_scenery.sector_2);
+        assert!(find(&mut world, discovery.clone(), sector_scenery.sector_2).is_none());
+
+        discovery.jumps.insert(sector_scenery.jump_1_to_2);
+        let path = find(&mut world, discovery, sector_scenery.sector_2).unwrap();
+        assert_eq!(2, path.len());
+    }
+
+    #[test]
+    fn test_find_path_should_prefer_lower_travel_time_over_number_of_jumps() {
+        let mut world = World::new();
+        let sector_a = world.spawn(Sector::new(P2I::new(0, 0))).id();
+        let sector_b = world.spawn(Sector::new(P2I::new(0, 1))).id();
+        let sector_c = world.spawn(Sector::new(P2I::new(1, 0))).id();
+
+        let mut add_jump = |from: SectorId, pos: V2, to: SectorId, target_pos: V2| {
+            world
+                .spawn((
+                    Jump {
+                        target_sector_id: to,
+                        target_pos,
+                    },
+                    LocationSpace {
+                        pos,
+                        sector_id: from,
+                    },
+                ))
+                .id()
+        };
+        // the direct jump is far away, the indirect one has gates near each other
+        let jump_a_c = add_jump(sector_a, V2::new(100.0, 0.0), sector_c, V2::ZERO);
+        let jump_a_b = add_jump(sector_a, V2::new(1.0, 0.0), sector_b, V2::ZERO);
+        let jump_b_c = add_jump(sector_b, V2::new(1.0, 0.0), sector_c, V2::ZERO);
+        world.run_system_once(system_update_sectors_index);
+
+        let params =
+            || FindPathParams::new(sector_a, sector_c).with_positions(V2::ZERO, V2::new(1.0, 0.0));
+
+        let path = world
+            .run_system_once_with(params(), super::find_path)
+            .unwrap();
+        assert_eq!(
+            vec![jump_a_b, jump_b_c],
+            path.iter().map(|i| i.jump_id).collect::<Vec<_>>()
+        );
+        assert_eq!(sector_b, path[0].target_sector_id);
+        assert_eq!(sector_c, path[1].target_sector_id);
+
+        // faster ships care less about the distance than the jump time
+        let path = world
+            .run_system_once_with(params().with_speed(Speed(100.0)), super::find_path)
+            .unwrap();
+        assert_eq!(
+            vec![jump_a_c],
+            path.iter().map(|i| i.jump_id).collect::<Vec<_>>()
+        );
+
+        // penalty of crossing the sector
+        world.get_mut::<Sector>(sector_b).unwrap().travel_penalty = DeltaTime(200.0);
+        let path = world
+            .run_system_once_with(params(), super::find_path)
+            .unwrap();
+        assert_eq!(
+            vec![jump_a_c],
+            path.iter().map(|i| i.jump_id).collect::<Vec<_>>()
+        );
+    }
+
     fn do_find_path(world: &mut World, from: SectorId, to: SectorId) -> Option<Vec<PathLeg>> {
         world.run_system_once_with(FindPathParams::new(from, to), super::find_path)
     }
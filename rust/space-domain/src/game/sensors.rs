@@ -0,0 +1,320 @@
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::game::factions::{Faction, FactionId, Owner};
+use crate::game::locations::{LocationDocked, LocationSpace, Locations};
+use crate::game::objects::ObjId;
+use crate::game::save::LoadingMapEntity;
+use crate::game::sectors::SectorId;
+use crate::game::utils::{TotalTime, V2};
+
+/// Signature of objects without one
+pub const DEFAULT_SIGNATURE: f32 = 1.0;
+
+/// Detect objects in the same sector up to the range, scaled by the strength and the object
+/// signature
+#[derive(Debug, Clone, Copy, Component, Serialize, Deserialize)]
+pub struct Sensor {
+    pub range: f32,
+    pub strength: f32,
+}
+
+impl Sensor {
+    pub fn detection_range(&self, signature: f32) -> f32 {
+        self.range * self.strength * signature
+    }
+}
+
+/// How easy an object is detected by sensors, objects without it use the DEFAULT_SIGNATURE
+#[derive(Debug, Clone, Copy, Component, Serialize, Deserialize)]
+pub struct Signature {
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visible {
+    /// currently detected or owned by the observer
+    Seen,
+    /// seen before, only the last sighting is known
+    Remembered,
+    Unknown,
+}
+
+/// Last known location of an object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sighting {
+    pub location: LocationSpace,
+    pub time: TotalTime,
+}
+
+/// What an observer group currently sees and what it remembers from previous sightings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObserverVisibility {
+    pub seen: HashSet<ObjId>,
+    pub sightings: HashMap<ObjId, Sighting>,
+}
+
+impl ObserverVisibility {
+    pub fn get(&self, obj_id: ObjId) -> Visible {
+        if self.seen.contains(&obj_id) {
+            Visible::Seen
+        } else if self.sightings.contains_key(&obj_id) {
+            Visible::Remembered
+        } else {
+            Visible::Unknown
+        }
+    }
+
+    pub fn get_sighting(&self, obj_id: ObjId) -> Option<&Sighting> {
+        self.sightings.get(&obj_id)
+    }
+
+    /// Objects last sighted at the sector, remembered ones are only included when requested
+    pub fn list_at_sector(&self, sector_id: SectorId, include_remembered: bool) -> Vec<ObjId> {
+        self.sightings
+            .iter()
+            .filter(|(id, sighting)| {
+                sighting.location.sector_id == sector_id
+                    && (include_remembered || self.seen.contains(id))
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+/// Visibility of each faction, computed from the sensors of the objects it owns
+#[derive(Debug, Clone, Default, Resource, Serialize, Deserialize)]
+pub struct FactionsVisibility {
+    pub factions: HashMap<FactionId, ObserverVisibility>,
+}
+
+impl FactionsVisibility {
+    pub fn get(&self, faction_id: FactionId) -> Option<&ObserverVisibility> {
+        self.factions.get(&faction_id)
+    }
+
+    pub fn get_visible(&self, faction_id: FactionId, obj_id: ObjId) -> Visible {
+        self.get(faction_id)
+            .map(|i| i.get(obj_id))
+            .unwrap_or(Visible::Unknown)
+    }
+}
+
+impl LoadingMapEntity for FactionsVisibility {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        // removed objects can not be mapped and are forgotten
+        let factions = std::mem::take(&mut self.factions);
+        for (mut faction_id, visibility) in factions {
+            if !entity_map.contains_key(&faction_id) {
+                continue;
+            }
+            faction_id.map_entity(entity_map);
+
+            let mut new_visibility = ObserverVisibility::default();
+            for (mut obj_id, mut sighting) in visibility.sightings {
+                if !entity_map.contains_key(&obj_id)
+                    || !entity_map.contains_key(&sighting.location.sector_id)
+                {
+                    continue;
+                }
+                let is_seen = visibility.seen.contains(&obj_id);
+                obj_id.map_entity(entity_map);
+                sighting.location.map_entity(entity_map);
+                if is_seen {
+                    new_visibility.seen.insert(obj_id);
+                }
+                new_visibility.sightings.insert(obj_id, sighting);
+            }
+            self.factions.insert(faction_id, new_visibility);
+        }
+    }
+}
+
+/// Compute for each faction the objects detected by its sensors. Owned objects are always seen,
+/// objects out of detection keep the last sighting. Removed objects are forgotten when a sensor
+/// covers their last sighting.
+pub fn system_visibility(
+    total_time: Res<TotalTime>,
+    mut visibility: ResMut<FactionsVisibility>,
+    query_factions: Query<Entity, With<Faction>>,
+    query_sensors: Query<(Entity, &Sensor, &Owner)>,
+    query_objects: Query<
+        (Entity, Option<&Signature>, Option<&Owner>),
+        Or<(With<LocationSpace>, With<LocationDocked>)>,
+    >,
+    query_locations: Query<(Entity, Option<&LocationSpace>, Option<&LocationDocked>)>,
+) {
+    log::trace!("running");
+
+    let total_time = *total_time;
+
+    // sensors by faction and sector
+    let mut sensors: HashMap<(FactionId, SectorId), Vec<(V2, Sensor)>> = HashMap::new();
+    for (obj_id, sensor, owner) in &query_sensors {
+        if let Some(location) = Locations::resolve_space_position(&query_locations, obj_id) {
+            sensors
+                .entry((owner.faction_id, location.sector_id))
+                .or_default()
+                .push((location.pos, *sensor));
+        }
+    }
+
+    // objects by sector and by owner
+    let mut objects_per_sector: HashMap<SectorId, Vec<(ObjId, V2, f32)>> = HashMap::new();
+    let mut objects_per_owner: HashMap<FactionId, Vec<(ObjId, LocationSpace)>> = HashMap::new();
+    for (obj_id, signature, owner) in &query_objects {
+        let Some(location) = Locations::resolve_space_position(&query_locations, obj_id) else {
+            continue;
+        };
+        let signature = signature.map(|i| i.value).unwrap_or(DEFAULT_SIGNATURE);
+        objects_per_sector
+            .entry(location.sector_id)
+            .or_default()
+            .push((obj_id, location.pos, signature));
+        if let Some(owner) = owner {
+            objects_per_owner
+                .entry(owner.faction_id)
+                .or_default()
+                .push((obj_id, location));
+        }
+    }
+
+    for faction_id in &query_factions {
+        let faction_visibility = visibility.factions.entry(faction_id).or_default();
+        faction_visibility.seen.clear();
+
+        let mut seen = |obj_id: ObjId, location: LocationSpace| {
+            faction_visibility.seen.insert(obj_id);
+            faction_visibility.sightings.insert(
+                obj_id,
+                Sighting {
+                    location,
+                    time: total_time,
+                },
+            );
+        };
+
+        for (obj_id, location) in objects_per_owner.get(&faction_id).into_iter().flatten() {
+            seen(*obj_id, location.clone());
+        }
+
+        for ((sensor_faction_id, sector_id), list) in &sensors {
+            if *sensor_faction_id != faction_id {
+                continue;
+            }
+
+            for (obj_id, pos, signature) in objects_per_sector.get(sector_id).into_iter().flatten()
+            {
+                let detected = list.iter().any(|(sensor_pos, sensor)| {
+                    sensor_pos.distance(*pos) <= sensor.detection_range(*signature)
+                });
+                if detected {
+                    seen(
+                        *obj_id,
+                        LocationSpace {
+                            pos: *pos,
+                            sector_id: *sector_id,
+                        },
+                    );
+                }
+            }
+        }
+
+        // forget removed objects once their last sighting is in range of a sensor
+        faction_visibility.sightings.retain(|obj_id, sighting| {
+            query_objects.contains(*obj_id)
+                || !sensors
+                    .get(&(faction_id, sighting.location.sector_id))
+                    .into_iter()
+                    .flatten()
+                    .any(|(sensor_pos, sensor)| {
+                        sensor_pos.distance(sighting.location.pos)
+                            <= sensor.detection_range(DEFAULT_SIGNATURE)
+                    })
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_visibility_should_see_detected_and_remember_last_sighting() {
+        let mut world = World::new();
+        world.insert_resource(TotalTime(0.0));
+        world.insert_resource(FactionsVisibility::default());
+
+        let faction_id = world.spawn(Faction::default()).id();
+        let sector_id = world.spawn_empty().id();
+        let at = |x: f32| LocationSpace {
+            pos: V2::new(x, 0.0),
+            sector_id,
+        };
+
+        let observer_id = world
+            .spawn((
+                at(0.0),
+                Owner::new(faction_id),
+                Sensor {
+                    range: 10.0,
+                    strength: 1.0,
+                },
+            ))
+            .id();
+        let near_id = world.spawn(at(5.0)).id();
+        let far_id = world.spawn(at(20.0)).id();
+        let stealth_id = world.spawn((at(5.0), Signature { value: 0.2 })).id();
+        let bright_id = world.spawn((at(20.0), Signature { value: 3.0 })).id();
+
+        world.run_system_once(system_visibility);
+
+        let visibility = world.resource::<FactionsVisibility>();
+        assert_eq!(
+            Visible::Seen,
+            visibility.get_visible(faction_id, observer_id)
+        );
+        assert_eq!(Visible::Seen, visibility.get_visible(faction_id, near_id));
+        assert_eq!(Visible::Unknown, visibility.get_visible(faction_id, far_id));
+        assert_eq!(
+            Visible::Unknown,
+            visibility.get_visible(faction_id, stealth_id)
+        );
+        assert_eq!(Visible::Seen, visibility.get_visible(faction_id, bright_id));
+
+        // move out of range
+        world.insert_resource(TotalTime(1.0));
+        world.entity_mut(near_id).insert(at(15.0));
+        world.entity_mut(bright_id).insert(at(1000.0));
+        world.run_system_once(system_visibility);
+
+        let visibility = world.resource::<FactionsVisibility>();
+        assert_eq!(
+            Visible::Remembered,
+            visibility.get_visible(faction_id, near_id)
+        );
+        let sighting = visibility
+            .get(faction_id)
+            .unwrap()
+            .get_sighting(near_id)
+            .unwrap();
+        assert_eq!(5.0, sighting.location.pos.x);
+        assert_eq!(0.0, sighting.time.as_f64());
+
+        // removed objects are forgotten only when their last sighting is in sensor range
+        world.despawn(near_id);
+        world.despawn(bright_id);
+        world.run_system_once(system_visibility);
+        let visibility = world.resource::<FactionsVisibility>();
+        assert_eq!(
+            Visible::Unknown,
+            visibility.get_visible(faction_id, near_id)
+        );
+        assert_eq!(
+            Visible::Remembered,
+            visibility.get_visible(faction_id, bright_id)
+        );
+    }
+}
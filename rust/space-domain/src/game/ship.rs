@@ -1,5 +1,6 @@
 pub mod damages;
 pub mod ship_combat;
+pub mod ship_combat_system;
 pub mod ship_internals;
 
 #[cfg(test)]
@@ -8,6 +9,7 @@ mod test {
     use crate::game::ship::ship_internals::{
         Component, ComponentId, Components, ShipComponentsConfig,
     };
+    use crate::game::{conf, loader};
     use commons::hocon;
 
     #[test]
@@ -18,11 +20,50 @@ mod test {
         println!("{:?}", components);
     }
 
+    #[test]
+    fn test_game_ship_designs_should_be_valid() {
+        let path = crate::game::data::get_file("game.conf").unwrap();
+        let mut cfg = conf::load_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let path = crate::game::data::get_file("ship_components.conf").unwrap();
+        conf::load_ship_components(&mut cfg, &std::fs::read_to_string(path).unwrap()).unwrap();
+
+        let components = Components::from_list(&cfg.prefabs.ship_components);
+        for design in &cfg.prefabs.ship_designs {
+            let spec = loader::into_ship_spec(&components, design);
+            assert!(spec.stats.speed.0 > 0.0);
+            assert!(spec.stats.cargo > 0);
+            assert!(spec.production_cost(&components).0 > 0.0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid ship design")]
+    fn test_ship_design_without_bridge_should_fail() {
+        let mut engine = Component::new(ComponentId(0));
+        engine.name = "engine".to_string();
+        engine.thrust = 1.0;
+        engine.weight = 1;
+        let components = Components::from_list(&[engine]);
+
+        loader::into_ship_spec(
+            &components,
+            &conf::ShipDesign {
+                code: "no_bridge".to_string(),
+                components: vec![conf::ShipDesignComponent {
+                    component: "engine".to_string(),
+                    amount: 1,
+                }],
+                armor: 0,
+            },
+        );
+    }
+
     #[test]
     fn test_ship_creation() {
         let mut components = Components::new();
         components.add(Component {
             id: ComponentId(0),
+            name: "engine".to_string(),
             weapon: None,
             thrust: 0.0,
             weight: 0,
@@ -33,6 +74,9 @@ mod test {
             size: 0,
             fuel_hold: 0.0,
             bridge_power: 0.0,
+            cargo: 0,
+            work: 0.0,
+            cost: vec![],
         });
 
         // create a ship specs
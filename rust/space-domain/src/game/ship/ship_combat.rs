@@ -251,11 +251,12 @@ impl Combat {
     ) -> Option<ShipInstanceId> {
         let team_id = ctx.ships.get(&attacker_id).unwrap().team_id;
 
+        // ships without distance are out of range
         let mut candidates = ctx
             .ships
             .iter()
-            .filter(|(_id, other)| other.team_id != team_id)
-            .map(|(id, _other)| (*id, ctx.distances.get(&(attacker_id, *id)).unwrap()))
+            .filter(|(_id, other)| other.team_id != team_id && !other.wreck)
+            .flat_map(|(id, _other)| Some((*id, ctx.distances.get(&(attacker_id, *id))?)))
             .collect::<Vec<_>>();
 
         candidates.sort_unstable_by(|a, b| a.1.partial_cmp(b.1).unwrap());
@@ -0,0 +1,324 @@
+use bevy_ecs::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::ship_combat::{Combat, CombatContext, CombatLog};
+use super::ship_internals::{Components, ShipInstance, ShipInstanceId, TeamId};
+use crate::game::events::{CommandSendEvent, EventKind, GEvent};
+use crate::game::factions::{Faction, Factions, Owner};
+use crate::game::locations::{LocationSpace, Moveable};
+use crate::game::objects::ObjId;
+use crate::game::sectors::SectorId;
+use crate::game::utils::{DeltaTime, Speed, TotalTime};
+use crate::game::wrecks::CommandDestroyObj;
+
+/// Max distance between two ships to engage in combat
+pub const COMBAT_RANGE: f32 = 2.0;
+
+///
+/// Ships of hostile factions in same sector and within range engage in combat. Each sector with
+/// opposing ships is a single encounter running the ship combat over the ShipInstance components.
+///
+/// Ships without owner or not in space never fight. Destroyed ships are removed from the game,
+/// leaving a wreck when wrecks are enabled.
+///
+pub fn system_ship_combat(
+    mut commands: Commands,
+    delta_time: Res<DeltaTime>,
+    total_time: Res<TotalTime>,
+    components: Option<Res<Components>>,
+    query_factions: Query<&Faction>,
+    mut query: Query<(
+        Entity,
+        &mut ShipInstance,
+        &LocationSpace,
+        Option<&Owner>,
+        Option<&mut Moveable>,
+    )>,
+) {
+    log::trace!("running");
+
+    let components = match components {
+        Some(components) => components,
+        None => return,
+    };
+
+    // collect ships that can fight by sector
+    let mut per_sector: HashMap<SectorId, Vec<(ObjId, LocationSpace, Owner)>> = HashMap::new();
+    for (obj_id, ship, location, maybe_owner, _) in &query {
+        match maybe_owner {
+            Some(owner) if !ship.wreck => per_sector.entry(location.sector_id).or_default().push((
+                obj_id,
+                location.clone(),
+                *owner,
+            )),
+            _ => {}
+        }
+    }
+
+    // find opposing ships within range
+    let mut encounters: Vec<Vec<(ObjId, ObjId, f32)>> = vec![];
+    for ships in per_sector.values() {
+        let mut in_range = vec![];
+        for (i, (id_0, location_0, owner_0)) in ships.iter().enumerate() {
+            for (id_1, location_1, owner_1) in ships.iter().skip(i + 1) {
+                if !Factions::is_hostile(&query_factions, owner_0.faction_id, owner_1.faction_id) {
+                    continue;
+                }
+
+                let distance = location_0.pos.distance(location_1.pos);
+                if distance <= COMBAT_RANGE {
+                    in_range.push((*id_0, *id_1, distance));
+                }
+            }
+        }
+
+        if !in_range.is_empty() {
+            encounters.push(in_range);
+        }
+    }
+
+    if encounters.is_empty() {
+        return;
+    }
+
+    let participants: HashSet<ObjId> = encounters
+        .iter()
+        .flatten()
+        .flat_map(|(id_0, id_1, _)| [*id_0, *id_1])
+        .collect();
+
+    let mut ships: HashMap<ObjId, Mut<ShipInstance>> = HashMap::new();
+    let mut moveables: HashMap<ObjId, (Speed, Mut<Moveable>)> = HashMap::new();
+    let mut teams: HashMap<ObjId, TeamId> = HashMap::new();
+    for (obj_id, ship, _, maybe_owner, maybe_moveable) in &mut query {
+        if participants.contains(&obj_id) {
+            teams.insert(obj_id, TeamId(maybe_owner.unwrap().faction_id.index()));
+            if let Some(moveable) = maybe_moveable {
+                moveables.insert(obj_id, (ship.current_stats.speed, moveable));
+            }
+            ships.insert(obj_id, ship);
+        }
+    }
+
+    let mut logs = vec![];
+    let mut instances: HashMap<ShipInstanceId, ObjId> = HashMap::new();
+
+    for encounter in encounters {
+        let encounter_ids: HashSet<ObjId> = encounter
+            .iter()
+            .flat_map(|(id_0, id_1, _)| [*id_0, *id_1])
+            .collect();
+
+        let mut ctx = CombatContext::new(&components);
+        ctx.set_time(delta_time.as_f32(), total_time.as_f64() as f32);
+
+        for (obj_id, ship) in ships.iter_mut() {
+            if !encounter_ids.contains(obj_id) {
+                continue;
+            }
+
+            let instance_id = ShipInstanceId(obj_id.index());
+            instances.insert(instance_id, *obj_id);
+
+            let ship: &mut ShipInstance = ship;
+            ship.id = instance_id;
+            ship.team_id = teams[obj_id];
+            ctx.add_ship(ship);
+        }
+
+        for (id_0, id_1, distance) in encounter {
+            ctx.set_distance(
+                ShipInstanceId(id_0.index()),
+                ShipInstanceId(id_1.index()),
+                distance,
+            );
+        }
+
+        Combat::execute(&mut ctx, &mut logs);
+    }
+
+    // damaged components reduce the ship speed
+    for (obj_id, (previous_speed, moveable)) in moveables.iter_mut() {
+        let speed = ships[obj_id].current_stats.speed;
+        if speed.0 != previous_speed.0 {
+            moveable.speed = speed;
+        }
+    }
+
+    // emit events and stop destroyed ships
+    let mut hits: HashSet<ObjId> = HashSet::new();
+    for log in logs {
+        match log {
+            CombatLog::Hit { target_id, .. } => {
+                let obj_id = instances[&target_id];
+                if hits.insert(obj_id) {
+                    commands.add(CommandSendEvent::from(GEvent::new(obj_id, EventKind::Hit)));
+                }
+            }
+            CombatLog::ShipDestroyed { id } => {
+                let obj_id = instances[&id];
+                log::info!("{:?} destroyed in combat", obj_id);
+
+                commands.add(CommandSendEvent::from(GEvent::new(
+                    obj_id,
+                    EventKind::Destroyed,
+                )));
+                commands.add(CommandDestroyObj {
+                    obj_id,
+                    leave_wreck: true,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::events::GEvents;
+    use crate::game::ship::ship_internals::{
+        Component, ComponentId, Damage, ShipSpec, Weapon, WeaponDamageType,
+    };
+    use crate::game::utils::V2;
+    use bevy_ecs::system::RunSystemOnce;
+
+    fn new_components() -> Components {
+        let mut components = Components::new();
+
+        let mut engine = Component::new(ComponentId(0));
+        engine.thrust = 10.0;
+        engine.weight = 1;
+        engine.size = 2;
+        components.add(engine);
+
+        let mut gun = Component::new(ComponentId(1));
+        gun.weight = 1;
+        gun.size = 1;
+        gun.weapon = Some(Weapon {
+            damage: Damage(2),
+            reload: 0.5,
+            rounds: 4,
+            damage_type: WeaponDamageType::Penetration,
+        });
+        components.add(gun);
+
+        components
+    }
+
+    fn new_ship(world: &mut World, sector_id: SectorId, pos: V2, faction_id: Entity) -> ObjId {
+        let components = world.resource::<Components>();
+        let spec = ShipSpec::new(
+            components,
+            [(ComponentId(0), 1), (ComponentId(1), 1)].into(),
+            1,
+        );
+        let ship = ShipInstance::new(components, ShipInstanceId(0), spec, TeamId(0));
+        world
+            .spawn((
+                ship,
+                LocationSpace { pos, sector_id },
+                Owner::new(faction_id),
+            ))
+            .id()
+    }
+
+    fn setup(distance: f32) -> (World, ObjId, ObjId) {
+        setup_with_hostility(distance, true)
+    }
+
+    fn setup_with_hostility(distance: f32, hostile: bool) -> (World, ObjId, ObjId) {
+        let mut world = World::new();
+        world.insert_resource(GEvents::default());
+        world.insert_resource(DeltaTime(1.0));
+        world.insert_resource(TotalTime(1.0));
+        world.insert_resource(new_components());
+
+        let sector_id = world.spawn_empty().id();
+        let faction_0 = world.spawn(Faction::default()).id();
+        let faction_1 = world
+            .spawn(Faction {
+                hostile: if hostile { vec![faction_0] } else { vec![] },
+                ..Default::default()
+            })
+            .id();
+
+        let ship_0 = new_ship(&mut world, sector_id, V2::ZERO, faction_0);
+        let ship_1 = new_ship(&mut world, sector_id, V2::new(distance, 0.0), faction_1);
+        (world, ship_0, ship_1)
+    }
+
+    #[test]
+    fn test_ship_combat_should_hit_opposing_ships_in_range() {
+        let (mut world, ship_0, ship_1) = setup(1.0);
+
+        for _ in 0..20 {
+            world.run_system_once(system_ship_combat);
+        }
+
+        let events = world.resource_mut::<GEvents>().take();
+        let hits: HashSet<ObjId> = events
+            .iter()
+            .filter(|e| matches!(e.kind, EventKind::Hit))
+            .map(|e| e.id)
+            .collect();
+        assert!(!hits.is_empty());
+        for obj_id in [ship_0, ship_1] {
+            // destroyed ships are removed
+            let damaged = world
+                .get::<ShipInstance>(obj_id)
+                .map(|ship| !ship.armor_damage.is_empty())
+                .unwrap_or(true);
+            assert_eq!(hits.contains(&obj_id), damaged);
+        }
+    }
+
+    #[test]
+    fn test_ship_combat_should_remove_destroyed_ships() {
+        let (mut world, ship_0, ship_1) = setup(1.0);
+
+        // an unarmed target can not damage the attacker weapons, so the combat always ends
+        let components = world.resource::<Components>();
+        let spec = ShipSpec::new(components, [(ComponentId(0), 1)].into(), 1);
+        let ship = ShipInstance::new(components, ShipInstanceId(0), spec, TeamId(0));
+        world.entity_mut(ship_1).insert(ship);
+
+        for _ in 0..500 {
+            world.run_system_once(system_ship_combat);
+        }
+
+        let destroyed: Vec<ObjId> = world
+            .resource::<GEvents>()
+            .list()
+            .iter()
+            .filter(|e| matches!(e.kind, EventKind::Destroyed))
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(vec![ship_1], destroyed);
+        assert!(world.get_entity(ship_0).is_some());
+        assert!(world.get_entity(ship_1).is_none());
+    }
+
+    #[test]
+    fn test_ship_combat_should_ignore_ships_of_non_hostile_factions() {
+        let (mut world, ship_0, _) = setup_with_hostility(1.0, false);
+
+        world.run_system_once(system_ship_combat);
+
+        assert!(world.resource::<GEvents>().list().is_empty());
+        let ship = world.get::<ShipInstance>(ship_0).unwrap();
+        assert!(ship.armor_damage.is_empty());
+    }
+
+    #[test]
+    fn test_ship_combat_should_ignore_ships_out_of_range() {
+        let (mut world, ship_0, _) = setup(COMBAT_RANGE * 2.0);
+
+        world.run_system_once(system_ship_combat);
+
+        assert!(world.resource::<GEvents>().list().is_empty());
+        let ship = world.get::<ShipInstance>(ship_0).unwrap();
+        assert!(ship.armor_damage.is_empty());
+        assert!(!ship.wreck);
+    }
+}
@@ -8,7 +8,7 @@ pub struct ShipComponentsConfig {
     pub components: Vec<Component>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Armor {
     pub width: u32,
     pub height: u32,
@@ -77,9 +77,18 @@ pub struct Weapon {
     pub damage_type: WeaponDamageType,
 }
 
+/// Ware and amount required to build one unit of a component
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComponentCost {
+    pub ware: String,
+    pub amount: u32,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Component {
     pub id: ComponentId,
+    #[serde(default)]
+    pub name: String,
     // pub component_type: ComponentType,
     #[serde(default)]
     pub weapon: Option<Weapon>,
@@ -101,6 +110,14 @@ pub struct Component {
     pub fuel_hold: f32,
     #[serde(default)]
     pub bridge_power: f32,
+    /// cargo volume
+    #[serde(default)]
+    pub cargo: u32,
+    /// work required to build one unit
+    #[serde(default)]
+    pub work: f32,
+    #[serde(default)]
+    pub cost: Vec<ComponentCost>,
 }
 
 impl Component {
@@ -108,6 +125,7 @@ impl Component {
     pub fn new(id: ComponentId) -> Self {
         Component {
             id,
+            name: String::new(),
             weapon: None,
             thrust: 0.0,
             weight: 0,
@@ -118,6 +136,9 @@ impl Component {
             size: 0,
             fuel_hold: 0.0,
             bridge_power: 0.0,
+            cargo: 0,
+            work: 0.0,
+            cost: vec![],
         }
     }
 }
@@ -129,7 +150,7 @@ struct ComponentItem {
     // pub working: u32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ShipSpec {
     pub armor: Armor,
     pub components: HashMap<ComponentId, u32>,
@@ -185,6 +206,9 @@ impl ShipSpec {
         let mut thrust: f32 = 0.0;
         let mut weight: u32 = 0;
         let mut width: u32 = 0;
+        let mut cargo: u32 = 0;
+        let mut fuel_hold: f32 = 0.0;
+        let mut fuel_consume: f32 = 0.0;
 
         for (id, amount) in ship_components {
             let component = components.get(id);
@@ -192,6 +216,8 @@ impl ShipSpec {
 
             weight += component.weight * amount;
             width += component.size * amount;
+            cargo += component.cargo * amount;
+            fuel_hold += component.fuel_hold * *amount as f32;
 
             if destroyed_amount.0 >= *amount {
                 // all components from this category were destroyed
@@ -209,6 +235,7 @@ impl ShipSpec {
             crew += component.crew * active_amount_f32;
             engineer += component.engineer * active_amount_f32;
             thrust += component.thrust * active_amount_f32;
+            fuel_consume += component.fuel_consume * active_amount_f32;
         }
 
         let armor_weight = armor.width * armor.height * 10;
@@ -223,6 +250,9 @@ impl ShipSpec {
             engineer_balance: engineer,
             thrust: thrust,
             speed: Speed(10.0 * thrust / (weight as f32)),
+            cargo,
+            fuel_hold,
+            fuel_consume,
         }
     }
 
@@ -270,6 +300,25 @@ impl ShipSpec {
             .collect()
     }
 
+    /// Work and wares required to build all components
+    pub fn production_cost(&self, components: &Components) -> (f32, Vec<ComponentCost>) {
+        let mut work = 0.0;
+        let mut cost: Vec<ComponentCost> = vec![];
+        for (component, amount) in self.map_components(components) {
+            work += component.work * amount.0 as f32;
+            for i in &component.cost {
+                match cost.iter_mut().find(|j| j.ware == i.ware) {
+                    Some(j) => j.amount += i.amount * amount.0,
+                    None => cost.push(ComponentCost {
+                        ware: i.ware.clone(),
+                        amount: i.amount * amount.0,
+                    }),
+                }
+            }
+        }
+        (work, cost)
+    }
+
     pub fn get_hull_hp(&self, components: &Components) -> Hp {
         let total = self
             .map_components(components)
@@ -281,7 +330,7 @@ impl ShipSpec {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WeaponState {
     pub recharge: f32,
 }
@@ -292,7 +341,7 @@ impl WeaponState {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ComponentTable {
     pub total: u32,
     pub sequence: Vec<(ComponentId, u32)>,
@@ -318,7 +367,8 @@ impl ComponentTable {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Ship built from components, also used as ECS component by ships that can fight
+#[derive(Debug, Clone, bevy_ecs::component::Component, Serialize, Deserialize)]
 pub struct ShipInstance {
     pub id: ShipInstanceId,
     pub spec: ShipSpec,
@@ -394,6 +444,7 @@ impl ShipInstance {
     }
 }
 
+#[derive(bevy_ecs::system::Resource)]
 pub struct Components {
     index: HashMap<ComponentId, Component>,
 }
@@ -405,6 +456,14 @@ impl Components {
         }
     }
 
+    pub fn from_list(list: &[Component]) -> Self {
+        let mut components = Components::new();
+        for component in list {
+            components.add(component.clone());
+        }
+        components
+    }
+
     pub fn add(&mut self, component: Component) {
         if self.index.contains_key(&component.id) {
             panic!();
@@ -417,6 +476,10 @@ impl Components {
     pub fn get(&self, component_id: &ComponentId) -> &Component {
         self.index.get(component_id).unwrap()
     }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&Component> {
+        self.index.values().find(|i| i.name == name)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -428,7 +491,7 @@ pub enum ShipComponentsValidation {
     NeedEngineer { amount: f32 },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShipStats {
     pub bridge: bool,
     pub total_weight: u32,
@@ -438,4 +501,9 @@ pub struct ShipStats {
     pub engineer_balance: f32,
     pub thrust: f32,
     pub speed: Speed,
+    pub cargo: u32,
+    pub fuel_hold: f32,
+    /// fuel consumed for each unit of distance moved
+    #[serde(default)]
+    pub fuel_consume: f32,
 }
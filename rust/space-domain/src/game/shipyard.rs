@@ -1,4 +1,6 @@
+use crate::game::factions::Owner;
 use crate::game::loader::Loader;
+use crate::game::locations::LocationSpace;
 use bevy_ecs::prelude::*;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -7,6 +9,7 @@ use std::collections::HashMap;
 use crate::game::order::{TradeOrders, TRADE_ORDER_ID_SHIPYARD};
 use crate::game::prefab::{Prefab, PrefabId};
 use crate::game::save::LoadingMapEntity;
+use crate::game::stats::{EconomyStats, StatKind};
 use crate::game::utils::DeltaTime;
 use crate::game::wares::{Cargo, VecWareAmount};
 use crate::game::work::WorkUnit;
@@ -140,7 +143,15 @@ pub fn system_shipyard(
     mut commands: Commands,
     delta_time: Res<DeltaTime>,
     query_prefabs: Query<(Entity, &Prefab)>,
-    mut query: Query<(Entity, &mut Shipyard, Option<&mut TradeOrders>, &mut Cargo)>,
+    mut query: Query<(
+        Entity,
+        &mut Shipyard,
+        Option<&mut TradeOrders>,
+        &mut Cargo,
+        Option<&LocationSpace>,
+        Option<&Owner>,
+    )>,
+    mut stats: Option<ResMut<EconomyStats>>,
 ) {
     log::trace!("running");
 
@@ -149,7 +160,9 @@ pub fn system_shipyard(
     // collect all prefabs as candidates for random production
     let prefabs_candidates: Vec<_> = query_prefabs.iter().filter(|(_, p)| p.shipyard).collect();
 
-    for (shipyard_id, mut shipyard, trade_order, mut cargo) in &mut query {
+    for (shipyard_id, mut shipyard, trade_order, mut cargo, maybe_location, maybe_owner) in
+        &mut query
+    {
         let mut trade_order = match trade_order {
             Some(to) => to,
             None => {
@@ -164,6 +177,7 @@ pub fn system_shipyard(
                     &mut commands,
                     &prefabs_candidates,
                     shipyard_id,
+                    maybe_owner,
                     &mut shipyard,
                     prefab_id,
                 );
@@ -177,6 +191,18 @@ pub fn system_shipyard(
                     &mut cargo,
                     &mut trade_order,
                 );
+
+                // record the wares consumed when a new production start
+                let started_cost = shipyard.get_producing().and_then(|prefab_id| {
+                    prefabs_candidates
+                        .iter()
+                        .find(|(id, _)| *id == prefab_id)
+                        .and_then(|(_, prefab)| prefab.obj.production_cost.as_ref())
+                });
+                if let (Some(stats), Some(cost)) = (stats.as_mut(), started_cost) {
+                    let sector_id = maybe_location.map(|l| l.sector_id);
+                    stats.record_all(StatKind::Consumed, shipyard_id, sector_id, &cost.cost);
+                }
             }
         };
     }
@@ -186,6 +212,7 @@ fn run_shipyard_complete_order(
     mut commands: &mut Commands,
     prefabs_candidates: &Vec<(Entity, &Prefab)>,
     shipyard_id: Entity,
+    maybe_owner: Option<&Owner>,
     shipyard: &mut Shipyard,
     prefab_id: PrefabId,
 ) {
@@ -197,8 +224,9 @@ fn run_shipyard_complete_order(
     if let Some((_, prefab)) = prefabs_candidates.iter().find(|(id, _)| *id == prefab_id) {
         let mut new_obj = prefab.obj.clone();
 
-        // put into shipyard
+        // put into shipyard, owned by the shipyard owner
         new_obj = new_obj.at_dock(shipyard_id);
+        new_obj.owner = maybe_owner.copied();
         log::debug!("{:?} complete production of {:?}", shipyard_id, new_obj);
 
         Loader::add_object(&mut commands, &new_obj);
@@ -270,8 +298,9 @@ fn run_shipyard_next_order(
             shipyard.production_order = ProductionOrder::Random;
         }
 
-        // remove requesting orders
+        // remove requesting orders, they are recreated once the next order is known
         trade_order.remove_by_id(TRADE_ORDER_ID_SHIPYARD);
+        shipyard.dirt_trade_order = true;
 
         log::debug!(
                         "{:?} staring production of prefab {:?}, expected to be complete at {:?}, next order is {:?}",
@@ -0,0 +1,538 @@
+use bevy_ecs::entity::Entities;
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use crate::game::locations::LocationSpace;
+use crate::game::objects::ObjId;
+use crate::game::save::LoadingMapEntity;
+use crate::game::sectors::SectorId;
+use crate::game::station::Station;
+use crate::game::utils::{DeltaTime, TotalTime};
+use crate::game::wares::{
+    Cargo, CargoTransfer, Volume, WareAmount, WareId, WareKinds, WareSelector,
+};
+
+/// Time between each sample of the series
+pub const SAMPLE_PERIOD: DeltaTime = DeltaTime(10.0);
+
+/// Amount of samples kept in each series
+pub const HISTORY_SIZE: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StatKind {
+    /// wares created by factories
+    Produced,
+    /// wares consumed by factories and shipyards
+    Consumed,
+    /// wares extracted from asteroids
+    Mined,
+    /// wares transferred between ships and stations
+    Moved,
+    /// wares stored at stations, sampled instead of accumulated
+    Stock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StatScope {
+    Global,
+    Sector(SectorId),
+    /// a station or ship
+    Obj(ObjId),
+}
+
+impl LoadingMapEntity for StatScope {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        match self {
+            StatScope::Global => {}
+            StatScope::Sector(id) => id.map_entity(entity_map),
+            StatScope::Obj(id) => id.map_entity(entity_map),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StatKey {
+    pub kind: StatKind,
+    pub ware_id: WareId,
+    pub scope: StatScope,
+}
+
+impl LoadingMapEntity for StatKey {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.ware_id.map_entity(entity_map);
+        self.scope.map_entity(entity_map);
+    }
+}
+
+/// Rolling series of a single key, each sample is the amount accumulated during a sample period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatSeries {
+    key: StatKey,
+    total: u64,
+    current: Volume,
+    samples: VecDeque<Volume>,
+}
+
+impl StatSeries {
+    fn new(key: StatKey) -> Self {
+        StatSeries {
+            key,
+            total: 0,
+            current: 0,
+            samples: Default::default(),
+        }
+    }
+
+    fn push_sample(&mut self, value: Volume) {
+        if self.samples.len() >= HISTORY_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+}
+
+/// Aggregate economy counters into per ware time series by global, sector and object scopes.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct EconomyStats {
+    series: Vec<StatSeries>,
+    next_sample: TotalTime,
+    #[serde(skip)]
+    index: HashMap<StatKey, usize>,
+}
+
+impl Default for EconomyStats {
+    fn default() -> Self {
+        EconomyStats {
+            series: vec![],
+            next_sample: TotalTime(SAMPLE_PERIOD.as_f32() as f64),
+            index: Default::default(),
+        }
+    }
+}
+
+impl EconomyStats {
+    pub fn record(
+        &mut self,
+        kind: StatKind,
+        obj_id: ObjId,
+        sector_id: Option<SectorId>,
+        ware_id: WareId,
+        amount: Volume,
+    ) {
+        if amount == 0 {
+            return;
+        }
+
+        let mut scopes = vec![StatScope::Global, StatScope::Obj(obj_id)];
+        if let Some(sector_id) = sector_id {
+            scopes.push(StatScope::Sector(sector_id));
+        }
+
+        for scope in scopes {
+            let series = self.get_or_create(StatKey {
+                kind,
+                ware_id,
+                scope,
+            });
+            series.current += amount;
+            series.total += amount as u64;
+        }
+    }
+
+    pub fn record_all(
+        &mut self,
+        kind: StatKind,
+        obj_id: ObjId,
+        sector_id: Option<SectorId>,
+        wares: &[WareAmount],
+    ) {
+        for wa in wares {
+            self.record(kind, obj_id, sector_id, wa.ware_id, wa.amount);
+        }
+    }
+
+    /// Record wares moved from a transfer, once at global and sector scope and at the scope of
+    /// both objects
+    pub fn record_move(
+        &mut self,
+        from_id: ObjId,
+        to_id: ObjId,
+        sector_id: Option<SectorId>,
+        transfer: &CargoTransfer,
+    ) {
+        self.record_all(StatKind::Moved, from_id, sector_id, &transfer.moved);
+        for wa in &transfer.moved {
+            if wa.amount == 0 {
+                continue;
+            }
+            let series = self.get_or_create(StatKey {
+                kind: StatKind::Moved,
+                ware_id: wa.ware_id,
+                scope: StatScope::Obj(to_id),
+            });
+            series.current += wa.amount;
+            series.total += wa.amount as u64;
+        }
+    }
+
+    /// Amount accumulated since the start of the game, for stock is the last sample
+    pub fn get_total(&self, key: &StatKey) -> u64 {
+        match key.kind {
+            StatKind::Stock => self
+                .get_series(key)
+                .and_then(|s| s.samples.back())
+                .map(|value| *value as u64)
+                .unwrap_or(0),
+            _ => self.get_series(key).map(|s| s.total).unwrap_or(0),
+        }
+    }
+
+    /// Average amount per second on the last samples
+    pub fn get_rate(&self, key: &StatKey, samples: usize) -> f32 {
+        let Some(series) = self.get_series(key) else {
+            return 0.0;
+        };
+
+        let count = samples.min(series.samples.len());
+        if count == 0 {
+            return 0.0;
+        }
+
+        let sum: Volume = series.samples.iter().rev().take(count).sum();
+        sum as f32 / (count as f32 * SAMPLE_PERIOD.as_f32())
+    }
+
+    /// Samples from the oldest to the newest
+    pub fn get_history(&self, key: &StatKey) -> Vec<Volume> {
+        self.get_series(key)
+            .map(|s| s.samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Sum of `get_total` of all wares that match any of the selectors
+    pub fn get_total_selected(
+        &self,
+        kind: StatKind,
+        scope: StatScope,
+        kinds: &WareKinds,
+        selectors: &[WareSelector],
+    ) -> u64 {
+        self.list_selected(kind, scope, kinds, selectors)
+            .map(|key| self.get_total(&key))
+            .sum()
+    }
+
+    /// Sum of `get_rate` of all wares that match any of the selectors
+    pub fn get_rate_selected(
+        &self,
+        kind: StatKind,
+        scope: StatScope,
+        kinds: &WareKinds,
+        selectors: &[WareSelector],
+        samples: usize,
+    ) -> f32 {
+        self.list_selected(kind, scope, kinds, selectors)
+            .map(|key| self.get_rate(&key, samples))
+            .sum()
+    }
+
+    fn list_selected<'a>(
+        &'a self,
+        kind: StatKind,
+        scope: StatScope,
+        kinds: &'a WareKinds,
+        selectors: &'a [WareSelector],
+    ) -> impl Iterator<Item = StatKey> + 'a {
+        self.series.iter().map(|s| s.key).filter(move |key| {
+            key.kind == kind && key.scope == scope && kinds.matches(selectors, key.ware_id)
+        })
+    }
+
+    pub fn list_keys(&self) -> Vec<StatKey> {
+        self.series.iter().map(|s| s.key).collect()
+    }
+
+    pub fn is_sample_time(&self, total_time: TotalTime) -> bool {
+        total_time.is_after(self.next_sample)
+    }
+
+    /// Close the current sample of all series and start a new one
+    pub fn sample(&mut self, total_time: TotalTime, stocks: Vec<(StatKey, Volume)>) {
+        for series in &mut self.series {
+            if series.key.kind == StatKind::Stock {
+                continue;
+            }
+            let value = series.current;
+            series.current = 0;
+            series.push_sample(value);
+        }
+
+        let mut stock_by_key: HashMap<StatKey, Volume> = HashMap::new();
+        for (key, amount) in stocks {
+            *stock_by_key.entry(key).or_insert(0) += amount;
+        }
+
+        // series without stock still need a sample
+        for series in &mut self.series {
+            if series.key.kind == StatKind::Stock {
+                let value = stock_by_key.remove(&series.key).unwrap_or(0);
+                series.push_sample(value);
+            }
+        }
+
+        for (key, amount) in stock_by_key {
+            self.get_or_create(key).push_sample(amount);
+        }
+
+        self.next_sample = total_time.add(SAMPLE_PERIOD);
+    }
+
+    fn get_series(&self, key: &StatKey) -> Option<&StatSeries> {
+        self.index.get(key).map(|index| &self.series[*index])
+    }
+
+    fn get_or_create(&mut self, key: StatKey) -> &mut StatSeries {
+        let index = match self.index.get(&key) {
+            Some(index) => *index,
+            None => {
+                self.series.push(StatSeries::new(key));
+                self.index.insert(key, self.series.len() - 1);
+                self.series.len() - 1
+            }
+        };
+        &mut self.series[index]
+    }
+
+    /// Discard series of wares, sectors and objects that not exist anymore
+    pub fn prune(&mut self, exists: impl Fn(Entity) -> bool) {
+        let count = self.series.len();
+        self.series.retain(|s| {
+            exists(s.key.ware_id)
+                && match s.key.scope {
+                    StatScope::Global => true,
+                    StatScope::Sector(id) | StatScope::Obj(id) => exists(id),
+                }
+        });
+        if count != self.series.len() {
+            self.rebuild_index();
+        }
+    }
+
+    fn rebuild_index(&mut self) {
+        self.index = self
+            .series
+            .iter()
+            .enumerate()
+            .map(|(index, s)| (s.key, index))
+            .collect();
+    }
+}
+
+impl LoadingMapEntity for EconomyStats {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        // series of removed objects are discarded
+        self.prune(|id| entity_map.contains_key(&id));
+        for series in &mut self.series {
+            series.key.map_entity(entity_map);
+        }
+        self.rebuild_index();
+    }
+}
+
+/// Close the current sample of all series once the sample period is complete, collecting the
+/// stations stock and discarding series of despawned objects
+pub fn system_economy_stats(
+    total_time: Res<TotalTime>,
+    mut stats: ResMut<EconomyStats>,
+    entities: &Entities,
+    query_stations: Query<(Entity, &Cargo, Option<&LocationSpace>), With<Station>>,
+) {
+    log::trace!("running");
+
+    let total_time = *total_time;
+    if !stats.is_sample_time(total_time) {
+        return;
+    }
+
+    stats.prune(|id| entities.contains(id));
+
+    let mut stocks = vec![];
+    for (obj_id, cargo, maybe_location) in &query_stations {
+        for wa in cargo.get_wares() {
+            let mut scopes = vec![StatScope::Global, StatScope::Obj(obj_id)];
+            if let Some(location) = maybe_location {
+                scopes.push(StatScope::Sector(location.sector_id));
+            }
+
+            for scope in scopes {
+                let key = StatKey {
+                    kind: StatKind::Stock,
+                    ware_id: wa.ware_id,
+                    scope,
+                };
+                stocks.push((key, wa.amount));
+            }
+        }
+    }
+
+    stats.sample(total_time, stocks);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::wares::{WareCategory, WareKind};
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_stats_should_aggregate_by_scope_and_sample() {
+        let mut world = World::new();
+        let ware_id = world.spawn_empty().id();
+        let sector_id = world.spawn_empty().id();
+        let station_0 = world.spawn_empty().id();
+        let station_1 = world.spawn_empty().id();
+
+        let mut stats = EconomyStats::default();
+        stats.record(StatKind::Produced, station_0, Some(sector_id), ware_id, 10);
+        stats.record(StatKind::Produced, station_1, None, ware_id, 5);
+
+        let global = StatKey {
+            kind: StatKind::Produced,
+            ware_id,
+            scope: StatScope::Global,
+        };
+        let sector = StatKey {
+            scope: StatScope::Sector(sector_id),
+            ..global
+        };
+        let station = StatKey {
+            scope: StatScope::Obj(station_1),
+            ..global
+        };
+
+        assert_eq!(15, stats.get_total(&global));
+        assert_eq!(10, stats.get_total(&sector));
+        assert_eq!(5, stats.get_total(&station));
+        assert_eq!(0.0, stats.get_rate(&global, 1));
+
+        stats.sample(TotalTime(10.0), vec![]);
+        stats.record(StatKind::Produced, station_1, None, ware_id, 5);
+        stats.sample(TotalTime(20.0), vec![]);
+
+        assert_eq!(vec![15, 5], stats.get_history(&global));
+        assert_eq!(0.5, stats.get_rate(&global, 1));
+        assert_eq!(1.0, stats.get_rate(&global, 2));
+        assert_eq!(20, stats.get_total(&global));
+    }
+
+    #[test]
+    fn test_stats_should_sum_wares_by_category() {
+        let mut world = World::new();
+        let ore_id = world.spawn_empty().id();
+        let ice_id = world.spawn_empty().id();
+        let energy_id = world.spawn_empty().id();
+        let station_id = world.spawn_empty().id();
+
+        let kinds = WareKinds::from(HashMap::from([
+            (ore_id, WareKind::new(Some(WareCategory::Raw), vec![])),
+            (ice_id, WareKind::new(Some(WareCategory::Raw), vec![])),
+            (energy_id, WareKind::default()),
+        ]));
+
+        let mut stats = EconomyStats::default();
+        stats.record(StatKind::Mined, station_id, None, ore_id, 5);
+        stats.record(StatKind::Mined, station_id, None, ice_id, 15);
+        stats.record(StatKind::Mined, station_id, None, energy_id, 10);
+
+        let raw = [WareSelector::Category(WareCategory::Raw)];
+        assert_eq!(
+            20,
+            stats.get_total_selected(StatKind::Mined, StatScope::Global, &kinds, &raw)
+        );
+        assert_eq!(
+            0,
+            stats.get_total_selected(StatKind::Produced, StatScope::Global, &kinds, &raw)
+        );
+
+        stats.sample(TotalTime(10.0), vec![]);
+        assert_eq!(
+            2.0,
+            stats.get_rate_selected(StatKind::Mined, StatScope::Obj(station_id), &kinds, &raw, 1)
+        );
+    }
+
+    #[test]
+    fn test_stats_system_should_sample_stations_stock() {
+        let mut world = World::new();
+        let ware_id = world.spawn_empty().id();
+        let sector_id = world.spawn_empty().id();
+
+        let mut cargo = Cargo::new(100);
+        cargo.add(ware_id, 30).unwrap();
+        world.spawn((
+            Station {},
+            cargo.clone(),
+            LocationSpace {
+                pos: Default::default(),
+                sector_id,
+            },
+        ));
+        world.spawn((Station {}, cargo));
+
+        world.insert_resource(EconomyStats::default());
+        world.insert_resource(TotalTime(5.0));
+        world.run_system_once(system_economy_stats);
+        assert!(world.resource::<EconomyStats>().list_keys().is_empty());
+
+        world.insert_resource(TotalTime(10.0));
+        world.run_system_once(system_economy_stats);
+
+        let stats = world.resource::<EconomyStats>();
+        let global = StatKey {
+            kind: StatKind::Stock,
+            ware_id,
+            scope: StatScope::Global,
+        };
+        let sector = StatKey {
+            scope: StatScope::Sector(sector_id),
+            ..global
+        };
+        assert_eq!(vec![60], stats.get_history(&global));
+        assert_eq!(30, stats.get_total(&sector));
+    }
+
+    #[test]
+    fn test_stats_system_should_discard_series_of_despawned_objects() {
+        let mut world = World::new();
+        let ware_id = world.spawn_empty().id();
+        let ship_id = world.spawn_empty().id();
+        let station_id = world.spawn_empty().id();
+
+        let transfer = CargoTransfer {
+            moved: vec![WareAmount::new(ware_id, 5)],
+        };
+        let mut stats = EconomyStats::default();
+        stats.record_move(ship_id, station_id, None, &transfer);
+
+        let key = |scope| StatKey {
+            kind: StatKind::Moved,
+            ware_id,
+            scope,
+        };
+        assert_eq!(5, stats.get_total(&key(StatScope::Global)));
+        assert_eq!(5, stats.get_total(&key(StatScope::Obj(ship_id))));
+        assert_eq!(5, stats.get_total(&key(StatScope::Obj(station_id))));
+
+        world.despawn(ship_id);
+        world.insert_resource(stats);
+        world.insert_resource(TotalTime(10.0));
+        world.run_system_once(system_economy_stats);
+
+        let stats = world.resource::<EconomyStats>();
+        assert_eq!(5, stats.get_total(&key(StatScope::Global)));
+        assert_eq!(0, stats.get_total(&key(StatScope::Obj(ship_id))));
+        assert_eq!(5, stats.get_total(&key(StatScope::Obj(station_id))));
+        assert_eq!(2, stats.list_keys().len());
+    }
+}
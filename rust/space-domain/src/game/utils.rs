@@ -47,6 +47,11 @@ impl Tick {
     }
 }
 
+/// Seed of the game scenery, used to derive the random sequences of systems running during the
+/// game so a loaded game keeps the same sequences
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Resource, Default)]
+pub struct Seed(pub u64);
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Resource)]
 pub struct DeltaTime(pub f32);
 
@@ -180,6 +185,16 @@ where
     selected
 }
 
+/// Search the value with lower score between all values
+pub fn lower<Value, Iter>(iter: Iter) -> Option<Value>
+where
+    Value: Sized,
+    Iter: Iterator<Item = (f32, Value)>,
+{
+    iter.min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, value)| value)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
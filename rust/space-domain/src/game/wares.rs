@@ -1,12 +1,17 @@
 use crate::game::code::HasCode;
 use crate::game::factory::Factory;
+use crate::game::maintenance::RepairDock;
 use crate::game::prefab::Prefab;
 use crate::game::save::LoadingMapEntity;
+use crate::game::sectors::SectorId;
 use crate::game::shipyard::Shipyard;
+use crate::game::stats::EconomyStats;
+use crate::game::utils::TotalTime;
 use bevy_ecs::prelude::*;
 use log;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use super::objects::ObjId;
 
@@ -14,6 +19,8 @@ pub type WareId = Entity;
 
 pub type Volume = u32;
 
+pub type Mass = f32;
+
 /** amount of resources extracted per second */
 pub type ResourceAccessibility = f32;
 
@@ -23,6 +30,54 @@ pub type ResourceExtraction = f32;
 #[derive(Debug, Clone, Component, Serialize, Deserialize)]
 pub struct Ware;
 
+/// Volume and mass of a single unit of ware. Wares without it use one volume and no mass.
+#[derive(Debug, Clone, Copy, Component, PartialEq, Serialize, Deserialize)]
+pub struct WareUnit {
+    pub volume: Volume,
+    pub mass: Mass,
+}
+
+impl Default for WareUnit {
+    fn default() -> Self {
+        WareUnit {
+            volume: 1,
+            mass: 0.0,
+        }
+    }
+}
+
+/// Units of all wares, shared between every cargo. Rebuild from the `WareUnit` components by
+/// `system_cargo_ware_units`.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct WareUnits {
+    units: Arc<HashMap<WareId, WareUnit>>,
+}
+
+impl WareUnits {
+    pub fn new(units: Vec<(WareId, WareUnit)>) -> Self {
+        WareUnits {
+            units: Arc::new(units.into_iter().collect()),
+        }
+    }
+
+    pub fn get(&self, ware_id: WareId) -> WareUnit {
+        self.units.get(&ware_id).copied().unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+
+    /// True when both share the same units instance
+    pub fn is_same(&self, other: &WareUnits) -> bool {
+        Arc::ptr_eq(&self.units, &other.units)
+    }
+}
+
 pub struct Wares;
 
 impl Wares {
@@ -40,6 +95,164 @@ impl Wares {
             .map(|(id, _, code)| (id, code.code.clone()))
             .collect()
     }
+
+    pub fn list_wares_kinds(query: &Query<(Entity, Option<&WareKind>), With<Ware>>) -> WareKinds {
+        WareKinds::from(
+            query
+                .iter()
+                .map(|(id, kind)| (id, kind.cloned().unwrap_or_default()))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WareCategory {
+    /// extracted from asteroids and planets
+    Raw,
+    /// produced and consumed by factories
+    Intermediate,
+    /// final goods consumed by habitats
+    Product,
+    Fuel,
+    Ammo,
+}
+
+impl WareCategory {
+    /// Parse "raw", "intermediate", "product", "fuel" or "ammo"
+    pub fn from_code(code: &str) -> Option<WareCategory> {
+        match code {
+            "raw" => Some(WareCategory::Raw),
+            "intermediate" => Some(WareCategory::Intermediate),
+            "product" => Some(WareCategory::Product),
+            "fuel" => Some(WareCategory::Fuel),
+            "ammo" => Some(WareCategory::Ammo),
+            _ => None,
+        }
+    }
+}
+
+/// Category and free tags of a ware. Wares without it have no category and no tags.
+#[derive(Debug, Clone, Component, Default, PartialEq, Serialize, Deserialize)]
+pub struct WareKind {
+    pub category: Option<WareCategory>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl WareKind {
+    pub fn new(category: Option<WareCategory>, tags: Vec<String>) -> Self {
+        WareKind { category, tags }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|i| i == tag)
+    }
+}
+
+/// Select wares by id, category or tag
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WareSelector {
+    Ware(WareId),
+    Category(WareCategory),
+    Tag(String),
+}
+
+impl WareSelector {
+    /// Parse "category:<category>", "tag:<tag>" or a ware code
+    pub fn from_code(wares: &WaresByCode, code: &str) -> Option<WareSelector> {
+        if let Some(category) = code.strip_prefix("category:") {
+            WareCategory::from_code(category).map(WareSelector::Category)
+        } else if let Some(tag) = code.strip_prefix("tag:") {
+            Some(WareSelector::Tag(tag.to_string()))
+        } else {
+            wares.get(code).map(WareSelector::Ware)
+        }
+    }
+
+    pub fn matches(&self, ware_id: WareId, kind: Option<&WareKind>) -> bool {
+        match self {
+            WareSelector::Ware(id) => *id == ware_id,
+            WareSelector::Category(category) => kind.and_then(|k| k.category) == Some(*category),
+            WareSelector::Tag(tag) => kind.map(|k| k.has_tag(tag)).unwrap_or(false),
+        }
+    }
+}
+
+impl LoadingMapEntity for WareSelector {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        if let WareSelector::Ware(ware_id) = self {
+            ware_id.map_entity(entity_map);
+        }
+    }
+}
+
+/// Kind of all wares, used to resolve selectors
+#[derive(Debug, Clone, Default)]
+pub struct WareKinds {
+    map: HashMap<WareId, WareKind>,
+}
+
+impl WareKinds {
+    pub fn get(&self, ware_id: WareId) -> Option<&WareKind> {
+        self.map.get(&ware_id)
+    }
+
+    pub fn matches(&self, selectors: &[WareSelector], ware_id: WareId) -> bool {
+        let kind = self.get(ware_id);
+        selectors.iter().any(|s| s.matches(ware_id, kind))
+    }
+
+    /// All known wares that match any of the selectors
+    pub fn select(&self, selectors: &[WareSelector]) -> Vec<WareId> {
+        let mut wares: Vec<WareId> = self
+            .map
+            .keys()
+            .copied()
+            .filter(|ware_id| self.matches(selectors, *ware_id))
+            .collect();
+        wares.sort();
+        wares
+    }
+}
+
+impl From<HashMap<WareId, WareKind>> for WareKinds {
+    fn from(value: HashMap<WareId, WareKind>) -> Self {
+        Self { map: value }
+    }
+}
+
+/// Wares that are not allowed at a sector or by a faction, carrying them can be refused or the
+/// wares confiscated.
+#[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
+pub struct RestrictedWares {
+    pub wares: Vec<WareSelector>,
+}
+
+impl RestrictedWares {
+    pub fn new(wares: Vec<WareSelector>) -> Self {
+        RestrictedWares { wares }
+    }
+
+    pub fn is_restricted(&self, kinds: &WareKinds, ware_id: WareId) -> bool {
+        kinds.matches(&self.wares, ware_id)
+    }
+
+    /// Restricted wares found in the cargo
+    pub fn find_restricted(&self, kinds: &WareKinds, cargo: &Cargo) -> Vec<WareAmount> {
+        cargo
+            .get_wares()
+            .iter()
+            .filter(|wa| self.is_restricted(kinds, wa.ware_id))
+            .copied()
+            .collect()
+    }
+}
+
+impl LoadingMapEntity for RestrictedWares {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.wares.map_entity(entity_map);
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -110,14 +323,103 @@ pub enum CargoError {
     NotEnoughSpace,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReservationKind {
+    /// space reserved to receive wares
+    In,
+    /// wares reserved to be taken
+    Out,
+}
+
+/// Claim of an object over part of a cargo, until released or the deadline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CargoReservation {
+    pub owner_id: ObjId,
+    pub ware_id: WareId,
+    pub amount: Volume,
+    pub kind: ReservationKind,
+    pub deadline: TotalTime,
+}
+
+impl LoadingMapEntity for CargoReservation {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.owner_id.map_entity(entity_map);
+        self.ware_id.map_entity(entity_map);
+    }
+}
+
+/// How the cargo volume is split between the whitelisted wares
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum StorageAllocation {
+    /// each ware receive the same volume
+    #[default]
+    Equal,
+    /// fixed volume for each listed ware, the remaining volume is equally split between the other
+    /// wares. When the quotas exceed the cargo volume they are scaled down proportionally
+    Quotas(Vec<(WareId, Volume)>),
+    /// volume proportional to the ware weight, not listed wares have weight 1.0
+    Weighted(Vec<(WareId, f32)>),
+    /// all wares share the full volume
+    SharedPool,
+}
+
+impl StorageAllocation {
+    /// Parse "equal", "quotas", "weighted" or "shared", values are ignored when not used by the
+    /// policy
+    pub fn from_code(code: &str, values: Vec<(WareId, f32)>) -> Option<StorageAllocation> {
+        match code {
+            "equal" => Some(StorageAllocation::Equal),
+            "quotas" => Some(StorageAllocation::Quotas(
+                values
+                    .into_iter()
+                    .map(|(ware_id, value)| (ware_id, value.max(0.0) as Volume))
+                    .collect(),
+            )),
+            "weighted" => Some(StorageAllocation::Weighted(values)),
+            "shared" => Some(StorageAllocation::SharedPool),
+            _ => None,
+        }
+    }
+}
+
+impl LoadingMapEntity for StorageAllocation {
+    fn map_entity(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        match self {
+            StorageAllocation::Quotas(list) => {
+                for (ware_id, _) in list {
+                    ware_id.map_entity(entity_map);
+                }
+            }
+            StorageAllocation::Weighted(list) => {
+                for (ware_id, _) in list {
+                    ware_id.map_entity(entity_map);
+                }
+            }
+            StorageAllocation::Equal | StorageAllocation::SharedPool => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone, Component, Default, Serialize, Deserialize)]
 pub struct Cargo {
     max_volume: Volume,
     current_volume: Volume,
     wares: Vec<WareAmount>,
-    /// When a whitelist is defined, the total cargo is equally distributed between the wares.
-    /// Any other ware is not accepted
+    /// When a whitelist is defined, the total cargo is distributed between the wares by the
+    /// allocation. Any other ware is not accepted
     whitelist: Vec<WareId>,
+    /// Wares always included in the whitelist when the cargo distribution is updated
+    #[serde(default)]
+    whitelist_selectors: Vec<WareSelector>,
+    #[serde(default)]
+    allocation: StorageAllocation,
+    /// Reserved wares are not available in `get_amount` and reserved space is not available in
+    /// `free_volume`
+    #[serde(default)]
+    reservations: Vec<CargoReservation>,
+    /// Volume and mass of each ware unit, any ware not listed use default `WareUnit`
+    #[serde(skip)]
+    ware_units: WareUnits,
 }
 
 impl Cargo {
@@ -127,9 +429,107 @@ impl Cargo {
             current_volume: 0,
             wares: vec![],
             whitelist: vec![],
+            whitelist_selectors: vec![],
+            allocation: StorageAllocation::Equal,
+            reservations: vec![],
+            ware_units: WareUnits::default(),
         }
     }
 
+    pub fn get_allocation(&self) -> &StorageAllocation {
+        &self.allocation
+    }
+
+    pub fn set_allocation(&mut self, allocation: StorageAllocation) {
+        self.allocation = allocation;
+    }
+
+    /// Volume allocated to the ware giving the whitelist and allocation, shared pool and cargos
+    /// without whitelist allocate the full volume to every ware
+    pub fn get_allocated_volume(&self, ware_id: WareId) -> Volume {
+        if self.whitelist.is_empty() {
+            return self.max_volume;
+        }
+
+        if !self.whitelist.contains(&ware_id) {
+            return 0;
+        }
+
+        match &self.allocation {
+            StorageAllocation::Equal => self.max_volume / self.whitelist.len() as Volume,
+            StorageAllocation::Quotas(quotas) => {
+                let quota_of = |ware_id: WareId| {
+                    quotas
+                        .iter()
+                        .find(|(id, _)| *id == ware_id)
+                        .map(|(_, volume)| *volume)
+                };
+
+                let mut reserved: Volume = 0;
+                let mut free_wares = 0;
+                for ware_id in &self.whitelist {
+                    match quota_of(*ware_id) {
+                        Some(volume) => reserved = reserved.saturating_add(volume),
+                        None => free_wares += 1,
+                    }
+                }
+
+                if let Some(volume) = quota_of(ware_id) {
+                    return if reserved > self.max_volume {
+                        (volume as u64 * self.max_volume as u64 / reserved as u64) as Volume
+                    } else {
+                        volume
+                    };
+                }
+
+                self.max_volume.saturating_sub(reserved) / free_wares
+            }
+            StorageAllocation::Weighted(weights) => {
+                let weight_of = |ware_id: WareId| {
+                    weights
+                        .iter()
+                        .find(|(id, _)| *id == ware_id)
+                        .map(|(_, weight)| weight.max(0.0))
+                        .unwrap_or(1.0)
+                };
+
+                let total: f32 = self.whitelist.iter().map(|id| weight_of(*id)).sum();
+                if total <= 0.0 {
+                    0
+                } else {
+                    (self.max_volume as f32 * weight_of(ware_id) / total) as Volume
+                }
+            }
+            StorageAllocation::SharedPool => self.max_volume,
+        }
+    }
+
+    pub fn get_ware_unit(&self, ware_id: WareId) -> WareUnit {
+        self.ware_units.get(ware_id)
+    }
+
+    pub fn get_ware_units(&self) -> &WareUnits {
+        &self.ware_units
+    }
+
+    /// Update the wares units and recompute the current volume
+    pub fn set_ware_units(&mut self, ware_units: WareUnits) {
+        self.ware_units = ware_units;
+        self.current_volume = self
+            .wares
+            .iter()
+            .map(|wa| wa.amount * self.get_ware_unit(wa.ware_id).volume)
+            .sum();
+    }
+
+    /// Total mass of all wares in the cargo
+    pub fn get_mass(&self) -> Mass {
+        self.wares
+            .iter()
+            .map(|wa| wa.amount as Mass * self.get_ware_unit(wa.ware_id).mass)
+            .sum()
+    }
+
     pub fn get_wares(&self) -> &Vec<WareAmount> {
         &self.wares
     }
@@ -138,15 +538,24 @@ impl Cargo {
         self.whitelist = wares;
     }
 
+    pub fn get_whitelist_selectors(&self) -> &Vec<WareSelector> {
+        &self.whitelist_selectors
+    }
+
+    pub fn set_whitelist_selectors(&mut self, selectors: Vec<WareSelector>) {
+        self.whitelist_selectors = selectors;
+    }
+
     pub fn remove(&mut self, ware_id: WareId, amount: Volume) -> Result<(), ()> {
+        let volume = amount * self.get_ware_unit(ware_id).volume;
         if let Some(index) = self.wares.iter().position(|i| i.ware_id == ware_id) {
             if self.wares[index].amount == amount {
                 self.wares.remove(index);
-                self.current_volume -= amount;
+                self.current_volume -= volume;
                 Ok(())
             } else if self.wares[index].amount > amount {
                 self.wares[index].amount -= amount;
-                self.current_volume -= amount;
+                self.current_volume -= volume;
                 Ok(())
             } else {
                 Err(())
@@ -174,20 +583,41 @@ impl Cargo {
             }
         }
 
-        self.current_volume += amount;
+        self.current_volume += amount * self.get_ware_unit(ware_id).volume;
         Ok(())
     }
 
     pub fn add_all_or_none(&mut self, wares: &Vec<WareAmount>) -> Result<(), CargoError> {
-        for w in wares {
+        // merge repeated wares, they share the same allocation
+        let mut required: Vec<WareAmount> = vec![];
+        for w in wares.iter().filter(|w| w.amount > 0) {
+            match required.iter_mut().find(|i| i.ware_id == w.ware_id) {
+                Some(i) => i.amount += w.amount,
+                None => required.push(*w),
+            }
+        }
+
+        // wares share the same space, check the whole change before apply
+        let mut total_volume = 0;
+        for w in &required {
             if self.free_volume(w.ware_id)? < w.amount {
                 return Err(CargoError::NotEnoughSpace);
             }
+            total_volume += w.amount * self.get_ware_unit(w.ware_id).volume;
         }
 
-        for w in wares {
-            self.add(w.ware_id, w.amount)
-                .expect("fail to add cargo after check that was possible");
+        if self.is_shared_pool() {
+            let free = self
+                .max_volume
+                .saturating_sub(self.current_volume)
+                .saturating_sub(self.get_reserved_volume(None));
+            if total_volume > free {
+                return Err(CargoError::NotEnoughSpace);
+            }
+        }
+
+        for w in required {
+            self.add(w.ware_id, w.amount).unwrap();
         }
 
         Ok(())
@@ -229,27 +659,48 @@ impl Cargo {
         self.wares.clear();
     }
 
+    /// Amount of ware units that can still be added, considering the ware unit volume
     pub fn free_volume(&self, ware_id: WareId) -> Result<Volume, CargoError> {
-        let amount = if self.whitelist.is_empty() {
-            self.max_volume - self.current_volume
-        } else {
-            if !self.whitelist.contains(&ware_id) {
-                return Err(CargoError::NotAllowed);
-            }
+        let unit_volume = self.get_ware_unit(ware_id).volume.max(1);
 
-            let share = self.max_volume / self.whitelist.len() as Volume;
-            share - self.get_amount(ware_id)
+        if !self.whitelist.is_empty() && !self.whitelist.contains(&ware_id) {
+            return Err(CargoError::NotAllowed);
+        }
+
+        let amount = if self.is_shared_pool() {
+            let reserved = self.get_reserved_volume(None);
+            self.max_volume
+                .saturating_sub(self.current_volume)
+                .saturating_sub(reserved)
+                / unit_volume
+        } else {
+            let share = self.get_allocated_volume(ware_id);
+            let used = self.get_stored_amount(ware_id) * unit_volume;
+            let reserved = self.get_reserved_volume(Some(ware_id));
+            share.saturating_sub(used).saturating_sub(reserved) / unit_volume
         };
 
-        if amount <= 0 {
+        if amount == 0 {
             Err(CargoError::Full)
         } else {
             Ok(amount)
         }
     }
 
+    /// All wares share the whole cargo volume instead of having an allocated share
+    fn is_shared_pool(&self) -> bool {
+        self.whitelist.is_empty() || matches!(self.allocation, StorageAllocation::SharedPool)
+    }
+
+    /// Full when can not fit one more unit of any ware already in the cargo
     pub fn is_full(&self) -> bool {
-        self.current_volume >= self.max_volume
+        let min_unit_volume = self
+            .wares
+            .iter()
+            .map(|wa| self.get_ware_unit(wa.ware_id).volume)
+            .min()
+            .unwrap_or(1);
+        self.current_volume + min_unit_volume > self.max_volume
     }
 
     pub fn is_empty(&self) -> bool {
@@ -264,7 +715,14 @@ impl Cargo {
         self.wares.iter().map(|i| i.ware_id)
     }
 
+    /// Amount of ware available, not counting wares reserved to be taken
     pub fn get_amount(&self, ware_id: WareId) -> Volume {
+        self.get_stored_amount(ware_id)
+            .saturating_sub(self.get_reserved(ware_id, ReservationKind::Out))
+    }
+
+    /// Amount of ware stored, including reserved wares
+    pub fn get_stored_amount(&self, ware_id: WareId) -> Volume {
         self.wares
             .iter()
             .find(|i| i.ware_id == ware_id)
@@ -272,6 +730,87 @@ impl Cargo {
             .unwrap_or(0)
     }
 
+    pub fn get_reservations(&self) -> &Vec<CargoReservation> {
+        &self.reservations
+    }
+
+    pub fn get_reserved(&self, ware_id: WareId, kind: ReservationKind) -> Volume {
+        self.reservations
+            .iter()
+            .filter(|r| r.ware_id == ware_id && r.kind == kind)
+            .map(|r| r.amount)
+            .sum()
+    }
+
+    /// Volume reserved to receive wares, from a single ware or all
+    fn get_reserved_volume(&self, ware_id: Option<WareId>) -> Volume {
+        self.reservations
+            .iter()
+            .filter(|r| r.kind == ReservationKind::In)
+            .filter(|r| ware_id.is_none() || ware_id == Some(r.ware_id))
+            .map(|r| r.amount * self.get_ware_unit(r.ware_id).volume)
+            .sum()
+    }
+
+    /// Reserve space to receive up to amount of ware, return the amount reserved
+    pub fn reserve_in(
+        &mut self,
+        owner_id: ObjId,
+        ware_id: WareId,
+        amount: Volume,
+        deadline: TotalTime,
+    ) -> Volume {
+        let amount = amount.min(self.free_volume(ware_id).unwrap_or(0));
+        self.reserve(owner_id, ware_id, amount, ReservationKind::In, deadline)
+    }
+
+    /// Reserve up to amount of ware to be taken, return the amount reserved
+    pub fn reserve_out(
+        &mut self,
+        owner_id: ObjId,
+        ware_id: WareId,
+        amount: Volume,
+        deadline: TotalTime,
+    ) -> Volume {
+        let amount = amount.min(self.get_amount(ware_id));
+        self.reserve(owner_id, ware_id, amount, ReservationKind::Out, deadline)
+    }
+
+    fn reserve(
+        &mut self,
+        owner_id: ObjId,
+        ware_id: WareId,
+        amount: Volume,
+        kind: ReservationKind,
+        deadline: TotalTime,
+    ) -> Volume {
+        if amount == 0 {
+            return 0;
+        }
+
+        self.reservations.push(CargoReservation {
+            owner_id,
+            ware_id,
+            amount,
+            kind,
+            deadline,
+        });
+        amount
+    }
+
+    /// Release all reservations of the owner
+    pub fn release(&mut self, owner_id: ObjId) {
+        self.reservations.retain(|r| r.owner_id != owner_id);
+    }
+
+    /// Release all reservations with deadline before the time, return true if any was released
+    pub fn release_expired(&mut self, total_time: TotalTime) -> bool {
+        let count = self.reservations.len();
+        self.reservations
+            .retain(|r| !total_time.is_after(r.deadline));
+        count != self.reservations.len()
+    }
+
     pub fn get_max(&self) -> Volume {
         self.max_volume
     }
@@ -285,10 +824,13 @@ impl LoadingMapEntity for Cargo {
         for ware in &mut self.whitelist {
             ware.map_entity(entity_map);
         }
+        self.whitelist_selectors.map_entity(entity_map);
+        self.allocation.map_entity(entity_map);
+        self.reservations.map_entity(entity_map);
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CargoTransfer {
     pub moved: Vec<WareAmount>,
 }
@@ -296,14 +838,20 @@ pub struct CargoTransfer {
 impl CargoTransfer {
     /// Move all cargo possible from to
     pub fn transfer_all(from: &Cargo, to: &Cargo) -> CargoTransfer {
-        CargoTransfer::transfer_impl(from, to, None)
+        CargoTransfer::transfer_impl(from, to, None, None)
     }
 
     pub fn transfer_only(from: &Cargo, to: &Cargo, wares: &Vec<WareId>) -> CargoTransfer {
-        CargoTransfer::transfer_impl(from, to, Some(wares))
+        CargoTransfer::transfer_impl(from, to, Some(wares), None)
     }
 
-    fn transfer_impl(from: &Cargo, to: &Cargo, wares: Option<&Vec<WareId>>) -> CargoTransfer {
+    /// Move each ware until max_amount
+    fn transfer_impl(
+        from: &Cargo,
+        to: &Cargo,
+        wares: Option<&Vec<WareId>>,
+        max_amount: Option<Volume>,
+    ) -> CargoTransfer {
         let mut change = CargoTransfer { moved: vec![] };
 
         // use a temporary copy to simulate the transfer
@@ -317,7 +865,10 @@ impl CargoTransfer {
             }
 
             let available = tmp_to.free_volume(w.ware_id).unwrap_or(0);
-            let amount_to_move = w.amount.min(available);
+            let amount_to_move = from
+                .get_amount(w.ware_id)
+                .min(available)
+                .min(max_amount.unwrap_or(Volume::MAX));
             if amount_to_move > 0 {
                 tmp_to.add(w.ware_id, amount_to_move).unwrap();
                 change
@@ -340,18 +891,92 @@ impl CargoTransfer {
 
 pub struct Cargos;
 
+/// Moves between cargos record the moved wares into the economy stats when given, at the sector
+/// where the move happens.
 impl Cargos {
     pub fn move_only(
         cargos: &mut Query<&mut Cargo>,
         from_id: ObjId,
         to_id: ObjId,
         wares: &Vec<WareId>,
+        stats: Option<&mut EconomyStats>,
+        sector_id: Option<SectorId>,
     ) -> CargoTransfer {
-        Cargos::move_impl(cargos, from_id, to_id, Some(wares))
+        Cargos::move_impl(cargos, from_id, to_id, Some(wares), None, stats, sector_id)
     }
 
-    pub fn move_all(cargos: &mut Query<&mut Cargo>, from_id: ObjId, to_id: ObjId) -> CargoTransfer {
-        Cargos::move_impl(cargos, from_id, to_id, None)
+    /// Same as move_only, but move at most amount of each ware
+    pub fn move_only_up_to(
+        cargos: &mut Query<&mut Cargo>,
+        from_id: ObjId,
+        to_id: ObjId,
+        wares: &Vec<WareId>,
+        amount: Volume,
+        stats: Option<&mut EconomyStats>,
+        sector_id: Option<SectorId>,
+    ) -> CargoTransfer {
+        Cargos::move_impl(
+            cargos,
+            from_id,
+            to_id,
+            Some(wares),
+            Some(amount),
+            stats,
+            sector_id,
+        )
+    }
+
+    pub fn move_all(
+        cargos: &mut Query<&mut Cargo>,
+        from_id: ObjId,
+        to_id: ObjId,
+        stats: Option<&mut EconomyStats>,
+        sector_id: Option<SectorId>,
+    ) -> CargoTransfer {
+        Cargos::move_impl(cargos, from_id, to_id, None, None, stats, sector_id)
+    }
+
+    /// Reserve wares in from and space in to for a future transfer. Each reservation is owned by
+    /// the other side of the transfer.
+    pub fn reserve_only(
+        cargos: &mut Query<&mut Cargo>,
+        from_id: ObjId,
+        to_id: ObjId,
+        wares: &Vec<WareId>,
+        deadline: TotalTime,
+    ) -> CargoTransfer {
+        let cargo_from = cargos.get(from_id).expect("Entity cargo not found");
+        let cargo_to = cargos.get(to_id).expect("Deliver cargo not found");
+        let transfer = CargoTransfer::transfer_only(cargo_from, cargo_to, wares);
+
+        let mut cargo_from = cargos.get_mut(from_id).expect("Entity cargo not found");
+        for wa in &transfer.moved {
+            cargo_from.reserve_out(to_id, wa.ware_id, wa.amount, deadline);
+        }
+
+        let mut cargo_to = cargos.get_mut(to_id).expect("Deliver cargo not found");
+        for wa in &transfer.moved {
+            cargo_to.reserve_in(from_id, wa.ware_id, wa.amount, deadline);
+        }
+
+        log::trace!(
+            "reserve wares from {:?} to {:?}, reservation is {:?}",
+            from_id,
+            to_id,
+            transfer,
+        );
+
+        transfer
+    }
+
+    /// Release all reservations between both objects
+    pub fn release(cargos: &mut Query<&mut Cargo>, obj_a: ObjId, obj_b: ObjId) {
+        if let Ok(mut cargo) = cargos.get_mut(obj_a) {
+            cargo.release(obj_b);
+        }
+        if let Ok(mut cargo) = cargos.get_mut(obj_b) {
+            cargo.release(obj_a);
+        }
     }
 
     fn move_impl(
@@ -359,10 +984,13 @@ impl Cargos {
         from_id: ObjId,
         to_id: ObjId,
         wares: Option<&Vec<WareId>>,
+        max_amount: Option<Volume>,
+        stats: Option<&mut EconomyStats>,
+        sector_id: Option<SectorId>,
     ) -> CargoTransfer {
         let cargo_from = cargos.get(from_id).expect("Entity cargo not found");
         let cargo_to = cargos.get(to_id).expect("Deliver cargo not found");
-        let transfer = CargoTransfer::transfer_impl(cargo_from, cargo_to, wares);
+        let transfer = CargoTransfer::transfer_impl(cargo_from, cargo_to, wares, max_amount);
 
         log::trace!(
             "move wares from  {:?} {:?} to {:?} {:?}, transfer is {:?}, with filter {:?}",
@@ -384,24 +1012,80 @@ impl Cargos {
             .apply_move_to(&mut cargo_to)
             .expect("To add wares to be transfer");
 
+        if let Some(stats) = stats {
+            stats.record_move(from_id, to_id, sector_id, &transfer);
+        }
+
         transfer
     }
 }
 
+pub fn system_cargo_release_expired_reservations(
+    total_time: Res<TotalTime>,
+    mut query: Query<(Entity, &mut Cargo)>,
+) {
+    log::trace!("running");
+
+    for (obj_id, mut cargo) in &mut query {
+        if cargo.reservations.is_empty() {
+            continue;
+        }
+
+        if cargo.release_expired(*total_time) {
+            log::debug!("{:?} cargo reservations expired", obj_id);
+        }
+    }
+}
+
+/// Rebuild the shared `WareUnits` when any ware unit changes and share it with every cargo
+pub fn system_cargo_ware_units(
+    mut ware_units: ResMut<WareUnits>,
+    query_ware_units: Query<(Entity, &WareUnit)>,
+    query_changed: Query<(), Changed<WareUnit>>,
+    mut query_cargos: Query<&mut Cargo>,
+) {
+    log::trace!("running");
+
+    if !query_changed.is_empty() || query_ware_units.iter().len() != ware_units.len() {
+        *ware_units = WareUnits::new(
+            query_ware_units
+                .iter()
+                .map(|(id, unit)| (id, *unit))
+                .collect(),
+        );
+        log::debug!("ware units updated to {:?}", ware_units);
+    }
+
+    for mut cargo in &mut query_cargos {
+        if !cargo.get_ware_units().is_same(&ware_units) {
+            cargo.set_ware_units(ware_units.clone());
+        }
+    }
+}
+
 pub fn system_cargo_distribution(
     mut commands: Commands,
     mut query: Query<
-        (Entity, &mut Cargo, Option<&Factory>, Option<&Shipyard>),
+        (
+            Entity,
+            &mut Cargo,
+            Option<&Factory>,
+            Option<&Shipyard>,
+            Option<&RepairDock>,
+        ),
         With<CargoDistributionDirty>,
     >,
     query_prefabs: Query<(Entity, &Prefab)>,
+    query_ware_kinds: Query<(Entity, Option<&WareKind>), With<Ware>>,
 ) {
     log::trace!("running CargoDistributionDirtySystem");
 
+    let ware_kinds = Wares::list_wares_kinds(&query_ware_kinds);
+
     let mut shipyard_caching = None;
 
     // update cargos giving others component requirements
-    for (obj_id, mut cargo, maybe_factory, maybe_shipyard) in &mut query {
+    for (obj_id, mut cargo, maybe_factory, maybe_shipyard, maybe_repair_dock) in &mut query {
         let mut wares = HashSet::new();
         if let Some(f) = maybe_factory {
             wares.extend(f.get_cargos_allocation());
@@ -424,6 +1108,10 @@ pub fn system_cargo_distribution(
             }
             wares.extend(shipyard_caching.as_ref().unwrap().iter());
         }
+        if let Some(repair_dock) = maybe_repair_dock {
+            wares.extend(repair_dock.cost.iter().map(|wa| wa.ware_id));
+        }
+        wares.extend(ware_kinds.select(cargo.get_whitelist_selectors()));
 
         log::debug!("update {obj_id:?} cargo wares to {wares:?}");
         cargo.set_whitelist(wares.into_iter().collect());
@@ -436,6 +1124,7 @@ pub fn system_cargo_distribution(
 #[cfg(test)]
 mod test {
     use super::*;
+    use bevy_ecs::system::RunSystemOnce;
 
     fn create_wares() -> (WareId, WareId, WareId) {
         let mut world = World::new();
@@ -544,6 +1233,44 @@ mod test {
         assert_eq!(cargo.add_to_max(ware_0, 20), 6);
     }
 
+    #[test]
+    fn test_ware_selectors_should_match_by_id_category_and_tag() {
+        let (ware_0, ware_1, ware_2) = create_wares();
+        let kinds = WareKinds::from(HashMap::from([
+            (ware_0, WareKind::new(Some(WareCategory::Raw), vec![])),
+            (
+                ware_1,
+                WareKind::new(Some(WareCategory::Product), vec!["narcotic".to_string()]),
+            ),
+            (ware_2, WareKind::default()),
+        ]));
+
+        assert_eq!(
+            vec![ware_0],
+            kinds.select(&[WareSelector::Category(WareCategory::Raw)])
+        );
+        assert_eq!(
+            vec![ware_1],
+            kinds.select(&[WareSelector::Tag("narcotic".to_string())])
+        );
+        assert_eq!(
+            vec![ware_0, ware_2],
+            kinds.select(&[
+                WareSelector::Ware(ware_2),
+                WareSelector::Category(WareCategory::Raw)
+            ])
+        );
+
+        let restricted = RestrictedWares::new(vec![WareSelector::Tag("narcotic".to_string())]);
+        let mut cargo = Cargo::new(10);
+        cargo.add(ware_0, 2).unwrap();
+        cargo.add(ware_1, 3).unwrap();
+        let found = restricted.find_restricted(&kinds, &cargo);
+        assert_eq!(1, found.len());
+        assert_eq!(ware_1, found[0].ware_id);
+        assert_eq!(3, found[0].amount);
+    }
+
     #[test]
     fn test_cargo_whitelist_should_split_cargo_even() {
         let (ware_0, ware_1, _ware_2) = create_wares();
@@ -566,6 +1293,61 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_cargo_allocation_quotas_should_split_remaining_volume() {
+        let (ware_0, ware_1, ware_2) = create_wares();
+
+        let mut cargo = Cargo::new(100);
+        cargo.set_whitelist(vec![ware_0, ware_1, ware_2]);
+        cargo.set_allocation(StorageAllocation::Quotas(vec![(ware_0, 60)]));
+
+        assert_eq!(60, cargo.free_volume(ware_0).unwrap());
+        assert_eq!(20, cargo.free_volume(ware_1).unwrap());
+        assert_eq!(20, cargo.free_volume(ware_2).unwrap());
+    }
+
+    #[test]
+    fn test_cargo_allocation_quotas_should_scale_down_when_exceed_max_volume() {
+        let (ware_0, ware_1, ware_2) = create_wares();
+
+        let mut cargo = Cargo::new(100);
+        cargo.set_whitelist(vec![ware_0, ware_1, ware_2]);
+        cargo.set_allocation(StorageAllocation::Quotas(vec![(ware_0, 150), (ware_1, 50)]));
+
+        assert_eq!(75, cargo.free_volume(ware_0).unwrap());
+        assert_eq!(25, cargo.free_volume(ware_1).unwrap());
+        assert!(matches!(cargo.free_volume(ware_2), Err(CargoError::Full)));
+    }
+
+    #[test]
+    fn test_cargo_allocation_weighted_should_split_by_weight() {
+        let (ware_0, ware_1, ware_2) = create_wares();
+
+        let mut cargo = Cargo::new(100);
+        cargo.set_whitelist(vec![ware_0, ware_1]);
+        cargo.set_allocation(StorageAllocation::Weighted(vec![(ware_0, 3.0)]));
+
+        assert_eq!(75, cargo.free_volume(ware_0).unwrap());
+        assert_eq!(25, cargo.free_volume(ware_1).unwrap());
+        assert!(matches!(
+            cargo.free_volume(ware_2),
+            Err(CargoError::NotAllowed)
+        ));
+    }
+
+    #[test]
+    fn test_cargo_allocation_shared_pool_should_share_full_volume() {
+        let (ware_0, ware_1, ware_2) = create_wares();
+
+        let mut cargo = Cargo::new(10);
+        cargo.set_whitelist(vec![ware_0, ware_1]);
+        cargo.set_allocation(StorageAllocation::SharedPool);
+
+        cargo.add(ware_0, 8).unwrap();
+        assert_eq!(2, cargo.free_volume(ware_1).unwrap());
+        assert!(cargo.add(ware_2, 1).is_err());
+    }
+
     #[test]
     fn test_cargo_should_not_return_empty_lists() {
         let (ware_0, _ware_1, _ware_2) = create_wares();
@@ -587,4 +1369,153 @@ mod test {
 
         assert_eq!(5, cargo.get_amount(ware_0));
     }
+
+    #[test]
+    fn test_cargo_reservations_should_reduce_amount_and_free_volume() {
+        let (ware_0, ware_1, owner_id) = create_wares();
+
+        let mut cargo = Cargo::new(10);
+        cargo.add(ware_0, 4).unwrap();
+
+        assert_eq!(3, cargo.reserve_out(owner_id, ware_0, 3, TotalTime(1.0)));
+        assert_eq!(1, cargo.get_amount(ware_0));
+        assert_eq!(4, cargo.get_stored_amount(ware_0));
+
+        // can not reserve more than available
+        assert_eq!(1, cargo.reserve_out(owner_id, ware_0, 3, TotalTime(1.0)));
+        assert_eq!(0, cargo.get_amount(ware_0));
+
+        assert_eq!(5, cargo.reserve_in(owner_id, ware_1, 5, TotalTime(2.0)));
+        assert_eq!(1, cargo.free_volume(ware_1).unwrap());
+
+        cargo.release(owner_id);
+        assert_eq!(4, cargo.get_amount(ware_0));
+        assert_eq!(6, cargo.free_volume(ware_1).unwrap());
+    }
+
+    #[test]
+    fn test_cargo_reservations_should_expire() {
+        let (ware_0, _, owner_id) = create_wares();
+
+        let mut cargo = Cargo::new(10);
+        cargo.add(ware_0, 4).unwrap();
+        cargo.reserve_out(owner_id, ware_0, 2, TotalTime(1.0));
+        cargo.reserve_out(owner_id, ware_0, 2, TotalTime(2.0));
+
+        assert!(!cargo.release_expired(TotalTime(0.5)));
+        assert!(cargo.release_expired(TotalTime(1.5)));
+        assert_eq!(2, cargo.get_amount(ware_0));
+    }
+
+    #[test]
+    fn test_cargo_remove_all_or_none_should_not_consume_reserved_wares() {
+        let (ware_0, _, owner_id) = create_wares();
+
+        let mut cargo = Cargo::new(10);
+        cargo.add(ware_0, 4).unwrap();
+        cargo.reserve_out(owner_id, ware_0, 3, TotalTime(1.0));
+
+        let wares = vec![WareAmount::new(ware_0, 2)];
+        assert!(!cargo.has_all(&wares));
+        assert!(cargo.remove_all_or_none(&wares).is_err());
+        assert_eq!(4, cargo.get_stored_amount(ware_0));
+
+        cargo.release(owner_id);
+        assert!(cargo.remove_all_or_none(&wares).is_ok());
+        assert_eq!(2, cargo.get_stored_amount(ware_0));
+    }
+
+    #[test]
+    fn test_cargo_transfer_should_not_move_reserved_wares() {
+        let (ware_0, _, owner_id) = create_wares();
+
+        let mut cargo_from = Cargo::new(10);
+        cargo_from.add(ware_0, 4).unwrap();
+        cargo_from.reserve_out(owner_id, ware_0, 3, TotalTime(1.0));
+
+        let cargo_to = Cargo::new(10);
+        let transfer = CargoTransfer::transfer_all(&cargo_from, &cargo_to);
+        assert_eq!(1, transfer.moved[0].amount);
+    }
+
+    #[test]
+    fn test_cargo_should_use_ware_unit_volume_and_mass() {
+        let (ware_0, ware_1, _) = create_wares();
+
+        let mut cargo = Cargo::new(10);
+        cargo.set_ware_units(WareUnits::new(vec![(
+            ware_0,
+            WareUnit {
+                volume: 3,
+                mass: 2.0,
+            },
+        )]));
+
+        assert_eq!(3, cargo.free_volume(ware_0).unwrap());
+        assert_eq!(10, cargo.free_volume(ware_1).unwrap());
+
+        cargo.add(ware_0, 3).unwrap();
+        assert_eq!(9, cargo.get_current_volume());
+        assert_eq!(6.0, cargo.get_mass());
+        assert!(cargo.is_full());
+        assert!(matches!(cargo.free_volume(ware_0), Err(CargoError::Full)));
+        assert_eq!(1, cargo.free_volume(ware_1).unwrap());
+
+        cargo.remove(ware_0, 1).unwrap();
+        assert_eq!(6, cargo.get_current_volume());
+        assert!(!cargo.is_full());
+    }
+
+    #[test]
+    fn test_system_cargo_ware_units_should_share_units_with_cargos() {
+        let mut world = World::new();
+        world.insert_resource(WareUnits::default());
+        let ware_id = world
+            .spawn(WareUnit {
+                volume: 2,
+                mass: 1.0,
+            })
+            .id();
+        let cargo_0 = world.spawn(Cargo::new(10)).id();
+        let cargo_1 = world.spawn(Cargo::new(10)).id();
+
+        world.run_system_once(system_cargo_ware_units);
+
+        let ware_units = world.resource::<WareUnits>();
+        assert_eq!(2, ware_units.get(ware_id).volume);
+        for obj_id in [cargo_0, cargo_1] {
+            let cargo = world.get::<Cargo>(obj_id).unwrap();
+            assert!(cargo.get_ware_units().is_same(ware_units));
+            assert_eq!(5, cargo.free_volume(ware_id).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_cargo_add_all_or_none_should_consider_unit_volume() {
+        let (ware_0, ware_1, _) = create_wares();
+
+        let mut cargo = Cargo::new(10);
+        cargo.set_ware_units(WareUnits::new(vec![(
+            ware_0,
+            WareUnit {
+                volume: 2,
+                mass: 1.0,
+            },
+        )]));
+
+        let result = cargo.add_all_or_none(&vec![
+            WareAmount::new(ware_0, 4),
+            WareAmount::new(ware_1, 3),
+        ]);
+        assert!(matches!(result, Err(CargoError::NotEnoughSpace)));
+        assert!(cargo.is_empty());
+
+        cargo
+            .add_all_or_none(&vec![
+                WareAmount::new(ware_0, 4),
+                WareAmount::new(ware_1, 2),
+            ])
+            .unwrap();
+        assert_eq!(10, cargo.get_current_volume());
+    }
 }
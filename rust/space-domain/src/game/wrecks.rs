@@ -0,0 +1,455 @@
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::{Command, RunSystemOnce};
+use serde::{Deserialize, Serialize};
+
+use crate::game::actions::{
+    ActionActive, ActionDock, ActionExtract, ActionGeneric, ActionJump, ActionMoveTo,
+    ActionRequest, ActionSalvage, ActionUndock,
+};
+use crate::game::commands;
+use crate::game::dock::HasDocking;
+use crate::game::events::{EventKind, GEvent, GEvents};
+use crate::game::fleets::{FleetGroup, Fleets};
+use crate::game::fuel::Refuel;
+use crate::game::label::Label;
+use crate::game::loader::Loader;
+use crate::game::locations::{LocationDocked, LocationOrbit, LocationSpace, Locations};
+use crate::game::maintenance::Maintenance;
+use crate::game::navigations::{NavRequest, Navigation};
+use crate::game::new_obj::NewObj;
+use crate::game::objects::ObjId;
+use crate::game::production_cost::ProductionCost;
+use crate::game::utils::{DeltaTime, TotalTime};
+use crate::game::wares::{Cargo, Volume, WareAmount};
+
+/// Remains of a destroyed object, its cargo hold the wares that can be salvaged until it decays
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct Wreck {
+    pub decay_at: TotalTime,
+}
+
+/// How much of a destroyed object is left in its wreck, objects are destroyed without wrecks when
+/// not defined
+#[derive(Debug, Clone, Resource)]
+pub struct WreckParams {
+    /// fraction of the cargo left in the wreck
+    pub cargo_ratio: f32,
+    /// fraction of the production cost that can be recovered
+    pub materials_ratio: f32,
+    pub decay_time: DeltaTime,
+}
+
+impl WreckParams {
+    /// Wares left in the wreck from the cargo and the materials used to build the object
+    pub fn wreck_wares(
+        &self,
+        cargo: Option<&Cargo>,
+        production_cost: Option<&ProductionCost>,
+    ) -> Vec<WareAmount> {
+        let mut wares: Vec<WareAmount> = vec![];
+        let mut add = |wa: &WareAmount, ratio: f32| {
+            let amount = (wa.amount as f32 * ratio).floor() as Volume;
+            if amount == 0 {
+                return;
+            }
+            match wares.iter_mut().find(|i| i.ware_id == wa.ware_id) {
+                Some(current) => current.amount += amount,
+                None => wares.push(WareAmount::new(wa.ware_id, amount)),
+            }
+        };
+
+        for wa in cargo
+            .map(|cargo| cargo.get_wares().as_slice())
+            .unwrap_or(&[])
+        {
+            add(wa, self.cargo_ratio);
+        }
+        for wa in production_cost.map(|pc| pc.cost.as_slice()).unwrap_or(&[]) {
+            add(wa, self.materials_ratio);
+        }
+
+        wares
+    }
+}
+
+/// Remove the object from the game, see `Wrecks::destroy`
+pub struct CommandDestroyObj {
+    pub obj_id: ObjId,
+    pub leave_wreck: bool,
+}
+
+impl Command for CommandDestroyObj {
+    fn apply(self, world: &mut World) {
+        if let Err(err) = Wrecks::destroy(world, self.obj_id, self.leave_wreck) {
+            log::warn!("{:?} fail to destroy: {}", self.obj_id, err);
+        }
+    }
+}
+
+pub struct Wrecks;
+
+impl Wrecks {
+    /// Remove the object and every reference to it. Ships docked at or orbiting it are left in
+    /// space, and commands, navigations and actions using it are stopped to be planned again.
+    ///
+    /// When requested and wreck params are defined, a wreck holding part of its cargo and
+    /// materials is left at its position. Return the wreck id, if any.
+    pub fn destroy(
+        world: &mut World,
+        obj_id: ObjId,
+        leave_wreck: bool,
+    ) -> Result<Option<ObjId>, &'static str> {
+        if world.get_entity(obj_id).is_none() {
+            return Err("obj not found");
+        }
+
+        let location = world.run_system_once_with(obj_id, Locations::resolve_space_position_system);
+        let new_wreck = if leave_wreck {
+            Self::new_wreck(world, obj_id, location.as_ref())
+        } else {
+            None
+        };
+
+        Self::remove_references(world, obj_id, location.as_ref());
+        world.despawn(obj_id);
+        log::info!("{:?} destroyed", obj_id);
+        world
+            .resource_mut::<GEvents>()
+            .push(GEvent::new(obj_id, EventKind::Removed));
+
+        Ok(new_wreck.map(|new_obj| Loader::add_object_from_world(world, &new_obj)))
+    }
+
+    fn new_wreck(world: &World, obj_id: ObjId, location: Option<&LocationSpace>) -> Option<NewObj> {
+        let params = world.get_resource::<WreckParams>()?;
+        let location = location?;
+        if world.get::<Wreck>(obj_id).is_some() {
+            return None;
+        }
+
+        let cargo = world.get::<Cargo>(obj_id);
+        let wares = params.wreck_wares(cargo, world.get::<ProductionCost>(obj_id));
+        if wares.is_empty() {
+            return None;
+        }
+
+        let volume = wares
+            .iter()
+            .map(|wa| {
+                let unit = cargo
+                    .map(|c| c.get_ware_unit(wa.ware_id))
+                    .unwrap_or_default();
+                wa.amount * unit.volume
+            })
+            .sum();
+        let mut wreck_cargo = Cargo::new(volume);
+        wreck_cargo
+            .add_all_or_none(&wares)
+            .expect("wreck cargo should fit all wares");
+
+        let label = world
+            .get::<Label>(obj_id)
+            .map(|l| format!("wreck of {}", l.label))
+            .unwrap_or_else(|| "wreck".to_string());
+        let decay_at = world.resource::<TotalTime>().add(params.decay_time);
+
+        Some(
+            NewObj::new()
+                .with_label(label)
+                .with_cargo(wreck_cargo)
+                .at_position(location.sector_id, location.pos)
+                .with_wreck(Wreck { decay_at }),
+        )
+    }
+
+    fn remove_references(world: &mut World, obj_id: ObjId, location: Option<&LocationSpace>) {
+        // fleet groups
+        if let Some(group) = world.get::<FleetGroup>(obj_id).cloned() {
+            for member_id in group.members {
+                Fleets::leave_group(world, member_id);
+            }
+        }
+        Fleets::leave_group(world, obj_id);
+
+        // docking slots and queues
+        for mut docking in world.query::<&mut HasDocking>().iter_mut(world) {
+            docking.undock(obj_id);
+            docking.remove_from_queue(obj_id);
+        }
+
+        // objects docked or orbiting it are left in space
+        let docked: Vec<ObjId> = world
+            .query::<(Entity, &LocationDocked)>()
+            .iter(world)
+            .filter(|(_, docked)| docked.parent_id == obj_id)
+            .map(|(id, _)| id)
+            .collect();
+        for id in docked {
+            let mut entity = world.entity_mut(id);
+            entity.remove::<LocationDocked>();
+            if let Some(location) = location {
+                entity.insert(location.clone());
+            }
+            Self::stop(world, id);
+            world
+                .resource_mut::<GEvents>()
+                .push(GEvent::new(id, EventKind::Undock));
+        }
+
+        let orbiting: Vec<ObjId> = world
+            .query::<(Entity, &LocationOrbit)>()
+            .iter(world)
+            .filter(|(_, orbit)| orbit.parent_id == obj_id)
+            .map(|(id, _)| id)
+            .collect();
+        for id in orbiting {
+            world.entity_mut(id).remove::<LocationOrbit>();
+            world
+                .resource_mut::<GEvents>()
+                .push(GEvent::new(id, EventKind::Deorbit));
+        }
+
+        // commands, navigations and actions using it
+        let mut escorts = vec![];
+        for (id, mut command) in world
+            .query::<(Entity, &mut commands::Command)>()
+            .iter_mut(world)
+        {
+            if command.forget_target(obj_id) && matches!(*command, commands::Command::Escort { .. })
+            {
+                escorts.push(id);
+            }
+        }
+        for id in escorts {
+            world.entity_mut(id).remove::<commands::Command>();
+        }
+
+        // maintenance and refuel at it are canceled, restoring the suspended command
+        Self::cancel_suspended::<Maintenance>(world, obj_id, |i| i.target_id, |i| &mut i.command);
+        Self::cancel_suspended::<Refuel>(world, obj_id, |i| i.target_id, |i| &mut i.command);
+
+        let users: Vec<ObjId> = world
+            .query::<(
+                Entity,
+                Option<&Navigation>,
+                Option<&NavRequest>,
+                Option<&ActionActive>,
+                Option<&ActionRequest>,
+            )>()
+            .iter(world)
+            .filter(|(_, navigation, request, active, action_request)| {
+                navigation.is_some_and(|i| i.is_using(obj_id))
+                    || request.is_some_and(|i| i.get_target_id() == Some(obj_id))
+                    || active.is_some_and(|i| i.get_action().get_target_id() == Some(obj_id))
+                    || action_request
+                        .is_some_and(|i| i.get_action().get_target_id() == Some(obj_id))
+            })
+            .map(|(id, ..)| id)
+            .collect();
+        for id in users {
+            Self::stop(world, id);
+        }
+
+        // reservations owned by it
+        for mut cargo in world.query::<&mut Cargo>().iter_mut(world) {
+            cargo.release(obj_id);
+        }
+    }
+
+    /// Cancel the components suspending a command when it targets the object, otherwise the
+    /// suspended command forgets the object
+    fn cancel_suspended<T: Component>(
+        world: &mut World,
+        obj_id: ObjId,
+        target_of: fn(&T) -> ObjId,
+        command_of: fn(&mut T) -> &mut Option<commands::Command>,
+    ) {
+        let mut canceled = vec![];
+        for (id, mut suspended) in world.query::<(Entity, &mut T)>().iter_mut(world) {
+            if target_of(&suspended) == obj_id {
+                canceled.push(id);
+                continue;
+            }
+
+            let command = command_of(&mut suspended);
+            if let Some(value) = command.as_mut() {
+                if value.forget_target(obj_id) && matches!(value, commands::Command::Escort { .. })
+                {
+                    *command = None;
+                }
+            }
+        }
+
+        for id in canceled {
+            let mut entity = world.entity_mut(id);
+            if let Some(command) = entity
+                .take::<T>()
+                .and_then(|mut i| command_of(&mut i).take())
+            {
+                entity.insert(command);
+            }
+        }
+    }
+
+    /// Drop the current navigation and action, leaving the command to plan again
+    fn stop(world: &mut World, obj_id: ObjId) {
+        log::debug!("{:?} stopping navigation and actions", obj_id);
+        world.entity_mut(obj_id).remove::<(
+            Navigation,
+            NavRequest,
+            ActionActive,
+            ActionRequest,
+            ActionUndock,
+            ActionDock,
+            ActionMoveTo,
+            ActionJump,
+            ActionExtract,
+            ActionSalvage,
+            ActionGeneric,
+        )>();
+    }
+}
+
+/// Remove wrecks once they decay or are fully salvaged
+pub fn system_wrecks_decay(
+    mut commands: Commands,
+    total_time: Res<TotalTime>,
+    query: Query<(Entity, &Wreck, Option<&Cargo>)>,
+) {
+    log::trace!("running");
+
+    for (obj_id, wreck, maybe_cargo) in &query {
+        let is_empty = maybe_cargo.map(|cargo| cargo.is_empty()).unwrap_or(true);
+        if is_empty || total_time.is_after(wreck.decay_at) {
+            log::debug!("{:?} wreck decayed", obj_id);
+            commands.add(CommandDestroyObj {
+                obj_id,
+                leave_wreck: false,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::commands::TradeState;
+    use crate::game::dock::SizeClass;
+    use crate::game::save::{load_world, save_world};
+    use crate::game::stats::EconomyStats;
+    use crate::game::utils::{Tick, V2};
+
+    fn new_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(TotalTime(0.0));
+        world.insert_resource(GEvents::default());
+        world.insert_resource(Tick::default());
+        world.insert_resource(EconomyStats::default());
+        world.insert_resource(WreckParams {
+            cargo_ratio: 0.5,
+            materials_ratio: 0.25,
+            decay_time: DeltaTime(10.0),
+        });
+        world
+    }
+
+    #[test]
+    fn test_destroy_should_clean_references_and_leave_wreck() {
+        let mut world = new_world();
+        let sector_id = world.spawn_empty().id();
+        let ware_id = world.spawn_empty().id();
+
+        let mut cargo = Cargo::new(100);
+        cargo.add(ware_id, 10).unwrap();
+        let mut docking = HasDocking::default();
+        let station_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::new(1.0, 2.0),
+                    sector_id,
+                },
+                cargo,
+                ProductionCost {
+                    cost: vec![WareAmount::new(ware_id, 8)],
+                    work: 1.0,
+                },
+            ))
+            .id();
+
+        // docked ship and trader on the way
+        let docked_id = world
+            .spawn(LocationDocked {
+                parent_id: station_id,
+            })
+            .id();
+        docking.request_dock(docked_id, SizeClass::Small);
+        world.entity_mut(station_id).insert(docking);
+
+        let trader_id = world
+            .spawn((
+                LocationSpace {
+                    pos: V2::ZERO,
+                    sector_id,
+                },
+                commands::Command::Trade(TradeState::PickUp {
+                    target_id: station_id,
+                    wares: vec![ware_id],
+                }),
+                NavRequest::MoveAndDockAt {
+                    target_id: station_id,
+                },
+            ))
+            .id();
+
+        let wreck_id = Wrecks::destroy(&mut world, station_id, true)
+            .unwrap()
+            .expect("wreck not created");
+
+        assert!(world.get_entity(station_id).is_none());
+        assert!(world.get::<LocationDocked>(docked_id).is_none());
+        assert_eq!(
+            V2::new(1.0, 2.0),
+            world.get::<LocationSpace>(docked_id).unwrap().pos
+        );
+        assert!(world.get::<NavRequest>(trader_id).is_none());
+        assert!(matches!(
+            world.get::<commands::Command>(trader_id),
+            Some(commands::Command::Trade(TradeState::Idle))
+        ));
+
+        // half of cargo and a quarter of materials
+        assert_eq!(7, world.get::<Cargo>(wreck_id).unwrap().get_amount(ware_id));
+        assert_eq!(
+            V2::new(1.0, 2.0),
+            world.get::<LocationSpace>(wreck_id).unwrap().pos
+        );
+
+        // references are clean and the world can be saved
+        let data = save_world(&mut world);
+        let mut world = World::new();
+        load_world(&mut world, data);
+    }
+
+    #[test]
+    fn test_wrecks_should_decay() {
+        let mut world = new_world();
+        let ware_id = world.spawn_empty().id();
+        let mut cargo = Cargo::new(10);
+        cargo.add(ware_id, 1).unwrap();
+        let wreck_id = world
+            .spawn((
+                Wreck {
+                    decay_at: TotalTime(5.0),
+                },
+                cargo,
+            ))
+            .id();
+
+        world.run_system_once(system_wrecks_decay);
+        assert!(world.get_entity(wreck_id).is_some());
+
+        world.insert_resource(TotalTime(6.0));
+        world.run_system_once(system_wrecks_decay);
+        assert!(world.get_entity(wreck_id).is_none());
+    }
+}
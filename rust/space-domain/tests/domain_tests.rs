@@ -3,18 +3,21 @@ extern crate space_domain;
 use space_domain::game::commands::Command;
 
 use bevy_ecs::prelude::*;
-use bevy_ecs::system::SystemState;
+use bevy_ecs::system::{RunSystemOnce, SystemState};
 use commons::math::P2;
 use space_domain::game;
 use space_domain::game::bevy_utils::WorldExt;
 use space_domain::game::building_site::BuildingSite;
+use space_domain::game::events::EventKind;
+use space_domain::game::factions::Factions;
 use space_domain::game::game::Game;
 use space_domain::game::label::Label;
 use space_domain::game::loader::Loader;
+use space_domain::game::raiders::{RaiderSector, RaidersSpawn};
 use space_domain::game::sceneries;
 use space_domain::game::scenery_random::{InitialCondition, RandomMapCfg};
 use space_domain::game::station::Station;
-use space_domain::game::utils::{DeltaTime, Speed};
+use space_domain::game::utils::{DeltaTime, Speed, TotalTime};
 use space_domain::game::wares::WareAmount;
 
 #[test]
@@ -101,7 +104,10 @@ fn test_load_random_scenery() {
 
     let path = "../data/game.conf";
     let content = std::fs::read_to_string(path).expect("fail to read config file");
-    let cfg = game::conf::load_str(&content).unwrap();
+    let mut cfg = game::conf::load_str(&content).unwrap();
+    let content = std::fs::read_to_string("../data/ship_components.conf")
+        .expect("fail to read ship components file");
+    game::conf::load_ship_components(&mut cfg, &content).unwrap();
 
     game.world
         .run_commands(|mut commands| game::loader::load_prefabs(&mut commands, &cfg.prefabs));
@@ -195,6 +201,101 @@ fn test_save() {
     panic!("max tickets completed without desired result");
 }
 
+#[test]
+fn test_raiders_should_rob_traders() {
+    let mut game = Game::new(Default::default());
+    let scenery = sceneries::load_basic_scenery(&mut game);
+
+    game.world
+        .entity_mut(scenery.sector_0)
+        .insert(RaiderSector::default());
+    game.world.insert_resource(RaidersSpawn::new(
+        Loader::new_ship(3.0, "raider".to_string()),
+        None,
+        DeltaTime(1.0),
+        1,
+        TotalTime(0.0),
+    ));
+
+    let delta = DeltaTime(0.5);
+    for _tick in 0..2000 {
+        game.tick(delta);
+        if game
+            .take_events()
+            .iter()
+            .any(|event| event.kind == EventKind::Raided)
+        {
+            return;
+        }
+    }
+
+    panic!("max tickets completed without a raid");
+}
+
+#[test]
+fn test_raiders_should_attack_and_destroy_hostile_ships() {
+    let mut game = Game::new(Default::default());
+
+    let factions = game.world.run_system_once(Factions::list_factions_by_code);
+    let sector_id = game.list_sectors()[0].0;
+    let raider = Loader::new_by_prefab_code(&mut game.world, "raider_fleet".to_string())
+        .expect("raider prefab not found")
+        .with_owner(factions["raiders"])
+        .at_position(sector_id, P2::new(0.0, 0.0));
+    let raider_id = Loader::add_object_from_world(&mut game.world, &raider);
+    let trader = Loader::new_by_prefab_code(&mut game.world, "trade_fleet".to_string())
+        .expect("trader prefab not found")
+        .with_owner(factions["free_traders"])
+        .at_position(sector_id, P2::new(1.0, 0.0));
+    let trader_id = Loader::add_object_from_world(&mut game.world, &trader);
+
+    let mut hit = false;
+    for _tick in 0..2000 {
+        game.tick(DeltaTime(0.5));
+        for event in game.take_events() {
+            match event.kind {
+                EventKind::Hit if event.id == trader_id => hit = true,
+                EventKind::Destroyed if event.id == trader_id => {
+                    assert!(hit);
+                    assert!(game.world.get_entity(trader_id).is_none());
+                    assert!(game.world.get_entity(raider_id).is_some());
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    panic!("max tickets completed without the trader being destroyed");
+}
+
+#[test]
+fn test_destroy_station_should_leave_wreck_and_keep_game_running() {
+    let mut game = Game::new(Default::default());
+    let scenery = sceneries::load_basic_scenery(&mut game);
+
+    for _ in 0..20 {
+        game.tick(DeltaTime(0.5));
+    }
+
+    assert!(game.destroy_obj(scenery.sector_0).is_err());
+    assert!(game.world.get_entity(scenery.sector_0).is_some());
+
+    game.destroy_obj(scenery.component_factory_id)
+        .expect("fail to destroy");
+    assert!(game
+        .world
+        .get_entity(scenery.component_factory_id)
+        .is_none());
+
+    for _ in 0..20 {
+        game.tick(DeltaTime(0.5));
+    }
+
+    let data = game.save_to_string();
+    Game::load_from_string(data).expect("fail to load data");
+}
+
 fn tick_eventually(game: &mut Game, expected_check: fn(game: &mut Game) -> bool) {
     let delta = DeltaTime(0.5);
     for _tick in 0..500 {
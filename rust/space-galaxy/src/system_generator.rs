@@ -318,6 +318,12 @@ fn new_moon(
     vec![moon]
 }
 
+/// Generate a single asteroid field orbiting the star, used to seed new fields into existing systems
+pub fn new_asteroid_field(cfg: &UniverseCfg, rng: &mut StdRng) -> SpaceBody {
+    let mut id_gen = IdGen { v: 1 };
+    new_asteroid(cfg, rng, &mut id_gen, 0).remove(0)
+}
+
 fn new_asteroid(
     cfg: &UniverseCfg,
     rng: &mut StdRng,
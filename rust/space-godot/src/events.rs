@@ -4,6 +4,7 @@ use godot::prelude::*;
 pub struct GameEvent {
     pub target_id: i64,
     pub added: bool,
+    pub removed: bool,
 }
 
 #[derive(GodotClass)]
@@ -35,4 +36,9 @@ impl EventsList {
     pub fn is_add(&self, i: i32) -> bool {
         self.events[i as usize].added
     }
+
+    #[func]
+    pub fn is_removed(&self, i: i32) -> bool {
+        self.events[i as usize].removed
+    }
 }
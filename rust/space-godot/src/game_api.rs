@@ -1,8 +1,10 @@
+mod factory_info;
 mod label_info;
 mod obj_info;
 mod shipyard_info;
 mod ware_amount_info;
 
+use self::factory_info::FactoryInfo;
 use self::obj_info::ObjExtendedInfo;
 use self::shipyard_info::ShipyardInfo;
 use self::ware_amount_info::WareAmountInfo;
@@ -11,6 +13,7 @@ use crate::game_api::label_info::LabelInfo;
 use crate::utils;
 use crate::utils::{decode_entity_and_get, encode_entity};
 use bevy_ecs::prelude::*;
+use bevy_ecs::system::RunSystemOnce;
 use commons::math::{P2, V2I};
 use godot::obj::Base;
 use godot::prelude::*;
@@ -18,11 +21,15 @@ use space_domain::app::App;
 use space_domain::game::actions::{Action, ActionActive};
 use space_domain::game::astrobody::{AstroBody, AstroBodyKind};
 use space_domain::game::bevy_utils::WorldExt;
-use space_domain::game::commands::Command;
+use space_domain::game::code::HasCode;
+use space_domain::game::commands::{Command, HaulAction, HaulStep, PatrolWaypoint};
 use space_domain::game::events::EventKind;
 use space_domain::game::extractables::Extractable;
+use space_domain::game::factions::Faction;
+use space_domain::game::factory::{Factory, RateSource, ReceiptPolicy};
 use space_domain::game::fleets::Fleet;
 use space_domain::game::game::{Game, NewGameParams};
+use space_domain::game::habitat::Habitat;
 use space_domain::game::label::Label;
 use space_domain::game::loader::Loader;
 use space_domain::game::locations::{LocationDocked, LocationOrbit, LocationSpace, Locations};
@@ -31,10 +38,13 @@ use space_domain::game::order::TradeOrders;
 use space_domain::game::prefab::Prefab;
 use space_domain::game::save_manager::SaveManager;
 use space_domain::game::sectors::{Jump, Sector};
+use space_domain::game::sensors::Visible;
 use space_domain::game::shipyard;
 use space_domain::game::shipyard::Shipyard;
 use space_domain::game::station::Station;
-use space_domain::game::utils::TotalTime;
+use space_domain::game::stats::{EconomyStats, StatKey, StatKind, StatScope};
+use space_domain::game::utils::{DeltaTime, TotalTime, V2};
+use space_domain::game::wares::{Cargo, StorageAllocation, Volume, WareId};
 use std::path::PathBuf;
 
 pub type Id = i64;
@@ -174,6 +184,15 @@ impl GameRunning {
         let shipyard = self
             .get_shipyard_info(obj_id)
             .map(|value| Gd::from_object(value));
+        let factory = self
+            .get_factory_info(obj_id)
+            .map(|value| Gd::from_object(value));
+        let (population, population_capacity) = self
+            .game
+            .world
+            .get::<Habitat>(obj_id)
+            .map(|habitat| (habitat.population, habitat.capacity))
+            .unwrap_or((0.0, 0.0));
         let cargo = self.list_cargo(obj_id);
         let (requesting_wares, providing_wares) = self.list_requesting_and_providing_wares(obj_id);
         let extractable_resources = self.list_extractable_resources(obj_id);
@@ -218,6 +237,12 @@ impl GameRunning {
             Some(command) => match command {
                 Command::Mine(_) => "mine".to_string(),
                 Command::Trade(_) => "trade".to_string(),
+                Command::Haul(_) => "haul".to_string(),
+                Command::Patrol(_) => "patrol".to_string(),
+                Command::Escort { .. } => "escort".to_string(),
+                Command::Raid(_) => "raid".to_string(),
+                Command::Salvage(_) => "salvage".to_string(),
+                Command::Explore(_) => "explore".to_string(),
             },
             None => "none".to_string(),
         };
@@ -264,6 +289,9 @@ impl GameRunning {
             is_station: station.is_some(),
             is_orbiting: orbit.is_some(),
             shipyard: shipyard,
+            factory: factory,
+            population,
+            population_capacity,
             orbit_parent_id: orbit.map(|i| encode_entity(i.parent_id)).unwrap_or(NULL_ID),
             command,
             action,
@@ -309,6 +337,47 @@ impl GameRunning {
         })
     }
 
+    fn get_factory_info(&mut self, obj_id: ObjId) -> Option<FactoryInfo> {
+        let factory = self.game.world.get::<Factory>(obj_id)?;
+        Some(FactoryInfo {
+            factory: factory.clone(),
+        })
+    }
+
+    /// kind is one of "produced", "consumed", "mined", "moved" or "stock", scope_id can be a
+    /// sector, a station or NULL_ID for global
+    fn resolve_stat_key(&mut self, kind: &str, ware_id: Id, scope_id: Id) -> Option<StatKey> {
+        let kind = match kind {
+            "produced" => StatKind::Produced,
+            "consumed" => StatKind::Consumed,
+            "mined" => StatKind::Mined,
+            "moved" => StatKind::Moved,
+            "stock" => StatKind::Stock,
+            _ => {
+                log::warn!("invalid economy stat kind {:?}", kind);
+                return None;
+            }
+        };
+
+        let ware_id = self.decode_entity_and_get(ware_id);
+        let scope = if scope_id == NULL_ID {
+            StatScope::Global
+        } else {
+            let obj_id = self.decode_entity_and_get(scope_id);
+            if self.game.world.get::<Sector>(obj_id).is_some() {
+                StatScope::Sector(obj_id)
+            } else {
+                StatScope::Obj(obj_id)
+            }
+        };
+
+        Some(StatKey {
+            kind,
+            ware_id,
+            scope,
+        })
+    }
+
     fn decode_entity_and_get(&mut self, id: Id) -> ObjId {
         decode_entity_and_get(&self.game, id)
     }
@@ -528,6 +597,11 @@ impl GameApi {
                     added: true,
                     ..Default::default()
                 }),
+                EventKind::Removed => Some(GameEvent {
+                    target_id: encode_entity(e.id),
+                    removed: true,
+                    ..Default::default()
+                }),
                 _ => None,
             })
             .collect();
@@ -545,6 +619,63 @@ impl GameApi {
             .collect()
     }
 
+    /// Objects at the sector seen by the faction, remembered objects are listed at their last
+    /// known position when include_remembered is true
+    #[func]
+    pub fn list_at_sector_visible(
+        &mut self,
+        sector_id: Id,
+        faction_id: Id,
+        include_remembered: bool,
+    ) -> Array<i64> {
+        let running = self.get_current();
+        let sector_id = running.decode_entity_and_get(sector_id);
+        let faction_id = running.decode_entity_and_get(faction_id);
+        running
+            .game
+            .list_at_sector_visible_by(sector_id, faction_id, include_remembered)
+            .into_iter()
+            .map(|obj_id| encode_entity(obj_id))
+            .collect()
+    }
+
+    /// Return "seen", "remembered" or "unknown"
+    #[func]
+    pub fn get_visibility(&mut self, faction_id: Id, id: Id) -> GString {
+        let running = self.get_current();
+        let faction_id = running.decode_entity_and_get(faction_id);
+        // removed objects can still be remembered
+        let obj_id = utils::decode_removed_entity(id);
+        let value = match running.game.get_visibility(faction_id, obj_id) {
+            Visible::Seen => "seen",
+            Visible::Remembered => "remembered",
+            Visible::Unknown => "unknown",
+        };
+        value.into()
+    }
+
+    /// Return true when the faction explored the sector, factions without discovery know every
+    /// sector
+    #[func]
+    pub fn is_sector_explored(&mut self, faction_id: Id, sector_id: Id) -> bool {
+        let running = self.get_current();
+        let faction_id = running.decode_entity_and_get(faction_id);
+        let sector_id = running.decode_entity_and_get(sector_id);
+        running.game.is_sector_explored(faction_id, sector_id)
+    }
+
+    #[func]
+    pub fn get_faction_by_code(&mut self, code: GString) -> Id {
+        let game = &mut self.get_current().game;
+        let code = code.to_string();
+        game.world
+            .query_filtered::<(Entity, &HasCode), With<Faction>>()
+            .iter(&game.world)
+            .find(|(_, c)| c.code.eq_ignore_ascii_case(code.as_str()))
+            .map(|(obj_id, _)| encode_entity(obj_id))
+            .unwrap_or(NULL_ID)
+    }
+
     #[func]
     pub fn resolve_space_position(&mut self, id: Id) -> Variant {
         let running = self.get_current();
@@ -653,6 +784,322 @@ impl GameApi {
         log::debug!("{:?} set production order to {:?}", obj_id, prefab_id);
     }
 
+    #[func]
+    fn set_factory_receipt(&mut self, obj_id: Id, receipt_index: i64) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        let mut factory = running
+            .game
+            .world
+            .get_mut::<Factory>(obj_id)
+            .expect("factory not found");
+        if factory.set_current(receipt_index as usize) {
+            log::debug!("{:?} set factory receipt to {:?}", obj_id, receipt_index);
+        } else {
+            log::warn!("{:?} invalid factory receipt {:?}", obj_id, receipt_index);
+        }
+    }
+
+    /// policy is "queue", "round_robin" or "demand", a "queue" produces all receipts in order,
+    /// use set_factory_queue for a custom queue
+    #[func]
+    fn set_factory_policy(&mut self, obj_id: Id, policy: String) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        let mut factory = running
+            .game
+            .world
+            .get_mut::<Factory>(obj_id)
+            .expect("factory not found");
+        let Some(policy) = ReceiptPolicy::from_code(policy.as_str(), factory.get_receipts().len())
+        else {
+            log::warn!("{:?} invalid factory policy {:?}", obj_id, policy);
+            return;
+        };
+        log::debug!("{:?} set factory policy to {:?}", obj_id, policy);
+        factory.set_policy(policy);
+    }
+
+    #[func]
+    fn set_factory_queue(&mut self, obj_id: Id, receipts_indexes: Array<i64>) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        let queue: Vec<usize> = receipts_indexes.iter_shared().map(|i| i as usize).collect();
+        let mut factory = running
+            .game
+            .world
+            .get_mut::<Factory>(obj_id)
+            .expect("factory not found");
+        log::debug!("{:?} set factory queue to {:?}", obj_id, queue);
+        factory.set_policy(ReceiptPolicy::Queue(queue));
+    }
+
+    /// production rate multiplier from station upgrades, 1.0 removes the upgrade
+    #[func]
+    fn set_factory_upgrade(&mut self, obj_id: Id, rate: f32) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        if rate <= 0.0 {
+            log::warn!("{:?} invalid factory upgrade rate {:?}", obj_id, rate);
+            return;
+        }
+        let mut factory = running
+            .game
+            .world
+            .get_mut::<Factory>(obj_id)
+            .expect("factory not found");
+        log::debug!("{:?} set factory upgrade rate to {:?}", obj_id, rate);
+        if rate == 1.0 {
+            factory.remove_rate_modifier(RateSource::Upgrade);
+        } else {
+            factory.set_rate_modifier(RateSource::Upgrade, rate);
+        }
+    }
+
+    /// policy is one of "equal", "quotas", "weighted" or "shared", values are the volume or
+    /// weight of each ware in ware_ids
+    #[func]
+    fn set_storage_allocation(
+        &mut self,
+        obj_id: Id,
+        policy: String,
+        ware_ids: Array<i64>,
+        values: Array<f32>,
+    ) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        if ware_ids.len() != values.len() {
+            log::warn!(
+                "{:?} invalid storage allocation, {} wares and {} values",
+                obj_id,
+                ware_ids.len(),
+                values.len()
+            );
+            return;
+        }
+        let values: Vec<(WareId, f32)> = ware_ids
+            .iter_shared()
+            .zip(values.iter_shared())
+            .map(|(ware_id, value)| (running.decode_entity_and_get(ware_id), value))
+            .collect();
+        let Some(allocation) = StorageAllocation::from_code(&policy, values) else {
+            log::warn!("{:?} invalid storage allocation {}", obj_id, policy);
+            return;
+        };
+        let mut cargo = running
+            .game
+            .world
+            .get_mut::<Cargo>(obj_id)
+            .expect("cargo not found");
+        log::debug!("{:?} set storage allocation to {:?}", obj_id, allocation);
+        cargo.set_allocation(allocation);
+    }
+
+    /// Replace the fleet command by a haul route, each index of the arrays is a step. Actions are
+    /// "load" or "unload", amounts and thresholds lower than 0 are not limited
+    #[func]
+    fn set_haul_route(
+        &mut self,
+        obj_id: Id,
+        targets: Array<i64>,
+        actions: Array<GString>,
+        wares: Array<i64>,
+        amounts: Array<i64>,
+        thresholds: Array<i64>,
+    ) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+
+        let len = targets.len();
+        if [actions.len(), wares.len(), amounts.len(), thresholds.len()]
+            .iter()
+            .any(|other| *other != len)
+        {
+            log::warn!(
+                "{:?} invalid haul route, arrays must have the same length",
+                obj_id
+            );
+            return;
+        }
+
+        let mut route = vec![];
+        for i in 0..targets.len() {
+            let action = match actions.get(i).to_string().as_str() {
+                "load" => HaulAction::Load,
+                "unload" => HaulAction::Unload,
+                other => {
+                    log::warn!("{:?} invalid haul action {}", obj_id, other);
+                    return;
+                }
+            };
+            let limit = |value: i64| {
+                if value < 0 {
+                    None
+                } else {
+                    Some(value as Volume)
+                }
+            };
+
+            route.push(HaulStep {
+                target_id: running.decode_entity_and_get(targets.get(i)),
+                action,
+                ware_id: running.decode_entity_and_get(wares.get(i)),
+                amount: limit(amounts.get(i)),
+                threshold: limit(thresholds.get(i)),
+            });
+        }
+
+        log::debug!("{:?} set haul route {:?}", obj_id, route);
+        running
+            .game
+            .world
+            .entity_mut(obj_id)
+            .insert(Command::haul(route));
+    }
+
+    /// Replace the fleet command by a patrol, each index of the arrays is a waypoint. Waypoints
+    /// with a target lower than 0 move to the sector position, dwell lower or equal 0 do not wait
+    #[func]
+    fn set_patrol_route(
+        &mut self,
+        obj_id: Id,
+        sectors: Array<i64>,
+        positions: Array<Vector2>,
+        targets: Array<i64>,
+        dwells: Array<f64>,
+    ) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+
+        let len = targets.len();
+        if [sectors.len(), positions.len(), dwells.len()]
+            .iter()
+            .any(|other| *other != len)
+        {
+            log::warn!(
+                "{:?} invalid patrol route, arrays must have the same length",
+                obj_id
+            );
+            return;
+        }
+
+        let mut waypoints = vec![];
+        for i in 0..targets.len() {
+            let mut waypoint = if targets.get(i) < 0 {
+                let pos = positions.get(i);
+                PatrolWaypoint::pos(
+                    running.decode_entity_and_get(sectors.get(i)),
+                    P2::new(pos.x, pos.y),
+                )
+            } else {
+                PatrolWaypoint::obj(running.decode_entity_and_get(targets.get(i)))
+            };
+
+            let dwell = dwells.get(i);
+            if dwell > 0.0 {
+                waypoint = waypoint.with_dwell(DeltaTime(dwell as f32));
+            }
+
+            waypoints.push(waypoint);
+        }
+
+        log::debug!("{:?} set patrol route {:?}", obj_id, waypoints);
+        running
+            .game
+            .world
+            .entity_mut(obj_id)
+            .insert(Command::patrol(waypoints));
+    }
+
+    /// Replace the fleet command by an escort of the target, leaving any fleet group
+    #[func]
+    fn set_escort(&mut self, obj_id: Id, target_id: Id) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        let target_id = running.decode_entity_and_get(target_id);
+        log::debug!("{:?} set escort of {:?}", obj_id, target_id);
+        running.game.leave_fleet_group(obj_id);
+        running
+            .game
+            .world
+            .entity_mut(obj_id)
+            .insert(Command::Escort { target_id });
+    }
+
+    /// Add the fleet into the leader group at the formation offset, return false when the group
+    /// can not be joined
+    #[func]
+    fn join_group(&mut self, obj_id: Id, leader_id: Id, offset: Vector2) -> bool {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        let leader_id = running.decode_entity_and_get(leader_id);
+        match running
+            .game
+            .join_fleet_group(leader_id, obj_id, V2::new(offset.x, offset.y))
+        {
+            Ok(()) => true,
+            Err(err) => {
+                log::warn!(
+                    "{:?} fail to join group of {:?}: {}",
+                    obj_id,
+                    leader_id,
+                    err
+                );
+                false
+            }
+        }
+    }
+
+    /// Remove the fleet from its group, return false if it was not in a group
+    #[func]
+    fn leave_group(&mut self, obj_id: Id) -> bool {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        running.game.leave_fleet_group(obj_id)
+    }
+
+    /// Replace the fleet command by exploration of the sectors unknown to its faction
+    #[func]
+    fn set_explore(&mut self, obj_id: Id) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        log::debug!("{:?} set explore", obj_id);
+        running
+            .game
+            .world
+            .entity_mut(obj_id)
+            .insert(Command::explore());
+    }
+
+    /// Replace the fleet command by salvaging the nearest wrecks
+    #[func]
+    fn set_salvage(&mut self, obj_id: Id) {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        log::debug!("{:?} set salvage", obj_id);
+        running
+            .game
+            .world
+            .entity_mut(obj_id)
+            .insert(Command::salvage());
+    }
+
+    /// Remove the fleet, station or wreck leaving a wreck in its place, return the wreck id or
+    /// NULL_ID. Any other object is not destroyed
+    #[func]
+    fn destroy_obj(&mut self, obj_id: Id) -> Id {
+        let running = self.get_current();
+        let obj_id = running.decode_entity_and_get(obj_id);
+        match running.game.destroy_obj(obj_id) {
+            Ok(Some(wreck_id)) => encode_entity(wreck_id),
+            Ok(None) => NULL_ID,
+            Err(err) => {
+                log::warn!("{:?} fail to destroy: {}", obj_id, err);
+                NULL_ID
+            }
+        }
+    }
+
     #[func]
     pub fn set_speed(&mut self, speed: f32) {
         let running = self.get_current();
@@ -682,6 +1129,55 @@ impl GameApi {
             .collect()
     }
 
+    #[func]
+    pub fn get_economy_history(&mut self, kind: String, ware_id: Id, scope_id: Id) -> Array<i64> {
+        let running = self.get_current();
+        let Some(key) = running.resolve_stat_key(kind.as_str(), ware_id, scope_id) else {
+            return Array::new();
+        };
+        running
+            .game
+            .world
+            .resource::<EconomyStats>()
+            .get_history(&key)
+            .into_iter()
+            .map(|value| value as i64)
+            .collect()
+    }
+
+    #[func]
+    pub fn get_economy_total(&mut self, kind: String, ware_id: Id, scope_id: Id) -> i64 {
+        let running = self.get_current();
+        let Some(key) = running.resolve_stat_key(kind.as_str(), ware_id, scope_id) else {
+            return 0;
+        };
+        running
+            .game
+            .world
+            .resource::<EconomyStats>()
+            .get_total(&key) as i64
+    }
+
+    /// average per second over the last samples
+    #[func]
+    pub fn get_economy_rate(
+        &mut self,
+        kind: String,
+        ware_id: Id,
+        scope_id: Id,
+        samples: i64,
+    ) -> f32 {
+        let running = self.get_current();
+        let Some(key) = running.resolve_stat_key(kind.as_str(), ware_id, scope_id) else {
+            return 0.0;
+        };
+        running
+            .game
+            .world
+            .resource::<EconomyStats>()
+            .get_rate(&key, samples as usize)
+    }
+
     #[func]
     pub fn get_total_time(&mut self) -> f32 {
         let mut game = &mut self.get_current().game;
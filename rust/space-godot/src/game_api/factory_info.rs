@@ -0,0 +1,90 @@
+use godot::prelude::*;
+use space_domain::game::factory::{Factory, FactoryState, ReceiptPolicy};
+
+#[derive(Clone, Debug, GodotClass)]
+#[class(no_init)]
+pub struct FactoryInfo {
+    pub factory: Factory,
+}
+
+#[godot_api]
+impl FactoryInfo {
+    #[func]
+    pub fn get_receipts_len(&self) -> i64 {
+        self.factory.get_receipts().len() as i64
+    }
+
+    /// empty when the index is invalid
+    #[func]
+    pub fn get_receipt_label(&self, index: i64) -> String {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.factory.get_receipts().get(index))
+            .map(|receipt| receipt.label.clone())
+            .unwrap_or_default()
+    }
+
+    #[func]
+    pub fn get_current_receipt(&self) -> i64 {
+        self.factory.get_current_index() as i64
+    }
+
+    #[func]
+    pub fn is_producing(&self) -> bool {
+        self.factory.is_producing()
+    }
+
+    /// one of "idle", "producing" or "stalled", stalled factories have no space for the output
+    #[func]
+    pub fn get_state(&self) -> String {
+        match self.factory.get_state() {
+            FactoryState::Idle => "idle",
+            FactoryState::Producing => "producing",
+            FactoryState::Stalled => "stalled",
+        }
+        .to_string()
+    }
+
+    #[func]
+    pub fn get_rate(&self) -> f32 {
+        self.factory.get_rate()
+    }
+
+    #[func]
+    pub fn get_lines(&self) -> i64 {
+        self.factory.get_lines() as i64
+    }
+
+    #[func]
+    pub fn get_lines_in_use(&self) -> i64 {
+        self.factory.get_producing().len() as i64
+    }
+
+    /// empty when not producing
+    #[func]
+    pub fn get_producing_label(&self) -> String {
+        self.factory
+            .get_producing_receipt()
+            .map(|receipt| receipt.label.clone())
+            .unwrap_or_default()
+    }
+
+    /// one of "queue", "round_robin" or "demand"
+    #[func]
+    pub fn get_policy(&self) -> String {
+        match self.factory.get_policy() {
+            ReceiptPolicy::Queue(_) => "queue",
+            ReceiptPolicy::RoundRobin => "round_robin",
+            ReceiptPolicy::Demand => "demand",
+        }
+        .to_string()
+    }
+
+    #[func]
+    pub fn get_queue(&self) -> Array<i64> {
+        match self.factory.get_policy() {
+            ReceiptPolicy::Queue(queue) => queue.iter().map(|i| *i as i64).collect(),
+            _ => Array::new(),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::factory_info::FactoryInfo;
 use super::shipyard_info::ShipyardInfo;
 use super::ware_amount_info::WareAmountInfo;
 use crate::game_api::label_info::LabelInfo;
@@ -21,6 +22,9 @@ pub struct ObjExtendedInfo {
     pub is_station: bool,
     pub is_orbiting: bool,
     pub shipyard: Option<Gd<ShipyardInfo>>,
+    pub factory: Option<Gd<FactoryInfo>>,
+    pub population: f32,
+    pub population_capacity: f32,
     pub orbit_parent_id: Id,
     pub command: String,
     pub action: String,
@@ -81,6 +85,18 @@ impl ObjExtendedInfo {
         self.shipyard.clone()
     }
     #[func]
+    pub fn get_factory(&self) -> Option<Gd<FactoryInfo>> {
+        self.factory.clone()
+    }
+    #[func]
+    pub fn get_population(&self) -> f32 {
+        self.population
+    }
+    #[func]
+    pub fn get_population_capacity(&self) -> f32 {
+        self.population_capacity
+    }
+    #[func]
     pub fn get_orbit_parent_id(&self) -> Id {
         self.orbit_parent_id
     }
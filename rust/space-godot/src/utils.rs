@@ -54,6 +54,12 @@ pub fn try_decode_entity_and_get(g: &Game, id: Id) -> Option<ObjId> {
     }
 }
 
+/// Decode the entity without checking if it still exists, like removed objects still remembered
+pub fn decode_removed_entity(id: Id) -> ObjId {
+    let (index, egen) = decode_entity(id);
+    ObjId::from_bits((egen as u64) << 32 | index as u64)
+}
+
 pub fn decode_entity_and_get(g: &Game, id: Id) -> ObjId {
     try_decode_entity_and_get(g, id).expect("invalid i")
 }